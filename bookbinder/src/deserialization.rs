@@ -1,5 +1,11 @@
-use crate::{create_epub, create_pdf, BookSrc, BookSrcBuilder, EpubOptions, LatexOptions};
-use bookbinder_ast::BookEvent;
+use crate::{
+    create_epub, create_pdf, create_statistics_report, BookSrc, BookSrcBuilder, EpubOptions,
+    LatexOptions, StatisticsReport,
+};
+use bookbinder_ast::{
+    BookDocument, CitationStyle, DiagnosticKind, HeadingMapping, Lint, LintOptions, LintRule,
+    NumberFormat, OpeningStyle, PageTemplates, SceneBreak,
+};
 use bookbinder_latex::PaperSize;
 use serde::Deserialize;
 use std::borrow::Cow;
@@ -17,11 +23,33 @@ pub fn create_epub_from_json(src: &str) -> Result<Vec<u8>, Box<dyn Error>> {
     create_from_json(src, OutputFormat::Epub)
 }
 
-pub fn create_ast_from_json<'a>(src: &'a str) -> Result<Vec<BookEvent<'a>>, Box<dyn Error>> {
+/// Create a processed, serializable `BookDocument` from a json representation of a DeserializableBook.
+///
+/// The document can be saved using `BookDocument::to_json` and read back later
+/// with `BookDocument::from_json`, then rendered using `create_epub` or `create_pdf`.
+pub fn create_ast_from_json<'a>(src: &'a str) -> Result<BookDocument<'a>, Box<dyn Error>> {
     let book = DeserializableBook::new(src)?;
     book.into_ast()
 }
 
+/// Create a report of the statistics of a book from a json representation of a DeserializableBook.
+pub fn create_statistics_from_json(src: &str) -> Result<StatisticsReport, Box<dyn Error>> {
+    let book = DeserializableBook::new(src)?;
+    let src = BookSrc::try_from(book.src)?;
+    Ok(create_statistics_report(&src))
+}
+
+/// Check a book, from a json representation of a DeserializableBook, against every lint rule it does not disable.
+pub fn create_lints_from_json(src: &str) -> Result<Vec<Lint>, Box<dyn Error>> {
+    let book = DeserializableBook::new(src)?;
+    let mut options = LintOptions::default();
+    for rule in book.src.disabled_lint_rules.iter() {
+        options.disable(*rule);
+    }
+    let src = BookSrc::try_from(book.src)?;
+    Ok(src.lint(&options))
+}
+
 enum OutputFormat {
     Epub,
     Pdf,
@@ -87,6 +115,50 @@ pub struct UnauthoredAncillaryText<'a> {
     pub title: Option<Cow<'a, str>>,
 }
 
+/// A fragment of mainmatter: either markdown text or a path to a markdown file,
+/// optionally with a heading mapping which applies to that fragment alone
+/// and the authors of its chapters
+///
+/// ```
+/// # use bookbinder::deserialization::DeserializableBook;
+/// let json = r##"{
+///     "title": "Hello World",
+///     "heading_mapping": {"chapter": 1},
+///     "mainmatter": [
+///         "# A Chapter\n\n## A Section\n\nText goes here",
+///         {"text": "# A Part\n\n## A Chapter\n\nText goes here", "heading_mapping": {"part": 1, "chapter": 2}},
+///         {"text": "# An Essay\n\nText goes here", "authors": ["A.N. Author"]}
+///     ]
+/// }"##;
+/// let book: DeserializableBook = serde_json::from_str(json).unwrap();
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum MainmatterItem<'a> {
+    /// Mainmatter whose headings are interpreted like the rest of the book
+    Plain(PathOrString<'a>),
+    /// Mainmatter with its own heading mapping or authors
+    Detailed {
+        /// Either the text of this item, or a path to a markdown file containing the text
+        text: PathOrString<'a>,
+        /// The heading mapping to use for this item
+        #[serde(default)]
+        heading_mapping: Option<HeadingMapping>,
+        /// names of the authors of each chapter in this item
+        #[serde(default)]
+        authors: Vec<Cow<'a, str>>,
+    },
+}
+
+/// Templates for generated pages, given either in place
+/// or as the path to a toml, yaml or json file of them
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PageTemplatesSource {
+    Path(PathBuf),
+    Templates(PageTemplates),
+}
+
 /// Deserializable representation of an epigraph
 #[derive(Deserialize, Debug)]
 pub struct Epigraph<'a> {
@@ -124,6 +196,8 @@ pub struct DeserializableBookSrc<'a> {
     #[serde(default)]
     pub do_not_generate_copyrightpage: bool,
     #[serde(default)]
+    pub do_not_generate_index: bool,
+    #[serde(default)]
     pub authors: Option<Vec<Cow<'a, str>>>,
     #[serde(default)]
     pub translators: Option<Vec<Cow<'a, str>>>,
@@ -157,11 +231,27 @@ pub struct DeserializableBookSrc<'a> {
     pub do_not_assert_moral_rights: bool,
     #[serde(default)]
     pub is_not_first_publication: bool,
+    /// The year in which the book was first published, if this edition is a later one
+    #[serde(default)]
+    pub first_published: Option<Cow<'a, str>>,
+    /// The date on which this edition is published, as `YYYY-MM-DD`
+    #[serde(default)]
+    pub publication_date: Option<Cow<'a, str>>,
+    /// The language of the book, as an IETF language tag such as `en` or `fr-CA`
+    #[serde(default)]
+    pub language: Option<Cow<'a, str>>,
+    /// Templates for the text of the generated copyright page, halftitle and titlepage
+    #[serde(default)]
+    pub page_templates: Option<PageTemplatesSource>,
     #[serde(default)]
     pub dedication: Option<Cow<'a, str>>,
     #[serde(default)]
     pub colophon: Option<Cow<'a, str>>,
     #[serde(default)]
+    pub series_page: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub frontispiece: Option<Cow<'a, str>>,
+    #[serde(default)]
     pub forewords: Vec<AuthoredAncillaryText<'a>>,
     #[serde(default)]
     pub afterwords: Vec<AuthoredAncillaryText<'a>>,
@@ -173,9 +263,43 @@ pub struct DeserializableBookSrc<'a> {
     pub acknowledgements: Option<UnauthoredAncillaryText<'a>>,
     #[serde(default)]
     pub appendices: Vec<UnauthoredAncillaryText<'a>>,
-    pub mainmatter: Vec<PathOrString<'a>>,
+    #[serde(default)]
+    pub prologue: Option<UnauthoredAncillaryText<'a>>,
+    #[serde(default)]
+    pub conclusion: Option<UnauthoredAncillaryText<'a>>,
+    #[serde(default)]
+    pub epilogue: Option<UnauthoredAncillaryText<'a>>,
+    #[serde(default)]
+    pub glossary: Option<UnauthoredAncillaryText<'a>>,
+    #[serde(default)]
+    pub bibliography: Option<UnauthoredAncillaryText<'a>>,
+    #[serde(default)]
+    pub contributors: Option<UnauthoredAncillaryText<'a>>,
+    #[serde(default)]
+    pub errata: Option<UnauthoredAncillaryText<'a>>,
+    /// Paths to BibTeX (`.bib`) or CSL-JSON (`.json`) files of works cited in the text
+    #[serde(default)]
+    pub bibliography_sources: Vec<PathBuf>,
+    /// How citations refer to works: `author_date` (the default) or `numeric`
+    #[serde(default)]
+    pub citation_style: Option<CitationStyle>,
+    /// Paths to markdown definition lists, or toml, yaml or json files, of glossary terms and abbreviations
+    #[serde(default)]
+    pub glossary_sources: Vec<PathBuf>,
+    pub mainmatter: Vec<MainmatterItem<'a>>,
+    /// Which heading levels in mainmatter mark parts, chapters, sections and subsections;
+    /// if not set, this is inferred from the headings present
+    #[serde(default)]
+    pub heading_mapping: Option<HeadingMapping>,
     #[serde(default, borrow)]
     pub epigraphs: Vec<Epigraph<'a>>,
+    /// Fail if any problems, such as empty footnotes, are found while processing the book,
+    /// rather than only printing them
+    #[serde(default)]
+    pub deny_warnings: bool,
+    /// The ids of lint rules not to check this book against, such as `straight-quote`
+    #[serde(default)]
+    pub disabled_lint_rules: Vec<LintRule>,
 }
 
 impl<'a> TryFrom<DeserializableBookSrc<'a>> for BookSrc<'a> {
@@ -208,8 +332,13 @@ impl<'a> TryFrom<DeserializableBookSrc<'a>> for BookSrc<'a> {
         ifsomethen!(subtitle, subtitle);
         ifsomethen!(copyright_statement, copyright_statement);
         ifsomethen!(print_location, print_location);
+        ifsomethen!(first_published, first_published);
+        ifsomethen!(publication_date, publication_date);
+        ifsomethen!(language, language);
         ifsomethen!(dedication, set_dedication);
         ifsomethen!(colophon, set_colophon);
+        ifsomethen!(series_page, set_series_page);
+        ifsomethen!(frontispiece, set_frontispiece);
 
         macro_rules! add_authored_ancillary {
             ($srcfield:ident, $targetfilefunc:ident, $targetstrfunc:ident) => {
@@ -254,6 +383,17 @@ impl<'a> TryFrom<DeserializableBookSrc<'a>> for BookSrc<'a> {
             add_acknowledgements_from_file,
             add_acknowledgements
         );
+        add_unauthored_ancillary!(prologue, add_prologue_from_file, add_prologue);
+        add_unauthored_ancillary!(conclusion, add_conclusion_from_file, add_conclusion);
+        add_unauthored_ancillary!(epilogue, add_epilogue_from_file, add_epilogue);
+        add_unauthored_ancillary!(glossary, add_glossary_from_file, add_glossary);
+        add_unauthored_ancillary!(bibliography, add_bibliography_from_file, add_bibliography);
+        add_unauthored_ancillary!(
+            contributors,
+            add_list_of_contributors_from_file,
+            add_list_of_contributors
+        );
+        add_unauthored_ancillary!(errata, add_errata_from_file, add_errata);
 
         for appendix in src.appendices.into_iter() {
             let title = appendix.title;
@@ -267,14 +407,49 @@ impl<'a> TryFrom<DeserializableBookSrc<'a>> for BookSrc<'a> {
             }
         }
 
+        ifsomethen!(heading_mapping, set_heading_mapping);
+        ifsomethen!(citation_style, set_citation_style);
+
+        match src.page_templates {
+            Some(PageTemplatesSource::Path(p)) => {
+                builder.set_page_templates_from_file(p)?;
+            }
+            Some(PageTemplatesSource::Templates(templates)) => {
+                builder.set_page_templates(templates);
+            }
+            None => {}
+        }
+
+        for path in src.bibliography_sources.iter() {
+            builder.add_bibliography_source_from_file(path)?;
+        }
+
+        for path in src.glossary_sources.iter() {
+            builder.add_glossary_source_from_file(path)?;
+        }
+
         for item in src.mainmatter.into_iter() {
             match item {
-                PathOrString::Path(p) => {
+                MainmatterItem::Plain(PathOrString::Path(p)) => {
                     builder.add_mainmatter_from_file(p)?;
                 }
-                PathOrString::Str(s) => {
+                MainmatterItem::Plain(PathOrString::Str(s)) => {
                     builder.add_mainmatter(s);
                 }
+                MainmatterItem::Detailed {
+                    text: PathOrString::Path(p),
+                    heading_mapping,
+                    authors,
+                } => {
+                    builder.add_authored_mainmatter_from_file(p, authors, heading_mapping)?;
+                }
+                MainmatterItem::Detailed {
+                    text: PathOrString::Str(s),
+                    heading_mapping,
+                    authors,
+                } => {
+                    builder.add_authored_mainmatter(s, authors, heading_mapping);
+                }
             }
         }
 
@@ -299,6 +474,10 @@ impl<'a> TryFrom<DeserializableBookSrc<'a>> for BookSrc<'a> {
             builder.do_not_generate_copyrightpage();
         }
 
+        if src.do_not_generate_index {
+            builder.do_not_generate_index();
+        }
+
         if src.do_not_assert_moral_rights {
             builder.do_not_assert_moral_rights();
         }
@@ -307,8 +486,22 @@ impl<'a> TryFrom<DeserializableBookSrc<'a>> for BookSrc<'a> {
             builder.is_not_first_publication();
         }
 
-        let src = builder.process();
-        Ok(src)
+        if src.deny_warnings {
+            builder.deny_warnings();
+            let (src, _) = builder.process_checked()?;
+            Ok(src)
+        } else {
+            let src = builder.process();
+            // a book cannot be dated from a publication date which cannot be read
+            if let Some(date) = src.metadata.invalid_publication_date() {
+                let kind = DiagnosticKind::InvalidPublicationDate(date.to_string());
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    kind.to_string(),
+                ));
+            }
+            Ok(src)
+        }
     }
 }
 
@@ -336,6 +529,12 @@ pub struct UnifiedOptions {
     /// flag to use roman numerals for chapter numbers
     #[serde(default)]
     pub use_roman_numerals_for_chapter_labels: bool,
+    /// format of chapter numbers, overriding the flags above
+    #[serde(default)]
+    pub chapter_number_format: Option<NumberFormat>,
+    /// format of part numbers
+    #[serde(default)]
+    pub part_number_format: Option<NumberFormat>,
     /// flag to suppress chapter labels
     #[serde(default)]
     pub suppress_chapter_labels: bool,
@@ -407,6 +606,12 @@ pub struct UnifiedOptions {
     /// custom label for chapters -- e.g. `Letter 1` instead of `Chapter 1`
     #[serde(default)]
     pub chapter_label: Option<String>,
+    /// how scene breaks are shown in both pdf and epub output
+    #[serde(default)]
+    pub scene_break: Option<SceneBreak>,
+    /// how the opening paragraph of each chapter begins in both pdf and epub output
+    #[serde(default)]
+    pub opening_style: Option<OpeningStyle>,
 }
 
 impl From<UnifiedOptions> for EpubOptions {
@@ -420,6 +625,14 @@ impl From<UnifiedOptions> for EpubOptions {
             options.set_chapter_label(chapter_label);
         }
 
+        if let Some(scene_break) = src.scene_break {
+            options.scene_break(scene_break);
+        }
+
+        if let Some(opening_style) = src.opening_style {
+            options.opening_style(opening_style);
+        }
+
         if let Some(cover_image) = src.cover_image {
             options.cover_image(cover_image);
         }
@@ -444,6 +657,12 @@ impl From<UnifiedOptions> for EpubOptions {
         if src.use_roman_numerals_for_chapter_labels {
             options.use_roman_numerals_for_chapter_labels();
         }
+        if let Some(format) = src.chapter_number_format {
+            options.set_chapter_number_format(format);
+        }
+        if let Some(format) = src.part_number_format {
+            options.set_part_number_format(format);
+        }
         if src.suppress_chapter_labels {
             options.suppress_chapter_label();
         }
@@ -455,8 +674,10 @@ impl From<UnifiedOptions> for EpubOptions {
 }
 
 #[allow(unused_must_use)]
-impl From<UnifiedOptions> for LatexOptions {
-    fn from(src: UnifiedOptions) -> LatexOptions {
+impl TryFrom<UnifiedOptions> for LatexOptions {
+    type Error = String;
+
+    fn try_from(src: UnifiedOptions) -> Result<LatexOptions, Self::Error> {
         let mut options = LatexOptions::default();
 
         if src.open_any {
@@ -467,6 +688,14 @@ impl From<UnifiedOptions> for LatexOptions {
             options.chapter_label(chapter_label);
         }
 
+        if let Some(scene_break) = src.scene_break {
+            options.set_scene_break(scene_break)?;
+        }
+
+        if let Some(opening_style) = src.opening_style {
+            options.set_opening_style(opening_style);
+        }
+
         if let Some(publisher_imprint_logo) = src.publisher_imprint_logo {
             options.set_publisher_logo(publisher_imprint_logo);
         }
@@ -482,6 +711,12 @@ impl From<UnifiedOptions> for LatexOptions {
         if src.use_roman_numerals_for_chapter_labels {
             options.use_roman_numerals_for_chapter_labels();
         }
+        if let Some(format) = src.chapter_number_format {
+            options.set_chapter_number_format(format);
+        }
+        if let Some(format) = src.part_number_format {
+            options.set_part_number_format(format);
+        }
         if src.suppress_chapter_labels {
             options.suppress_chapter_label();
         }
@@ -554,7 +789,7 @@ impl From<UnifiedOptions> for LatexOptions {
             options.set_papersize(PaperSize::USLegal);
         }
 
-        options
+        Ok(options)
     }
 }
 
@@ -581,9 +816,9 @@ impl<'a> DeserializableBook<'a> {
         Ok(book)
     }
 
-    fn into_ast(self) -> Result<Vec<BookEvent<'a>>, Box<dyn Error>> {
+    fn into_ast(self) -> Result<BookDocument<'a>, Box<dyn Error>> {
         let src = BookSrc::try_from(self.src)?;
-        Ok(src.contents)
+        Ok(BookDocument::new(src))
     }
 
     fn into_epub(self) -> Result<Vec<u8>, Box<dyn Error>> {
@@ -594,7 +829,7 @@ impl<'a> DeserializableBook<'a> {
 
     fn into_pdf(self) -> Result<Vec<u8>, Box<dyn Error>> {
         let src = BookSrc::try_from(self.src)?;
-        let pdf = create_pdf(src, self.options.into())?;
+        let pdf = create_pdf(src, LatexOptions::try_from(self.options)?)?;
         Ok(pdf)
     }
 }
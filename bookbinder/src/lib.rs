@@ -90,9 +90,60 @@
 //! (typically italicised text in a minipage, but you could specify otherwise) that it's a dedication.
 //! (Of course, if you wanted to avoid having things like a copyright page generated, or provide your own, that's easily done too!)
 //!
+//! The text of these generated pages comes from a set of `PageTemplates`, in which `{publisher}` or any other metadata field is filled in
+//! and sections such as `{?publisher}by {publisher}{/publisher}` are kept only when the field is set.
+//! Setting your own templates with `set_page_templates` -- or `page_templates` in the json format -- lets them be written in another language;
+//! and setting the year a book was `first_published` gives a later edition a history, such as `First published 2019, this edition published 2026`.
+//! Setting its `language`, as a tag such as `fr`, also sorts any index by the rules of that language.
+//!
+//! Rendering the same source twice gives byte-identical epubs and pdfs, so long as the date they carry is fixed:
+//! set a `publication_date` (as `YYYY-MM-DD`; any other form is an error), or the `SOURCE_DATE_EPOCH` environment variable,
+//! and the copyright year, the modification date of an epub and the dates in a pdf are all taken from it rather than from the time of rendering.
+//!
 //! The mainmatter following would treat `# Early Life` as a part header, and `# I am born` as a chapter header, but if they'd
 //! both been top-level headers they would have both been treated as chapters (or if one was a second-level header and the other a third and there were no top-level headers, they would have been treated as chapters and sections.)
 //!
+//! If you'd rather not have the structure guessed like this, you can fix which heading levels mark parts, chapters and sections with `set_heading_mapping`.
+//!
+//! A markdown file can also begin with a block of yaml front matter (or toml, fenced by `+++`) to set the title, label, authors or role of the division it contains, or to leave it unnumbered:
+//!
+//! ```text
+//! ---
+//! title: A Letter from Abroad
+//! short_title: A Letter
+//! authors: A.N. Author
+//! unnumbered: true
+//! ---
+//! Dear Sir, ...
+//! ```
+//!
+//! A long manuscript can be split across files and composed with include directives:
+//! a line holding only `{<chapters/one.md}` is replaced by the markdown of that file, relative to the file it is in,
+//! and `{<chapters/one.md|+1}` makes each heading in it one level lower.
+//! Problems found in an included file are reported against that file and line, and images are looked for beside it too.
+//!
+//! Text which only makes sense in one format can be marked for that format alone:
+//! `<span class="pdf-only">see page 32</span><span class="epub-only">see the next chapter</span>`.
+//! Longer passages can be enclosed in `<div class="epub-only">` and `</div>`, set apart from them by blank lines.
+//!
+//! Poetry goes in a fenced block marked `verse`, where blank lines divide stanzas and the indentation of each line is kept;
+//! ```` ```verse numbered ```` numbers every fifth line. Verse is set with the LaTeX `verse` package in print,
+//! and in an epub an overlong line hangs beneath its beginning rather than running back to the margin.
+//!
+//! Plays go in a `<div class="drama" data-personae="HAMLET, HORATIO">`, set apart from its contents by blank lines:
+//! a paragraph beginning `HAMLET.` or `HAMLET:` is a speech by Hamlet, `(aside)` within it a direction,
+//! a paragraph wholly in brackets a stage direction, and `INT. KITCHEN -- NIGHT` the heading of a scene.
+//! The speaker's name hangs at the start of each speech, so that it is never separated from its lines,
+//! and a dramatis personae listing every speaker is generated unless one is added with `add_dramatis_personae`.
+//!
+//! Letters, diary entries and other documents go in a `<div class="letter">` or `<div class="document">`.
+//! Their parts are known from their place: opening lines before a salutation such as `Dear Lucy,` are the dateline,
+//! a closing such as `Yours ever,` near the end begins the signature, and `P.S.` begins a postscript.
+//! The dateline is set to the right and the signature in the right half of the page, in print and in an epub alike;
+//! with a chapter label such as `Letter`, a whole epistolary novel can be set this way.
+//!
+//! For a collection of essays or stories by different hands, `add_authored_mainmatter` attributes each chapter to its own authors, who are shown beneath its heading, in the running footer and in the table of contents.
+//!
 //! And so on. It's pretty cool.
 //!
 //! But even better is that it's easy to change how these basic semantic constructs are represented -- if you
@@ -111,6 +162,16 @@
 //!     .set_papersize(PaperSize::A4Paper); // and an A4 book is a good size
 //! ```
 //!
+//! Scene breaks -- `* * *` in the markdown -- can be shown in either format as a blank line, a short rule, an asterism, a dinkus,
+//! a glyph of your choosing, or a small image, by giving a `SceneBreak` to `set_scene_break` or `scene_break` in the options.
+//! The paragraph after a break is never indented, and in print a blank line which falls at the top or bottom of a page becomes a dinkus,
+//! so that the break is not lost.
+//!
+//! The opening paragraph of each chapter can begin with a drop cap falling through a given number of lines,
+//! with its first line or first few words in small caps, or both, by giving an `OpeningStyle` to `set_opening_style` or `opening_style`
+//! in the options. Quotation marks before the initial letter are set with the drop cap, and a chapter which begins with an epigraph or an image
+//! opens with the first paragraph after it.
+//!
 //! A book can be produced just by combining the options which can be applied to a particular output format with a source created through `BookSrcBuilder`.
 //!
 //! `BookSrcBuilder` lets you add a bewildering range of metadata (this was translated by a particular person, and someone else did the notes and gave an introduction, but the author was someone else again) and book elements (here's that introduction, here's the main text, here's a note by the translator).
@@ -162,14 +223,54 @@
 //!
 //! For full details, see the `deserialization` module.
 //!
-pub use bookbinder_ast::{BookSrc, BookSrcBuilder};
+//! A processed `BookSrc` can also be saved as a versioned `BookDocument`, in either json or a compact binary format,
+//! and then read back and rendered later without reprocessing the original markdown:
+//!
+//! ```
+//! use bookbinder::{create_epub, BookDocument, BookSrcBuilder, EpubOptions};
+//!
+//! let src = BookSrcBuilder::new("A Book")
+//!     .add_mainmatter("# Greetings\n\n Hello world...")
+//!     .process();
+//! let json = BookDocument::new(src).to_json().unwrap();
+//!
+//! let restored = BookDocument::from_json(&json).unwrap().into_src();
+//! let epub = create_epub(restored, EpubOptions::default())
+//!     .expect("Error producing epub");
+//! ```
+//!
+//! The binary accepts the flag `-ast` to write such a document as json instead of rendering a book.
+//!
+//! # Statistics
+//!
+//! `BookSrc::statistics` counts the words, footnotes and images in a book, by matter and by division,
+//! and `BookSrc::statistics_for` counts them as the book is rendered to a particular output;
+//! `create_statistics_report` adds an estimate of reading time and of the number of pages the book fills at each `PaperSize`.
+//! The binary writes such a report as json when given the flag `-statistics`.
+//!
+//! # Lints
+//!
+//! `BookSrc::lint` checks a book for typographical slips, such as straight quotes, doubled spaces or hyphens in number ranges,
+//! and for problems like empty chapters, images without alt text, or misspellings of the names of contributors.
+//! Each `LintRule` has an id, such as `straight-quote`, which can be given in the `disabled_lint_rules` of a json book
+//! to turn the rule off for that project.
+//! The binary prints the lints of a book, one per line, when given the flag `-lint`.
+//!
+pub use bookbinder_ast::{
+    BookDocument, BookEvent, BookSrc, BookSrcBuilder, BookTransform, Diagnostic, DiagnosticKind,
+    DiagnosticsError, HeadingMapping, Lint, LintOptions, LintRule, Metadata, NumberFormat,
+    OpeningStyle, PageTemplates, RenderTarget, SceneBreak, Severity, SmallCapsOpening,
+    SourceLocation, Statistics,
+};
 use bookbinder_epub::EpubRenderer;
 pub use bookbinder_epub::Options as EpubOptions;
 pub use bookbinder_epub::RenderingError as EpubRenderingError;
 pub use bookbinder_latex::LatexSecNumDepth;
+pub use bookbinder_latex::PaperSize;
 use bookbinder_latex::PdfRenderer;
 pub use bookbinder_latex::PreambleOptions as LatexOptions;
 pub mod deserialization;
+use serde::Serialize;
 
 /// Create an epub 3.2 from a `BookSrc` with the given options
 pub fn create_epub(src: BookSrc<'_>, options: EpubOptions) -> Result<Vec<u8>, EpubRenderingError> {
@@ -190,3 +291,46 @@ pub fn create_epub_default(src: BookSrc<'_>) -> Result<Vec<u8>, EpubRenderingErr
 pub fn create_pdf_default(src: BookSrc<'_>) -> Result<Vec<u8>, std::io::Error> {
     src.render_to_pdf()
 }
+
+/// The estimated length of a book printed at a particular paper size
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageEstimate {
+    /// The name of the paper size, such as `6x9` or `a4`
+    pub paper_size: &'static str,
+    /// Roughly how many pages the book fills
+    pub pages: usize,
+}
+
+/// The statistics of a book, along with how long it takes to read
+/// and how many pages it fills at each paper size
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatisticsReport {
+    /// Counts of the words, footnotes and images common to every output of the book
+    #[serde(flatten)]
+    pub statistics: Statistics,
+    /// Roughly how many minutes reading the epub takes at an average speed
+    pub reading_minutes: usize,
+    /// The estimated length of the printed book at each paper size, from smallest to largest
+    pub estimated_pages: Vec<PageEstimate>,
+}
+
+/// Gather the statistics of a `BookSrc`, estimating its reading time and length in print
+pub fn create_statistics_report(src: &BookSrc<'_>) -> StatisticsReport {
+    let statistics = src.statistics();
+    let reading_minutes = src
+        .statistics_for(RenderTarget::Epub)
+        .reading_minutes(Statistics::AVERAGE_WORDS_PER_MINUTE);
+    let print = src.statistics_for(RenderTarget::Latex);
+    let estimated_pages = PaperSize::ALL
+        .iter()
+        .map(|size| PageEstimate {
+            paper_size: size.name(),
+            pages: print.estimated_pages(size.words_per_page()),
+        })
+        .collect();
+    StatisticsReport {
+        statistics,
+        reading_minutes,
+        estimated_pages,
+    }
+}
@@ -1,4 +1,7 @@
-use bookbinder::deserialization::{create_epub_from_json, create_pdf_from_json};
+use bookbinder::deserialization::{
+    create_ast_from_json, create_epub_from_json, create_lints_from_json, create_pdf_from_json,
+    create_statistics_from_json,
+};
 use std::env;
 use std::error::Error;
 use std::io::{self, Read, Write};
@@ -8,11 +11,21 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut stdin = io::stdin();
     stdin.read_to_string(&mut json)?;
 
-    let output = if env::args().any(|x| x == "-epub") {
-        create_epub_from_json(&json)
+    let output = if env::args().any(|x| x == "-ast") {
+        create_ast_from_json(&json)?.to_json_pretty()?.into_bytes()
+    } else if env::args().any(|x| x == "-statistics") {
+        serde_json::to_string_pretty(&create_statistics_from_json(&json)?)?.into_bytes()
+    } else if env::args().any(|x| x == "-lint") {
+        let mut output = String::new();
+        for lint in create_lints_from_json(&json)?.iter() {
+            output.push_str(&format!("{}\n", lint));
+        }
+        output.into_bytes()
+    } else if env::args().any(|x| x == "-epub") {
+        create_epub_from_json(&json)?
     } else {
-        create_pdf_from_json(&json)
-    }?;
+        create_pdf_from_json(&json)?
+    };
 
     io::stdout().write_all(&output)?;
     Ok(())
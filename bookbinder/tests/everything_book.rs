@@ -1,4 +1,11 @@
-use bookbinder::deserialization::{create_epub_from_json, create_pdf_from_json};
+use bookbinder::deserialization::{
+    create_ast_from_json, create_epub_from_json, create_lints_from_json, create_pdf_from_json,
+    create_statistics_from_json,
+};
+use bookbinder::deserialization::UnifiedOptions;
+use bookbinder::{create_epub_default, BookDocument, BookSrcBuilder, LatexOptions, LintRule};
+use bookbinder_latex::TexRenderer;
+use std::convert::TryFrom;
 
 static JSON_BOOK: &str = include_str!("everything_book.json");
 static EXPECTED_PDF: &[u8] = include_bytes!("test.pdf");
@@ -6,10 +13,58 @@ static EXPECTED_PDF: &[u8] = include_bytes!("test.pdf");
 #[test]
 fn create_epub() {
     let _epub = create_epub_from_json(JSON_BOOK).unwrap();
-    // epub files will have extensive use of uuids, especially for ids, so it's hard to do a direct comparison with
-    // expected output. Instead we just check validity.
     //std::fs::write("./tests/test.epub", &epub).unwrap();
     //bookbinder_common::epubcheck("./tests/test.epub".into()).unwrap();
+
+    // with its date fixed, the same book always gives the same epub
+    let json = JSON_BOOK.replacen('{', r#"{"publication_date": "2020-06-01","#, 1);
+    let epub = create_epub_from_json(&json).unwrap();
+    assert_eq!(epub, create_epub_from_json(&json).unwrap());
+
+    // a date which cannot be read is an error rather than being passed over
+    let json = JSON_BOOK.replacen('{', r#"{"publication_date": "1 June 2020","#, 1);
+    let error = create_epub_from_json(&json).unwrap_err();
+    assert!(error.to_string().contains("1 June 2020"));
+}
+
+#[test]
+fn create_ast() {
+    // a saved document should render just as the original source would
+    let json = create_ast_from_json(JSON_BOOK).unwrap().to_json().unwrap();
+    let document = BookDocument::from_json(&json).unwrap();
+    let _epub = create_epub_default(document.into_src()).unwrap();
+}
+
+#[test]
+fn create_statistics() {
+    let report = create_statistics_from_json(JSON_BOOK).unwrap();
+    let statistics = &report.statistics;
+    assert_eq!(
+        statistics.words,
+        statistics.frontmatter_words + statistics.mainmatter_words + statistics.backmatter_words
+    );
+    assert!(statistics.mainmatter_words > 0);
+    assert!(statistics.images > 0);
+    // smaller pages hold fewer words, so the same book fills more of them
+    let pages = report
+        .estimated_pages
+        .iter()
+        .map(|estimate| estimate.pages)
+        .collect::<Vec<_>>();
+    assert!(pages.first() >= pages.last());
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["words"], statistics.words);
+}
+
+#[test]
+fn create_lints() {
+    let lints = create_lints_from_json(JSON_BOOK).unwrap();
+    assert!(lints.iter().any(|l| l.rule == LintRule::StraightQuote));
+
+    let json = JSON_BOOK.replacen('{', r#"{"disabled_lint_rules": ["straight-quote"],"#, 1);
+    let lints = create_lints_from_json(&json).unwrap();
+    assert!(!lints.is_empty());
+    assert!(lints.iter().all(|l| l.rule != LintRule::StraightQuote));
 }
 
 #[test]
@@ -34,3 +89,39 @@ fn create_pdf() {
         assert_eq!(p1, p2);
     }
 }
+
+#[test]
+fn scene_break_options() {
+    let src = || {
+        BookSrcBuilder::new("A Book")
+            .add_mainmatter("Before.\n\n* * *\n\nAfter.")
+            .process()
+    };
+    let options = |scene_break: &str| {
+        let json = format!(r#"{{"scene_break": {}}}"#, scene_break);
+        serde_json::from_str::<UnifiedOptions>(&json).unwrap()
+    };
+
+    let glyph = LatexOptions::try_from(options(r#"{"Glyph": "❦ & ❦"}"#)).unwrap();
+    let tex = src().render_to_tex_with_options(glyph);
+    assert!(tex.contains(r"\renewcommand{\pfbreakdisplay}{❦ \& ❦}"));
+
+    let ornament = LatexOptions::try_from(options(r#"{"Ornament": "tests/image.png"}"#)).unwrap();
+    let tex = src().render_to_tex_with_options(ornament);
+    let expected = format!(
+        r"\renewcommand{{\pfbreakdisplay}}{{\includegraphics[height=1.5em]{{{}}}}}",
+        std::fs::canonicalize("tests/image.png").unwrap().display()
+    );
+    assert!(tex.contains(&expected));
+
+    // an ornament which cannot be found is an error rather than being quietly dropped
+    let missing = LatexOptions::try_from(options(r#"{"Ornament": "tests/missing.png"}"#));
+    assert!(missing.unwrap_err().contains("missing.png"));
+    let json = JSON_BOOK.replacen(
+        '{',
+        r#"{"scene_break": {"Ornament": "tests/missing.png"},"#,
+        1,
+    );
+    let error = create_pdf_from_json(&json).unwrap_err();
+    assert!(error.to_string().contains("missing.png"));
+}
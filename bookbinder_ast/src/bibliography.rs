@@ -0,0 +1,795 @@
+use crate::{BookEvent, NumberFormat, SemanticRole};
+use biblatex::ChunksExt;
+use extended_pulldown::{CitationItem, Event, Tag};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// A person or organisation named as an author or editor of a work
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name {
+    /// The family name, including any particle such as `van`, or the whole name of an organisation
+    pub family: String,
+    /// Any given names
+    pub given: Option<String>,
+}
+
+impl Name {
+    fn inverted(&self) -> String {
+        match self.given {
+            Some(ref given) => format!("{}, {}", self.family, given),
+            None => self.family.clone(),
+        }
+    }
+
+    fn natural(&self) -> String {
+        match self.given {
+            Some(ref given) => format!("{} {}", given, self.family),
+            None => self.family.clone(),
+        }
+    }
+}
+
+/// A work which can be cited
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reference {
+    /// The key by which the work is cited
+    pub key: String,
+    /// The authors of the work
+    pub authors: Vec<Name>,
+    /// The editors of the work, named in place of authors when there are none
+    pub editors: Vec<Name>,
+    /// The title of the work
+    pub title: Option<String>,
+    /// The title of the journal or book in which the work appears, if it is part of another
+    pub container_title: Option<String>,
+    /// The publisher of the work
+    pub publisher: Option<String>,
+    /// Where the work was published
+    pub place: Option<String>,
+    /// The year the work was published
+    pub year: Option<String>,
+    /// The volume of the work or the journal it appears in
+    pub volume: Option<String>,
+    /// The issue of the journal the work appears in
+    pub issue: Option<String>,
+    /// The pages the work occupies within another
+    pub pages: Option<String>,
+    /// The DOI of the work
+    pub doi: Option<String>,
+    /// A url at which the work can be found
+    pub url: Option<String>,
+}
+
+/// The works which a book can cite, read from BibTeX or CSL-JSON.
+///
+/// Only the works actually cited appear in the bibliography generated for a book.
+///
+/// ```
+/// use bookbinder_ast::Bibliography;
+///
+/// let bibtex = "@book{smith2004, author = {Smith, John and Mary Jones}, title = {A Book}, year = {2004}}";
+/// let bibliography = Bibliography::from_bibtex(bibtex).unwrap();
+/// let reference = bibliography.get("smith2004").unwrap();
+/// assert_eq!(reference.authors[1].family, "Jones");
+/// assert_eq!(reference.year.as_deref(), Some("2004"));
+///
+/// let json = r#"[{"id": "jones1999", "type": "article-journal", "title": "An Article",
+///     "author": [{"family": "Jones", "given": "Mary"}], "container-title": "A Journal",
+///     "volume": 12, "issued": {"date-parts": [[1999, 4]]}}]"#;
+/// let bibliography = Bibliography::from_csl_json(json).unwrap();
+/// let reference = bibliography.get("jones1999").unwrap();
+/// assert_eq!(reference.volume.as_deref(), Some("12"));
+/// assert_eq!(reference.year.as_deref(), Some("1999"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bibliography {
+    references: Vec<Reference>,
+}
+
+/// Errors possible while reading a bibliography
+#[derive(Debug)]
+pub enum BibliographyError {
+    /// The BibTeX could not be read
+    Bibtex(biblatex::ParseError),
+    /// The CSL-JSON could not be read
+    CslJson(serde_json::Error),
+}
+
+impl From<biblatex::ParseError> for BibliographyError {
+    fn from(src: biblatex::ParseError) -> Self {
+        BibliographyError::Bibtex(src)
+    }
+}
+
+impl From<serde_json::Error> for BibliographyError {
+    fn from(src: serde_json::Error) -> Self {
+        BibliographyError::CslJson(src)
+    }
+}
+
+impl std::fmt::Display for BibliographyError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            BibliographyError::Bibtex(e) => write!(formatter, "invalid BibTeX: {}", e),
+            BibliographyError::CslJson(e) => write!(formatter, "invalid CSL-JSON: {}", e),
+        }
+    }
+}
+
+impl Error for BibliographyError {}
+
+impl From<BibliographyError> for std::io::Error {
+    fn from(src: BibliographyError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+/// only the leading digits of a date, if there are any
+fn year_of(date: String) -> String {
+    let year = date
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>();
+    if year.is_empty() {
+        date
+    } else {
+        year
+    }
+}
+
+fn from_bibtex_entry(entry: &biblatex::Entry) -> Reference {
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| entry.get(name))
+            .map(|chunks| chunks.format_verbatim().replace("--", "–"))
+            .filter(|value| !value.is_empty())
+    };
+    let names = |people: Vec<biblatex::Person>| {
+        people
+            .into_iter()
+            .map(|person| Name {
+                family: if person.prefix.is_empty() {
+                    person.name
+                } else {
+                    format!("{} {}", person.prefix, person.name)
+                },
+                given: Some(person.given_name).filter(|given| !given.is_empty()),
+            })
+            .collect()
+    };
+    let editors = entry
+        .editors()
+        .ok()
+        .and_then(|editors| editors.into_iter().next())
+        .map(|(people, _)| names(people))
+        .unwrap_or_default();
+
+    Reference {
+        key: entry.key.clone(),
+        authors: names(entry.author().unwrap_or_default()),
+        editors,
+        title: field(&["title"]),
+        container_title: field(&["journaltitle", "journal", "booktitle"]),
+        publisher: field(&["publisher", "institution", "school"]),
+        place: field(&["location", "address"]),
+        year: field(&["year", "date"]).map(year_of),
+        volume: field(&["volume"]),
+        issue: field(&["number", "issue"]),
+        pages: field(&["pages"]),
+        doi: field(&["doi"]),
+        url: field(&["url"]),
+    }
+}
+
+/// A single item of CSL-JSON, whose id may be a string or a number
+#[derive(Deserialize)]
+struct CslItem {
+    id: Value,
+    #[serde(flatten)]
+    fields: Map<String, Value>,
+}
+
+fn csl_text(fields: &Map<String, Value>, key: &str) -> Option<String> {
+    match fields.get(key)? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn csl_names(fields: &Map<String, Value>, key: &str) -> Vec<Name> {
+    let names = match fields.get(key).and_then(Value::as_array) {
+        Some(names) => names,
+        None => return Vec::new(),
+    };
+    names
+        .iter()
+        .filter_map(Value::as_object)
+        .filter_map(|name| {
+            let family = csl_text(name, "family").or_else(|| csl_text(name, "literal"))?;
+            let family = match csl_text(name, "non-dropping-particle") {
+                Some(particle) => format!("{} {}", particle, family),
+                None => family,
+            };
+            Some(Name {
+                family,
+                given: csl_text(name, "given"),
+            })
+        })
+        .collect()
+}
+
+fn csl_year(fields: &Map<String, Value>) -> Option<String> {
+    let issued = fields.get("issued")?.as_object()?;
+    let first_part = issued
+        .get("date-parts")
+        .and_then(|parts| parts.get(0)?.get(0))
+        .map(|year| match year {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        });
+    first_part
+        .or_else(|| csl_text(issued, "raw").or_else(|| csl_text(issued, "literal")))
+        .map(year_of)
+}
+
+impl From<CslItem> for Reference {
+    fn from(item: CslItem) -> Self {
+        let fields = &item.fields;
+        Reference {
+            key: match item.id {
+                Value::String(ref s) => s.clone(),
+                ref other => other.to_string(),
+            },
+            authors: csl_names(fields, "author"),
+            editors: csl_names(fields, "editor"),
+            title: csl_text(fields, "title"),
+            container_title: csl_text(fields, "container-title"),
+            publisher: csl_text(fields, "publisher"),
+            place: csl_text(fields, "publisher-place"),
+            year: csl_year(fields),
+            volume: csl_text(fields, "volume"),
+            issue: csl_text(fields, "issue"),
+            pages: csl_text(fields, "page").map(|pages| pages.replace('-', "–")),
+            doi: csl_text(fields, "DOI"),
+            url: csl_text(fields, "URL"),
+        }
+    }
+}
+
+impl Bibliography {
+    /// Read a bibliography from BibTeX or BibLaTeX
+    pub fn from_bibtex(src: &str) -> Result<Self, BibliographyError> {
+        let parsed = biblatex::Bibliography::parse(src)?;
+        Ok(Bibliography {
+            references: parsed.iter().map(from_bibtex_entry).collect(),
+        })
+    }
+
+    /// Read a bibliography from CSL-JSON, as exported by reference managers such as Zotero
+    pub fn from_csl_json(src: &str) -> Result<Self, BibliographyError> {
+        let items: Vec<CslItem> = serde_json::from_str(src)?;
+        Ok(Bibliography {
+            references: items.into_iter().map(Reference::from).collect(),
+        })
+    }
+
+    /// Add a work, replacing any with the same key
+    pub fn insert(&mut self, reference: Reference) {
+        match self.references.iter_mut().find(|r| r.key == reference.key) {
+            Some(existing) => *existing = reference,
+            None => self.references.push(reference),
+        }
+    }
+
+    /// Add every work in `other`, replacing any with the same key
+    pub fn extend(&mut self, other: Bibliography) {
+        for reference in other.references.into_iter() {
+            self.insert(reference);
+        }
+    }
+
+    /// The work with the key `key`, if there is one
+    pub fn get(&self, key: &str) -> Option<&Reference> {
+        self.references.iter().find(|r| r.key == key)
+    }
+
+    /// The number of works in this bibliography
+    pub fn len(&self) -> usize {
+        self.references.len()
+    }
+
+    /// Whether this bibliography has no works in it
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+}
+
+/// How citations in the text refer to works, and how the generated bibliography lists them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CitationStyle {
+    /// By author and year, as `(Smith 2004, p. 4)`, with the bibliography sorted by author
+    #[default]
+    AuthorDate,
+    /// By number, as `[1, p. 4]`, with the bibliography numbered in the order works are first cited
+    Numeric,
+}
+
+/// A span of text in a bibliography entry
+enum Piece {
+    Plain(String),
+    Emphasised(String),
+}
+
+/// what closes `text` as a sentence, given that it may already end with punctuation
+fn closing(text: &str) -> &'static str {
+    if text.ends_with(['.', '?', '!']) {
+        " "
+    } else {
+        ". "
+    }
+}
+
+fn sentence(text: &str) -> String {
+    format!("{}{}", text, closing(text))
+}
+
+/// a list of names as they begin a bibliography entry
+fn list_names(names: &[Name], invert_first: bool) -> String {
+    let names = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            if i == 0 && invert_first {
+                name.inverted()
+            } else {
+                name.natural()
+            }
+        })
+        .collect::<Vec<_>>();
+    match names.len() {
+        0 => String::new(),
+        1 => names[0].clone(),
+        2 if !invert_first => format!("{} and {}", names[0], names[1]),
+        n => format!("{}, and {}", names[..n - 1].join(", "), names[n - 1]),
+    }
+}
+
+/// a list of names as they appear in a citation in the text
+fn short_names(names: &[Name]) -> Option<String> {
+    match names {
+        [] => None,
+        [only] => Some(only.family.clone()),
+        [first, second] => Some(format!("{} and {}", first.family, second.family)),
+        [first, ..] => Some(format!("{} et al.", first.family)),
+    }
+}
+
+fn short_contributors(reference: &Reference) -> String {
+    short_names(&reference.authors)
+        .or_else(|| short_names(&reference.editors))
+        .or_else(|| reference.title.clone())
+        .unwrap_or_else(|| reference.key.clone())
+}
+
+fn sort_name(reference: &Reference) -> String {
+    reference
+        .authors
+        .first()
+        .or_else(|| reference.editors.first())
+        .map(|name| name.inverted())
+        .or_else(|| reference.title.clone())
+        .unwrap_or_else(|| reference.key.clone())
+        .to_lowercase()
+}
+
+/// The works cited in a book, in the order they are first cited
+struct Citations<'b> {
+    style: CitationStyle,
+    cited: Vec<&'b Reference>,
+    /// the year to show for each work cited, distinguishing works by the same authors in the same year
+    years: HashMap<&'b str, String>,
+    unknown: Vec<String>,
+}
+
+impl<'b> Citations<'b> {
+    fn new<'k, I>(bibliography: &'b Bibliography, style: CitationStyle, keys: I) -> Self
+    where
+        I: Iterator<Item = &'k str>,
+    {
+        let mut cited: Vec<&Reference> = Vec::new();
+        let mut unknown = Vec::new();
+        for key in keys {
+            match bibliography.get(key) {
+                Some(reference) => {
+                    if !cited.iter().any(|r| r.key == key) {
+                        cited.push(reference);
+                    }
+                }
+                None => {
+                    if !unknown.iter().any(|k| k == key) {
+                        unknown.push(key.to_string());
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<(String, Option<&str>), Vec<&Reference>> = HashMap::new();
+        for reference in cited.iter() {
+            groups
+                .entry((short_contributors(reference), reference.year.as_deref()))
+                .or_default()
+                .push(reference);
+        }
+        let mut years = HashMap::new();
+        for ((_, year), mut references) in groups.into_iter() {
+            let year = year.unwrap_or("n.d.");
+            if references.len() == 1 {
+                years.insert(references[0].key.as_str(), year.to_string());
+            } else {
+                references.sort_by_key(|r| r.title.as_deref().map(str::to_lowercase));
+                // suffixes run from `a` to `z`, then `aa`, `ab` and so on
+                for (i, reference) in references.into_iter().enumerate() {
+                    let suffix = bookbinder_common::number_to_letters(i + 1).to_lowercase();
+                    years.insert(reference.key.as_str(), format!("{}{}", year, suffix));
+                }
+            }
+        }
+
+        if style == CitationStyle::AuthorDate {
+            cited.sort_by_cached_key(|r| (sort_name(r), years.get(r.key.as_str()).cloned()));
+        }
+
+        Citations {
+            style,
+            cited,
+            years,
+            unknown,
+        }
+    }
+
+    fn number(&self, key: &str) -> Option<usize> {
+        self.cited.iter().position(|r| r.key == key).map(|i| i + 1)
+    }
+
+    /// The year shown for `reference`, with any suffix distinguishing it
+    fn year<'s>(&'s self, reference: &'s Reference) -> &'s str {
+        self.years
+            .get(reference.key.as_str())
+            .map(String::as_str)
+            .or(reference.year.as_deref())
+            .unwrap_or("n.d.")
+    }
+
+    /// The text of a citation of `items`
+    fn cite(&self, items: &[CitationItem<'_>]) -> String {
+        let items = items
+            .iter()
+            .map(|item| {
+                let key = item.key.as_ref();
+                let cited = match self.style {
+                    CitationStyle::AuthorDate => self
+                        .cited
+                        .iter()
+                        .find(|r| r.key == key)
+                        .map(|r| format!("{} {}", short_contributors(r), self.year(r))),
+                    CitationStyle::Numeric => self.number(key).map(|n| n.to_string()),
+                };
+                let cited = cited.unwrap_or_else(|| format!("{}?", key));
+                match item.locator {
+                    Some(ref locator) => format!("{}, {}", cited, locator),
+                    None => cited,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        match self.style {
+            CitationStyle::AuthorDate => format!("({})", items),
+            CitationStyle::Numeric => format!("[{}]", items),
+        }
+    }
+
+    /// The parts of the bibliography entry for `reference`
+    fn entry(&self, reference: &Reference) -> Vec<Piece> {
+        let author_date = self.style == CitationStyle::AuthorDate;
+        let year = self.years.get(reference.key.as_str());
+        let mut pieces = Vec::new();
+
+        if let Some(n) = self.number(&reference.key).filter(|_| !author_date) {
+            pieces.push(Piece::Plain(format!("[{}] ", n)));
+        }
+        if !reference.authors.is_empty() {
+            pieces.push(Piece::Plain(sentence(&list_names(
+                &reference.authors,
+                author_date,
+            ))));
+        } else if !reference.editors.is_empty() {
+            let ed = if reference.editors.len() == 1 {
+                "ed"
+            } else {
+                "eds"
+            };
+            let editors = list_names(&reference.editors, author_date);
+            pieces.push(Piece::Plain(format!("{}, {}. ", editors, ed)));
+        }
+        if author_date {
+            if let Some(year) = year {
+                pieces.push(Piece::Plain(sentence(year)));
+            }
+        }
+
+        let mut year_given = author_date;
+        match (&reference.title, &reference.container_title) {
+            (title, Some(container)) => {
+                if let Some(title) = title {
+                    pieces.push(Piece::Plain(format!("“{}” ", sentence(title).trim_end())));
+                }
+                pieces.push(Piece::Emphasised(container.clone()));
+                let mut details = String::new();
+                if let Some(ref volume) = reference.volume {
+                    details.push(' ');
+                    details.push_str(volume);
+                }
+                if let Some(ref issue) = reference.issue {
+                    if author_date {
+                        details.push_str(&format!(" ({})", issue));
+                    } else {
+                        details.push_str(&format!(", no. {}", issue));
+                    }
+                }
+                if let (false, Some(year)) = (author_date, year) {
+                    details.push_str(&format!(" ({})", year));
+                    year_given = true;
+                }
+                if let Some(ref pages) = reference.pages {
+                    let separator = if details.is_empty() { "," } else { ":" };
+                    details.push_str(&format!("{} {}", separator, pages));
+                }
+                pieces.push(Piece::Plain(sentence(&details)));
+            }
+            (Some(title), None) => {
+                pieces.push(Piece::Emphasised(title.clone()));
+                pieces.push(Piece::Plain(closing(title).to_string()));
+            }
+            (None, None) => {}
+        }
+
+        let publication = match (&reference.place, &reference.publisher) {
+            (Some(place), Some(publisher)) => Some(format!("{}: {}", place, publisher)),
+            (place, publisher) => place.clone().or_else(|| publisher.clone()),
+        };
+        match (publication, year.filter(|_| !year_given)) {
+            (Some(publication), Some(year)) => pieces.push(Piece::Plain(sentence(&format!(
+                "{}, {}",
+                publication, year
+            )))),
+            (Some(publication), None) => pieces.push(Piece::Plain(sentence(&publication))),
+            (None, Some(year)) => pieces.push(Piece::Plain(sentence(year))),
+            (None, None) => {}
+        }
+
+        let link = match (&reference.doi, &reference.url) {
+            (Some(doi), _) => Some(format!("https://doi.org/{}", doi)),
+            (None, url) => url.clone(),
+        };
+        if let Some(link) = link {
+            pieces.push(Piece::Plain(sentence(&link)));
+        }
+
+        if let Some(Piece::Plain(last)) = pieces.last_mut() {
+            last.truncate(last.trim_end().len());
+        }
+        pieces
+    }
+
+    /// A bibliography of every work cited, or nothing if none were
+    fn section<'a>(&self) -> Vec<BookEvent<'a>> {
+        if self.cited.is_empty() {
+            return Vec::new();
+        }
+        let role = SemanticRole::Bibliography;
+        let mut events = vec![
+            BookEvent::BeginSemantic(role),
+            BookEvent::BeginDivisionHeader(false),
+            BookEvent::DivisionHeaderLabel {
+                text: role.get_label().map(Cow::from),
+                number: None,
+                number_format: NumberFormat::Arabic,
+            },
+            BookEvent::EndDivisionHeader(false),
+        ];
+        for reference in self.cited.iter() {
+            events.push(BookEvent::Event(Event::Start(Tag::UnindentedParagraph)));
+            for piece in self.entry(reference).into_iter() {
+                match piece {
+                    Piece::Plain(text) => events.push(BookEvent::Event(Event::Text(text.into()))),
+                    Piece::Emphasised(text) => {
+                        events.push(BookEvent::Event(Event::Start(Tag::Emphasis)));
+                        events.push(BookEvent::Event(Event::Text(text.into())));
+                        events.push(BookEvent::Event(Event::End(Tag::Emphasis)));
+                    }
+                }
+            }
+            events.push(BookEvent::Event(Event::End(Tag::UnindentedParagraph)));
+        }
+        events.push(BookEvent::EndSemantic(role));
+        events
+    }
+}
+
+/// Replace every citation in `sections` with its text in `style`, returning a bibliography
+/// of the works cited, if any were, and the keys of any cited works not in `bibliography`
+pub(crate) fn resolve_citations<'a>(
+    sections: &mut [&mut Vec<BookEvent<'a>>],
+    bibliography: &Bibliography,
+    style: CitationStyle,
+) -> (Vec<BookEvent<'a>>, Vec<String>) {
+    let keys = sections
+        .iter()
+        .flat_map(|section| section.iter())
+        .filter_map(|event| match event {
+            BookEvent::Event(Event::Citation(items)) => Some(items),
+            _ => None,
+        })
+        .flatten()
+        .map(|item| item.key.as_ref());
+    let citations = Citations::new(bibliography, style, keys);
+
+    for event in sections.iter_mut().flat_map(|section| section.iter_mut()) {
+        if let BookEvent::Event(Event::Citation(items)) = event {
+            *event = BookEvent::Event(Event::Text(citations.cite(items).into()));
+        }
+    }
+    (citations.section(), citations.unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(key: &str, family: &str, title: &str, year: &str) -> Reference {
+        Reference {
+            key: key.to_string(),
+            authors: vec![Name {
+                family: family.to_string(),
+                given: None,
+            }],
+            title: Some(title.to_string()),
+            year: Some(year.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn cite(citations: &Citations<'_>, key: &'static str) -> String {
+        citations.cite(&[CitationItem {
+            key: key.into(),
+            locator: None,
+        }])
+    }
+
+    #[test]
+    fn works_by_the_same_authors_in_the_same_year_are_distinguished() {
+        let mut undated = book("undated", "Jones", "Dogs", "");
+        undated.year = None;
+        let bibliography = Bibliography {
+            references: vec![
+                book("second", "Smith", "Bees", "2004"),
+                book("first", "Smith", "Ants", "2004"),
+                book("other", "Smith", "Cats", "2005"),
+                undated,
+            ],
+        };
+        let keys = ["second", "first", "other", "undated"];
+        let citations = Citations::new(
+            &bibliography,
+            CitationStyle::AuthorDate,
+            keys.iter().copied(),
+        );
+        assert_eq!(cite(&citations, "first"), "(Smith 2004a)");
+        assert_eq!(cite(&citations, "second"), "(Smith 2004b)");
+        assert_eq!(cite(&citations, "other"), "(Smith 2005)");
+        assert_eq!(cite(&citations, "undated"), "(Jones n.d.)");
+    }
+
+    #[test]
+    fn suffixes_continue_past_z() {
+        let references = (0..28)
+            .map(|i| {
+                book(
+                    &format!("key{}", i),
+                    "Smith",
+                    &format!("Work {:02}", i),
+                    "2004",
+                )
+            })
+            .collect::<Vec<_>>();
+        let bibliography = Bibliography { references };
+        let keys = (0..28).map(|i| format!("key{}", i)).collect::<Vec<_>>();
+        let citations = Citations::new(
+            &bibliography,
+            CitationStyle::AuthorDate,
+            keys.iter().map(String::as_str),
+        );
+        assert_eq!(citations.cited.len(), 28);
+        assert_eq!(cite(&citations, "key0"), "(Smith 2004a)");
+        assert_eq!(cite(&citations, "key25"), "(Smith 2004z)");
+        assert_eq!(cite(&citations, "key26"), "(Smith 2004aa)");
+        assert_eq!(cite(&citations, "key27"), "(Smith 2004ab)");
+    }
+
+    #[test]
+    fn missing_keys_are_reported_and_marked() {
+        let bibliography = Bibliography {
+            references: vec![book("smith2004", "Smith", "Ants", "2004")],
+        };
+        let keys = ["nowhere", "smith2004", "nowhere"];
+        for style in [CitationStyle::AuthorDate, CitationStyle::Numeric] {
+            let citations = Citations::new(&bibliography, style, keys.iter().copied());
+            assert_eq!(citations.unknown, ["nowhere"]);
+            let text = citations.cite(&[
+                CitationItem {
+                    key: "nowhere".into(),
+                    locator: Some("p. 4".into()),
+                },
+                CitationItem {
+                    key: "smith2004".into(),
+                    locator: None,
+                },
+            ]);
+            match style {
+                CitationStyle::AuthorDate => assert_eq!(text, "(nowhere?, p. 4; Smith 2004)"),
+                CitationStyle::Numeric => assert_eq!(text, "[nowhere?, p. 4; 1]"),
+            }
+        }
+    }
+
+    #[test]
+    fn bibliography_section_lists_the_works_cited() {
+        let bibliography = Bibliography {
+            references: vec![
+                book("smith2004", "Smith", "Ants", "2004"),
+                book("adams1990", "Adams", "Bees", "1990"),
+                book("unused", "Nobody", "Cats", "2000"),
+            ],
+        };
+        let keys = ["smith2004", "adams1990"];
+        let section = Citations::new(
+            &bibliography,
+            CitationStyle::AuthorDate,
+            keys.iter().copied(),
+        )
+        .section();
+        assert_eq!(
+            section.first(),
+            Some(&BookEvent::BeginSemantic(SemanticRole::Bibliography))
+        );
+        assert_eq!(
+            section.last(),
+            Some(&BookEvent::EndSemantic(SemanticRole::Bibliography))
+        );
+        let entries = section
+            .split(|e| *e == BookEvent::Event(Event::Start(Tag::UnindentedParagraph)))
+            .skip(1)
+            .map(|entry| {
+                entry
+                    .iter()
+                    .filter_map(|e| match e {
+                        BookEvent::Event(Event::Text(t)) => Some(t.as_ref()),
+                        _ => None,
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        // sorted by author rather than the order of citation
+        assert_eq!(entries, ["Adams. 1990. Bees.", "Smith. 2004. Ants."]);
+
+        let none_cited =
+            Citations::new(&bibliography, CitationStyle::AuthorDate, std::iter::empty());
+        assert!(none_cited.section().is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{BookEvent, BookSrc, Event, Tag};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// The labels of a book, each resolved to the text which a reference to it should show.
+///
+/// A label on a numbered division, such as a chapter, resolves to its number in the format
+/// in which it is displayed; on an unnumbered division or any other heading, to its title;
+/// and on a figure caption, to the number of the figure, counting every image in the book.
+/// A label placed in running text takes on the text of whatever preceded it,
+/// so that its page can be referred to.
+///
+/// ```
+/// use bookbinder_ast::BookSrcBuilder;
+///
+/// let src = BookSrcBuilder::new("A Book")
+///     .add_mainmatter("# Beginnings {#ch:start}\n\n![A map {#fig:map}](map.png)")
+///     .add_mainmatter("# Endings\n\nAs in Chapter {@ch:start}, see Figure {@fig:map} and {@nowhere}.")
+///     .process();
+/// let cross_references = src.cross_references();
+/// assert_eq!(cross_references.resolve("ch:start"), Some("1"));
+/// assert_eq!(cross_references.resolve("fig:map"), Some("1"));
+/// assert_eq!(cross_references.dangling(), ["nowhere"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrossReferences {
+    labels: HashMap<String, String>,
+    duplicates: Vec<String>,
+    dangling: Vec<String>,
+}
+
+impl CrossReferences {
+    /// The text a reference to `label` should show, if the label exists
+    pub fn resolve(&self, label: &str) -> Option<&str> {
+        self.labels.get(label).map(String::as_str)
+    }
+
+    /// Labels which are referred to but never defined, in the order they are first referred to
+    pub fn dangling(&self) -> &[String] {
+        &self.dangling
+    }
+
+    /// Labels defined more than once; references to these resolve to the first definition
+    pub fn duplicates(&self) -> &[String] {
+        &self.duplicates
+    }
+
+    fn define(&mut self, label: String, text: &str) {
+        match self.labels.entry(label) {
+            Entry::Occupied(entry) => {
+                if !self.duplicates.contains(entry.key()) {
+                    self.duplicates.push(entry.key().clone());
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(text.trim().to_string());
+            }
+        }
+    }
+}
+
+/// What a label currently being read will resolve to, once its end is reached
+#[derive(Debug)]
+enum Target {
+    DivisionHeader { number: Option<String> },
+    Heading,
+    Figure,
+}
+
+impl BookSrc<'_> {
+    /// Resolve the labels of this book, and find any references to labels which do not exist.
+    ///
+    /// Since the text of a label can depend on the format of headers,
+    /// this should be called after any changes to them.
+    pub fn cross_references(&self) -> CrossReferences {
+        let mut cross_references = CrossReferences::default();
+        let mut references = Vec::new();
+        // the text a label placed in running text would resolve to
+        let mut current = String::new();
+        let mut target = None;
+        let mut target_text = String::new();
+        let mut pending = Vec::new();
+        let mut figures = 0;
+
+        for event in self.contents.iter() {
+            match event {
+                BookEvent::BeginDivisionHeader(_) => {
+                    target = Some(Target::DivisionHeader { number: None });
+                    target_text.clear();
+                }
+                BookEvent::DivisionHeaderLabel {
+                    number: Some(n),
+                    number_format,
+                    ..
+                } => {
+                    if let Some(Target::DivisionHeader { ref mut number }) = target {
+                        *number = Some(number_format.format(*n));
+                    }
+                }
+                BookEvent::Event(Event::Start(Tag::Heading(_))) if target.is_none() => {
+                    target = Some(Target::Heading);
+                    target_text.clear();
+                }
+                BookEvent::Event(Event::Start(Tag::Image(_, _, _))) => {
+                    figures += 1;
+                    target = Some(Target::Figure);
+                }
+                BookEvent::Event(Event::Text(text)) | BookEvent::Event(Event::Code(text))
+                    if target.is_some() =>
+                {
+                    target_text.push_str(text);
+                }
+                BookEvent::Event(Event::Label(label)) if target.is_some() => {
+                    pending.push(label.to_string());
+                }
+                BookEvent::Event(Event::Label(label)) => {
+                    cross_references.define(label.to_string(), &current);
+                }
+                BookEvent::Event(Event::Reference(label))
+                | BookEvent::Event(Event::PageReference(label)) => {
+                    references.push(label.to_string());
+                }
+                BookEvent::EndDivisionHeader(_)
+                | BookEvent::Event(Event::End(Tag::Heading(_)))
+                | BookEvent::Event(Event::End(Tag::Image(_, _, _))) => {
+                    current = match target.take() {
+                        Some(Target::DivisionHeader {
+                            number: Some(number),
+                        }) => number,
+                        Some(Target::Figure) => figures.to_string(),
+                        _ => std::mem::take(&mut target_text),
+                    };
+                    for label in pending.drain(..) {
+                        cross_references.define(label, &current);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for label in references.into_iter() {
+            if !cross_references.labels.contains_key(&label)
+                && !cross_references.dangling.contains(&label)
+            {
+                cross_references.dangling.push(label);
+            }
+        }
+        cross_references
+    }
+}
@@ -0,0 +1,486 @@
+use extended_pulldown::{attribute, malformed_labels, Event, Tag};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Where something was found in the markdown source of a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The file the markdown was read from; `None` if it was added as text
+    pub file: Option<PathBuf>,
+    /// The line, counting from 1
+    pub line: usize,
+}
+
+impl SourceLocation {
+    /// the first line of `file`
+    pub(crate) fn start_of(file: Option<PathBuf>) -> Self {
+        SourceLocation { file, line: 1 }
+    }
+
+    /// the location of the byte `offset` in `text`, which begins at this location
+    pub(crate) fn advanced_by(&self, text: &str, offset: usize) -> Self {
+        let lines = text[..offset].matches('\n').count();
+        SourceLocation {
+            file: self.file.clone(),
+            line: self.line + lines,
+        }
+    }
+}
+
+/// Where each stretch of some markdown was read from,
+/// as the byte offsets at which each begins and the location there
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SourceMap {
+    starts: Vec<(usize, SourceLocation)>,
+}
+
+impl SourceMap {
+    /// note that from byte `offset` on, the markdown was read from `location`
+    pub(crate) fn push(&mut self, offset: usize, location: SourceLocation) {
+        self.starts.push((offset, location));
+    }
+
+    /// the location of the byte `offset` in `text`
+    fn locate(&self, text: &str, offset: usize) -> SourceLocation {
+        match self.starts.iter().rev().find(|(start, _)| *start <= offset) {
+            Some((start, location)) => location.advanced_by(&text[*start..], offset - start),
+            None => SourceLocation::start_of(None),
+        }
+    }
+
+    /// this map as it applies to `text[offset..]`
+    pub(crate) fn starting_at(self, text: &str, offset: usize) -> Self {
+        let mut starts = vec![(0, self.locate(text, offset))];
+        starts.extend(
+            self.starts
+                .into_iter()
+                .filter(|(start, _)| *start > offset)
+                .map(|(start, location)| (start - offset, location)),
+        );
+        SourceMap { starts }
+    }
+}
+
+impl From<SourceLocation> for SourceMap {
+    fn from(src: SourceLocation) -> Self {
+        SourceMap {
+            starts: vec![(0, src)],
+        }
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        match self.file {
+            Some(ref file) => write!(formatter, "{}:{}", file.display(), self.line),
+            None => write!(formatter, "line {}", self.line),
+        }
+    }
+}
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The book can be rendered, but probably not as intended
+    Warning,
+    /// The book cannot be rendered correctly
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Severity::Warning => formatter.write_str("warning"),
+            Severity::Error => formatter.write_str("error"),
+        }
+    }
+}
+
+/// The kinds of problem which can be found while processing a book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// An image which could not be found, either as given or in the directory of any source file
+    MissingImage(PathBuf),
+    /// A footnote with no text, or a reference to a footnote which is never defined
+    EmptyFootnote(String),
+    /// A footnote label defined more than once in the same source
+    DuplicateFootnoteLabel(String),
+    /// A chapter with a heading and nothing else
+    EmptyChapter(String),
+    /// A span with a class which is not recognised, and so has no effect
+    UnknownSpanClass(String),
+    /// A heading which a heading mapping marks as a section, but which is not within any chapter
+    SectionOutsideChapter {
+        /// the text of the heading
+        heading: String,
+        /// its markdown level
+        level: u32,
+    },
+    /// A heading at a level which a heading mapping does not account for
+    UnmappedHeading {
+        /// the text of the heading
+        heading: String,
+        /// its markdown level
+        level: u32,
+    },
+    /// A reference to a label which is never defined
+    DanglingReference(String),
+    /// A label defined more than once
+    DuplicateLabel(String),
+    /// A label or reference whose label contains characters a label cannot,
+    /// and which is therefore left as plain text
+    MalformedLabel(String),
+    /// A citation of a work which is not in the bibliography
+    UnknownCitation(String),
+    /// A glossary term defined more than once, of which only the first definition is kept
+    DuplicateGlossaryTerm(String),
+    /// A publication date which is not of the form `YYYY-MM-DD`, and so cannot date the book
+    InvalidPublicationDate(String),
+}
+
+impl DiagnosticKind {
+    /// How serious this kind of problem is
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::MissingImage(_)
+            | DiagnosticKind::DanglingReference(_)
+            | DiagnosticKind::UnknownCitation(_)
+            | DiagnosticKind::InvalidPublicationDate(_) => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            DiagnosticKind::MissingImage(path) => {
+                write!(formatter, "image {:?} could not be found", path.display())
+            }
+            DiagnosticKind::EmptyFootnote(label) => {
+                write!(formatter, "footnote [^{}] has no text", label)
+            }
+            DiagnosticKind::DuplicateFootnoteLabel(label) => {
+                write!(formatter, "footnote [^{}] is defined more than once", label)
+            }
+            DiagnosticKind::EmptyChapter(title) => {
+                write!(formatter, "chapter {:?} has no text", title)
+            }
+            DiagnosticKind::UnknownSpanClass(class) => {
+                write!(formatter, "span class {:?} is not recognised", class)
+            }
+            DiagnosticKind::SectionOutsideChapter { heading, level } => write!(
+                formatter,
+                "{:?} (level {}) is a section, but is not within any chapter",
+                heading, level
+            ),
+            DiagnosticKind::UnmappedHeading { heading, level } => write!(
+                formatter,
+                "{:?} (level {}) does not correspond to any division in the heading mapping",
+                heading, level
+            ),
+            DiagnosticKind::DanglingReference(label) => {
+                write!(
+                    formatter,
+                    "label {:?} is referred to but never defined",
+                    label
+                )
+            }
+            DiagnosticKind::DuplicateLabel(label) => {
+                write!(formatter, "label {:?} is defined more than once", label)
+            }
+            DiagnosticKind::MalformedLabel(label) => write!(
+                formatter,
+                "{:?} is not a valid label, which may contain only letters, numbers and `:`, `.`, `-` or `_`",
+                label
+            ),
+            DiagnosticKind::UnknownCitation(key) => {
+                write!(formatter, "@{} is cited but not in the bibliography", key)
+            }
+            DiagnosticKind::DuplicateGlossaryTerm(term) => write!(
+                formatter,
+                "glossary term {:?} is defined more than once",
+                term
+            ),
+            DiagnosticKind::InvalidPublicationDate(date) => write!(
+                formatter,
+                "publication date {:?} is not of the form YYYY-MM-DD",
+                date
+            ),
+        }
+    }
+}
+
+/// A problem found while processing a book, with where it was found if that is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// What the problem is
+    pub kind: DiagnosticKind,
+    /// How serious it is
+    pub severity: Severity,
+    /// Where in the source it was found
+    pub location: Option<SourceLocation>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(kind: DiagnosticKind, location: Option<SourceLocation>) -> Self {
+        Diagnostic {
+            severity: kind.severity(),
+            kind,
+            location,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        if let Some(ref location) = self.location {
+            write!(formatter, "{}: ", location)?;
+        }
+        write!(formatter, "{}: {}", self.severity, self.kind)
+    }
+}
+
+/// The diagnostics of a book which could not be processed without errors,
+/// or without warnings if these were denied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticsError {
+    /// every diagnostic found, including any which would not have caused failure alone
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Display for DiagnosticsError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            formatter,
+            "the book could not be processed cleanly ({} problems found)",
+            self.diagnostics.len()
+        )?;
+        for diagnostic in self.diagnostics.iter() {
+            write!(formatter, "\n- {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DiagnosticsError {}
+
+impl From<DiagnosticsError> for std::io::Error {
+    fn from(src: DiagnosticsError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+/// Where the front matter and headings of some markdown were found,
+/// so that problems with the divisions they open can be located
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DivisionLocations {
+    /// the front matter the markdown began with, if any
+    pub(crate) front_matter: Option<SourceLocation>,
+    /// each heading, in order
+    pub(crate) headings: Vec<SourceLocation>,
+}
+
+/// Where the images, labels, references and citations of the markdown added to a book were found,
+/// so that problems discovered only once the book is processed can still be located
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceIndex {
+    images: Vec<(PathBuf, SourceLocation)>,
+    labels: Vec<(String, SourceLocation)>,
+    references: Vec<(String, SourceLocation)>,
+    citations: Vec<(String, SourceLocation)>,
+}
+
+impl SourceIndex {
+    /// the locations of every use of the image at `path`
+    pub(crate) fn locate_image(&self, path: &Path) -> Vec<SourceLocation> {
+        self.images
+            .iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, location)| location.clone())
+            .collect()
+    }
+
+    /// the locations of every reference to `label`
+    pub(crate) fn locate_references(&self, label: &str) -> Vec<SourceLocation> {
+        self.references
+            .iter()
+            .filter(|(l, _)| l == label)
+            .map(|(_, location)| location.clone())
+            .collect()
+    }
+
+    /// the locations of every citation of `key`
+    pub(crate) fn locate_citations(&self, key: &str) -> Vec<SourceLocation> {
+        self.citations
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, location)| location.clone())
+            .collect()
+    }
+
+    /// give a location to a diagnostic about a duplicated label, if the label can be found
+    pub(crate) fn locate(&self, diagnostic: &mut Diagnostic) {
+        if diagnostic.location.is_some() {
+            return;
+        }
+        if let DiagnosticKind::DuplicateLabel(ref label) = diagnostic.kind {
+            // the first definition is the one kept, so point to the second
+            diagnostic.location = self
+                .labels
+                .iter()
+                .filter(|(l, _)| l == label)
+                .nth(1)
+                .map(|(_, location)| location.clone());
+        }
+    }
+}
+
+/// note that the footnote being defined, if any, has something in it
+fn fill_definition(open_definition: &mut Option<(String, SourceLocation, bool)>) {
+    if let Some((_, _, ref mut has_text)) = open_definition {
+        *has_text = true;
+    }
+}
+
+/// A check of markdown for problems, made event by event as the markdown is parsed,
+/// which notes in a `SourceIndex` where its images, labels, references and citations are
+/// so that problems found only once the book is processed can be located too
+pub(crate) struct SourceCheck<'i> {
+    sources: SourceMap,
+    index: &'i mut SourceIndex,
+    diagnostics: Vec<Diagnostic>,
+    locations: DivisionLocations,
+    references: Vec<(String, SourceLocation)>,
+    definitions: HashSet<String>,
+    // the footnote being defined, where, and whether it has any text yet
+    open_definition: Option<(String, SourceLocation, bool)>,
+    in_code_block: bool,
+}
+
+impl<'i> SourceCheck<'i> {
+    /// Check markdown read from where `sources` says,
+    /// after any front matter found at `front_matter`
+    pub(crate) fn new(
+        sources: SourceMap,
+        front_matter: Option<SourceLocation>,
+        index: &'i mut SourceIndex,
+    ) -> Self {
+        SourceCheck {
+            sources,
+            index,
+            diagnostics: Vec::new(),
+            locations: DivisionLocations {
+                front_matter,
+                headings: Vec::new(),
+            },
+            references: Vec::new(),
+            definitions: HashSet::new(),
+            open_definition: None,
+            in_code_block: false,
+        }
+    }
+
+    /// Check `event`, parsed from the byte `range` of the markdown `text`
+    pub(crate) fn inspect(&mut self, text: &str, event: &Event<'_>, range: Range<usize>) {
+        let sources = &self.sources;
+        let location = || sources.locate(text, range.start);
+        match event {
+            Event::FootnoteReference(label) => {
+                let location = location();
+                self.references.push((label.to_string(), location));
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                let location = location();
+                let label = label.to_string();
+                if !self.definitions.insert(label.clone()) {
+                    self.diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::DuplicateFootnoteLabel(label.clone()),
+                        Some(location.clone()),
+                    ));
+                }
+                self.open_definition = Some((label, location, false));
+            }
+            Event::End(Tag::FootnoteDefinition(_)) => {
+                if let Some((label, location, false)) = self.open_definition.take() {
+                    self.diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::EmptyFootnote(label),
+                        Some(location),
+                    ));
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => self.in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => self.in_code_block = false,
+            Event::Text(t) | Event::Code(t) if !t.trim().is_empty() => {
+                fill_definition(&mut self.open_definition);
+                // what the parser left as text, but is written as a label, cannot be one
+                if let (Event::Text(_), false) = (event, self.in_code_block) {
+                    for label in malformed_labels(t) {
+                        let location = location();
+                        self.diagnostics.push(Diagnostic::new(
+                            DiagnosticKind::MalformedLabel(label.to_string()),
+                            Some(location),
+                        ));
+                    }
+                }
+            }
+            Event::Label(label) => {
+                let location = location();
+                self.index.labels.push((label.to_string(), location));
+            }
+            Event::Reference(label) | Event::PageReference(label) => {
+                let location = location();
+                self.index.references.push((label.to_string(), location));
+            }
+            Event::Citation(items) => {
+                fill_definition(&mut self.open_definition);
+                let location = location();
+                for item in items.iter() {
+                    self.index
+                        .citations
+                        .push((item.key.to_string(), location.clone()));
+                }
+            }
+            Event::Start(Tag::Image(_, dest, _)) => {
+                fill_definition(&mut self.open_definition);
+                let location = location();
+                self.index
+                    .images
+                    .push((PathBuf::from(dest.as_ref()), location));
+            }
+            Event::Start(Tag::Heading(_)) => {
+                let location = location();
+                self.locations.headings.push(location);
+            }
+            // the parser gives a span a meaning of its own if it recognises its class,
+            // and leaves it as html otherwise
+            Event::Html(html) if html.starts_with("<span") => {
+                if let (None, Some(class)) = (attribute(html, "style"), attribute(html, "class")) {
+                    let location = location();
+                    self.diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::UnknownSpanClass(class.to_string()),
+                        Some(location),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Finish the check, returning the problems found
+    /// and where the front matter and headings of the markdown were
+    pub(crate) fn finish(mut self) -> (Vec<Diagnostic>, DivisionLocations) {
+        for (label, location) in self.references.into_iter() {
+            if !self.definitions.contains(&label) {
+                self.diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::EmptyFootnote(label),
+                    Some(location),
+                ));
+            }
+        }
+        (self.diagnostics, self.locations)
+    }
+}
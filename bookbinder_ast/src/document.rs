@@ -0,0 +1,138 @@
+use crate::BookSrc;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// The version of the serialized format written by `BookDocument`.
+///
+/// This is incremented whenever a change to `BookSrc` or any of the types it contains
+/// means that previously serialized documents can no longer be read.
+/// That includes adding a variant to any enum it contains, such as `BookEvent` or `Tag`,
+/// since the binary format records variants by their position.
+pub const DOCUMENT_FORMAT_VERSION: u32 = 18;
+
+/// A versioned wrapper around a processed `BookSrc`,
+/// allowing it to be saved, inspected or diffed as json (or stored more compactly as binary)
+/// and later read back and rendered without going back to the markdown source.
+///
+/// ```
+/// use bookbinder_ast::{BookDocument, BookSrcBuilder};
+///
+/// let src = BookSrcBuilder::new("A Book")
+///     .add_mainmatter("# Hello World\n\nText goes here...")
+///     .process();
+/// let json = BookDocument::new(src).to_json().unwrap();
+/// let restored = BookDocument::from_json(&json).unwrap().into_src();
+/// assert_eq!(restored.metadata.title, "A Book");
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookDocument<'a> {
+    /// the format version this document was written with
+    pub version: u32,
+    /// the processed book source
+    pub src: BookSrc<'a>,
+}
+
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: u32,
+}
+
+/// Errors possible while reading or writing a `BookDocument`
+#[derive(Debug)]
+pub enum DocumentError {
+    /// The document was written with an unsupported format version
+    UnsupportedVersion(u32),
+    /// There was an error reading or writing json
+    Json(serde_json::Error),
+    /// There was an error reading or writing the binary format
+    Binary(bincode::Error),
+}
+
+impl From<serde_json::Error> for DocumentError {
+    fn from(src: serde_json::Error) -> Self {
+        DocumentError::Json(src)
+    }
+}
+
+impl From<bincode::Error> for DocumentError {
+    fn from(src: bincode::Error) -> Self {
+        DocumentError::Binary(src)
+    }
+}
+
+impl std::fmt::Display for DocumentError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        formatter.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for DocumentError {}
+
+const fn check_version(version: u32) -> Result<(), DocumentError> {
+    if version == DOCUMENT_FORMAT_VERSION {
+        Ok(())
+    } else {
+        Err(DocumentError::UnsupportedVersion(version))
+    }
+}
+
+impl<'a> BookDocument<'a> {
+    /// Wrap a processed `BookSrc` with the current format version
+    pub fn new(src: BookSrc<'a>) -> Self {
+        BookDocument {
+            version: DOCUMENT_FORMAT_VERSION,
+            src,
+        }
+    }
+
+    /// Unwrap the `BookSrc` contained in this document, ready to be rendered
+    pub fn into_src(self) -> BookSrc<'a> {
+        self.src
+    }
+
+    /// Serialize this document to a json string
+    pub fn to_json(&self) -> Result<String, DocumentError> {
+        let json = serde_json::to_string(self)?;
+        Ok(json)
+    }
+
+    /// Serialize this document to a pretty-printed json string,
+    /// which is more suitable for inspecting or diffing
+    pub fn to_json_pretty(&self) -> Result<String, DocumentError> {
+        let json = serde_json::to_string_pretty(self)?;
+        Ok(json)
+    }
+
+    /// Serialize this document to a compact binary representation
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DocumentError> {
+        let bytes = bincode::serialize(self)?;
+        Ok(bytes)
+    }
+}
+
+impl BookDocument<'static> {
+    /// Deserialize a document from json produced by `to_json` or `to_json_pretty`;
+    /// returns an error if the document was written with a different format version
+    pub fn from_json(json: &str) -> Result<Self, DocumentError> {
+        let VersionOnly { version } = serde_json::from_str(json)?;
+        check_version(version)?;
+        let document = serde_json::from_str(json)?;
+        Ok(document)
+    }
+
+    /// Deserialize a document from bytes produced by `to_bytes`;
+    /// returns an error if the document was written with a different format version
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DocumentError> {
+        // the version is always the first field serialized
+        let version: u32 = bincode::deserialize(bytes)?;
+        check_version(version)?;
+        let document = bincode::deserialize(bytes)?;
+        Ok(document)
+    }
+}
+
+impl<'a> From<BookSrc<'a>> for BookDocument<'a> {
+    fn from(src: BookSrc<'a>) -> Self {
+        BookDocument::new(src)
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{BookEvent, HeadingMapping, NumberFormat, SemanticRole};
+use serde::de::{Error as DeError, IntoDeserializer};
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::error::Error;
+
+/// Settings for a single division of a book, such as a chapter or foreword,
+/// given in a block of yaml (fenced by `---`) or toml (fenced by `+++`)
+/// at the very top of a markdown file.
+///
+/// Front matter is read from files added through methods like `add_mainmatter_from_file`
+/// or `add_foreword_from_file`; text added directly is never taken to contain it.
+/// A file which simply begins with a thematic break is read as markdown:
+/// a block is only taken as front matter if it is closed and holds keys and values,
+/// or if its first line sets a field such as `title`.
+///
+/// ```
+/// use bookbinder_ast::ParseHelper;
+///
+/// let text = "---\ntitle: The Beginning\nshort_title: Beginning\nauthors: A.N. Author\nunnumbered: true\n---\nText goes here...";
+/// let (front_matter, body) = text.split_front_matter().unwrap();
+/// let front_matter = front_matter.unwrap();
+/// assert_eq!(front_matter.title.as_deref(), Some("The Beginning"));
+/// assert_eq!(front_matter.authors, vec!["A.N. Author"]);
+/// assert!(front_matter.unnumbered);
+/// assert_eq!(body, "Text goes here...");
+///
+/// let text = "+++\nrole = \"prologue\"\nlabel = \"Before\"\n+++\n# Long Ago";
+/// let (front_matter, _) = text.split_front_matter().unwrap();
+/// assert_eq!(front_matter.unwrap().label.as_deref(), Some("Before"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatter {
+    /// The title of the division, in markdown.
+    /// If this is set, the body of the file should not begin with a heading for the division;
+    /// otherwise, the title is taken from that heading as usual
+    pub title: Option<String>,
+    /// A briefer version of the title, used in the table of contents
+    pub short_title: Option<String>,
+    /// A label to use instead of the default for the division's role,
+    /// e.g. `Letter` to give `Letter 3` rather than `Chapter 3`
+    pub label: Option<String>,
+    /// The authors of this division; either a single name or a list
+    #[serde(alias = "author", deserialize_with = "deserialize_names")]
+    pub authors: Vec<String>,
+    /// The semantic role of the division, such as `chapter`, `part` or `prologue`.
+    /// A division keeps its place in the book whatever its role;
+    /// in mainmatter, any role other than `chapter` or `part` is left unnumbered
+    #[serde(deserialize_with = "deserialize_role")]
+    pub role: Option<SemanticRole>,
+    /// Whether the division should be left unnumbered, without a label or number,
+    /// and without disturbing the numbering of those around it
+    pub unnumbered: bool,
+    /// For mainmatter only, which heading levels in the rest of the file
+    /// mark which divisions
+    pub heading_mapping: Option<HeadingMapping>,
+}
+
+/// Errors possible while reading front matter
+#[derive(Debug)]
+pub enum FrontMatterError {
+    /// A block of front matter was opened but never closed
+    Unterminated,
+    /// The yaml front matter could not be read
+    Yaml(serde_yaml::Error),
+    /// The toml front matter could not be read
+    Toml(toml::de::Error),
+}
+
+impl From<serde_yaml::Error> for FrontMatterError {
+    fn from(src: serde_yaml::Error) -> Self {
+        FrontMatterError::Yaml(src)
+    }
+}
+
+impl From<toml::de::Error> for FrontMatterError {
+    fn from(src: toml::de::Error) -> Self {
+        FrontMatterError::Toml(src)
+    }
+}
+
+impl std::fmt::Display for FrontMatterError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            FrontMatterError::Unterminated => {
+                formatter.write_str("front matter was opened but never closed")
+            }
+            FrontMatterError::Yaml(e) => write!(formatter, "invalid yaml front matter: {}", e),
+            FrontMatterError::Toml(e) => write!(formatter, "invalid toml front matter: {}", e),
+        }
+    }
+}
+
+impl Error for FrontMatterError {}
+
+impl From<FrontMatterError> for std::io::Error {
+    fn from(src: FrontMatterError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+/// accept either a single name or a list of names
+fn deserialize_names<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Names {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Names::deserialize(deserializer)? {
+        Names::One(name) => vec![name],
+        Names::Many(names) => names,
+    })
+}
+
+/// accept a role in any case, with words optionally separated,
+/// so that `prologue` or `series page` are as good as `Prologue` or `SeriesPage`
+fn deserialize_role<'de, D>(deserializer: D) -> Result<Option<SemanticRole>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = match Option::<String>::deserialize(deserializer)? {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let variant = name
+        .split(|c: char| c == '-' || c == '_' || c.is_whitespace())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+    let deserializer: serde::de::value::StrDeserializer<'_, D::Error> =
+        variant.as_str().into_deserializer();
+    SemanticRole::deserialize(deserializer)
+        .map(Some)
+        .map_err(|_| D::Error::custom(format!("unknown role {:?}", name)))
+}
+
+#[derive(Clone, Copy)]
+enum FrontMatterFormat {
+    Yaml,
+    Toml,
+}
+
+/// the keys which can begin a block of front matter
+const FRONT_MATTER_KEYS: &[&str] = &[
+    "title",
+    "short_title",
+    "label",
+    "authors",
+    "author",
+    "role",
+    "unnumbered",
+    "heading_mapping",
+];
+
+/// whether `line`, the first after an opening fence, sets a field of front matter,
+/// showing that the block was meant as front matter rather than beginning with a thematic break
+fn is_front_matter_entry(line: &str, format: FrontMatterFormat) -> bool {
+    let separator = match format {
+        FrontMatterFormat::Yaml => ':',
+        FrontMatterFormat::Toml => '=',
+    };
+    line.split_once(separator)
+        .is_some_and(|(key, _)| FRONT_MATTER_KEYS.contains(&key.trim()))
+}
+
+/// Parse `block` as front matter, if it is a mapping of keys to values.
+/// Anything else is taken to be markdown which merely begins with a thematic break,
+/// unless `intended` shows that it was meant as front matter, in which case any error is returned
+fn parse_block(
+    block: &str,
+    format: FrontMatterFormat,
+    intended: bool,
+) -> Result<Option<FrontMatter>, FrontMatterError> {
+    let front_matter = match format {
+        FrontMatterFormat::Yaml => match serde_yaml::from_str(block) {
+            Ok(value @ serde_yaml::Value::Mapping(_)) => serde_yaml::from_value(value)?,
+            Ok(_) => return Ok(None),
+            Err(e) if intended => return Err(e.into()),
+            Err(_) => return Ok(None),
+        },
+        FrontMatterFormat::Toml => match toml::from_str(block) {
+            Ok(value @ toml::Value::Table(_)) => value.try_into()?,
+            Ok(_) => return Ok(None),
+            Err(e) if intended => return Err(e.into()),
+            Err(_) => return Ok(None),
+        },
+    };
+    Ok(Some(front_matter))
+}
+
+/// Find and parse any front matter at the start of `text`,
+/// returning it along with the remaining body.
+///
+/// A block is only taken as front matter if it is closed and holds a mapping of keys to values,
+/// so that a file may begin with a thematic break;
+/// but one whose first line sets a field of front matter, such as `title:`, is always taken as such,
+/// and an error returned if it cannot be read.
+pub(crate) fn split_front_matter(
+    text: &str,
+) -> Result<Option<(FrontMatter, &str)>, FrontMatterError> {
+    let first_line_end = match text.find('\n') {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let fence = text[..first_line_end].trim_end();
+    let format = match fence {
+        "---" => FrontMatterFormat::Yaml,
+        "+++" => FrontMatterFormat::Toml,
+        _ => return Ok(None),
+    };
+    let rest = &text[first_line_end + 1..];
+    let intended = rest
+        .lines()
+        .next()
+        .is_some_and(|line| is_front_matter_entry(line, format));
+
+    let mut block_len = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == fence {
+            let block = &rest[..block_len];
+            let body = &rest[block_len + line.len()..];
+            return Ok(parse_block(block, format, intended)?.map(|f| (f, body)));
+        }
+        block_len += line.len();
+    }
+    if intended {
+        Err(FrontMatterError::Unterminated)
+    } else {
+        Ok(None)
+    }
+}
+
+impl FrontMatter {
+    /// the label for the division, if one was set or follows from a changed role
+    pub(crate) fn get_label(&self) -> Option<Cow<'static, str>> {
+        match (&self.label, self.role) {
+            (Some(label), _) => Some(label.clone().into()),
+            (None, Some(role)) => role.get_label().map(|l| l.into()),
+            (None, None) => None,
+        }
+    }
+
+    /// events to add to the header of the division for its authors and short title
+    pub(crate) fn header_events<'a>(&self) -> Vec<BookEvent<'a>> {
+        let mut events = Vec::with_capacity(2);
+        if !self.authors.is_empty() {
+            let authors = self.authors.iter().cloned().map(Cow::Owned).collect();
+            events.push(BookEvent::DivisionAuthors(authors));
+        }
+        if let Some(ref short_title) = self.short_title {
+            events.push(BookEvent::DivisionShortTitle(short_title.clone().into()));
+        }
+        events
+    }
+
+    /// Apply these settings, other than the title, to the events of a single division
+    /// which has already been created
+    pub(crate) fn apply_to(&self, events: &mut Vec<BookEvent<'_>>) {
+        if let Some(role) = self.role {
+            if let Some(BookEvent::BeginSemantic(r)) = events.first_mut() {
+                *r = role;
+            }
+            if let Some(BookEvent::EndSemantic(r)) = events.last_mut() {
+                *r = role;
+            }
+        }
+
+        let header_end = match events
+            .iter()
+            .position(|e| matches!(e, BookEvent::EndDivisionHeader(_)))
+        {
+            Some(i) => i,
+            None => return,
+        };
+
+        let mut has_label = false;
+        for event in events[..=header_end].iter_mut() {
+            match event {
+                BookEvent::BeginDivisionHeader(is_starred)
+                | BookEvent::EndDivisionHeader(is_starred) => {
+                    *is_starred |= self.unnumbered;
+                }
+                BookEvent::DivisionHeaderLabel { text, number, .. } => {
+                    has_label = true;
+                    if let Some(label) = self.get_label() {
+                        *text = Some(label);
+                    }
+                    if self.unnumbered {
+                        *number = None;
+                    }
+                }
+                BookEvent::DivisionAuthors(_) if !self.authors.is_empty() => {
+                    *event = BookEvent::Null;
+                }
+                _ => {}
+            }
+        }
+
+        let mut additions = self.header_events();
+        if !has_label {
+            if let Some(label) = self.get_label() {
+                additions.push(BookEvent::DivisionHeaderLabel {
+                    text: Some(label),
+                    number: None,
+                    number_format: NumberFormat::Arabic,
+                });
+            }
+        }
+        events.splice(header_end..header_end, additions);
+    }
+}
@@ -0,0 +1,541 @@
+use crate::diagnostics::SourceLocation;
+use crate::index::compare_terms;
+use crate::{BookEvent, NumberFormat, ParseHelper, SemanticRole};
+use extended_pulldown::{Event, Tag};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+/// A term defined in a glossary
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlossaryEntry {
+    /// The term itself
+    pub term: String,
+    /// Its definition, in markdown
+    pub definition: String,
+    /// Whether the term is an abbreviation, defined by what it stands for;
+    /// its first use in the text is expanded
+    pub abbreviation: bool,
+}
+
+/// The terms a book defines, read from a markdown definition list or a data file.
+///
+/// Only the first term with any particular spelling is kept, regardless of case;
+/// any later ones are recorded as duplicates.
+///
+/// In markdown, a term is given on a line of its own, followed by its definition
+/// on a line beginning `: `; an abbreviation is given as `*[ABBR]: what it stands for`.
+///
+/// ```
+/// use bookbinder_ast::Glossary;
+///
+/// let src = "Hobbit\n: A small person, fond of comfort.\n\n*[NASA]: National Aeronautics and Space Administration\n\nhobbit\n: A duplicate.";
+/// let glossary = Glossary::from_markdown(src);
+/// assert_eq!(glossary.len(), 2);
+/// assert_eq!(glossary.get("hobbit").unwrap().definition, "A small person, fond of comfort.");
+/// assert!(glossary.get("NASA").unwrap().abbreviation);
+/// assert_eq!(glossary.duplicates(), ["hobbit"]);
+///
+/// let toml = "[terms]\nHobbit = \"A small person\"\n\n[abbreviations]\nNASA = \"National Aeronautics and Space Administration\"";
+/// let glossary = Glossary::from_toml(toml).unwrap();
+/// assert_eq!(glossary.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Glossary {
+    entries: Vec<GlossaryEntry>,
+    // where each entry was defined, if that is known
+    locations: Vec<Option<SourceLocation>>,
+    duplicates: Vec<String>,
+    // where each duplicate was defined, if that is known
+    duplicate_locations: Vec<Option<SourceLocation>>,
+}
+
+/// Errors possible while reading a glossary from a data file
+#[derive(Debug)]
+pub enum GlossaryError {
+    /// The toml could not be read
+    Toml(toml::de::Error),
+    /// The yaml could not be read
+    Yaml(serde_yaml::Error),
+    /// The json could not be read
+    Json(serde_json::Error),
+}
+
+impl From<toml::de::Error> for GlossaryError {
+    fn from(src: toml::de::Error) -> Self {
+        GlossaryError::Toml(src)
+    }
+}
+
+impl From<serde_yaml::Error> for GlossaryError {
+    fn from(src: serde_yaml::Error) -> Self {
+        GlossaryError::Yaml(src)
+    }
+}
+
+impl From<serde_json::Error> for GlossaryError {
+    fn from(src: serde_json::Error) -> Self {
+        GlossaryError::Json(src)
+    }
+}
+
+impl std::fmt::Display for GlossaryError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            GlossaryError::Toml(e) => write!(formatter, "invalid toml glossary: {}", e),
+            GlossaryError::Yaml(e) => write!(formatter, "invalid yaml glossary: {}", e),
+            GlossaryError::Json(e) => write!(formatter, "invalid json glossary: {}", e),
+        }
+    }
+}
+
+impl Error for GlossaryError {}
+
+impl From<GlossaryError> for std::io::Error {
+    fn from(src: GlossaryError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+/// a glossary as given in a data file
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GlossaryData {
+    terms: BTreeMap<String, String>,
+    abbreviations: BTreeMap<String, String>,
+}
+
+impl From<GlossaryData> for Glossary {
+    fn from(data: GlossaryData) -> Self {
+        let mut glossary = Glossary::default();
+        for (term, definition) in data.terms.into_iter() {
+            glossary.insert(GlossaryEntry {
+                term,
+                definition,
+                abbreviation: false,
+            });
+        }
+        for (term, definition) in data.abbreviations.into_iter() {
+            glossary.insert(GlossaryEntry {
+                term,
+                definition,
+                abbreviation: true,
+            });
+        }
+        glossary
+    }
+}
+
+/// the abbreviation and its expansion given by a line like `*[ABBR]: expansion`
+fn abbreviation_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("*[")?;
+    let close = rest.find("]:")?;
+    let abbreviation = rest[..close].trim();
+    let expansion = rest[close + 2..].trim();
+    if abbreviation.is_empty() || expansion.is_empty() {
+        None
+    } else {
+        Some((abbreviation, expansion))
+    }
+}
+
+impl Glossary {
+    /// Read a glossary from a markdown definition list and abbreviation definitions
+    pub fn from_markdown(src: &str) -> Self {
+        let mut glossary = Glossary::default();
+        // each entry, with the line on which its term is given
+        let mut entries: Vec<(GlossaryEntry, usize)> = Vec::new();
+        let mut term: Option<(&str, usize)> = None;
+        // whether indented lines continue the last definition
+        let mut in_definition = false;
+
+        for (i, line) in src.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some((abbreviation, expansion)) = abbreviation_line(trimmed) {
+                let entry = GlossaryEntry {
+                    term: abbreviation.to_string(),
+                    definition: expansion.to_string(),
+                    abbreviation: true,
+                };
+                entries.push((entry, i + 1));
+                term = None;
+                in_definition = false;
+            } else if let Some(definition) = line.strip_prefix(':') {
+                let definition = definition.trim();
+                match term.take() {
+                    Some((term, line)) => {
+                        let entry = GlossaryEntry {
+                            term: term.to_string(),
+                            definition: definition.to_string(),
+                            abbreviation: false,
+                        };
+                        entries.push((entry, line));
+                    }
+                    // a second definition of the same term is joined to the first
+                    None if in_definition => {
+                        let (last, _) = entries.last_mut().unwrap();
+                        last.definition.push(' ');
+                        last.definition.push_str(definition);
+                    }
+                    None => continue,
+                }
+                in_definition = true;
+            } else if in_definition && line.starts_with(char::is_whitespace) {
+                let (last, _) = entries.last_mut().unwrap();
+                last.definition.push(' ');
+                last.definition.push_str(trimmed);
+            } else {
+                term = Some((trimmed, i + 1));
+                in_definition = false;
+            }
+        }
+
+        for (entry, line) in entries.into_iter() {
+            glossary.insert_at(entry, Some(SourceLocation { file: None, line }));
+        }
+        glossary
+    }
+
+    /// Read a glossary from toml, with a table of `terms` and a table of `abbreviations`,
+    /// each mapping what is defined to its definition
+    pub fn from_toml(src: &str) -> Result<Self, GlossaryError> {
+        let data: GlossaryData = toml::from_str(src)?;
+        Ok(data.into())
+    }
+
+    /// Read a glossary from yaml, laid out as for `from_toml`
+    pub fn from_yaml(src: &str) -> Result<Self, GlossaryError> {
+        let data: GlossaryData = serde_yaml::from_str(src)?;
+        Ok(data.into())
+    }
+
+    /// Read a glossary from json, laid out as for `from_toml`
+    pub fn from_json(src: &str) -> Result<Self, GlossaryError> {
+        let data: GlossaryData = serde_json::from_str(src)?;
+        Ok(data.into())
+    }
+
+    /// Add an entry, unless a term with the same spelling is already defined,
+    /// in which case it is recorded as a duplicate
+    pub fn insert(&mut self, entry: GlossaryEntry) {
+        self.insert_at(entry, None);
+    }
+
+    /// add an entry defined at `location`, as `insert` does
+    fn insert_at(&mut self, entry: GlossaryEntry, location: Option<SourceLocation>) {
+        if self.get(&entry.term).is_some() {
+            self.duplicates.push(entry.term);
+            self.duplicate_locations.push(location);
+        } else {
+            self.entries.push(entry);
+            self.locations.push(location);
+        }
+    }
+
+    /// Add every entry in `other`, along with its duplicates
+    pub fn extend(&mut self, other: Glossary) {
+        self.duplicates.extend(other.duplicates);
+        self.duplicate_locations.extend(other.duplicate_locations);
+        for (entry, location) in other.entries.into_iter().zip(other.locations) {
+            self.insert_at(entry, location);
+        }
+    }
+
+    /// note that this glossary was read from the file at `path`;
+    /// entries whose line is not known are located at its start
+    pub(crate) fn set_file(&mut self, path: &Path) {
+        for location in self
+            .locations
+            .iter_mut()
+            .chain(self.duplicate_locations.iter_mut())
+        {
+            let line = location.as_ref().map_or(1, |location| location.line);
+            *location = Some(SourceLocation {
+                file: Some(path.to_path_buf()),
+                line,
+            });
+        }
+    }
+
+    /// Each duplicate, along with where it was defined if that is known
+    pub(crate) fn located_duplicates(
+        &self,
+    ) -> impl Iterator<Item = (&String, Option<&SourceLocation>)> {
+        self.duplicates
+            .iter()
+            .zip(self.duplicate_locations.iter().map(Option::as_ref))
+    }
+
+    /// The entry for `term`, regardless of case
+    pub fn get(&self, term: &str) -> Option<&GlossaryEntry> {
+        let term = term.to_lowercase();
+        self.entries.iter().find(|e| e.term.to_lowercase() == term)
+    }
+
+    /// Terms which were defined again after their first definition, in the order they were found
+    pub fn duplicates(&self) -> &[String] {
+        &self.duplicates
+    }
+
+    /// The number of terms defined
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no terms are defined
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// a key for `term` made up only of characters safe in both an xhtml id and a LaTeX label
+fn slug(term: &str) -> String {
+    let mut slug = String::with_capacity(term.len());
+    for c in term.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        slug.push_str("term");
+    }
+    slug
+}
+
+fn is_word_character(c: Option<char>) -> bool {
+    matches!(c, Some(c) if c.is_alphanumeric())
+}
+
+/// The byte range of the first whole-word use of `term` in `text`;
+/// abbreviations must match exactly, while other terms may differ in case
+fn find_term(text: &str, term: &str, exact: bool) -> Option<(usize, usize)> {
+    let same = |a: char, b: char| {
+        if exact {
+            a == b
+        } else {
+            a == b || a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+    let mut previous = None;
+    for (start, _) in text.char_indices() {
+        if !is_word_character(previous) {
+            let mut haystack = text[start..].char_indices();
+            let mut end = None;
+            let mut matched = true;
+            for t in term.chars() {
+                match haystack.next() {
+                    Some((i, c)) if same(c, t) => end = Some(start + i + c.len_utf8()),
+                    _ => {
+                        matched = false;
+                        break;
+                    }
+                }
+            }
+            if let (true, Some(end)) = (matched, end) {
+                if !is_word_character(text[end..].chars().next()) {
+                    return Some((start, end));
+                }
+            }
+        }
+        previous = text[start..].chars().next();
+    }
+    None
+}
+
+/// The entries of a glossary, sorted alphabetically regardless of case, with their keys
+/// and whether each has been used yet
+struct Terms<'g> {
+    entries: Vec<(&'g GlossaryEntry, String, bool)>,
+}
+
+impl<'g> Terms<'g> {
+    fn new(glossary: &'g Glossary) -> Self {
+        let mut entries: Vec<&GlossaryEntry> = glossary.entries.iter().collect();
+        entries.sort_by(|a, b| compare_terms(&a.term, &b.term));
+        let mut keys: Vec<String> = Vec::with_capacity(entries.len());
+        for entry in entries.iter() {
+            let base = slug(&entry.term);
+            let mut key = base.clone();
+            let mut n = 1;
+            while keys.contains(&key) {
+                n += 1;
+                key = format!("{}-{}", base, n);
+            }
+            keys.push(key);
+        }
+        Terms {
+            entries: entries
+                .into_iter()
+                .zip(keys)
+                .map(|(entry, key)| (entry, key, false))
+                .collect(),
+        }
+    }
+
+    /// Split `text` at the first uses of any terms not yet used
+    fn mark<'a>(&mut self, text: &str, marked: &mut Vec<BookEvent<'a>>) {
+        let mut rest = text;
+        loop {
+            // the earliest use of any term, preferring the longest at the same place
+            let first = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, _, used))| !used)
+                .filter_map(|(i, (entry, _, _))| {
+                    find_term(rest, &entry.term, entry.abbreviation).map(|range| (range, i))
+                })
+                .min_by_key(|&((start, end), _)| (start, std::cmp::Reverse(end)));
+            let ((start, end), i) = match first {
+                Some(first) => first,
+                None => break,
+            };
+            let (entry, key, used) = &mut self.entries[i];
+            *used = true;
+            if start > 0 {
+                marked.push(BookEvent::Event(Event::Text(
+                    rest[..start].to_string().into(),
+                )));
+            }
+            let text = if entry.abbreviation {
+                format!("{} ({})", entry.definition, &rest[start..end])
+            } else {
+                rest[start..end].to_string()
+            };
+            let tag = Tag::GlossaryReference(key.clone().into());
+            marked.push(BookEvent::Event(Event::Start(tag.clone())));
+            marked.push(BookEvent::Event(Event::Text(text.into())));
+            marked.push(BookEvent::Event(Event::End(tag)));
+            rest = &rest[end..];
+        }
+        if !rest.is_empty() {
+            marked.push(BookEvent::Event(Event::Text(rest.to_string().into())));
+        }
+    }
+
+    /// A glossary listing every entry, or nothing if there are none
+    fn section<'a>(&self) -> Vec<BookEvent<'a>> {
+        if self.entries.is_empty() {
+            return Vec::new();
+        }
+        let role = SemanticRole::Glossary;
+        let mut events = vec![
+            BookEvent::BeginSemantic(role),
+            BookEvent::BeginDivisionHeader(false),
+            BookEvent::DivisionHeaderLabel {
+                text: role.get_label().map(Cow::from),
+                number: None,
+                number_format: NumberFormat::Arabic,
+            },
+            BookEvent::EndDivisionHeader(false),
+            BookEvent::Event(Event::Start(Tag::Glossary)),
+        ];
+        for (entry, key, _) in self.entries.iter() {
+            let term = Tag::GlossaryTerm(key.clone().into());
+            events.push(BookEvent::Event(Event::Start(term.clone())));
+            events.push(BookEvent::Event(Event::Text(entry.term.clone().into())));
+            events.push(BookEvent::Event(Event::End(term)));
+            events.push(BookEvent::Event(Event::Start(Tag::GlossaryDefinition)));
+            events.extend(entry.definition.clone().parse_inline());
+            events.push(BookEvent::Event(Event::End(Tag::GlossaryDefinition)));
+        }
+        events.push(BookEvent::Event(Event::End(Tag::Glossary)));
+        events.push(BookEvent::EndSemantic(role));
+        events
+    }
+}
+
+/// whether terms used within a section with `role` are left unmarked
+fn is_unmarked_role(role: SemanticRole) -> bool {
+    matches!(
+        role,
+        SemanticRole::Halftitle
+            | SemanticRole::Titlepage
+            | SemanticRole::Copyrightpage
+            | SemanticRole::Glossary
+            | SemanticRole::Bibliography
+            | SemanticRole::Index
+            | SemanticRole::DramatisPersonae
+    )
+}
+
+/// whether terms used within an element begun or ended by `tag` are left unmarked
+fn is_unmarked_tag(tag: &Tag<'_>) -> bool {
+    matches!(
+        tag,
+        Tag::Heading(_)
+            | Tag::Link(..)
+            | Tag::Image(..)
+            | Tag::CodeBlock(_)
+            | Tag::GlossaryReference(_)
+    )
+}
+
+/// Mark the first use in `sections` of every term in `glossary`, in reading order,
+/// expanding abbreviations as they are first used, and return a glossary listing every term
+pub(crate) fn mark_glossary_terms<'a>(
+    sections: &mut [&mut Vec<BookEvent<'a>>],
+    glossary: &Glossary,
+) -> Vec<BookEvent<'a>> {
+    if glossary.is_empty() {
+        return Vec::new();
+    }
+    let mut terms = Terms::new(glossary);
+
+    for section in sections.iter_mut() {
+        // how deeply nested we are within things whose text is left unmarked
+        let mut unmarked = 0usize;
+        let events = std::mem::take(*section);
+        let mut marked = Vec::with_capacity(events.len());
+        for event in events.into_iter() {
+            match event {
+                BookEvent::BeginSemantic(role) if is_unmarked_role(role) => unmarked += 1,
+                BookEvent::EndSemantic(role) if is_unmarked_role(role) => {
+                    unmarked = unmarked.saturating_sub(1)
+                }
+                BookEvent::BeginDivisionHeader(_) | BookEvent::BeginTitlePage => unmarked += 1,
+                BookEvent::EndDivisionHeader(_) | BookEvent::EndTitlePage => {
+                    unmarked = unmarked.saturating_sub(1)
+                }
+                BookEvent::Event(Event::Start(ref tag)) if is_unmarked_tag(tag) => unmarked += 1,
+                BookEvent::Event(Event::End(ref tag)) if is_unmarked_tag(tag) => {
+                    unmarked = unmarked.saturating_sub(1)
+                }
+                BookEvent::Event(Event::Text(ref text)) if unmarked == 0 => {
+                    terms.mark(text, &mut marked);
+                    continue;
+                }
+                _ => {}
+            }
+            marked.push(event);
+        }
+        **section = marked;
+    }
+    terms.section()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_are_found_as_whole_words() {
+        assert_eq!(
+            find_term("The hobbits' hobbit", "Hobbit", false),
+            Some((13, 19))
+        );
+        assert_eq!(find_term("the Hobbit", "hobbit", true), None);
+        assert_eq!(find_term("café society", "Café", false), Some((0, 5)));
+        assert_eq!(slug("Ent-wife (pl. Ent-wives)"), "ent-wife-pl-ent-wives");
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+/// Which markdown heading levels in mainmatter mark which divisions of a book.
+///
+/// Without a mapping, the structure of mainmatter is inferred from the heading levels it contains:
+/// if it has both `#` and `##` headings, `#` is taken to mark parts and `##` chapters;
+/// otherwise `#` marks chapters.
+/// This means that adding a single `##` heading to one chapter can change the structure of the whole book;
+/// setting a mapping fixes the structure instead.
+///
+/// Headings at a level which the mapping does not account for are treated as sections,
+/// and a warning is given when the book is processed.
+///
+/// ```
+/// use bookbinder_ast::{BookEvent, BookSrcBuilder, HeadingMapping, SemanticRole};
+///
+/// let src = BookSrcBuilder::new("A Book")
+///     .set_heading_mapping(HeadingMapping::default())
+///     .add_mainmatter("# A Chapter\n\n## A Section\n\nText goes here...")
+///     .process();
+/// assert!(!src.contents.contains(&BookEvent::BeginSemantic(SemanticRole::Part)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadingMapping {
+    /// the heading level marking parts, if the book is divided into parts
+    pub part: Option<u32>,
+    /// the heading level marking chapters
+    pub chapter: u32,
+    /// the heading level marking sections within a chapter;
+    /// if this is not set, it is the level below `chapter`
+    pub section: Option<u32>,
+    /// the heading level marking subsections;
+    /// if this is not set, it is the level below `section`
+    pub subsection: Option<u32>,
+}
+
+impl Default for HeadingMapping {
+    /// `#` marks chapters, `##` sections and `###` subsections; there are no parts
+    fn default() -> Self {
+        HeadingMapping {
+            part: None,
+            chapter: 1,
+            section: None,
+            subsection: None,
+        }
+    }
+}
+
+/// What a markdown heading in mainmatter represents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeadingDivision {
+    Part,
+    Chapter,
+    /// a heading within a chapter, to be output at the given level
+    Subheading(u32),
+}
+
+impl HeadingMapping {
+    /// `#` marks parts, `##` chapters, `###` sections and `####` subsections
+    pub const fn with_parts() -> Self {
+        HeadingMapping {
+            part: Some(1),
+            chapter: 2,
+            section: None,
+            subsection: None,
+        }
+    }
+
+    fn section_level(&self) -> u32 {
+        self.section.unwrap_or(self.chapter + 1)
+    }
+
+    fn subsection_level(&self) -> u32 {
+        self.subsection.unwrap_or_else(|| self.section_level() + 1)
+    }
+
+    /// What a heading of markdown `level` represents under this mapping, if anything.
+    ///
+    /// Sections and anything below them are output at the same levels
+    /// as they would be in a book without parts whose structure was inferred,
+    /// so that setting the default mapping does not change the output.
+    pub(crate) fn classify(&self, level: u32) -> Option<HeadingDivision> {
+        let subsection = self.subsection_level();
+        if self.part == Some(level) {
+            Some(HeadingDivision::Part)
+        } else if level == self.chapter {
+            Some(HeadingDivision::Chapter)
+        } else if level == self.section_level() {
+            Some(HeadingDivision::Subheading(3))
+        } else if level == subsection {
+            Some(HeadingDivision::Subheading(4))
+        } else if level > subsection {
+            Some(HeadingDivision::Subheading(level - subsection + 4))
+        } else {
+            None
+        }
+    }
+}
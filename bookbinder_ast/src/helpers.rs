@@ -1,9 +1,9 @@
 //! Helpers for common ast manipulations required across different crates,
 //! such as collating multi-event elements.
-use crate::{BookEvent, EventHelper, NumberFormat, SemanticRole};
+use crate::{BookEvent, ContributorList, EventHelper, NumberFormat, SemanticRole};
 use bookbinder_common::MimeTypeHelper;
 use extended_pulldown::CowStr;
-use extended_pulldown::{Event, Tag};
+use extended_pulldown::{Event, IndexEntry, Tag};
 use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::path::PathBuf;
@@ -155,7 +155,7 @@ pub struct CollatedHeader<'a, T> {
     /// Text of any label, e.g. `Chapter`
     pub label_text: Option<Cow<'a, str>>,
     /// Number of any label, e.g. 1 for the first chapter
-    pub label_number: Option<u8>,
+    pub label_number: Option<usize>,
     /// Format in which to display the number, such as
     /// arabic, roman etc
     pub label_number_format: Option<NumberFormat>,
@@ -163,8 +163,14 @@ pub struct CollatedHeader<'a, T> {
     pub text: Option<Vec<Event<'a>>>,
     /// Names of any authors
     pub authors: Option<Vec<Cow<'a, str>>>,
+    /// A briefer version of the title, for use in a table of contents
+    pub short_title: Option<Cow<'a, str>>,
     /// Whether the header should be treated as starred
     pub is_starred: bool,
+    /// Any labels given to the header, so that it can be referred to
+    pub labels: Vec<CowStr<'a>>,
+    /// Any terms marked for the index within the header
+    pub index_entries: Vec<IndexEntry<'a>>,
 }
 
 type LabelAndTitle<'a> = (Option<Cow<'a, str>>, Option<Cow<'a, str>>);
@@ -194,6 +200,11 @@ impl<'a, T: MarkerHelper> CollatedHeader<'a, T> {
         }
     }
 
+    /// Escaped version of any short title
+    pub fn get_short_title(&'a self) -> Option<Cow<'a, str>> {
+        self.short_title.as_deref().map(Self::escape)
+    }
+
     /// Escaped version of the label text
     pub fn get_label_text(&'a self) -> Option<Cow<'a, str>> {
         self.label_text.as_deref().map(Self::escape)
@@ -202,26 +213,9 @@ impl<'a, T: MarkerHelper> CollatedHeader<'a, T> {
     /// Get a representation of the label number in the appropriate format,
     /// if any.
     pub fn get_label_number(&self) -> Option<Cow<'a, str>> {
-        match (self.label_number, self.label_number_format) {
-            (None, _) => None,
-            (Some(n), Some(NumberFormat::Arabic)) | (Some(n), None) => {
-                Some(format!("{}", n).into())
-            }
-            (Some(n), Some(NumberFormat::Roman)) => {
-                let r = bookbinder_common::number_to_roman(n);
-                Some(r.into())
-            }
-            (Some(n), Some(NumberFormat::Words)) => {
-                let w = bookbinder_common::number_to_words(n);
-                Some(w.into())
-            }
-            (Some(n), Some(NumberFormat::Letter)) => {
-                let l = bookbinder_common::number_to_letter(n)
-                    .map(|n| n.to_string())
-                    .unwrap_or(format!("{}", n));
-                Some(l.into())
-            }
-        }
+        let number = self.label_number?;
+        let format = self.label_number_format.clone().unwrap_or_default();
+        Some(format.format(number).into())
     }
 
     /// write the text of the title to a string,
@@ -291,9 +285,17 @@ impl<'a, T: MarkerHelper> CollatedHeader<'a, T> {
             None
         }
     }
-}
 
-type ContributorList<'a> = Vec<(Option<&'a str>, Vec<Cow<'a, str>>)>;
+    /// get the names of all authors as a single string, in the format `A, B and C`;
+    /// all names will be escaped
+    pub fn get_joined_authors(&'a self) -> Option<Cow<'a, str>> {
+        match self.get_authors() {
+            Some((first, Some(second))) => Some(format!("{} and {}", first, second).into()),
+            Some((first, None)) => Some(first),
+            None => None,
+        }
+    }
+}
 
 /// A collation of events within a titlepage
 #[derive(Debug)]
@@ -387,6 +389,9 @@ where
         let mut label_number = None;
         let mut label_number_format = None;
         let mut authors = None;
+        let mut short_title = None;
+        let mut labels = Vec::new();
+        let mut index_entries = Vec::new();
 
         let mut events = Vec::new();
         for event in self {
@@ -413,6 +418,9 @@ where
                         authors = Some(a);
                     }
                 }
+                BookEvent::DivisionShortTitle(t) => short_title = Some(t),
+                BookEvent::Event(Event::Label(label)) => labels.push(label),
+                BookEvent::Event(Event::IndexEntry(entry)) => index_entries.push(entry),
                 BookEvent::Event(e) => text.push(e),
                 _ => {}
             }
@@ -427,7 +435,10 @@ where
             label_number,
             label_number_format,
             authors,
+            short_title,
             is_starred,
+            labels,
+            index_entries,
         }
     }
 
@@ -454,10 +465,6 @@ where
                     in_subtitle = false;
                 }
                 BookEvent::TitlePageContributors(v) => {
-                    let v = v
-                        .into_iter()
-                        .map(|(role, names)| (role.get_label(), names))
-                        .collect();
                     contributors = Some(v);
                 }
                 BookEvent::Event(e) => {
@@ -0,0 +1,371 @@
+use crate::diagnostics::SourceMap;
+use crate::front_matter::split_front_matter;
+use crate::SourceLocation;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// A problem found while replacing the include directives in a markdown file
+#[derive(Debug)]
+pub enum IncludeError {
+    /// An included file could not be read
+    Io {
+        /// the file, as named by the directive which included it
+        path: PathBuf,
+        /// why it could not be read
+        error: std::io::Error,
+    },
+    /// A file includes itself, directly or through the files it includes;
+    /// these are the files involved, beginning and ending with the same one
+    Cycle(Vec<PathBuf>),
+}
+
+impl std::fmt::Display for IncludeError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            IncludeError::Io { path, error } => {
+                write!(
+                    formatter,
+                    "could not include {:?}: {}",
+                    path.display(),
+                    error
+                )
+            }
+            IncludeError::Cycle(paths) => {
+                let paths = paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>();
+                write!(
+                    formatter,
+                    "files include each other: {}",
+                    paths.join(" -> ")
+                )
+            }
+        }
+    }
+}
+
+impl Error for IncludeError {}
+
+impl From<IncludeError> for std::io::Error {
+    fn from(src: IncludeError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+/// Markdown in which every include directive has been replaced by the file it names
+#[derive(Debug)]
+pub(crate) struct Expanded {
+    pub(crate) text: String,
+    /// where each stretch of `text` was read from
+    pub(crate) sources: SourceMap,
+    /// every file included, directly or not
+    pub(crate) files: Vec<PathBuf>,
+}
+
+/// the file named by an include directive (`{<path}` or `{<path|+1}`) which fills `line`,
+/// with how many levels its headings are to be shifted
+fn directive(line: &str) -> Option<(&str, i32)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let inner = trimmed.trim_end().strip_prefix("{<")?.strip_suffix('}')?;
+    let mut parts = inner.splitn(2, '|');
+    let path = parts.next()?.trim();
+    let shift = match parts.next() {
+        Some(shift) => shift.trim().parse().ok()?,
+        None => 0,
+    };
+    if path.is_empty() {
+        None
+    } else {
+        Some((path, shift))
+    }
+}
+
+/// the fence which opens a fenced code block, if `line` begins one
+fn fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    if length >= 3 {
+        Some(&trimmed[..length])
+    } else {
+        None
+    }
+}
+
+/// `line` with its level changed by `shift`, if it is an atx heading
+fn shift_heading(line: &str, shift: i32) -> Option<String> {
+    let trimmed = line.trim_start_matches(' ');
+    let indent = line.len() - trimmed.len();
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    let rest = &trimmed[level..];
+    if indent > 3
+        || level == 0
+        || level > 6
+        || !(rest.is_empty() || rest.starts_with(char::is_whitespace))
+    {
+        return None;
+    }
+    let level = (level as i32 + shift).clamp(1, 6) as usize;
+    Some(format!("{}{}{}", &line[..indent], "#".repeat(level), rest))
+}
+
+/// the level of the setext heading which `line` underlines, if it could be such an underline
+fn setext_underline(line: &str) -> Option<i32> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let trimmed = trimmed.trim_end();
+    let level = match trimmed.chars().next()? {
+        '=' => 1,
+        '-' => 2,
+        _ => return None,
+    };
+    if trimmed.chars().all(|c| trimmed.starts_with(c)) {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+/// whether `line` begins or continues a paragraph rather than some other block;
+/// this errs on the side of caution, so that only the headings of plain paragraphs are shifted
+fn is_paragraph_line(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 || trimmed.trim().is_empty() {
+        return false;
+    }
+    let after_number = trimmed.trim_start_matches(|c: char| c.is_ascii_digit());
+    let is_list_item = |rest: &str| rest.is_empty() || rest.starts_with(char::is_whitespace);
+    let is_thematic_break = ['-', '*', '_'].iter().any(|&marker| {
+        let marks = trimmed.trim().chars().filter(|c| *c == marker).count();
+        marks >= 3 && trimmed.trim().chars().all(|c| c == marker || c == ' ')
+    });
+    !(is_thematic_break
+        || trimmed.starts_with(['>', '#', '<', '|'])
+        || trimmed.starts_with("[^")
+        || ['-', '*', '+']
+            .iter()
+            .any(|&marker| trimmed.strip_prefix(marker).is_some_and(is_list_item))
+        || (after_number.len() < trimmed.len()
+            && (after_number.strip_prefix('.').is_some_and(is_list_item)
+                || after_number.strip_prefix(')').is_some_and(is_list_item))))
+}
+
+/// Replace each include directive in `text`, the markdown of the file at `path`,
+/// with the markdown of the file it names, relative to `path`;
+/// included files may include others in turn, but never themselves
+pub(crate) fn expand_includes(text: &str, path: &Path) -> Result<Expanded, IncludeError> {
+    let canonical = path.canonicalize().map_err(|error| IncludeError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let mut expanded = Expanded {
+        text: String::with_capacity(text.len()),
+        sources: SourceMap::default(),
+        files: Vec::new(),
+    };
+    let start = SourceLocation::start_of(Some(path.to_path_buf()));
+    expand(text, path, start, 0, &mut vec![canonical], &mut expanded)?;
+    Ok(expanded)
+}
+
+/// append `text`, which begins at `start` in the file at `path`, to `expanded`,
+/// shifting its headings by `shift` and expanding any directives within it;
+/// `stack` holds the canonical paths of the files being expanded, innermost last
+fn expand(
+    text: &str,
+    path: &Path,
+    start: SourceLocation,
+    shift: i32,
+    stack: &mut Vec<PathBuf>,
+    expanded: &mut Expanded,
+) -> Result<(), IncludeError> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    expanded.sources.push(expanded.text.len(), start.clone());
+    let mut open_fence: Option<&str> = None;
+    // where in `expanded.text` the paragraph we are within begins,
+    // in case it turns out to be a setext heading
+    let mut paragraph: Option<usize> = None;
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if open_fence.is_none() && shift != 0 {
+            if let (Some(begins), Some(level)) = (paragraph, setext_underline(line)) {
+                // written as an atx heading, so that it can be given any level
+                let heading = expanded.text[begins..]
+                    .lines()
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let level = (level + shift).clamp(1, 6) as usize;
+                expanded.text.truncate(begins);
+                expanded.text.push_str(&"#".repeat(level));
+                expanded.text.push(' ');
+                expanded.text.push_str(&heading);
+                expanded.text.push('\n');
+                // the heading now takes fewer lines than it did in its file
+                expanded.sources.push(
+                    expanded.text.len(),
+                    SourceLocation {
+                        file: start.file.clone(),
+                        line: start.line + i + 1,
+                    },
+                );
+                paragraph = None;
+                continue;
+            }
+            if is_paragraph_line(line) && directive(line).is_none() && fence(line).is_none() {
+                paragraph.get_or_insert(expanded.text.len());
+            } else {
+                paragraph = None;
+            }
+        }
+
+        if let Some(opening) = open_fence {
+            if fence(line).is_some_and(|f| f.starts_with(opening)) {
+                open_fence = None;
+            }
+            expanded.text.push_str(line);
+            continue;
+        }
+        if let Some(opening) = fence(line) {
+            open_fence = Some(opening);
+            expanded.text.push_str(line);
+            continue;
+        }
+        let (included, included_shift) = match directive(line) {
+            Some(directive) => directive,
+            None => {
+                match shift_heading(line, shift) {
+                    Some(heading) if shift != 0 => expanded.text.push_str(&heading),
+                    _ => expanded.text.push_str(line),
+                }
+                continue;
+            }
+        };
+
+        let included = dir.join(included);
+        let io_error = |error| IncludeError::Io {
+            path: included.clone(),
+            error,
+        };
+        let canonical = included.canonicalize().map_err(io_error)?;
+        if let Some(i) = stack.iter().position(|p| *p == canonical) {
+            let mut cycle = stack[i..].to_vec();
+            cycle.push(canonical);
+            return Err(IncludeError::Cycle(cycle));
+        }
+        let included_text = std::fs::read_to_string(&included).map_err(io_error)?;
+        // any front matter of an included file is ignored
+        let (body, front_matter_lines) = match split_front_matter(&included_text) {
+            Ok(Some((_, body))) => (
+                body,
+                included_text[..included_text.len() - body.len()]
+                    .matches('\n')
+                    .count(),
+            ),
+            _ => (included_text.as_str(), 0),
+        };
+
+        // included markdown stands apart from whatever surrounds it
+        expanded.text.push('\n');
+        stack.push(canonical);
+        let included_start = SourceLocation {
+            file: Some(included.clone()),
+            line: front_matter_lines + 1,
+        };
+        expand(
+            body,
+            &included,
+            included_start,
+            shift + included_shift,
+            stack,
+            expanded,
+        )?;
+        stack.pop();
+        expanded.files.push(included);
+        if !expanded.text.ends_with('\n') {
+            expanded.text.push('\n');
+        }
+        expanded.text.push('\n');
+        expanded.sources.push(
+            expanded.text.len(),
+            SourceLocation {
+                file: start.file.clone(),
+                line: start.line + i + 1,
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_and_headings() {
+        assert_eq!(
+            directive("{<chapters/one.md}\n"),
+            Some(("chapters/one.md", 0))
+        );
+        assert_eq!(directive("  {< two.md | +1 }"), Some(("two.md", 1)));
+        assert_eq!(directive("{<three.md|-2}"), Some(("three.md", -2)));
+        assert_eq!(directive("    {<indented.md}"), None);
+        assert_eq!(directive("see {<inline.md}"), None);
+        assert_eq!(directive("{<}"), None);
+
+        assert_eq!(shift_heading("# Title\n", 1).as_deref(), Some("## Title\n"));
+        assert_eq!(shift_heading("## Title", -3).as_deref(), Some("# Title"));
+        assert_eq!(
+            shift_heading("###### Title", 2).as_deref(),
+            Some("###### Title")
+        );
+        assert_eq!(shift_heading("#hashtag", 1), None);
+        assert_eq!(fence("````rust\n"), Some("````"));
+        assert_eq!(fence("``inline``"), None);
+    }
+
+    fn shifted(text: &str, shift: i32) -> Expanded {
+        let path = Path::new("book.md");
+        let mut expanded = Expanded {
+            text: String::new(),
+            sources: SourceMap::default(),
+            files: Vec::new(),
+        };
+        let start = SourceLocation::start_of(Some(path.to_path_buf()));
+        expand(text, path, start, shift, &mut Vec::new(), &mut expanded).unwrap();
+        expanded
+    }
+
+    #[test]
+    fn setext_headings_are_shifted() {
+        assert_eq!(
+            shifted("A Title\n=======\n\nText.\n", 1).text,
+            "## A Title\n\nText.\n"
+        );
+        assert_eq!(
+            shifted("A Long\nSubtitle\n---\n", 2).text,
+            "#### A Long Subtitle\n"
+        );
+        assert_eq!(shifted("A Subtitle\n---\n", -1).text, "# A Subtitle\n");
+        // neither lists, quotations nor thematic breaks are underlined as headings
+        for text in &[
+            "- An item\n---\n",
+            "> A quotation\n---\n",
+            "***\n---\n",
+            "Text.\n\n---\n",
+            "```\nCode\n---\n```\n",
+        ] {
+            assert_eq!(shifted(text, 1).text, *text);
+        }
+        assert_eq!(shifted("A Title\n===\n", 0).text, "A Title\n===\n");
+    }
+}
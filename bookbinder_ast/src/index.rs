@@ -0,0 +1,128 @@
+use extended_pulldown::{IndexCrossReference, IndexEntry};
+use std::cmp::Ordering;
+
+/// A back-of-book index, gathering the places where terms are marked
+/// into headings sorted alphabetically, regardless of case.
+///
+/// Since the way a location is given depends on the output format,
+/// the type of a location is left to whatever builds the index.
+///
+/// ```
+/// use bookbinder_ast::{Index, IndexCrossReference, IndexEntry};
+///
+/// let mut index = Index::default();
+/// let roses = IndexEntry {
+///     terms: vec!["plants".into(), "roses".into()],
+///     cross_reference: None,
+/// };
+/// let flora = IndexEntry {
+///     terms: vec!["Flora".into()],
+///     cross_reference: Some(IndexCrossReference::See("plants".into())),
+/// };
+/// index.add(&roses, "page-1");
+/// index.add(&flora, "page-2");
+/// index.add(&roses, "page-3");
+///
+/// let headings = index.headings();
+/// assert_eq!(headings[0].term, "Flora");
+/// assert_eq!(headings[0].see, ["plants"]);
+/// assert!(headings[0].locations.is_empty());
+/// assert_eq!(headings[1].term, "plants");
+/// assert_eq!(headings[1].subheadings[0].locations, ["page-1", "page-3"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index<L> {
+    headings: Vec<IndexHeading<L>>,
+}
+
+/// A term in an index, with everywhere it is found and any subentries beneath it
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexHeading<L> {
+    /// The term itself
+    pub term: String,
+    /// The places the term is marked, in the order they were added
+    pub locations: Vec<L>,
+    /// Terms under which this one is indexed instead
+    pub see: Vec<String>,
+    /// Related terms which are indexed as well
+    pub see_also: Vec<String>,
+    /// Entries beneath this one, sorted like the index itself
+    pub subheadings: Vec<IndexHeading<L>>,
+}
+
+impl<L> Default for Index<L> {
+    fn default() -> Self {
+        Index {
+            headings: Vec::new(),
+        }
+    }
+}
+
+pub(crate) fn compare_terms(a: &str, b: &str) -> Ordering {
+    a.to_lowercase()
+        .cmp(&b.to_lowercase())
+        .then_with(|| a.cmp(b))
+}
+
+/// Find the heading for `term` among `headings`, inserting it in order if it is not present
+fn heading_for<'h, L>(
+    headings: &'h mut Vec<IndexHeading<L>>,
+    term: &str,
+) -> &'h mut IndexHeading<L> {
+    let i = match headings.binary_search_by(|h| compare_terms(&h.term, term)) {
+        Ok(i) => i,
+        Err(i) => {
+            headings.insert(
+                i,
+                IndexHeading {
+                    term: term.to_string(),
+                    locations: Vec::new(),
+                    see: Vec::new(),
+                    see_also: Vec::new(),
+                    subheadings: Vec::new(),
+                },
+            );
+            i
+        }
+    };
+    &mut headings[i]
+}
+
+impl<L> Index<L> {
+    /// Add an entry found at `location`;
+    /// an entry which is only a cross-reference does not record the location
+    pub fn add(&mut self, entry: &IndexEntry<'_>, location: L) {
+        let mut terms = entry.terms.iter();
+        let first = match terms.next() {
+            Some(term) => term,
+            None => return,
+        };
+        let mut heading = heading_for(&mut self.headings, first);
+        for term in terms {
+            heading = heading_for(&mut heading.subheadings, term);
+        }
+        match entry.cross_reference {
+            Some(IndexCrossReference::See(ref other)) => {
+                if !heading.see.iter().any(|s| s == other.as_ref()) {
+                    heading.see.push(other.to_string());
+                }
+            }
+            Some(IndexCrossReference::SeeAlso(ref other)) => {
+                if !heading.see_also.iter().any(|s| s == other.as_ref()) {
+                    heading.see_also.push(other.to_string());
+                }
+            }
+            None => heading.locations.push(location),
+        }
+    }
+
+    /// The headings of the index, in alphabetical order
+    pub fn headings(&self) -> &[IndexHeading<L>] {
+        &self.headings
+    }
+
+    /// Whether nothing has been indexed
+    pub fn is_empty(&self) -> bool {
+        self.headings.is_empty()
+    }
+}
@@ -36,7 +36,21 @@
 //! to get absolutely right without actually understanding text, but the algorithm used is fairly robust
 //! and takes into account semantics (so that, for example,
 //! quotes in code are not transformed). Still, for perfect accuracy, it's best to use curly quotes explicitly.
+//! - format-only content: `<span class="pdf-only">see page 32</span>`, or a `<div class="epub-only">` enclosing markdown set apart by blank lines, is kept in the book but rendered only to that format; see `BookSrc::select_format`. This applies to metadata used in generated pages, such as a copyright statement, too.
 //! - sub and superscript: `22^nd^ July`, `H~2~0`;
+//! - front matter: a file added from disk can begin with a block of yaml or toml describing its division; see `FrontMatter`.
+//! - includes: in a file added from disk, a line holding only `{<path}` is replaced by the markdown of the file at `path`, relative to the file it is in; `{<path|+1}` makes each of its headings one level lower, and `{<path|-1}` one level higher. Included files may include others in turn, but never themselves; see `IncludeError`.
+//! - cross-references: `{#label}` labels the heading, figure caption or text it follows; `{@label}` refers to it and `{@page:label}` to its page; see `CrossReferences`.
+//! - index entries: `{^term}` marks a term for a back-of-book index, with subentries as `{^term!subentry}` and cross-references as `{^term|see other}` or `{^term|see also other}`; an index is generated whenever terms are marked.
+//! - citations: `[@key]`, `[@key, p. 4]` or `[@key; @other]` cite works from a bibliography source, such as a BibTeX or CSL-JSON file added with `add_bibliography_source_from_file`; a bibliography of the works cited is generated in the chosen `CitationStyle`.
+//! - drama: within a `<div class="drama">`, paragraphs beginning with a speaker's name in capitals followed by a colon, or by a full stop where the name is listed in the div's `data-personae`, are speeches; a dramatis personae of the speakers is generated unless one is added with `add_dramatis_personae` or `do_not_generate_dramatis_personae` is set.
+//! - letters: within a `<div class="letter">` or `<div class="document">`, the dateline, salutation, signature and postscript are marked from their place in the letter.
+//! - glossaries: the first use of each term from a glossary source, such as a markdown definition list added with `add_glossary_source_from_file`, is linked to its definition, and abbreviations are expanded on first use; see `Glossary`.
+//!
+//! # Diagnostics
+//! Problems such as missing images, empty footnotes or unrecognised span classes are printed by `process`;
+//! `process_checked` returns them instead, located by file and line where the markdown was read from a file,
+//! and fails if any are errors -- or, if `deny_warnings` is set, if there are any at all.
 
 #![deny(dead_code)]
 #![deny(unreachable_patterns)]
@@ -49,20 +63,54 @@
 #![deny(variant_size_differences)]
 use bookbinder_common::MimeTypeHelper;
 use extended_pulldown::{flatten_footnotes, InlineParser, MakeStatic, Parser};
-pub use extended_pulldown::{Event, Tag};
+pub use extended_pulldown::{Event, IndexCrossReference, IndexEntry, Tag};
 pub use pulldown_cmark::CowStr;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::iter::Peekable;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 mod metadata;
 pub use metadata::Metadata;
+use serde::{Deserialize, Serialize};
+mod document;
+pub use document::{BookDocument, DocumentError, DOCUMENT_FORMAT_VERSION};
+mod transform;
+pub use transform::{BookTransform, RenderTarget, TransformPipeline};
+mod heading_mapping;
+use heading_mapping::HeadingDivision;
+pub use heading_mapping::HeadingMapping;
+mod front_matter;
+pub use front_matter::{FrontMatter, FrontMatterError};
+mod diagnostics;
+use diagnostics::{DivisionLocations, SourceCheck, SourceIndex, SourceMap};
+mod cross_references;
+pub use cross_references::CrossReferences;
+mod bibliography;
+mod glossary;
+mod include;
+pub use include::IncludeError;
+mod index;
+mod lint;
+mod opening;
+mod statistics;
+mod templates;
+pub use bibliography::{Bibliography, BibliographyError, CitationStyle, Name, Reference};
+pub use diagnostics::{Diagnostic, DiagnosticKind, DiagnosticsError, Severity, SourceLocation};
+pub use glossary::{Glossary, GlossaryEntry, GlossaryError};
+pub use index::{Index, IndexHeading};
+pub use lint::{Lint, LintOptions, LintRule};
+pub use opening::{OpeningStyle, SmallCapsOpening};
+pub use statistics::{DivisionStatistics, Statistics};
+pub use templates::{PageTemplates, PageTemplatesError};
 pub mod helpers;
 
 /// Specification of a section's semantic role, such as being a foreword or a chapter.
 /// These are taken from Epub 3.2's structural semantics
-#[derive(Debug, PartialEq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq, Clone, Copy, Hash, Serialize, Deserialize)]
 #[allow(missing_docs)]
 pub enum SemanticRole {
     Halftitle,
@@ -79,6 +127,18 @@ pub enum SemanticRole {
     Chapter,
     Part,
     Preface,
+    Prologue,
+    Epilogue,
+    Conclusion,
+    Glossary,
+    Bibliography,
+    Errata,
+    SeriesPage,
+    Contributors,
+    Frontispiece,
+    Volume,
+    Index,
+    DramatisPersonae,
 }
 
 impl SemanticRole {
@@ -92,7 +152,8 @@ impl SemanticRole {
     pub const fn get_label(self) -> Option<&'static str> {
         use SemanticRole::*;
         match self {
-            Epigraph | Halftitle | Copyrightpage | Titlepage | Dedication => None,
+            Epigraph | Halftitle | Copyrightpage | Titlepage | Dedication | SeriesPage
+            | Frontispiece => None,
             Foreword => Some("Foreword"),
             Afterword => Some("Afterword"),
             Introduction => Some("Introduction"),
@@ -102,12 +163,22 @@ impl SemanticRole {
             Chapter => Some("Chapter"),
             Part => Some("Part"),
             Preface => Some("Preface"),
+            Prologue => Some("Prologue"),
+            Epilogue => Some("Epilogue"),
+            Conclusion => Some("Conclusion"),
+            Glossary => Some("Glossary"),
+            Bibliography => Some("Bibliography"),
+            Errata => Some("Errata"),
+            Contributors => Some("Contributors"),
+            Volume => Some("Volume"),
+            Index => Some("Index"),
+            DramatisPersonae => Some("Dramatis Personae"),
         }
     }
 }
 
 /// Indicate a contributor to the work who should appear on a titlepage
-#[derive(Debug, Clone, Copy, Hash, PartialEq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Serialize, Deserialize)]
 #[allow(missing_docs)]
 pub enum TitlePageContributorRole {
     Author,
@@ -135,17 +206,33 @@ impl TitlePageContributorRole {
     }
 }
 
+/// the words introducing each group of contributors on a titlepage, if any, and their names
+pub(crate) type ContributorList<'a> = Vec<(Option<Cow<'a, str>>, Vec<Cow<'a, str>>)>;
+
 /// The display format of a number
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NumberFormat {
-    /// In words: e.g. One
+    /// In uppercase words: e.g. ONE
     Words,
+    /// In lowercase words: e.g. one
+    LowercaseWords,
+    /// In title case words: e.g. Twenty-One
+    TitleCaseWords,
+    /// As an ordinal, in title case words: e.g. First
+    Ordinal,
     /// In arabic numberals: e.g 1
     Arabic,
-    /// In roman numerals: e.g i
+    /// In uppercase roman numerals: e.g I
     Roman,
-    /// As a letter: e.g. A
+    /// In lowercase roman numerals: e.g i
+    LowercaseRoman,
+    /// As a letter: e.g. A, with AA, AB and so on following Z
     Letter,
+    /// Taken from a custom sequence, in which the first item represents 1;
+    /// this allows, for example, numbers in words of a language other than English.
+    /// Numbers past the end of the sequence are displayed in arabic numerals.
+    /// A sequence can be given most easily with `NumberFormat::custom`.
+    Custom(Arc<[String]>),
 }
 
 impl Default for NumberFormat {
@@ -154,10 +241,143 @@ impl Default for NumberFormat {
     }
 }
 
+impl NumberFormat {
+    /// Display `number` in this format
+    ///
+    /// ```
+    /// use bookbinder_ast::NumberFormat;
+    ///
+    /// assert_eq!(NumberFormat::TitleCaseWords.format(121), "One Hundred and Twenty-One");
+    /// assert_eq!(NumberFormat::Ordinal.format(3), "Third");
+    /// assert_eq!(NumberFormat::LowercaseRoman.format(14), "xiv");
+    /// assert_eq!(NumberFormat::Letter.format(28), "AB");
+    /// let custom = NumberFormat::custom(vec!["Un", "Deux"]);
+    /// assert_eq!(custom.format(2), "Deux");
+    /// assert_eq!(custom.format(3), "3");
+    /// let json = r#"{"Custom": ["Un", "Deux"]}"#;
+    /// assert_eq!(serde_json::from_str::<NumberFormat>(json).unwrap(), custom);
+    /// ```
+    pub fn format(&self, number: usize) -> String {
+        match self {
+            NumberFormat::Words => bookbinder_common::number_to_words(number),
+            NumberFormat::LowercaseWords => {
+                bookbinder_common::number_to_words(number).to_lowercase()
+            }
+            NumberFormat::TitleCaseWords => {
+                to_title_case(&bookbinder_common::number_to_words(number))
+            }
+            NumberFormat::Ordinal => {
+                to_title_case(&bookbinder_common::number_to_ordinal_words(number))
+            }
+            NumberFormat::Arabic => number.to_string(),
+            NumberFormat::Roman => bookbinder_common::number_to_roman(number),
+            NumberFormat::LowercaseRoman => {
+                bookbinder_common::number_to_roman(number).to_lowercase()
+            }
+            NumberFormat::Letter => bookbinder_common::number_to_letters(number),
+            NumberFormat::Custom(sequence) => number
+                .checked_sub(1)
+                .and_then(|i| sequence.get(i))
+                .map(|item| item.to_string())
+                .unwrap_or_else(|| number.to_string()),
+        }
+    }
+
+    /// A format taking numbers from `sequence`, in which the first item represents 1
+    pub fn custom<I, S>(sequence: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        NumberFormat::Custom(sequence.into_iter().map(Into::into).collect())
+    }
+}
+
+/// A dramatis personae listing, in order of their first appearance, the speakers in `events`,
+/// or nothing if there are none
+fn dramatis_personae<'a, 'b: 'a, I>(events: I) -> Vec<BookEvent<'static>>
+where
+    I: Iterator<Item = &'a BookEvent<'b>>,
+{
+    let mut speakers: Vec<&str> = Vec::new();
+    for event in events {
+        if let BookEvent::Event(Event::Start(Tag::Speech(speaker))) = event {
+            if !speakers.contains(&speaker.as_ref()) {
+                speakers.push(speaker.as_ref());
+            }
+        }
+    }
+    if speakers.is_empty() {
+        return Vec::new();
+    }
+
+    let role = SemanticRole::DramatisPersonae;
+    let mut section = Vec::with_capacity(speakers.len() * 3 + 5);
+    section.push(BookEvent::BeginSemantic(role));
+    section.push(BookEvent::BeginDivisionHeader(false));
+    section.push(BookEvent::DivisionHeaderLabel {
+        text: role.get_label().map(Cow::from),
+        number: None,
+        number_format: NumberFormat::Arabic,
+    });
+    section.push(BookEvent::EndDivisionHeader(false));
+    for speaker in speakers.into_iter() {
+        section.push(BookEvent::Event(Event::Start(Tag::UnindentedParagraph)));
+        section.push(BookEvent::Event(Event::Text(to_title_case(speaker).into())));
+        section.push(BookEvent::Event(Event::End(Tag::UnindentedParagraph)));
+    }
+    section.push(BookEvent::EndSemantic(role));
+    section
+}
+
+/// capitalise each word of a number in words or a name, apart from `and`
+fn to_title_case(words: &str) -> String {
+    let mut title_cased = String::with_capacity(words.len());
+    for (i, word) in words.to_lowercase().split(' ').enumerate() {
+        if i > 0 {
+            title_cased.push(' ');
+        }
+        if word == "and" {
+            title_cased.push_str(word);
+            continue;
+        }
+        for (j, part) in word.split('-').enumerate() {
+            if j > 0 {
+                title_cased.push('-');
+            }
+            let mut chars = part.chars();
+            if let Some(first) = chars.next() {
+                title_cased.extend(first.to_uppercase());
+                title_cased.push_str(chars.as_str());
+            }
+        }
+    }
+    title_cased
+}
+
+/// How a scene break -- a thematic break, such as `* * *` in markdown -- is shown.
+/// Whatever the style, the paragraph after a break is not indented.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SceneBreak {
+    /// A blank line; in print, a dinkus is shown instead when the break falls at the top or bottom of a page,
+    /// where a blank line would be lost
+    BlankLine,
+    /// A short horizontal rule
+    Rule,
+    /// An asterism: ⁂
+    Asterism,
+    /// A dinkus: * * *
+    Dinkus,
+    /// A custom glyph or short run of text, such as ❦
+    Glyph(String),
+    /// A small image, such as an svg or png ornament, at the given path
+    Ornament(PathBuf),
+}
+
 /// A particular event in a book, such as the beginning of a paragraph
 /// or a span of text
 #[allow(missing_docs)]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BookEvent<'a> {
     BeginSemantic(SemanticRole),
     EndSemantic(SemanticRole),
@@ -168,17 +388,20 @@ pub enum BookEvent<'a> {
         /// the text of this label
         text: Option<Cow<'a, str>>,
         /// the number of this label
-        number: Option<u8>,
+        number: Option<usize>,
         /// the format the number of this label should be in
         number_format: NumberFormat,
     },
     DivisionAuthors(Vec<Cow<'a, str>>),
+    /// a briefer version of a division's title, for use in a table of contents
+    DivisionShortTitle(Cow<'a, str>),
     BeginTitlePage,
     BeginTitlePageTitle,
     EndTitlePageTitle,
     BeginTitlePageSubTitle,
     EndTitlePageSubTitle,
-    TitlePageContributors(Vec<(TitlePageContributorRole, Vec<Cow<'a, str>>)>),
+    /// the words introducing each group of contributors on a titlepage, if any, and their names
+    TitlePageContributors(ContributorList<'a>),
     EndTitlePage,
     BeginMainmatter,
     BeginFrontmatter,
@@ -229,24 +452,64 @@ impl<'a> TryFrom<BookEvent<'a>> for Event<'a> {
 #[derive(Debug, Clone, Default)]
 pub struct BookSrcBuilder<'a> {
     halftitle: Vec<BookEvent<'a>>,
+    series_page: Vec<BookEvent<'a>>,
+    frontispiece: Vec<BookEvent<'a>>,
     copyright_page: Vec<BookEvent<'a>>,
     dedication: Vec<BookEvent<'a>>,
     forewords: Vec<BookEvent<'a>>,
     epigraphs: Vec<BookEvent<'a>>,
     introductions: Vec<BookEvent<'a>>,
     prefaces: Vec<BookEvent<'a>>,
-    mainmatter: Vec<Event<'a>>,
+    dramatis_personae: Vec<BookEvent<'a>>,
+    prologues: Vec<BookEvent<'a>>,
+    mainmatter: Vec<MainmatterFragment<'a>>,
+    conclusions: Vec<BookEvent<'a>>,
+    epilogues: Vec<BookEvent<'a>>,
     appendices: Vec<BookEvent<'a>>,
     afterwords: Vec<BookEvent<'a>>,
+    glossaries: Vec<BookEvent<'a>>,
+    bibliographies: Vec<BookEvent<'a>>,
+    contributors: Vec<BookEvent<'a>>,
     colophon: Vec<BookEvent<'a>>,
     acknowledgements: Vec<BookEvent<'a>>,
+    errata: Vec<BookEvent<'a>>,
+    volumes: Vec<BookSrc<'a>>,
     metadata: Metadata<'a>,
     image_dirs: HashSet<PathBuf>,
-    appendices_count: u8,
+    appendices_count: usize,
     epigraph_count: usize,
     no_titlepage: bool,
     no_copyrightpage: bool,
     no_halftitle: bool,
+    no_index: bool,
+    no_dramatis_personae: bool,
+    bibliography: Bibliography,
+    citation_style: CitationStyle,
+    glossary: Glossary,
+    page_templates: PageTemplates,
+    continuous_volume_numbering: bool,
+    heading_mapping: Option<HeadingMapping>,
+    transforms: TransformPipeline,
+    diagnostics: Vec<Diagnostic>,
+    source_index: SourceIndex,
+    /// where the markdown of a file just read was read from, to be used when it is checked
+    pending_source: Option<SourceMap>,
+    /// where the front matter of a file just read was, if it had any
+    pending_front_matter: Option<SourceLocation>,
+    deny_warnings: bool,
+}
+
+/// A fragment of mainmatter, with the heading mapping set for it alone, if any,
+/// any front matter it began with, the authors of its chapters
+/// and the locations of its front matter and headings
+#[derive(Debug, Clone)]
+struct MainmatterFragment<'a> {
+    events: Vec<Event<'a>>,
+    heading_mapping: Option<HeadingMapping>,
+    front_matter: Option<FrontMatter>,
+    authors: Vec<Cow<'a, str>>,
+    /// where the front matter and each heading of the fragment were found
+    locations: DivisionLocations,
 }
 
 /// set a metadata value from its parent `BookSrcBuilder`
@@ -395,6 +658,7 @@ macro_rules! add_authored_ancillary_text {
             A: ContributorSource<'a>,
             P: ParseHelper<'a>,
         {
+            let (text, _) = self.parse_source(text);
             let authors = authors.to_vec();
             self.metadata.$meta_add_func(authors.clone());
             let (mut title, mut text) = if title.is_none() {
@@ -433,12 +697,14 @@ macro_rules! add_authored_ancillary_text {
             self
         }
 
-        read_file_wrapper!($from_file_doc, $from_file_fn, $fnname, ".");
+        read_file_wrapper!($from_file_doc, $from_file_fn, $fnname, $field, ".");
     };
 }
 
+/// Macro for reading ancillary text from a file, applying any front matter
+/// to the events added to `field`
 macro_rules! read_file_wrapper {
-    ($doc:meta, $fnname:ident, $wrapped_fn:ident) => {
+    ($doc:meta, $fnname:ident, $wrapped_fn:ident, $field:ident) => {
         #[$doc]
         pub fn $fnname<P, S>(
             &mut self,
@@ -450,11 +716,19 @@ macro_rules! read_file_wrapper {
             S: ToString,
         {
             let s = self.read_file_and_add_to_sources(filepath)?;
-            self.$wrapped_fn(s, title.map(|s| s.to_string()));
+            let (front_matter, s) = s.split_front_matter()?;
+            let title = title
+                .map(|s| s.to_string())
+                .or_else(|| front_matter.as_ref().and_then(|f| f.title.clone()));
+            let start = self.$field.len();
+            self.$wrapped_fn(s, title);
+            if let Some(front_matter) = front_matter {
+                self.$field.apply_front_matter_from(start, &front_matter);
+            }
             Ok(self)
         }
     };
-    ($doc:meta, $fnname:ident, $wrapped_fn:ident, $include_authors:expr) => {
+    ($doc:meta, $fnname:ident, $wrapped_fn:ident, $field:ident, $include_authors:expr) => {
         #[$doc]
         pub fn $fnname<A, P>(
             &mut self,
@@ -466,7 +740,20 @@ macro_rules! read_file_wrapper {
             P: AsRef<Path>,
         {
             let s = self.read_file_and_add_to_sources(filepath)?;
-            self.$wrapped_fn(s, None, authors);
+            let (front_matter, s) = s.split_front_matter()?;
+            let title = front_matter.as_ref().and_then(|f| f.title.clone());
+            // authors named in front matter take the place of any given here
+            let authors = match front_matter {
+                Some(ref f) if !f.authors.is_empty() => {
+                    f.authors.iter().cloned().map(Cow::Owned).collect()
+                }
+                _ => authors.to_vec(),
+            };
+            let start = self.$field.len();
+            self.$wrapped_fn(s, title, authors);
+            if let Some(front_matter) = front_matter {
+                self.$field.apply_front_matter_from(start, &front_matter);
+            }
             Ok(self)
         }
     };
@@ -485,6 +772,7 @@ macro_rules! add_unauthored_ancillary_text {
         where
             P: ParseHelper<'a>,
         {
+            let (text, _) = self.parse_source(text);
             let (mut title, mut text) = if title.is_none() {
                 text.parse_and_remove_initial_title()
             } else {
@@ -517,11 +805,12 @@ macro_rules! add_unauthored_ancillary_text {
             self
         }
 
-        read_file_wrapper!($from_file_doc, $from_file_fn, $fnname);
+        read_file_wrapper!($from_file_doc, $from_file_fn, $fnname, $field);
     };
 }
 
 /// What a top-level markdown heading in mainmatter represents
+#[derive(Clone, Copy)]
 enum StepLevel {
     TopToParts,
     TopToChapter,
@@ -553,6 +842,328 @@ impl StepLevel {
             (false, false, true) => StepLevel::TopToChapter,
         }
     }
+
+    fn classify(self, level: u32) -> HeadingDivision {
+        match (self, level) {
+            (StepLevel::TopToChapter, 1) => HeadingDivision::Chapter,
+            (StepLevel::TopToChapter, h) => HeadingDivision::Subheading(h + 1),
+            (StepLevel::TopToParts, 1) => HeadingDivision::Part,
+            (StepLevel::TopToParts, 2) => HeadingDivision::Chapter,
+            (StepLevel::TopToParts, h) => HeadingDivision::Subheading(h + 2),
+        }
+    }
+}
+
+/// How the headings of a fragment of mainmatter are to be interpreted
+#[derive(Clone, Copy)]
+enum Structure {
+    Inferred(StepLevel),
+    Mapped(HeadingMapping),
+}
+
+impl Structure {
+    fn classify(self, level: u32) -> Option<HeadingDivision> {
+        match self {
+            Structure::Inferred(step_level) => Some(step_level.classify(level)),
+            Structure::Mapped(mapping) => mapping.classify(level),
+        }
+    }
+}
+
+/// Make an epigraph from the contents of a blockquote;
+/// if its last paragraph begins with a dash, that paragraph is taken to be the source
+fn division_epigraph(mut content: Vec<Event<'_>>) -> Vec<BookEvent<'_>> {
+    if let Some(Event::Start(Tag::BlockQuote)) | Some(Event::Start(Tag::BlockQuotation)) =
+        content.first()
+    {
+        content.remove(0);
+        content.pop();
+    }
+
+    let source_start = content
+        .iter()
+        .rposition(|e| matches!(e, Event::Start(Tag::Paragraph)))
+        .filter(|i| match content.get(i + 1) {
+            Some(Event::Text(t)) => t.starts_with(&['—', '–'][..]),
+            _ => false,
+        });
+    let mut source = match source_start {
+        Some(i) => content.split_off(i),
+        None => Vec::new(),
+    };
+
+    if let Some(Event::Start(Tag::Paragraph)) = content.first() {
+        content[0] = Event::Start(Tag::UnindentedParagraph);
+        if let Some(end) = content
+            .iter_mut()
+            .find(|e| matches!(e, Event::End(Tag::Paragraph)))
+        {
+            *end = Event::End(Tag::UnindentedParagraph);
+        }
+    }
+
+    let mut epigraph = Vec::with_capacity(content.len() + source.len() + 6);
+    epigraph.push(BookEvent::BeginSemantic(SemanticRole::Epigraph));
+    epigraph.push(BookEvent::BeginEpigraphText);
+    epigraph.extend(content.into_iter().map(BookEvent::from));
+    epigraph.push(BookEvent::EndEpigraphText);
+    if !source.is_empty() {
+        // drop the enclosing paragraph and the leading dash
+        source.remove(0);
+        source.pop();
+        if let Some(Event::Text(t)) = source.first_mut() {
+            let stripped = t.trim_start_matches(&['—', '–', ' '][..]).to_string();
+            *t = stripped.into();
+        }
+        epigraph.push(BookEvent::BeginEpigraphSource);
+        epigraph.extend(source.into_iter().map(BookEvent::from));
+        epigraph.push(BookEvent::EndEpigraphSource);
+    }
+    epigraph.push(BookEvent::EndSemantic(SemanticRole::Epigraph));
+    epigraph
+}
+
+/// Divides fragments of mainmatter into parts and chapters,
+/// keeping count of them across fragments
+#[derive(Default)]
+struct SectionDivider<'a> {
+    collated: Vec<BookEvent<'a>>,
+    /// the role of the chapter-level division currently open, if any
+    open_chapter: Option<SemanticRole>,
+    /// what the heading currently being read represents, and whether it is unnumbered
+    open_heading: Option<(HeadingDivision, bool)>,
+    part_count: usize,
+    chapter_count: usize,
+    /// the text of the heading currently being read
+    heading_text: String,
+    /// where the heading currently being read was found, if that is known
+    heading_location: Option<SourceLocation>,
+    /// where the body of the open chapter begins in `collated`, and the chapter's title and location
+    chapter_body: Option<(usize, String, Option<SourceLocation>)>,
+    /// headings which contradict a heading mapping, and chapters with no text
+    diagnostics: Vec<Diagnostic>,
+    /// everyone named as the author of a division, without repetition
+    authors: Vec<Cow<'a, str>>,
+}
+
+impl<'a> SectionDivider<'a> {
+    /// Divide a fragment of mainmatter;
+    /// any front matter applies to the first part or chapter in the fragment,
+    /// or -- if it sets a title -- to a division opened before the rest of the fragment.
+    /// Every chapter in the fragment is attributed to `authors`,
+    /// unless its front matter names others.
+    /// Problems with the divisions it opens are located from `locations`
+    fn divide(
+        &mut self,
+        events: Vec<Event<'a>>,
+        structure: Structure,
+        front_matter: Option<&FrontMatter>,
+        authors: &[Cow<'a, str>],
+        locations: &DivisionLocations,
+    ) {
+        self.collated.reserve(events.len());
+        let mut events = events.into_iter().peekable();
+        let mut front_matter = front_matter;
+        let mut headings = locations.headings.iter();
+
+        if let Some(title) = front_matter.and_then(|f| f.title.clone()) {
+            let division = match front_matter.and_then(|f| f.role) {
+                Some(SemanticRole::Part) => HeadingDivision::Part,
+                _ => HeadingDivision::Chapter,
+            };
+            self.heading_text = title.clone();
+            self.heading_location = locations.front_matter.clone();
+            self.open_division(division, front_matter.take(), authors);
+            self.collated.append(&mut title.parse_inline());
+            self.close_heading(&mut events);
+        }
+
+        while let Some(event) = events.next() {
+            match event {
+                Event::Start(Tag::Heading(level)) => {
+                    self.heading_text = match events.peek() {
+                        Some(Event::Text(t)) => t.to_string(),
+                        _ => String::new(),
+                    };
+                    self.heading_location = headings.next().cloned();
+                    match structure.classify(level) {
+                        Some(division @ HeadingDivision::Part)
+                        | Some(division @ HeadingDivision::Chapter) => {
+                            let division = match front_matter.and_then(|f| f.role) {
+                                Some(SemanticRole::Part) => HeadingDivision::Part,
+                                Some(_) => HeadingDivision::Chapter,
+                                None => division,
+                            };
+                            self.open_division(division, front_matter.take(), authors);
+                        }
+                        Some(HeadingDivision::Subheading(h)) => {
+                            if self.open_chapter.is_none() {
+                                if let Structure::Mapped(_) = structure {
+                                    self.diagnostics.push(Diagnostic::new(
+                                        DiagnosticKind::SectionOutsideChapter {
+                                            heading: self.heading_text.clone(),
+                                            level,
+                                        },
+                                        self.heading_location.clone(),
+                                    ));
+                                }
+                            }
+                            self.open_heading = Some((HeadingDivision::Subheading(h), false));
+                            self.collated.push(Event::Start(Tag::Heading(h)).into());
+                        }
+                        None => {
+                            self.diagnostics.push(Diagnostic::new(
+                                DiagnosticKind::UnmappedHeading {
+                                    heading: self.heading_text.clone(),
+                                    level,
+                                },
+                                self.heading_location.clone(),
+                            ));
+                            self.open_heading = Some((HeadingDivision::Subheading(3), false));
+                            self.collated.push(Event::Start(Tag::Heading(3)).into());
+                        }
+                    }
+                }
+                Event::End(Tag::Heading(_)) => self.close_heading(&mut events),
+                other => self.collated.push(other.into()),
+            }
+        }
+    }
+
+    /// Open the header of a new part or chapter, closing any open chapter
+    fn open_division(
+        &mut self,
+        division: HeadingDivision,
+        front_matter: Option<&FrontMatter>,
+        authors: &[Cow<'a, str>],
+    ) {
+        let unnumbered = matches!(front_matter, Some(f) if f.unnumbered);
+        self.close_chapter();
+        let (role, number, number_format) = match division {
+            HeadingDivision::Part => {
+                let number = if unnumbered {
+                    None
+                } else {
+                    self.part_count += 1;
+                    Some(self.part_count)
+                };
+                (SemanticRole::Part, number, NumberFormat::Roman)
+            }
+            _ => {
+                let role = front_matter
+                    .and_then(|f| f.role)
+                    .unwrap_or(SemanticRole::Chapter);
+                let number = if unnumbered || role != SemanticRole::Chapter {
+                    None
+                } else {
+                    self.chapter_count += 1;
+                    Some(self.chapter_count)
+                };
+                self.open_chapter = Some(role);
+                (role, number, NumberFormat::Arabic)
+            }
+        };
+        let label = front_matter
+            .and_then(|f| f.get_label())
+            .or_else(|| role.get_label().map(|l| l.into()));
+
+        self.open_heading = Some((division, unnumbered));
+        self.collated.push(BookEvent::BeginSemantic(role));
+        self.collated
+            .push(BookEvent::BeginDivisionHeader(unnumbered));
+        self.collated.push(BookEvent::DivisionHeaderLabel {
+            text: label,
+            number,
+            number_format,
+        });
+        let mut header_events = front_matter.map(|f| f.header_events()).unwrap_or_default();
+        let has_authors = header_events
+            .iter()
+            .any(|e| matches!(e, BookEvent::DivisionAuthors(_)));
+        if !has_authors && division == HeadingDivision::Chapter && !authors.is_empty() {
+            header_events.push(BookEvent::DivisionAuthors(authors.to_vec()));
+        }
+        for event in header_events.iter() {
+            if let BookEvent::DivisionAuthors(names) = event {
+                for name in names.iter() {
+                    if !self.authors.contains(name) {
+                        self.authors.push(name.clone());
+                    }
+                }
+            }
+        }
+        self.collated.append(&mut header_events);
+    }
+
+    /// Close the heading currently being read
+    fn close_heading<I>(&mut self, events: &mut Peekable<I>)
+    where
+        I: Iterator<Item = Event<'a>>,
+    {
+        match self.open_heading.take() {
+            Some((HeadingDivision::Part, unnumbered)) => {
+                self.collated.push(BookEvent::EndDivisionHeader(unnumbered));
+                self.take_epigraphs(events);
+                self.collated
+                    .push(BookEvent::EndSemantic(SemanticRole::Part));
+            }
+            Some((HeadingDivision::Chapter, unnumbered)) => {
+                self.collated.push(BookEvent::EndDivisionHeader(unnumbered));
+                self.take_epigraphs(events);
+                let title = std::mem::take(&mut self.heading_text);
+                let location = self.heading_location.take();
+                self.chapter_body = Some((self.collated.len(), title, location));
+            }
+            Some((HeadingDivision::Subheading(h), _)) => {
+                self.collated.push(Event::End(Tag::Heading(h)).into());
+            }
+            None => {}
+        }
+    }
+
+    /// Add as epigraphs of the current division any blockquotes
+    /// wrapped in `<div class="epigraph">` which immediately follow its heading
+    fn take_epigraphs<I>(&mut self, events: &mut Peekable<I>)
+    where
+        I: Iterator<Item = Event<'a>>,
+    {
+        while let Some(Event::Html(html)) = events.peek() {
+            if html.trim() != "<div class=\"epigraph\">" {
+                break;
+            }
+            let _opening = events.next();
+            let mut content = Vec::new();
+            for event in events.by_ref() {
+                match event {
+                    Event::Html(ref html) if html.trim() == "</div>" => break,
+                    other => content.push(other),
+                }
+            }
+            self.collated.append(&mut division_epigraph(content));
+        }
+    }
+
+    /// Close any open chapter, noting if it was empty
+    fn close_chapter(&mut self) {
+        if let Some(role) = self.open_chapter.take() {
+            if let Some((start, title, location)) = self.chapter_body.take() {
+                if start == self.collated.len() {
+                    self.diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::EmptyChapter(title),
+                        location,
+                    ));
+                }
+            }
+            self.collated.push(BookEvent::EndSemantic(role));
+        }
+    }
+
+    /// Close any open chapter and return the divided events,
+    /// along with diagnostics of any problems found and the authors of divisions
+    fn finish(mut self) -> (Vec<BookEvent<'a>>, Vec<Diagnostic>, Vec<Cow<'a, str>>) {
+        self.close_chapter();
+        (self.collated, self.diagnostics, self.authors)
+    }
 }
 
 impl<'a> BookSrcBuilder<'a> {
@@ -564,6 +1175,22 @@ impl<'a> BookSrcBuilder<'a> {
         }
     }
 
+    /// Register a transform to apply to the processed book whenever it is rendered
+    pub fn add_transform<T: BookTransform + 'static>(&mut self, transform: T) -> &mut Self {
+        self.transforms.add(transform);
+        self
+    }
+
+    /// Register a transform to apply to the processed book only when it is rendered to `target`
+    pub fn add_transform_for<T: BookTransform + 'static>(
+        &mut self,
+        transform: T,
+        target: RenderTarget,
+    ) -> &mut Self {
+        self.transforms.add_for(transform, target);
+        self
+    }
+
     /// Do not include a halftitle if it has not been explicitly set
     pub fn do_not_generate_halftitle(&mut self) -> &mut Self {
         self.no_halftitle = true;
@@ -582,6 +1209,113 @@ impl<'a> BookSrcBuilder<'a> {
         self
     }
 
+    /// Do not generate an index, even if terms are marked for one
+    pub fn do_not_generate_index(&mut self) -> &mut Self {
+        self.no_index = true;
+        self
+    }
+
+    /// Do not generate a dramatis personae if it has not been explicitly set,
+    /// even if the book contains drama
+    pub fn do_not_generate_dramatis_personae(&mut self) -> &mut Self {
+        self.no_dramatis_personae = true;
+        self
+    }
+
+    /// Add works which can be cited in the text as `[@key]`;
+    /// those cited are listed in a bibliography generated after any added with `add_bibliography`
+    pub fn add_bibliography_source(&mut self, bibliography: Bibliography) -> &mut Self {
+        self.bibliography.extend(bibliography);
+        self
+    }
+
+    /// Add works which can be cited from a file of BibTeX, ending `.bib`,
+    /// or of CSL-JSON, ending `.json`
+    pub fn add_bibliography_source_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<&mut Self, std::io::Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let bibliography = match path.extension().and_then(|e| e.to_str()) {
+            Some("bib") => Bibliography::from_bibtex(&text)?,
+            Some("json") => Bibliography::from_csl_json(&text)?,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Unrecognised bibliography format",
+                ))
+            }
+        };
+        Ok(self.add_bibliography_source(bibliography))
+    }
+
+    /// Set how citations refer to works and how the generated bibliography lists them;
+    /// by default, this is `CitationStyle::AuthorDate`
+    pub fn set_citation_style(&mut self, style: CitationStyle) -> &mut Self {
+        self.citation_style = style;
+        self
+    }
+
+    /// Add terms and abbreviations whose first use in the text is marked;
+    /// every term is listed in a glossary generated after any added with `add_glossary`
+    pub fn add_glossary_source(&mut self, glossary: Glossary) -> &mut Self {
+        self.glossary.extend(glossary);
+        self
+    }
+
+    /// Add terms and abbreviations from a markdown definition list,
+    /// or from a data file of toml, yaml or json
+    pub fn add_glossary_source_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<&mut Self, std::io::Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let mut glossary = match path.extension().and_then(|e| e.to_str()) {
+            _ if path.is_markdown() => Glossary::from_markdown(&text),
+            Some("toml") => Glossary::from_toml(&text)?,
+            Some("yaml") | Some("yml") => Glossary::from_yaml(&text)?,
+            Some("json") => Glossary::from_json(&text)?,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Unrecognised glossary format",
+                ))
+            }
+        };
+        glossary.set_file(path);
+        Ok(self.add_glossary_source(glossary))
+    }
+
+    /// Set the templates from which the copyright page, halftitle and the text of the titlepage
+    /// are generated, as to write them in another language
+    pub fn set_page_templates(&mut self, templates: PageTemplates) -> &mut Self {
+        self.page_templates = templates;
+        self
+    }
+
+    /// Set the templates for generated pages from a data file of toml, yaml or json
+    pub fn set_page_templates_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<&mut Self, std::io::Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let templates = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => PageTemplates::from_toml(&text)?,
+            Some("yaml") | Some("yml") => PageTemplates::from_yaml(&text)?,
+            Some("json") => PageTemplates::from_json(&text)?,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Unrecognised page templates format",
+                ))
+            }
+        };
+        Ok(self.set_page_templates(templates))
+    }
+
     /// Set the copyright page of this book explicitly; if this is not set,
     /// it will be generated from metadata unless `do_not_generate_copyrightpage` was called.
     pub fn add_copyright_page<P: ParseHelper<'a>>(
@@ -627,7 +1361,8 @@ impl<'a> BookSrcBuilder<'a> {
         p: P,
     ) -> Result<&mut Self, std::io::Error> {
         let s = self.read_file_and_add_to_sources(p)?;
-        let (title, text) = s.parse_and_remove_initial_title_plain();
+        let (events, _) = self.parse_source(s);
+        let (title, text) = events.parse_and_remove_initial_title_plain();
         self.add_copyright_page(text, title);
         Ok(self)
     }
@@ -657,13 +1392,45 @@ impl<'a> BookSrcBuilder<'a> {
         doc = "Set a custom copyright statement"
     );
     metadata_bool_func!(do_not_assert_moral_rights, doc="Set this flag if you do not wish to assert the moral rights of the author on the copyright page");
+    metadata_func!(
+        first_published,
+        doc = "The year in which this book was first published, if this edition is a later one"
+    );
+    metadata_func!(
+        publication_date,
+        doc = "The date on which this edition is published, as `YYYY-MM-DD`; the copyright page, and the modification date of an epub or pdf, are dated from this rather than the time of rendering"
+    );
+    metadata_func!(
+        language,
+        doc = "The language of this book, as an IETF language tag such as `en` or `fr-CA`; an index is sorted by its rules, and an epub declares it"
+    );
     metadata_bool_func!(
         is_not_first_publication,
         doc = "Set this flag if this is not the book's first publication"
     );
 
+    /// Parse markdown about to be added, checking each event for problems as it is parsed
+    /// and noting where its images and labels are,
+    /// so that problems found only once the book is processed can be located too;
+    /// returns the events along with where its front matter and each of its headings were.
+    /// If the markdown was just read from a file, it is located in that file
+    fn parse_source<P: ParseHelper<'a>>(&mut self, text: P) -> (Vec<Event<'a>>, DivisionLocations) {
+        let sources = self
+            .pending_source
+            .take()
+            .unwrap_or_else(|| SourceLocation::start_of(None).into());
+        let front_matter = self.pending_front_matter.take();
+        let mut check = SourceCheck::new(sources, front_matter, &mut self.source_index);
+        let events =
+            text.parse_plain_inspected(|text, event, range| check.inspect(text, event, range));
+        let (mut diagnostics, locations) = check.finish();
+        self.diagnostics.append(&mut diagnostics);
+        (events, locations)
+    }
+
     /// a small helper function: given a filepath, check it is markdown, read it,
-    /// add the path to this book's resource list and return the string
+    /// replacing any include directives with the files they name,
+    /// add the path and those of any included files to this book's resource list and return the string
     fn read_file_and_add_to_sources<P: AsRef<Path>>(
         &mut self,
         path: P,
@@ -671,15 +1438,35 @@ impl<'a> BookSrcBuilder<'a> {
         let path = path.as_ref();
         if path.is_markdown() {
             let text = std::fs::read_to_string(path)?;
-            let r = path.canonicalize();
-            if let Ok(dir) = r {
-                if dir.is_dir() {
-                    self.image_dirs.insert(dir);
-                } else if let Some(parent) = dir.parent() {
-                    self.image_dirs.insert(parent.into());
+            let expanded = include::expand_includes(&text, path)?;
+            // the body of the file follows any front matter;
+            // if that is invalid, the file is not added, so nothing is left to be checked
+            let front_matter_length = match front_matter::split_front_matter(&expanded.text)? {
+                Some((_, body)) => expanded.text.len() - body.len(),
+                None => 0,
+            };
+            self.pending_source = Some(
+                expanded
+                    .sources
+                    .starting_at(&expanded.text, front_matter_length),
+            );
+            // front matter is always at the very start of the file
+            self.pending_front_matter = if front_matter_length > 0 {
+                Some(SourceLocation::start_of(Some(path.to_path_buf())))
+            } else {
+                None
+            };
+            for file in std::iter::once(path).chain(expanded.files.iter().map(PathBuf::as_path)) {
+                let r = file.canonicalize();
+                if let Ok(dir) = r {
+                    if dir.is_dir() {
+                        self.image_dirs.insert(dir);
+                    } else if let Some(parent) = dir.parent() {
+                        self.image_dirs.insert(parent.into());
+                    }
                 }
             }
-            Ok(text)
+            Ok(expanded.text)
         } else {
             Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
@@ -713,6 +1500,26 @@ impl<'a> BookSrcBuilder<'a> {
         self
     }
 
+    /// Set the work's series page, which typically lists other books in the same series
+    /// or by the same author and faces the titlepage.
+    pub fn set_series_page<S: ParseHelper<'a>>(&mut self, series_page: S) -> &mut Self {
+        let mut events = series_page.parse_plain();
+        events.make_paragraphs_unindented();
+        let mut events = events.parse();
+        events.wrap_division(SemanticRole::SeriesPage);
+        self.series_page = events;
+        self
+    }
+
+    /// Set the work's frontispiece, an illustration (with an optional caption)
+    /// placed immediately before the titlepage.
+    pub fn set_frontispiece<S: ParseHelper<'a>>(&mut self, frontispiece: S) -> &mut Self {
+        let mut events = frontispiece.parse();
+        events.wrap_division(SemanticRole::Frontispiece);
+        self.frontispiece = events;
+        self
+    }
+
     /// Set the work's colophon, a block of inline text with simple information
     /// about its production or licensing.
     pub fn set_colophon<S: ParseHelper<'a>>(&mut self, colophon: S) -> &mut Self {
@@ -769,6 +1576,15 @@ impl<'a> BookSrcBuilder<'a> {
         doc = "Add a preface from a file"
     );
 
+    add_unauthored_ancillary_text!(
+        add_dramatis_personae,
+        doc = "Add a dramatis personae listing the characters of a play; if none is added, one is generated from the speakers of any drama",
+        SemanticRole::DramatisPersonae,
+        dramatis_personae,
+        add_dramatis_personae_from_file,
+        doc = "Add a dramatis personae from a file"
+    );
+
     add_unauthored_ancillary_text!(
         add_acknowledgements,
         doc = "Add acknowledgements or an author's note",
@@ -778,11 +1594,75 @@ impl<'a> BookSrcBuilder<'a> {
         doc = "Add acknowledgements from a file"
     );
 
+    add_unauthored_ancillary_text!(
+        add_prologue,
+        doc = "Add a prologue, which will precede the mainmatter",
+        SemanticRole::Prologue,
+        prologues,
+        add_prologue_from_file,
+        doc = "Add a prologue from a file"
+    );
+
+    add_unauthored_ancillary_text!(
+        add_epilogue,
+        doc = "Add an epilogue, which will follow the mainmatter",
+        SemanticRole::Epilogue,
+        epilogues,
+        add_epilogue_from_file,
+        doc = "Add an epilogue from a file"
+    );
+
+    add_unauthored_ancillary_text!(
+        add_conclusion,
+        doc = "Add a conclusion, which will follow the mainmatter but precede any epilogue",
+        SemanticRole::Conclusion,
+        conclusions,
+        add_conclusion_from_file,
+        doc = "Add a conclusion from a file"
+    );
+
+    add_unauthored_ancillary_text!(
+        add_glossary,
+        doc = "Add a glossary",
+        SemanticRole::Glossary,
+        glossaries,
+        add_glossary_from_file,
+        doc = "Add a glossary from a file"
+    );
+
+    add_unauthored_ancillary_text!(
+        add_bibliography,
+        doc = "Add a bibliography or list of further reading",
+        SemanticRole::Bibliography,
+        bibliographies,
+        add_bibliography_from_file,
+        doc = "Add a bibliography from a file"
+    );
+
+    add_unauthored_ancillary_text!(
+        add_list_of_contributors,
+        doc = "Add a list of contributors, such as notes on the contributors to an anthology",
+        SemanticRole::Contributors,
+        contributors,
+        add_list_of_contributors_from_file,
+        doc = "Add a list of contributors from a file"
+    );
+
+    add_unauthored_ancillary_text!(
+        add_errata,
+        doc = "Add a list of errata",
+        SemanticRole::Errata,
+        errata,
+        add_errata_from_file,
+        doc = "Add a list of errata from a file"
+    );
+
     /// Add an appendix
     pub fn add_appendix<P>(&mut self, text: P, title: Option<P>) -> &mut Self
     where
         P: ParseHelper<'a>,
     {
+        let (text, _) = self.parse_source(text);
         let (mut title, mut text) = if title.is_none() {
             text.parse_and_remove_initial_title()
         } else {
@@ -802,7 +1682,7 @@ impl<'a> BookSrcBuilder<'a> {
         if let Some(label) = SemanticRole::Appendix.get_label() {
             let label = BookEvent::DivisionHeaderLabel {
                 text: Some(label.into()),
-                number: Some(self.appendices_count),
+                number: Some(self.appendices_count + 1),
                 number_format: NumberFormat::Letter,
             };
             events.push(label);
@@ -816,34 +1696,353 @@ impl<'a> BookSrcBuilder<'a> {
         self
     }
 
-    read_file_wrapper!(
-        doc = "Add an appendix from a file",
-        add_appendix_from_file,
-        add_appendix
-    );
+    /// Add an appendix from a file;
+    /// an appendix left unnumbered by its front matter does not affect the lettering of those which follow
+    pub fn add_appendix_from_file<P, S>(
+        &mut self,
+        filepath: P,
+        title: Option<S>,
+    ) -> Result<&mut Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+        S: ToString,
+    {
+        let s = self.read_file_and_add_to_sources(filepath)?;
+        let (front_matter, s) = s.split_front_matter()?;
+        let title = title
+            .map(|s| s.to_string())
+            .or_else(|| front_matter.as_ref().and_then(|f| f.title.clone()));
+        let start = self.appendices.len();
+        self.add_appendix(s, title);
+        if let Some(front_matter) = front_matter {
+            if front_matter.unnumbered {
+                self.appendices_count -= 1;
+            }
+            self.appendices
+                .apply_front_matter_from(start, &front_matter);
+        }
+        Ok(self)
+    }
 
-    /// Add a fragment of mainmatter
+    /// Add a fragment of mainmatter.
+    ///
+    /// A chapter or part can be given an epigraph by following its heading immediately
+    /// with a blockquote wrapped in `<div class="epigraph">`;
+    /// if the last paragraph of the blockquote begins with a dash, it is taken to be the epigraph's source.
+    /// Note that blank lines are needed around the blockquote.
+    ///
+    /// ```
+    /// use bookbinder_ast::{BookEvent, BookSrcBuilder, SemanticRole};
+    ///
+    /// let chapter = "# The Beginning\n\n<div class=\"epigraph\">\n\n> It was the best of times.\n>\n> --- Charles Dickens\n\n</div>\n\nText goes here...";
+    /// let src = BookSrcBuilder::new("A Book")
+    ///     .add_mainmatter(chapter)
+    ///     .process();
+    /// assert!(src.contents.contains(&BookEvent::BeginSemantic(SemanticRole::Epigraph)));
+    /// assert_eq!(src.expected_epigraph_count, 0);
+    /// ```
     pub fn add_mainmatter<P: ParseHelper<'a>>(&mut self, text: P) -> &mut Self {
-        let mut text = text.parse_plain();
-        self.mainmatter.append(&mut text);
+        let (events, locations) = self.parse_source(text);
+        self.mainmatter.push(MainmatterFragment {
+            events,
+            heading_mapping: None,
+            front_matter: None,
+            authors: Vec::new(),
+            locations,
+        });
         self
     }
 
-    /// Add a fragment of mainmatter from a file
+    /// Add a fragment of mainmatter from a file.
+    ///
+    /// If the file begins with front matter, this applies to the first part or chapter in the file,
+    /// or to a new division if it sets a title; see `FrontMatter`.
     pub fn add_mainmatter_from_file<P: AsRef<Path>>(
         &mut self,
         filepath: P,
     ) -> Result<&mut Self, std::io::Error> {
         let s = self.read_file_and_add_to_sources(filepath)?;
-        self.add_mainmatter(s);
+        let (front_matter, s) = s.split_front_matter()?;
+        let (events, locations) = self.parse_source(s);
+        self.mainmatter.push(MainmatterFragment {
+            events,
+            heading_mapping: front_matter.as_ref().and_then(|f| f.heading_mapping),
+            front_matter,
+            authors: Vec::new(),
+            locations,
+        });
         Ok(self)
     }
 
-    /// Add an epigraph
-    pub fn add_epigraph<P1, P2>(&mut self, text: P1, source: Option<P2>) -> &mut Self
+    /// Add a fragment of mainmatter whose headings are interpreted according to `mapping`,
+    /// rather than any mapping set for the book as a whole
+    pub fn add_mainmatter_with_heading_mapping<P: ParseHelper<'a>>(
+        &mut self,
+        text: P,
+        mapping: HeadingMapping,
+    ) -> &mut Self {
+        let (events, locations) = self.parse_source(text);
+        self.mainmatter.push(MainmatterFragment {
+            events,
+            heading_mapping: Some(mapping),
+            front_matter: None,
+            authors: Vec::new(),
+            locations,
+        });
+        self
+    }
+
+    /// Add a fragment of mainmatter from a file,
+    /// whose headings are interpreted according to `mapping`
+    /// rather than any mapping set for the book as a whole or in the file's front matter
+    pub fn add_mainmatter_from_file_with_heading_mapping<P: AsRef<Path>>(
+        &mut self,
+        filepath: P,
+        mapping: HeadingMapping,
+    ) -> Result<&mut Self, std::io::Error> {
+        let s = self.read_file_and_add_to_sources(filepath)?;
+        let (front_matter, s) = s.split_front_matter()?;
+        let (events, locations) = self.parse_source(s);
+        self.mainmatter.push(MainmatterFragment {
+            events,
+            heading_mapping: Some(mapping),
+            front_matter,
+            authors: Vec::new(),
+            locations,
+        });
+        Ok(self)
+    }
+
+    /// Add a fragment of mainmatter, such as an essay in a collection,
+    /// every chapter of which is attributed to `authors`.
+    /// If `mapping` is set, it is used to interpret the fragment's headings
+    /// rather than any mapping set for the book as a whole.
+    ///
+    /// The authors are shown beneath each chapter heading and in the table of contents,
+    /// and are added to the book's metadata as contributors.
+    ///
+    /// ```
+    /// use bookbinder_ast::{BookEvent, BookSrcBuilder, HeadingMapping};
+    ///
+    /// let src = BookSrcBuilder::new("Collected Essays")
+    ///     .editor("A. Editor")
+    ///     .add_authored_mainmatter("# On Brevity\n\nText goes here...", "A.N. Author", None)
+    ///     .add_authored_mainmatter(
+    ///         "# On Length\n\nText goes here...",
+    ///         vec!["A. Writer", "A.N. Other"],
+    ///         Some(HeadingMapping::default()),
+    ///     )
+    ///     .process();
+    /// assert!(src.contents.contains(&BookEvent::DivisionAuthors(vec!["A.N. Author".into()])));
+    /// assert_eq!(src.metadata.chapter_authors, vec!["A.N. Author", "A. Writer", "A.N. Other"]);
+    /// ```
+    pub fn add_authored_mainmatter<P, A>(
+        &mut self,
+        text: P,
+        authors: A,
+        mapping: Option<HeadingMapping>,
+    ) -> &mut Self
     where
-        P1: ParseHelper<'a>,
-        P2: ParseHelper<'a>,
+        P: ParseHelper<'a>,
+        A: ContributorSource<'a>,
+    {
+        let (events, locations) = self.parse_source(text);
+        self.mainmatter.push(MainmatterFragment {
+            events,
+            heading_mapping: mapping,
+            front_matter: None,
+            authors: authors.to_vec(),
+            locations,
+        });
+        self
+    }
+
+    /// Add a fragment of mainmatter from a file, every chapter of which is attributed to `authors`;
+    /// authors named in the file's front matter take their place for the division it applies to.
+    /// If `mapping` is set, it takes precedence over any heading mapping in the front matter
+    pub fn add_authored_mainmatter_from_file<P, A>(
+        &mut self,
+        filepath: P,
+        authors: A,
+        mapping: Option<HeadingMapping>,
+    ) -> Result<&mut Self, std::io::Error>
+    where
+        P: AsRef<Path>,
+        A: ContributorSource<'a>,
+    {
+        let s = self.read_file_and_add_to_sources(filepath)?;
+        let (front_matter, s) = s.split_front_matter()?;
+        let (events, locations) = self.parse_source(s);
+        self.mainmatter.push(MainmatterFragment {
+            events,
+            heading_mapping: mapping
+                .or_else(|| front_matter.as_ref().and_then(|f| f.heading_mapping)),
+            front_matter,
+            authors: authors.to_vec(),
+            locations,
+        });
+        Ok(self)
+    }
+
+    /// Fix which markdown heading levels in mainmatter mark parts, chapters, sections and subsections,
+    /// instead of inferring this from the heading levels present
+    pub fn set_heading_mapping(&mut self, mapping: HeadingMapping) -> &mut Self {
+        self.heading_mapping = Some(mapping);
+        self
+    }
+
+    /// Divide mainmatter into parts and chapters, returning the divided events
+    /// along with diagnostics of any headings which contradict a heading mapping
+    /// and any chapters which are empty
+    fn divide_mainmatter(&mut self) -> (Vec<BookEvent<'a>>, Vec<Diagnostic>) {
+        let fragments = std::mem::take(&mut self.mainmatter);
+        let book_mapping = self.heading_mapping;
+
+        // the structure of fragments without a mapping is inferred from all of them together
+        let inferred = StepLevel::get(
+            fragments
+                .iter()
+                .filter(|f| f.heading_mapping.or(book_mapping).is_none())
+                .flat_map(|f| f.events.iter()),
+        );
+
+        let mut divider = SectionDivider::default();
+        for fragment in fragments.into_iter() {
+            let structure = match fragment.heading_mapping.or(book_mapping) {
+                Some(mapping) => Structure::Mapped(mapping),
+                None => Structure::Inferred(inferred),
+            };
+            divider.divide(
+                fragment.events,
+                structure,
+                fragment.front_matter.as_ref(),
+                &fragment.authors,
+                &fragment.locations,
+            );
+        }
+        let (events, diagnostics, authors) = divider.finish();
+        self.metadata.add_chapter_authors(authors);
+        (events, diagnostics)
+    }
+
+    /// Add an existing, processed book as a volume of this one, making this book an omnibus.
+    ///
+    /// Volumes follow any mainmatter of this book, in the order in which they were added.
+    /// Each keeps its own titlepage, epigraphs and ancillary texts,
+    /// but its halftitle and copyright page are replaced by a volume heading
+    /// and the copyright page of the omnibus.
+    /// Any transforms registered on a volume will apply to the omnibus as a whole.
+    ///
+    /// ```
+    /// use bookbinder_ast::BookSrcBuilder;
+    ///
+    /// let first = BookSrcBuilder::new("The First Book")
+    ///     .add_mainmatter("# A Beginning\n\nText goes here...")
+    ///     .process();
+    /// let second = BookSrcBuilder::new("The Second Book")
+    ///     .add_mainmatter("# A Continuation\n\nMore text goes here...")
+    ///     .process();
+    /// let omnibus = BookSrcBuilder::new("The Complete Books")
+    ///     .add_volume(first)
+    ///     .add_volume(second)
+    ///     .number_chapters_continuously_across_volumes()
+    ///     .process();
+    /// ```
+    pub fn add_volume(&mut self, mut volume: BookSrc<'a>) -> &mut Self {
+        self.transforms.append(&mut volume.transforms);
+        self.volumes.push(volume);
+        self
+    }
+
+    /// Continue the numbering of chapters and parts from one volume to the next,
+    /// rather than restarting it in each volume
+    pub fn number_chapters_continuously_across_volumes(&mut self) -> &mut Self {
+        self.continuous_volume_numbering = true;
+        self
+    }
+
+    /// Merge any volumes into a single sequence of events;
+    /// each volume is wrapped in its own semantic division, opening with a heading
+    fn get_volumes(&mut self) -> Vec<BookEvent<'a>> {
+        let volumes = std::mem::take(&mut self.volumes);
+        let mut events = Vec::with_capacity(volumes.iter().map(|v| v.contents.len() + 6).sum());
+        let mut volume_count = 0;
+        let mut chapter_offset = 0;
+        let mut part_offset = 0;
+
+        for volume in volumes.into_iter() {
+            volume_count += 1;
+            events.push(BookEvent::BeginSemantic(SemanticRole::Volume));
+            events.push(BookEvent::BeginDivisionHeader(false));
+            events.append(&mut volume.metadata.title.parse_inline());
+            events.push(BookEvent::DivisionHeaderLabel {
+                text: SemanticRole::Volume.get_label().map(|l| l.into()),
+                number: Some(volume_count),
+                number_format: NumberFormat::Roman,
+            });
+            if !volume.metadata.authors.is_empty()
+                && volume.metadata.authors != self.metadata.authors
+            {
+                events.push(BookEvent::DivisionAuthors(volume.metadata.authors));
+            }
+            events.push(BookEvent::EndDivisionHeader(false));
+
+            let mut current_role = None;
+            let mut skipping = false;
+            let mut chapter_count = 0;
+            let mut part_count = 0;
+            for event in volume.contents.into_iter() {
+                match event {
+                    BookEvent::BeginSemantic(SemanticRole::Halftitle)
+                    | BookEvent::BeginSemantic(SemanticRole::Copyrightpage) => {
+                        skipping = true;
+                    }
+                    BookEvent::EndSemantic(SemanticRole::Halftitle)
+                    | BookEvent::EndSemantic(SemanticRole::Copyrightpage) => {
+                        skipping = false;
+                    }
+                    _ if skipping => {}
+                    BookEvent::BeginSemantic(role) => {
+                        current_role = Some(role);
+                        events.push(event);
+                    }
+                    BookEvent::DivisionHeaderLabel {
+                        text,
+                        number: Some(number),
+                        number_format,
+                    } if self.continuous_volume_numbering => {
+                        let number = match current_role {
+                            Some(SemanticRole::Chapter) => {
+                                chapter_count = chapter_count.max(number);
+                                number + chapter_offset
+                            }
+                            Some(SemanticRole::Part) => {
+                                part_count = part_count.max(number);
+                                number + part_offset
+                            }
+                            _ => number,
+                        };
+                        events.push(BookEvent::DivisionHeaderLabel {
+                            text,
+                            number: Some(number),
+                            number_format,
+                        });
+                    }
+                    other => events.push(other),
+                }
+            }
+            chapter_offset += chapter_count;
+            part_offset += part_count;
+            events.push(BookEvent::EndSemantic(SemanticRole::Volume));
+        }
+        events
+    }
+
+    /// Add an epigraph
+    pub fn add_epigraph<P1, P2>(&mut self, text: P1, source: Option<P2>) -> &mut Self
+    where
+        P1: ParseHelper<'a>,
+        P2: ParseHelper<'a>,
     {
         let mut epigraph = vec![
             BookEvent::BeginSemantic(SemanticRole::Epigraph),
@@ -915,7 +2114,18 @@ impl<'a> BookSrcBuilder<'a> {
 
     fn get_titlepage(&self) -> Vec<BookEvent<'a>> {
         let mut contents = Vec::with_capacity(5);
-        let contributors = self.metadata.get_titlepage_contributors();
+        let contributors = self
+            .metadata
+            .get_titlepage_contributors()
+            .into_iter()
+            .map(|(role, names)| {
+                let label = self
+                    .page_templates
+                    .contributor_label(role, &self.metadata)
+                    .map(Cow::Owned);
+                (label, names)
+            })
+            .collect();
         let mut title = self.metadata.get_title().to_string().parse_inline();
         let subtitle = self
             .metadata
@@ -938,25 +2148,90 @@ impl<'a> BookSrcBuilder<'a> {
 
     fn frontmatter_len(&self) -> usize {
         self.halftitle.len()
+            + self.series_page.len()
+            + self.frontispiece.len()
             + self.copyright_page.len()
             + self.dedication.len()
             + self.forewords.len()
             + self.epigraphs.len()
             + self.introductions.len()
             + self.prefaces.len()
+            + self.dramatis_personae.len()
+    }
+
+    fn mainmatter_len(&self) -> usize {
+        self.prologues.len() + self.conclusions.len() + self.epilogues.len()
     }
 
     fn backmatter_len(&self) -> usize {
         self.appendices.len()
             + self.afterwords.len()
+            + self.glossaries.len()
+            + self.bibliographies.len()
+            + self.contributors.len()
             + self.colophon.len()
             + self.acknowledgements.len()
+            + self.errata.len()
     }
 
     /// Finish processing this book, generate missing sections such as a titlepage (unless they have been suppressed),
     /// do some tidying like normalising image paths
-    /// and return a `BookSrc` ready for rendering
+    /// and return a `BookSrc` ready for rendering.
+    ///
+    /// Any problems found, such as missing images, are printed to stderr;
+    /// use `process_checked` to handle them instead.
     pub fn process(&mut self) -> BookSrc<'a> {
+        let (src, diagnostics) = self.process_with_diagnostics();
+        for diagnostic in diagnostics.iter() {
+            eprintln!("{}", diagnostic);
+        }
+        src
+    }
+
+    /// Treat any warnings found while processing as errors, so that `process_checked` fails
+    pub fn deny_warnings(&mut self) -> &mut Self {
+        self.deny_warnings = true;
+        self
+    }
+
+    /// Process this book like `process`, but return any problems found along with the `BookSrc`;
+    /// if any of them are errors, or are warnings and `deny_warnings` has been set,
+    /// return them all as an error instead.
+    ///
+    /// Problems found in markdown read from a file are located by file and line.
+    ///
+    /// ```
+    /// use bookbinder_ast::{BookSrcBuilder, DiagnosticKind, Severity};
+    ///
+    /// let (src, diagnostics) = BookSrcBuilder::new("A Book")
+    ///     .add_mainmatter("# A Chapter\n\nText goes here[^1]...\n\n# An Empty Chapter")
+    ///     .process_checked()
+    ///     .unwrap();
+    /// assert_eq!(diagnostics.len(), 2);
+    /// assert_eq!(diagnostics[0].kind, DiagnosticKind::EmptyFootnote("1".to_string()));
+    /// assert_eq!(diagnostics[0].location.as_ref().unwrap().line, 3);
+    /// assert_eq!(diagnostics[1].severity, Severity::Warning);
+    ///
+    /// let failed = BookSrcBuilder::new("A Book")
+    ///     .add_mainmatter("# An Empty Chapter")
+    ///     .deny_warnings()
+    ///     .process_checked();
+    /// assert!(failed.is_err());
+    /// ```
+    pub fn process_checked(&mut self) -> Result<(BookSrc<'a>, Vec<Diagnostic>), DiagnosticsError> {
+        let deny_warnings = self.deny_warnings;
+        let (src, diagnostics) = self.process_with_diagnostics();
+        if diagnostics
+            .iter()
+            .any(|d| deny_warnings || d.severity == Severity::Error)
+        {
+            Err(DiagnosticsError { diagnostics })
+        } else {
+            Ok((src, diagnostics))
+        }
+    }
+
+    fn process_with_diagnostics(&mut self) -> (BookSrc<'a>, Vec<Diagnostic>) {
         // first set some missing elements
 
         let mut estimated_len = 3;
@@ -970,39 +2245,66 @@ impl<'a> BookSrcBuilder<'a> {
         };
 
         if !self.no_halftitle && self.halftitle.is_empty() {
-            let shorttitle: Option<Cow<'a, str>> = match self.metadata.shorttitle.as_ref() {
-                Some(Cow::Borrowed(s)) => Some(Cow::Borrowed(s)),
-                Some(e) => Some(Cow::Owned(e.to_string())),
-                None => None,
-            };
-
-            let shorttitle = shorttitle.unwrap_or_else(|| match &self.metadata.title {
-                Cow::Borrowed(s) => Cow::Borrowed(s),
-                Cow::Owned(t) => Cow::Owned(t.clone()),
-            });
-            self.set_halftitle(shorttitle);
+            let halftitle = self.page_templates.halftitle(&self.metadata);
+            self.set_halftitle(halftitle);
         }
 
         if !self.no_copyrightpage && self.copyright_page.is_empty() {
             let shorttitle = self.metadata.get_short_title().to_string();
-            let cp = self.metadata.get_copyright_page_text();
+            let cp = self.page_templates.copyright_page(&self.metadata);
             self.add_copyright_page(cp, Some(shorttitle));
         }
 
         estimated_len += self.frontmatter_len();
 
-        let mut mainmatter = std::mem::take(&mut self.mainmatter).divide_into_sections();
+        let (mut mainmatter, mut mainmatter_diagnostics) = self.divide_mainmatter();
+        let volume_appendices_count: usize = self
+            .volumes
+            .iter()
+            .map(|v| v.expected_appendices_count)
+            .sum();
+        let volume_epigraph_count: usize =
+            self.volumes.iter().map(|v| v.expected_epigraph_count).sum();
+        let mut volumes = self.get_volumes();
+
+        if !self.no_dramatis_personae && self.dramatis_personae.is_empty() {
+            self.dramatis_personae = dramatis_personae(
+                [
+                    &self.prologues,
+                    &mainmatter,
+                    &volumes,
+                    &self.conclusions,
+                    &self.epilogues,
+                ]
+                .iter()
+                .flat_map(|events| events.iter()),
+            );
+            estimated_len += self.dramatis_personae.len();
+        }
 
         estimated_len += mainmatter.len();
+        estimated_len += volumes.len();
+        estimated_len += self.mainmatter_len();
         estimated_len += self.backmatter_len();
 
         let mut contents = Vec::with_capacity(estimated_len);
 
+        macro_rules! add_if_not_empty {
+            ($field:ident) => {
+                if !self.$field.is_empty() {
+                    contents.append(&mut self.$field);
+                }
+            };
+        }
+
         contents.push(BookEvent::BeginFrontmatter);
         if !self.no_halftitle {
             contents.append(&mut self.halftitle);
         }
 
+        add_if_not_empty!(series_page);
+        add_if_not_empty!(frontispiece);
+
         if let Some(mut titlepage) = titlepage {
             contents.append(&mut titlepage);
         }
@@ -1011,14 +2313,6 @@ impl<'a> BookSrcBuilder<'a> {
             contents.append(&mut self.copyright_page);
         }
 
-        macro_rules! add_if_not_empty {
-            ($field:ident) => {
-                if !self.$field.is_empty() {
-                    contents.append(&mut self.$field);
-                }
-            };
-        }
-
         add_if_not_empty!(dedication);
         add_if_not_empty!(forewords);
 
@@ -1034,57 +2328,209 @@ impl<'a> BookSrcBuilder<'a> {
         }
 
         add_if_not_empty!(prefaces);
+        add_if_not_empty!(dramatis_personae);
         contents.push(BookEvent::BeginMainmatter);
+        add_if_not_empty!(prologues);
         contents.append(&mut mainmatter);
+        contents.append(&mut volumes);
+        add_if_not_empty!(conclusions);
+        add_if_not_empty!(epilogues);
+
+        let (mut generated_bibliography, unknown_citations) = bibliography::resolve_citations(
+            &mut [
+                &mut contents,
+                &mut self.appendices,
+                &mut self.afterwords,
+                &mut self.glossaries,
+                &mut self.bibliographies,
+                &mut self.contributors,
+                &mut self.acknowledgements,
+                &mut self.errata,
+                &mut self.colophon,
+            ],
+            &self.bibliography,
+            self.citation_style,
+        );
+        self.bibliographies.append(&mut generated_bibliography);
+        for key in unknown_citations.into_iter() {
+            let locations = self.source_index.locate_citations(&key);
+            if locations.is_empty() {
+                mainmatter_diagnostics
+                    .push(Diagnostic::new(DiagnosticKind::UnknownCitation(key), None));
+            } else {
+                for location in locations.into_iter() {
+                    mainmatter_diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::UnknownCitation(key.clone()),
+                        Some(location),
+                    ));
+                }
+            }
+        }
 
-        if !self.appendices.is_empty()
-            || !self.afterwords.is_empty()
-            || !self.acknowledgements.is_empty()
-            || !self.colophon.is_empty()
-        {
+        let mut generated_glossary = glossary::mark_glossary_terms(
+            &mut [
+                &mut contents,
+                &mut self.appendices,
+                &mut self.afterwords,
+                &mut self.contributors,
+                &mut self.acknowledgements,
+                &mut self.errata,
+                &mut self.colophon,
+            ],
+            &self.glossary,
+        );
+        self.glossaries.append(&mut generated_glossary);
+        for (term, location) in self.glossary.located_duplicates() {
+            mainmatter_diagnostics.push(Diagnostic::new(
+                DiagnosticKind::DuplicateGlossaryTerm(term.clone()),
+                location.cloned(),
+            ));
+        }
+
+        let has_backmatter = self.backmatter_len() > 0;
+        if has_backmatter {
             contents.push(BookEvent::BeginBackmatter);
         }
 
         add_if_not_empty!(appendices);
         add_if_not_empty!(afterwords);
+        add_if_not_empty!(glossaries);
+        add_if_not_empty!(bibliographies);
+        add_if_not_empty!(contributors);
         add_if_not_empty!(acknowledgements);
+        add_if_not_empty!(errata);
+
+        // the index itself is generated by renderers from the terms marked throughout the book
+        let has_index_entries = contents
+            .iter()
+            .any(|event| matches!(event, BookEvent::Event(Event::IndexEntry(_))));
+        if has_index_entries && !self.no_index {
+            if !has_backmatter {
+                contents.push(BookEvent::BeginBackmatter);
+            }
+            contents.push(BookEvent::BeginSemantic(SemanticRole::Index));
+            contents.push(BookEvent::BeginDivisionHeader(false));
+            contents.push(BookEvent::DivisionHeaderLabel {
+                text: SemanticRole::Index.get_label().map(Cow::from),
+                number: None,
+                number_format: NumberFormat::Arabic,
+            });
+            contents.push(BookEvent::EndDivisionHeader(false));
+            contents.push(BookEvent::EndSemantic(SemanticRole::Index));
+        }
+
         add_if_not_empty!(colophon);
 
-        let image_dirs = self.image_dirs.drain().collect::<Vec<_>>();
+        let mut image_dirs = self.image_dirs.drain().collect::<Vec<_>>();
+        // searched in a fixed order, so that the same image is always found
+        image_dirs.sort();
+
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        diagnostics.append(&mut mainmatter_diagnostics);
+        for diagnostic in diagnostics.iter_mut() {
+            self.source_index.locate(diagnostic);
+        }
 
-        match contents.replace_missing_image_paths(&image_dirs) {
-            Ok(_) => {}
-            Err(missing_paths) => {
-                eprintln!("The following images could not be found:");
-                for item in missing_paths {
-                    eprintln!("- {:?}", item.display());
+        if let Err(mut missing_paths) = contents.replace_missing_image_paths(&image_dirs) {
+            missing_paths.sort();
+            for path in missing_paths.into_iter() {
+                let locations = self.source_index.locate_image(&path);
+                if locations.is_empty() {
+                    diagnostics.push(Diagnostic::new(DiagnosticKind::MissingImage(path), None));
+                } else {
+                    for location in locations.into_iter() {
+                        diagnostics.push(Diagnostic::new(
+                            DiagnosticKind::MissingImage(path.clone()),
+                            Some(location),
+                        ));
+                    }
                 }
             }
         }
 
-        BookSrc {
+        let src = BookSrc {
             metadata: std::mem::take(&mut self.metadata),
             contents,
-            expected_epigraph_count: self.epigraph_count,
-            expected_appendices_count: self.appendices_count.into(),
+            expected_epigraph_count: self.epigraph_count + volume_epigraph_count,
+            expected_appendices_count: self.appendices_count + volume_appendices_count,
+            transforms: std::mem::take(&mut self.transforms),
+        };
+
+        let cross_references = src.cross_references();
+        for label in cross_references.duplicates() {
+            let mut diagnostic =
+                Diagnostic::new(DiagnosticKind::DuplicateLabel(label.clone()), None);
+            self.source_index.locate(&mut diagnostic);
+            diagnostics.push(diagnostic);
+        }
+        for label in cross_references.dangling() {
+            let locations = self.source_index.locate_references(label);
+            if locations.is_empty() {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::DanglingReference(label.clone()),
+                    None,
+                ));
+            } else {
+                for location in locations.into_iter() {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::DanglingReference(label.clone()),
+                        Some(location),
+                    ));
+                }
+            }
+        }
+        if let Some(date) = src.metadata.invalid_publication_date() {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::InvalidPublicationDate(date.to_string()),
+                None,
+            ));
         }
+        (src, diagnostics)
     }
 }
 
-/// A processed book source ready to be rendered
-#[derive(Debug, Clone, Default)]
+/// A processed book source ready to be rendered.
+///
+/// To save or exchange a processed source, wrap it in a `BookDocument`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BookSrc<'a> {
     /// associated metadata of this book
     pub metadata: Metadata<'a>,
     /// the events which make up this book
     pub contents: Vec<BookEvent<'a>>,
-    /// the number of appendices in this book
+    /// the number of appendices in this book,
+    /// not counting any which are unnumbered
     pub expected_appendices_count: usize,
-    /// the number of epigraphs to this work
+    /// the number of epigraphs to this work as a whole,
+    /// not counting those of particular chapters or parts
     pub expected_epigraph_count: usize,
+    /// transforms to apply before rendering; these are not serialized
+    #[serde(skip)]
+    pub transforms: TransformPipeline,
 }
 
 impl<'a> BookSrc<'a> {
+    /// Count the words, footnotes and images in this book,
+    /// overall, in its front, main and back matter, and in each division.
+    ///
+    /// Only content common to every output is counted;
+    /// use `statistics_for` to include that marked as only for a particular format.
+    pub fn statistics(&self) -> Statistics {
+        statistics::count(&self.contents, None)
+    }
+
+    /// Count the words, footnotes and images in this book as it is rendered to `target`,
+    /// leaving out content marked as only for other formats
+    pub fn statistics_for(&self, target: RenderTarget) -> Statistics {
+        statistics::count(&self.contents, Some(target))
+    }
+
+    /// Check the typography of this book and the consistency of contributors' names
+    /// against every lint rule not disabled in `options`
+    pub fn lint(&self, options: &LintOptions) -> Vec<Lint> {
+        lint::lint(&self.contents, &self.metadata, options)
+    }
+
     /// Change chapter and part headers to be of the specified format
     pub fn change_headers(&mut self, format: TextHeaderOptions) {
         let mut in_chapter = false;
@@ -1109,11 +2555,12 @@ impl<'a> BookSrc<'a> {
                 BookEvent::EndSemantic(SemanticRole::Part) => {
                     in_part = false;
                 }
-                BookEvent::BeginDivisionHeader(_) if in_chapter => {
-                    in_chapter_header = true;
+                // unnumbered headers are left as they are
+                BookEvent::BeginDivisionHeader(is_starred) if in_chapter => {
+                    in_chapter_header = !*is_starred;
                 }
-                BookEvent::BeginDivisionHeader(_) if in_part => {
-                    in_part_header = true;
+                BookEvent::BeginDivisionHeader(is_starred) if in_part => {
+                    in_part_header = !*is_starred;
                 }
                 BookEvent::EndDivisionHeader(_) if in_chapter => {
                     in_chapter_header = false;
@@ -1155,7 +2602,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                     }
                 }
                 for text in chapter_header_text.into_iter() {
@@ -1175,7 +2622,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                     }
                 }
             }
@@ -1187,7 +2634,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                     }
                 }
                 for text in chapter_header_text.into_iter() {
@@ -1202,7 +2649,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                         *text = None;
                     }
                 }
@@ -1217,7 +2664,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                     }
                 }
                 for text in part_header_text.into_iter() {
@@ -1237,7 +2684,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                     }
                 }
             }
@@ -1249,7 +2696,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                         *number = None;
                     }
                 }
@@ -1265,7 +2712,7 @@ impl<'a> BookSrc<'a> {
                         number_format,
                     } = label
                     {
-                        *number_format = n;
+                        *number_format = n.clone();
                         *text = None;
                     }
                 }
@@ -1296,7 +2743,7 @@ impl Default for HeaderFormat {
 }
 
 /// Specification of text headers
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct TextHeaderOptions {
     /// how to format chapter headers
     pub chapter_header_format: HeaderFormat,
@@ -1330,6 +2777,16 @@ impl TextHeaderOptions {
         self.chapter_number_format = NumberFormat::Roman;
     }
 
+    /// set these options to use `format` for chapter numbers
+    pub fn set_chapter_number_format(&mut self, format: NumberFormat) {
+        self.chapter_number_format = format;
+    }
+
+    /// set these options to use `format` for part numbers
+    pub fn set_part_number_format(&mut self, format: NumberFormat) {
+        self.part_number_format = format;
+    }
+
     /// set these options to suppress chapter labels
     pub fn suppress_chapter_labels(&mut self) {
         self.chapter_header_format = HeaderFormat::TitleAlone;
@@ -1353,6 +2810,16 @@ pub trait ParseHelper<'a>: Sized {
     /// parse into Events with the appropriate lifetime,
     /// flattening footnotes if required
     fn parse_plain(self) -> Vec<Event<'a>>;
+    /// parse into Events as `parse_plain` does, first passing each to `inspect`
+    /// along with the markdown text of the source and the byte range of the event within it;
+    /// sources already parsed have no text, and so nothing is inspected
+    fn parse_plain_inspected<F>(self, inspect: F) -> Vec<Event<'a>>
+    where
+        F: FnMut(&str, &Event<'_>, Range<usize>),
+    {
+        let _ = inspect;
+        self.parse_plain()
+    }
     /// parse into inline BookEvents with the appropriate lifetime,
     fn parse_inline(self) -> Vec<BookEvent<'a>> {
         self.parse_inline_plain()
@@ -1385,6 +2852,12 @@ pub trait ParseHelper<'a>: Sized {
         };
         (title, events)
     }
+    /// split off any block of yaml or toml front matter at the start of the source,
+    /// returning it along with the rest of the source;
+    /// sources which have already been parsed have none
+    fn split_front_matter(self) -> Result<(Option<FrontMatter>, Self), FrontMatterError> {
+        Ok((None, self))
+    }
 }
 
 impl<'a> ParseHelper<'a> for String {
@@ -1394,15 +2867,33 @@ impl<'a> ParseHelper<'a> for String {
             .collect::<Vec<_>>()
     }
 
+    fn split_front_matter(self) -> Result<(Option<FrontMatter>, Self), FrontMatterError> {
+        match front_matter::split_front_matter(&self)? {
+            Some((front_matter, body)) => Ok((Some(front_matter), body.to_string())),
+            None => Ok((None, self)),
+        }
+    }
+
     fn parse_plain(self) -> Vec<Event<'a>> {
+        self.parse_plain_inspected(|_, _, _| {})
+    }
+
+    fn parse_plain_inspected<F>(self, mut inspect: F) -> Vec<Event<'a>>
+    where
+        F: FnMut(&str, &Event<'_>, Range<usize>),
+    {
         let mut has_footnotes = false;
         let mut events = Parser::new(&self)
-            .map(|e| match e {
-                e @ Event::FootnoteReference(_) => {
-                    has_footnotes = true;
-                    e.into_static()
+            .into_offset_iter()
+            .map(|(e, range)| {
+                inspect(&self, &e, range);
+                match e {
+                    e @ Event::FootnoteReference(_) => {
+                        has_footnotes = true;
+                        e.into_static()
+                    }
+                    e => e.into_static(),
                 }
-                e => e.into_static(),
             })
             .collect::<Vec<_>>();
 
@@ -1418,15 +2909,33 @@ impl<'a> ParseHelper<'a> for &'a str {
         InlineParser::new(self).collect::<Vec<_>>()
     }
 
+    fn split_front_matter(self) -> Result<(Option<FrontMatter>, Self), FrontMatterError> {
+        match front_matter::split_front_matter(self)? {
+            Some((front_matter, body)) => Ok((Some(front_matter), body)),
+            None => Ok((None, self)),
+        }
+    }
+
     fn parse_plain(self) -> Vec<Event<'a>> {
+        self.parse_plain_inspected(|_, _, _| {})
+    }
+
+    fn parse_plain_inspected<F>(self, mut inspect: F) -> Vec<Event<'a>>
+    where
+        F: FnMut(&str, &Event<'_>, Range<usize>),
+    {
         let mut has_footnotes = false;
         let mut events = Parser::new(self)
-            .map(|e| match e {
-                e @ Event::FootnoteReference(_) => {
-                    has_footnotes = true;
-                    e
+            .into_offset_iter()
+            .map(|(e, range)| {
+                inspect(self, &e, range);
+                match e {
+                    e @ Event::FootnoteReference(_) => {
+                        has_footnotes = true;
+                        e
+                    }
+                    _ => e,
                 }
-                _ => e,
             })
             .collect::<Vec<_>>();
         if has_footnotes {
@@ -1450,6 +2959,27 @@ impl<'a> ParseHelper<'a> for Cow<'a, str> {
             Cow::Owned(s) => s.parse_plain(),
         }
     }
+
+    fn parse_plain_inspected<F>(self, inspect: F) -> Vec<Event<'a>>
+    where
+        F: FnMut(&str, &Event<'_>, Range<usize>),
+    {
+        match self {
+            Cow::Borrowed(s) => s.parse_plain_inspected(inspect),
+            Cow::Owned(s) => s.parse_plain_inspected(inspect),
+        }
+    }
+
+    fn split_front_matter(self) -> Result<(Option<FrontMatter>, Self), FrontMatterError> {
+        match self {
+            Cow::Borrowed(s) => s
+                .split_front_matter()
+                .map(|(front_matter, body)| (front_matter, Cow::Borrowed(body))),
+            Cow::Owned(s) => s
+                .split_front_matter()
+                .map(|(front_matter, body)| (front_matter, Cow::Owned(body))),
+        }
+    }
 }
 
 impl<'a> ParseHelper<'a> for CowStr<'a> {
@@ -1466,6 +2996,28 @@ impl<'a> ParseHelper<'a> for CowStr<'a> {
             _ => self.to_string().parse_plain(),
         }
     }
+
+    fn parse_plain_inspected<F>(self, inspect: F) -> Vec<Event<'a>>
+    where
+        F: FnMut(&str, &Event<'_>, Range<usize>),
+    {
+        match self {
+            CowStr::Borrowed(s) => s.parse_plain_inspected(inspect),
+            _ => self.to_string().parse_plain_inspected(inspect),
+        }
+    }
+
+    fn split_front_matter(self) -> Result<(Option<FrontMatter>, Self), FrontMatterError> {
+        match self {
+            CowStr::Borrowed(s) => s
+                .split_front_matter()
+                .map(|(front_matter, body)| (front_matter, CowStr::Borrowed(body))),
+            _ => self
+                .to_string()
+                .split_front_matter()
+                .map(|(front_matter, body)| (front_matter, body.into())),
+        }
+    }
 }
 
 impl<'a> ParseHelper<'a> for Vec<Event<'a>> {
@@ -1559,105 +3111,27 @@ impl<'a> EventHelper<'a> for Vec<Event<'a>> {
     }
 
     fn divide_into_sections(self) -> Vec<BookEvent<'a>> {
-        let step_level = StepLevel::get(self.iter());
-        let mut collated = Vec::with_capacity(self.len());
-        let mut in_chapter = false;
-        let mut part_count = 0;
-        let mut chapter_count = 0;
-
-        let chapter_label: Option<Cow<'static, str>> =
-            SemanticRole::Chapter.get_label().map(|l| l.into());
-        let part_label: Option<Cow<'static, str>> =
-            SemanticRole::Part.get_label().map(|l| l.into());
-
-        for event in self.into_iter() {
-            match step_level {
-                StepLevel::TopToChapter => match event {
-                    Event::Start(Tag::Heading(1)) => {
-                        if in_chapter {
-                            collated.push(BookEvent::EndSemantic(SemanticRole::Chapter));
-                        }
-                        collated.push(BookEvent::BeginSemantic(SemanticRole::Chapter));
-                        in_chapter = true;
-                        collated.push(BookEvent::BeginDivisionHeader(false));
-                        chapter_count += 1;
-                        collated.push(BookEvent::DivisionHeaderLabel {
-                            text: chapter_label.clone(),
-                            number: Some(chapter_count),
-                            number_format: NumberFormat::Arabic,
-                        });
-                    }
-                    Event::End(Tag::Heading(1)) => {
-                        collated.push(BookEvent::EndDivisionHeader(false));
-                    }
-                    Event::Start(Tag::Heading(h)) => {
-                        collated.push(Event::Start(Tag::Heading(h + 1)).into())
-                    }
-                    Event::End(Tag::Heading(h)) => {
-                        collated.push(Event::End(Tag::Heading(h + 1)).into())
-                    }
-                    other => collated.push(other.into()),
-                },
-                StepLevel::TopToParts => match event {
-                    Event::Start(Tag::Heading(1)) => {
-                        if in_chapter {
-                            collated.push(BookEvent::EndSemantic(SemanticRole::Chapter));
-                            in_chapter = false;
-                        }
-                        collated.push(BookEvent::BeginSemantic(SemanticRole::Part));
-                        collated.push(BookEvent::BeginDivisionHeader(false));
-                        part_count += 1;
-                        collated.push(BookEvent::DivisionHeaderLabel {
-                            text: part_label.clone(),
-                            number: Some(part_count),
-                            number_format: NumberFormat::Roman,
-                        });
-                    }
-                    Event::End(Tag::Heading(1)) => {
-                        collated.push(BookEvent::EndDivisionHeader(false));
-                        collated.push(BookEvent::EndSemantic(SemanticRole::Part));
-                    }
-                    Event::Start(Tag::Heading(2)) => {
-                        if in_chapter {
-                            collated.push(BookEvent::EndSemantic(SemanticRole::Chapter));
-                        }
-                        collated.push(BookEvent::BeginSemantic(SemanticRole::Chapter));
-                        in_chapter = true;
-                        collated.push(BookEvent::BeginDivisionHeader(false));
-                        chapter_count += 1;
-                        collated.push(BookEvent::DivisionHeaderLabel {
-                            text: chapter_label.clone(),
-                            number: Some(chapter_count),
-                            number_format: NumberFormat::Arabic,
-                        });
-                    }
-                    Event::End(Tag::Heading(2)) => {
-                        collated.push(BookEvent::EndDivisionHeader(false));
-                    }
-                    Event::Start(Tag::Heading(h)) => {
-                        collated.push(Event::Start(Tag::Heading(h + 2)).into());
-                    }
-                    Event::End(Tag::Heading(h)) => {
-                        collated.push(Event::End(Tag::Heading(h + 2)).into());
-                    }
-                    other => collated.push(other.into()),
-                },
-            }
-        }
-
-        if in_chapter {
-            collated.push(BookEvent::EndSemantic(SemanticRole::Chapter));
-        }
-        collated
+        let structure = Structure::Inferred(StepLevel::get(self.iter()));
+        let mut divider = SectionDivider::default();
+        divider.divide(self, structure, None, &[], &DivisionLocations::default());
+        divider.finish().0
     }
 }
 
 trait BookEventHelper {
     fn wrap_division(&mut self, role: SemanticRole);
+    fn apply_front_matter_from(&mut self, start: usize, front_matter: &FrontMatter);
     fn replace_missing_image_paths(&mut self, image_dirs: &[PathBuf]) -> Result<(), Vec<PathBuf>>;
 }
 
 impl<'a> BookEventHelper for Vec<BookEvent<'a>> {
+    /// apply front matter to the single division beginning at `start`
+    fn apply_front_matter_from(&mut self, start: usize, front_matter: &FrontMatter) {
+        let mut division = self.split_off(start);
+        front_matter.apply_to(&mut division);
+        self.append(&mut division);
+    }
+
     fn wrap_division(&mut self, role: SemanticRole) {
         self.insert(0, BookEvent::BeginSemantic(role));
         self.push(BookEvent::EndSemantic(role));
@@ -1735,4 +3209,1114 @@ mod tests {
         ];
         assert_eq!(title, Some(expected_title));
     }
+
+    #[test]
+    fn document_roundtrip() {
+        let src = BookSrcBuilder::new("A Book")
+            .author("A.N. Author")
+            .add_mainmatter(
+                "# Hello *world*\n\nText[^1] with a [link](http://example.com).\n\n[^1]: A note",
+            )
+            .process();
+        let document = BookDocument::new(src.clone());
+
+        let json = document.to_json().unwrap();
+        let from_json = BookDocument::from_json(&json).unwrap().into_src();
+        assert_eq!(from_json.contents, src.contents);
+        assert_eq!(from_json.metadata.authors, src.metadata.authors);
+
+        let bytes = document.to_bytes().unwrap();
+        let from_bytes = BookDocument::from_bytes(&bytes).unwrap().into_src();
+        assert_eq!(from_bytes.contents, src.contents);
+
+        let current = format!("\"version\":{}", DOCUMENT_FORMAT_VERSION);
+        let future = json.replacen(&current, "\"version\":999", 1);
+        assert!(matches!(
+            BookDocument::from_json(&future),
+            Err(DocumentError::UnsupportedVersion(999))
+        ));
+    }
+
+    #[derive(Debug)]
+    struct AppendText(&'static str);
+
+    impl BookTransform for AppendText {
+        fn transform<'a>(&self, events: &mut Vec<BookEvent<'a>>, _metadata: &mut Metadata<'a>) {
+            events.push(Event::Text(self.0.into()).into());
+        }
+    }
+
+    #[test]
+    fn transforms_are_ordered_and_filtered() {
+        let mut builder = BookSrcBuilder::new("A Book");
+        builder
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_transform(AppendText("first"))
+            .add_transform_for(AppendText("epub only"), RenderTarget::Epub)
+            .add_transform_for(AppendText("latex only"), RenderTarget::Latex);
+        let mut additional = TransformPipeline::default();
+        additional.add(AppendText("last"));
+
+        let mut src = builder.process();
+        let untransformed_len = src.contents.len();
+        src.apply_transforms(RenderTarget::Epub, &additional);
+        let expected: Vec<BookEvent> = vec![
+            Event::Text("first".into()).into(),
+            Event::Text("epub only".into()).into(),
+            Event::Text("last".into()).into(),
+        ];
+        assert_eq!(src.contents[untransformed_len..], expected[..]);
+
+        // source transforms are only applied once
+        src.apply_transforms(RenderTarget::Epub, &TransformPipeline::default());
+        assert_eq!(src.contents[untransformed_len..], expected[..]);
+    }
+
+    #[test]
+    fn format_only_content_is_kept_until_rendering() {
+        let text = |t: &'static str| BookEvent::Event(Event::Text(t.into()));
+        let src = BookSrcBuilder::new("A Book")
+            .copyright_statement(
+                r#"All rights reserved<span class="epub-only">, including in this electronic edition</span>"#,
+            )
+            .add_mainmatter("# One\n\n<div class=\"pdf-only\">\n\nSee page 32.\n\n</div>\n\nText.")
+            .process();
+        assert!(src
+            .contents
+            .contains(&BookEvent::Event(Event::Start(Tag::FormatOnly(
+                "pdf".into()
+            )))));
+        assert!(src
+            .contents
+            .contains(&text(", including in this electronic edition")));
+
+        let mut epub = src.clone();
+        epub.select_format(RenderTarget::Epub);
+        assert!(epub
+            .contents
+            .contains(&text(", including in this electronic edition")));
+        assert!(!epub.contents.contains(&text("See page 32.")));
+        assert!(epub.contents.contains(&text("Text.")));
+
+        let mut latex = src;
+        latex.select_format(RenderTarget::Latex);
+        assert!(!latex
+            .contents
+            .contains(&text(", including in this electronic edition")));
+        assert!(latex.contents.contains(&text("See page 32.")));
+        assert!(!latex
+            .contents
+            .iter()
+            .any(|e| matches!(e, BookEvent::Event(Event::Start(Tag::FormatOnly(_))))));
+    }
+
+    #[test]
+    fn format_only_content_is_counted_per_output() {
+        let src = BookSrcBuilder::new("A Book")
+            .add_mainmatter(
+                r#"# One
+
+See <span class="pdf-only">page 32</span><span class="epub-only">the next chapter</span>.
+
+<div class="pdf-only">
+
+Printed in Australia.
+
+</div>"#,
+            )
+            .process();
+        let words = |statistics: Statistics| statistics.mainmatter_words;
+        assert_eq!(words(src.statistics()), 2);
+        assert_eq!(words(src.statistics_for(RenderTarget::Epub)), 5);
+        assert_eq!(words(src.statistics_for(RenderTarget::Latex)), 7);
+    }
+
+    #[test]
+    fn volumes_are_merged_and_renumbered() {
+        let volume = |title: &'static str| {
+            BookSrcBuilder::new(title)
+                .add_epigraph("An epigraph", None::<&str>)
+                .add_mainmatter("# One\n\nText.\n\n# Two\n\nText.")
+                .process()
+        };
+        let src = BookSrcBuilder::new("An Omnibus")
+            .add_volume(volume("The First Book"))
+            .add_volume(volume("The Second Book"))
+            .number_chapters_continuously_across_volumes()
+            .process();
+
+        assert_eq!(src.expected_epigraph_count, 2);
+        let count_of = |target: BookEvent| src.contents.iter().filter(|e| **e == target).count();
+        assert_eq!(count_of(BookEvent::BeginSemantic(SemanticRole::Volume)), 2);
+        assert_eq!(
+            count_of(BookEvent::BeginSemantic(SemanticRole::Halftitle)),
+            1
+        );
+        assert_eq!(count_of(BookEvent::BeginTitlePage), 3);
+
+        let chapter_numbers: Vec<usize> = src
+            .contents
+            .iter()
+            .filter_map(|event| match event {
+                BookEvent::DivisionHeaderLabel {
+                    text: Some(text),
+                    number: Some(number),
+                    ..
+                } if text == "Chapter" => Some(*number),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(chapter_numbers, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn structural_roles_are_ordered() {
+        let src = BookSrcBuilder::new("A Book")
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_errata("Page 1 is wrong.", None)
+            .add_epilogue("Afterwards.", None)
+            .add_glossary("Terms.", None)
+            .add_mainmatter("# A Chapter\n\nText.")
+            .add_prologue("Beforehand.", None)
+            .set_series_page("Also in this series")
+            .process();
+        let roles: Vec<SemanticRole> = src
+            .contents
+            .iter()
+            .filter_map(|event| match event {
+                BookEvent::BeginSemantic(role) => Some(*role),
+                _ => None,
+            })
+            .collect();
+        let expected = vec![
+            SemanticRole::SeriesPage,
+            SemanticRole::Prologue,
+            SemanticRole::Chapter,
+            SemanticRole::Epilogue,
+            SemanticRole::Glossary,
+            SemanticRole::Errata,
+        ];
+        assert_eq!(roles, expected);
+    }
+
+    #[test]
+    fn heading_mapping_fixes_structure() {
+        let text = "# One\n\nText.\n\n# Two\n\n## A Section\n\nText.";
+        let roles_of = |src: BookSrc| -> Vec<SemanticRole> {
+            src.contents
+                .iter()
+                .filter_map(|event| match event {
+                    BookEvent::BeginSemantic(role) => Some(*role),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let inferred = BookSrcBuilder::new("A Book")
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_mainmatter(text)
+            .process();
+        assert_eq!(
+            roles_of(inferred),
+            vec![
+                SemanticRole::Part,
+                SemanticRole::Part,
+                SemanticRole::Chapter
+            ]
+        );
+
+        let mapped = BookSrcBuilder::new("A Book")
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .set_heading_mapping(HeadingMapping::default())
+            .add_mainmatter(text)
+            .add_mainmatter_with_heading_mapping(
+                "# Three\n\n## Four\n\nText.",
+                HeadingMapping::with_parts(),
+            )
+            .process();
+        assert_eq!(
+            roles_of(mapped),
+            vec![
+                SemanticRole::Chapter,
+                SemanticRole::Chapter,
+                SemanticRole::Part,
+                SemanticRole::Chapter
+            ]
+        );
+    }
+
+    #[test]
+    fn heading_mapping_contradictions_are_reported() {
+        let mapping = HeadingMapping {
+            part: None,
+            chapter: 2,
+            section: None,
+            subsection: None,
+        };
+        let mut divider = SectionDivider::default();
+        divider.divide(
+            "### Too early\n\n# Too high\n\n## A Chapter\n\n### A Section".parse_plain(),
+            Structure::Mapped(mapping),
+            None,
+            &[],
+            &DivisionLocations::default(),
+        );
+        let (events, diagnostics, _) = divider.finish();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].kind,
+            DiagnosticKind::SectionOutsideChapter {
+                heading: "Too early".to_string(),
+                level: 3
+            }
+        );
+        assert_eq!(
+            diagnostics[1].kind,
+            DiagnosticKind::UnmappedHeading {
+                heading: "Too high".to_string(),
+                level: 1
+            }
+        );
+        assert!(events.contains(&Event::Start(Tag::Heading(3)).into()));
+    }
+
+    #[test]
+    fn label_numbers_are_unlimited() {
+        let mut builder = BookSrcBuilder::new("A Long Book");
+        for _ in 0..300 {
+            builder.add_mainmatter("# A Chapter\n\nText.");
+        }
+        for _ in 0..27 {
+            builder.add_appendix("Text.", None);
+        }
+        let src = builder.process();
+        let labels: Vec<String> = src
+            .contents
+            .iter()
+            .filter_map(|event| match event {
+                BookEvent::DivisionHeaderLabel {
+                    number: Some(number),
+                    number_format,
+                    ..
+                } => Some(number_format.format(*number)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(labels.len(), 327);
+        assert_eq!(labels[299], "300");
+        assert_eq!(labels[300], "A");
+        assert_eq!(labels[326], "AA");
+    }
+
+    #[test]
+    fn division_epigraphs_are_nested() {
+        let text = concat!(
+            "# Part One\n\n<div class=\"epigraph\">\n\n",
+            "> All the world's a stage.\n\n</div>\n\n",
+            "## A Chapter\n\n<div class=\"epigraph\">\n\n",
+            "> It was the best of times.\n>\n> --- Charles Dickens\n\n</div>\n\nText."
+        );
+        let src = BookSrcBuilder::new("A Book").add_mainmatter(text).process();
+        assert_eq!(src.expected_epigraph_count, 0);
+
+        let position = |target: &BookEvent<'_>| src.contents.iter().position(|e| e == target);
+        let epigraphs: Vec<usize> = src
+            .contents
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| **e == BookEvent::BeginSemantic(SemanticRole::Epigraph))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(epigraphs.len(), 2);
+        let end_part = position(&BookEvent::EndSemantic(SemanticRole::Part)).unwrap();
+        let begin_chapter = position(&BookEvent::BeginSemantic(SemanticRole::Chapter)).unwrap();
+        let end_chapter = position(&BookEvent::EndSemantic(SemanticRole::Chapter)).unwrap();
+        assert!(epigraphs[0] < end_part);
+        assert!(begin_chapter < epigraphs[1] && epigraphs[1] < end_chapter);
+        assert!(src
+            .contents
+            .contains(&BookEvent::Event(Event::Text("Charles Dickens".into()))));
+    }
+
+    /// write `text` to a temporary markdown file and return its path
+    /// a markdown file written for a test, which is removed when dropped
+    struct TempMarkdown(PathBuf);
+
+    impl std::ops::Deref for TempMarkdown {
+        type Target = PathBuf;
+
+        fn deref(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempMarkdown {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempMarkdown {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// a directory of its own for a test, removed along with everything in it when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            use temp_file_name::TempFilePath;
+            let path = (name, std::process::id()).temp_file_path(Some("bookbinder-tests"), "d");
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = PathBuf;
+
+        fn deref(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn temp_markdown(text: &str) -> TempMarkdown {
+        use temp_file_name::TempFilePath;
+        let path = text.temp_file_path(Some("bookbinder-tests"), "md");
+        std::fs::write(&path, text).unwrap();
+        TempMarkdown(path)
+    }
+
+    #[test]
+    fn included_files_are_expanded_and_located() {
+        let child =
+            temp_markdown("# A Chapter\n\nText.\n\n![An image](missing-included-image.png)\n");
+        let parent = temp_markdown(&format!(
+            "---\ntitle: ignored\n---\n# A Part\n\n{{<{}|+1}}\n\n![Another](missing-parent-image.png)",
+            child.file_name().unwrap().to_string_lossy()
+        ));
+        let diagnostics = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&parent)
+            .unwrap()
+            .process_checked()
+            .unwrap_err()
+            .diagnostics;
+        let locations = diagnostics
+            .iter()
+            .filter_map(|d| d.location.clone())
+            .collect::<Vec<_>>();
+        assert!(locations.contains(&SourceLocation {
+            file: Some(child.clone()),
+            line: 5
+        }));
+        assert!(locations.contains(&SourceLocation {
+            file: Some(parent.clone()),
+            line: 8
+        }));
+
+        let src = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&parent)
+            .unwrap()
+            .process();
+        assert!(src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::Part)));
+        assert!(src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::Chapter)));
+
+        let dir = TempDir::new("include-cycle");
+        let first = dir.join("first.md");
+        std::fs::write(&first, "Text.\n\n{<second.md}\n").unwrap();
+        std::fs::write(dir.join("second.md"), "More text.\n\n{<first.md}\n").unwrap();
+        let error = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&first)
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn front_matter_sets_chapter_header() {
+        let first = temp_markdown(
+            "---\ntitle: A *Letter*\nshort_title: Letter\nlabel: Letter\nauthors:\n  - A. Writer\n  - B. Writer\n---\nDear Sir,",
+        );
+        let second = temp_markdown(
+            "+++\nunnumbered = true\nauthor = \"C. Writer\"\n+++\n# An Interlude\n\nText.",
+        );
+        let third = temp_markdown("# Another Chapter\n\nText.");
+        let src = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&first)
+            .unwrap()
+            .add_mainmatter_from_file(&second)
+            .unwrap()
+            .add_mainmatter_from_file(&third)
+            .unwrap()
+            .process();
+
+        let labels: Vec<_> = src
+            .contents
+            .iter()
+            .filter_map(|e| match e {
+                BookEvent::DivisionHeaderLabel { text, number, .. } => {
+                    Some((text.as_deref().map(String::from), *number))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (Some("Letter".to_string()), Some(1)),
+                (Some("Chapter".to_string()), None),
+                (Some("Chapter".to_string()), Some(2)),
+            ]
+        );
+        assert!(src
+            .contents
+            .contains(&BookEvent::DivisionShortTitle("Letter".into())));
+        assert!(src.contents.contains(&BookEvent::DivisionAuthors(vec![
+            "A. Writer".into(),
+            "B. Writer".into()
+        ])));
+        assert!(src.contents.contains(&BookEvent::BeginDivisionHeader(true)));
+        assert!(src
+            .contents
+            .contains(&BookEvent::Event(Event::Text("Letter".into()))));
+        assert_eq!(
+            src.contents
+                .iter()
+                .filter(|e| **e == BookEvent::BeginSemantic(SemanticRole::Chapter))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn front_matter_sets_ancillary_role_and_authors() {
+        let foreword =
+            temp_markdown("---\nrole: preface\nauthors: A. Friend\n---\n# Some Words\n\nText.");
+        let appendix = temp_markdown("---\nunnumbered: true\n---\n# Notes\n\nText.");
+        let src = BookSrcBuilder::new("A Book")
+            .add_foreword_from_file(&foreword, "Someone Else")
+            .unwrap()
+            .add_appendix_from_file(&appendix, None::<&str>)
+            .unwrap()
+            .add_appendix("# Maps\n\nText.", None)
+            .process();
+
+        assert!(src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::Preface)));
+        assert!(!src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::Foreword)));
+        assert!(src
+            .contents
+            .contains(&BookEvent::DivisionAuthors(vec!["A. Friend".into()])));
+        assert_eq!(src.expected_appendices_count, 1);
+        assert!(src.contents.contains(&BookEvent::DivisionHeaderLabel {
+            text: Some("Appendix".into()),
+            number: Some(1),
+            number_format: NumberFormat::Letter,
+        }));
+    }
+
+    #[test]
+    fn thematic_breaks_are_not_front_matter() {
+        for text in [
+            "---\n\nText.",
+            "---\nText.\n\n---\n\nMore text.",
+            "---\n- one\n- two\n---\nText.",
+            "---\nNote: a thematic break\n",
+            "+++\nText.\n+++\n",
+        ] {
+            let (front_matter, body) = text.split_front_matter().unwrap();
+            assert_eq!(front_matter, None);
+            assert_eq!(body, text);
+        }
+        let chapter = temp_markdown("---\n\n# A Chapter\n\nText.");
+        let src = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&chapter)
+            .unwrap()
+            .process();
+        assert!(src.contents.contains(&BookEvent::Event(Event::Rule)));
+    }
+
+    #[test]
+    fn invalid_front_matter_is_an_error() {
+        let unterminated = temp_markdown("---\ntitle: Never Closed\n\nText.");
+        let unknown = temp_markdown("---\ntitel: Misspelt\n---\nText.");
+        let mut builder = BookSrcBuilder::new("A Book");
+        let err = builder.add_mainmatter_from_file(&unterminated).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(builder
+            .add_preface_from_file(&unknown, None::<&str>)
+            .is_err());
+    }
+
+    #[test]
+    fn authored_mainmatter_attributes_each_chapter() {
+        let essay = temp_markdown(
+            "---\nauthor: A. Guest\n---\n# A Reply\n\nText.\n\n# A Rejoinder\n\nText.",
+        );
+        let src = BookSrcBuilder::new("Collected Essays")
+            .add_authored_mainmatter(
+                "# One\n\nText.\n\n# Two\n\nText.",
+                vec!["A. Writer", "B. Writer"],
+                None,
+            )
+            .add_authored_mainmatter_from_file(&essay, "A. Writer", None)
+            .unwrap()
+            .add_mainmatter("# Unattributed\n\nText.")
+            .process();
+
+        let authors = src
+            .contents
+            .iter()
+            .filter_map(|e| match e {
+                BookEvent::DivisionAuthors(names) => Some(names.join(" & ")),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            authors,
+            vec![
+                "A. Writer & B. Writer",
+                "A. Writer & B. Writer",
+                "A. Guest",
+                "A. Writer"
+            ]
+        );
+        assert_eq!(
+            src.metadata.chapter_authors,
+            vec!["A. Writer", "B. Writer", "A. Guest"]
+        );
+        assert!(src.metadata.authors.is_empty());
+    }
+
+    #[test]
+    fn diagnostics_are_located_in_their_files() {
+        let chapter = temp_markdown(concat!(
+            "---\ntitle: A Chapter\n---\n",
+            "Text[^a] with <span class=\"sparkly\">a span</span>.\n\n",
+            "![A missing image](not_here.png)\n\n",
+            "[^a]: A note.\n\n",
+            "[^a]: Another note.\n"
+        ));
+        let (_, diagnostics) = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&chapter)
+            .unwrap()
+            .add_mainmatter("# Nothing Here")
+            .process_with_diagnostics();
+
+        let found = diagnostics
+            .iter()
+            .map(|d| {
+                let location = d.location.as_ref().unwrap();
+                (d.kind.clone(), location.file.clone(), location.line)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    DiagnosticKind::UnknownSpanClass("sparkly".to_string()),
+                    Some(chapter.clone()),
+                    4
+                ),
+                (
+                    DiagnosticKind::DuplicateFootnoteLabel("a".to_string()),
+                    Some(chapter.clone()),
+                    10
+                ),
+                (
+                    DiagnosticKind::EmptyChapter("Nothing Here".to_string()),
+                    None,
+                    1
+                ),
+                (
+                    DiagnosticKind::MissingImage(PathBuf::from("not_here.png")),
+                    Some(chapter.clone()),
+                    6
+                ),
+            ]
+        );
+        assert_eq!(diagnostics[3].severity, Severity::Error);
+    }
+
+    #[test]
+    fn repeated_headings_are_located_separately() {
+        let chapters = temp_markdown("# Interlude\n\n# A Chapter\n\nText.\n\n# Interlude\n");
+        let (_, diagnostics) = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&chapters)
+            .unwrap()
+            .process_with_diagnostics();
+        let lines = diagnostics
+            .iter()
+            .map(|d| (d.kind.clone(), d.location.as_ref().unwrap().line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (DiagnosticKind::EmptyChapter("Interlude".to_string()), 1),
+                (DiagnosticKind::EmptyChapter("Interlude".to_string()), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn front_matter_titles_glossary_terms_and_letters_are_located() {
+        let chapter = temp_markdown("---\ntitle: Nothing Here\n---\n");
+        let letter = temp_markdown(
+            "# A Chapter\n\n<div class=\"letter\">\n\nDear Sir,\n\nA word {#no way}.\n\nYours,\n\nA. Writer\n\n</div>\n",
+        );
+        let glossary = temp_markdown("Hobbit\n: A small person.\n\nhobbit\n: Again.\n");
+        let (_, diagnostics) = BookSrcBuilder::new("A Book")
+            .add_mainmatter_from_file(&chapter)
+            .unwrap()
+            .add_mainmatter_from_file(&letter)
+            .unwrap()
+            .add_glossary_source_from_file(&glossary)
+            .unwrap()
+            .process_with_diagnostics();
+
+        let found = diagnostics
+            .iter()
+            .map(|d| (d.kind.clone(), d.location.clone()))
+            .collect::<Vec<_>>();
+        let at = |file: &TempMarkdown, line| {
+            Some(SourceLocation {
+                file: Some(file.to_path_buf()),
+                line,
+            })
+        };
+        assert_eq!(
+            found,
+            vec![
+                (
+                    DiagnosticKind::MalformedLabel("no way".to_string()),
+                    at(&letter, 7)
+                ),
+                (
+                    DiagnosticKind::EmptyChapter("Nothing Here".to_string()),
+                    at(&chapter, 1)
+                ),
+                (
+                    DiagnosticKind::DuplicateGlossaryTerm("hobbit".to_string()),
+                    at(&glossary, 4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_file_with_invalid_front_matter_leaves_no_sources_behind() {
+        let invalid = temp_markdown("---\ntitle: Never Closed\n\n\n\n# Heading\n");
+        let mut builder = BookSrcBuilder::new("A Book");
+        assert!(builder.add_mainmatter_from_file(&invalid).is_err());
+        let (_, diagnostics) = builder
+            .add_mainmatter("# Nothing Here")
+            .process_with_diagnostics();
+        assert_eq!(
+            diagnostics[0].location,
+            Some(SourceLocation::start_of(None))
+        );
+    }
+
+    #[test]
+    fn cross_references_resolve_and_dangle() {
+        let (src, diagnostics) = BookSrcBuilder::new("A Book")
+            .set_heading_mapping(HeadingMapping::default())
+            .add_mainmatter(
+                "# Beginnings {#ch:start}\n\n## Early Days {#early}\n\nText {#here} goes here.",
+            )
+            .add_mainmatter(
+                "# Middles {#ch:start}\n\nAs in {@ch:start}, {@early} and {@page:here}...",
+            )
+            .add_mainmatter("# Endings\n\nSee {@ch:end},\nor {@page:ch:end}.")
+            .process_with_diagnostics();
+
+        let cross_references = src.cross_references();
+        assert_eq!(cross_references.resolve("ch:start"), Some("1"));
+        assert_eq!(cross_references.resolve("early"), Some("Early Days"));
+        assert_eq!(cross_references.resolve("here"), Some("Early Days"));
+        assert_eq!(cross_references.duplicates(), ["ch:start"]);
+
+        let found = diagnostics
+            .into_iter()
+            .map(|d| (d.kind, d.severity, d.location.map(|l| l.line)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    DiagnosticKind::DuplicateLabel("ch:start".to_string()),
+                    Severity::Warning,
+                    Some(1)
+                ),
+                (
+                    DiagnosticKind::DanglingReference("ch:end".to_string()),
+                    Severity::Error,
+                    Some(3)
+                ),
+                (
+                    DiagnosticKind::DanglingReference("ch:end".to_string()),
+                    Severity::Error,
+                    Some(4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_labels_are_reported() {
+        let (src, diagnostics) = BookSrcBuilder::new("A Book")
+            .add_mainmatter("# Water {#h_2_o}\n\nSee {@h_2_o} on the 2^nd^ day, not {@no way}.")
+            .process_with_diagnostics();
+        assert_eq!(src.cross_references().resolve("h_2_o"), Some("1"));
+        let found = diagnostics
+            .into_iter()
+            .map(|d| (d.kind, d.severity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![(
+                DiagnosticKind::MalformedLabel("no way".to_string()),
+                Severity::Warning
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_publication_dates_are_reported() {
+        let kinds = |date: &'static str| {
+            BookSrcBuilder::new("A Book")
+                .add_mainmatter("Text.")
+                .publication_date(date)
+                .process_with_diagnostics()
+                .1
+                .into_iter()
+                .map(|d| d.kind)
+                .collect::<Vec<_>>()
+        };
+        assert!(kinds("2021-06-01").is_empty());
+        assert!(kinds("2021").is_empty());
+        assert_eq!(
+            kinds("June 2021"),
+            vec![DiagnosticKind::InvalidPublicationDate(
+                "June 2021".to_string()
+            )]
+        );
+        assert!(BookSrcBuilder::new("A Book")
+            .add_mainmatter("Text.")
+            .publication_date("2021-13-01")
+            .process_checked()
+            .is_err());
+    }
+
+    #[test]
+    fn index_is_generated_from_marked_terms() {
+        let roles_of = |src: BookSrc| -> Vec<SemanticRole> {
+            src.contents
+                .iter()
+                .filter_map(|event| match event {
+                    BookEvent::BeginSemantic(role) => Some(*role),
+                    _ => None,
+                })
+                .collect()
+        };
+        let mut builder = BookSrcBuilder::new("A Book");
+        builder
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_mainmatter("# A Chapter\n\nRoses{^plants!roses} are red.")
+            .set_colophon("Set in Garamond.");
+
+        let src = builder.clone().process();
+        assert!(src.contents.contains(&BookEvent::BeginBackmatter));
+        assert_eq!(
+            roles_of(src),
+            vec![
+                SemanticRole::Chapter,
+                SemanticRole::Index,
+                SemanticRole::Colophon
+            ]
+        );
+
+        let src = builder.do_not_generate_index().process();
+        assert_eq!(
+            roles_of(src),
+            vec![SemanticRole::Chapter, SemanticRole::Colophon]
+        );
+
+        let src = BookSrcBuilder::new("A Book")
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_mainmatter("# A Chapter\n\nNothing to index.")
+            .process();
+        assert_eq!(roles_of(src), vec![SemanticRole::Chapter]);
+    }
+
+    #[test]
+    fn citations_are_resolved_and_listed_in_a_bibliography() {
+        let bibtex = r#"
+            @book{smith2004, author = {Smith, John}, title = {Gardens}, publisher = {Penguin}, address = {London}, year = {2004}}
+            @article{jones1999, author = {Jones, Mary and Brown, Alan}, title = {Roses}, journal = {Horticulture}, volume = {12}, number = {3}, pages = {45--67}, year = {1999}}
+            @book{unused, author = {Nobody}, title = {Uncited}, year = {2000}}
+        "#;
+        let text_of = |src: &BookSrc| -> String {
+            src.contents
+                .iter()
+                .filter_map(|event| match event {
+                    BookEvent::Event(Event::Text(t)) => Some(t.as_ref()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let mut builder = BookSrcBuilder::new("A Book");
+        builder
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_bibliography_source(Bibliography::from_bibtex(bibtex).unwrap())
+            .add_mainmatter("# A Chapter\n\nAs argued [@smith2004, p. 4; @jones1999], roses are red.\n\nSee also [@jones1999].");
+
+        let (src, diagnostics) = builder.clone().process_checked().unwrap();
+        assert!(diagnostics.is_empty());
+        let text = text_of(&src);
+        assert!(text.contains("As argued (Smith 2004, p. 4; Jones and Brown 1999), roses are red."));
+        assert!(text.contains("See also (Jones and Brown 1999)."));
+        assert!(text.contains("Jones, Mary, and Alan Brown. 1999. “Roses.” Horticulture 12 (3): 45–67.Smith, John. 2004. Gardens. London: Penguin."));
+        assert!(!text.contains("Uncited"));
+        assert!(src.contents.contains(&BookEvent::BeginBackmatter));
+        assert!(src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::Bibliography)));
+
+        let src = builder
+            .clone()
+            .set_citation_style(CitationStyle::Numeric)
+            .process();
+        let text = text_of(&src);
+        assert!(text.contains("As argued [1, p. 4; 2], roses are red."));
+        assert!(text.contains("[1] John Smith. Gardens. London: Penguin, 2004.[2] Mary Jones and Alan Brown. “Roses.” Horticulture 12, no. 3 (1999): 45–67."));
+
+        let failed = builder
+            .add_mainmatter("# Another\n\nAs [@nowhere] says.")
+            .process_checked();
+        assert_eq!(
+            failed.unwrap_err().diagnostics[0].kind,
+            DiagnosticKind::UnknownCitation("nowhere".to_string())
+        );
+    }
+
+    #[test]
+    fn first_uses_of_glossary_terms_are_marked() {
+        let glossary = Glossary::from_markdown(
+            "Hobbit\n: A small person.\n\n*[NASA]: National Aeronautics and Space Administration\n\nhobbit\n: Again.",
+        );
+        let (src, diagnostics) = BookSrcBuilder::new("A Book")
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_glossary_source(glossary)
+            .add_mainmatter("# The Hobbit\n\nA hobbit met NASA. Another hobbit met NASA.")
+            .process_checked()
+            .unwrap();
+        assert_eq!(
+            diagnostics[0].kind,
+            DiagnosticKind::DuplicateGlossaryTerm("hobbit".to_string())
+        );
+
+        let reference = |key: &str| Event::Start(Tag::GlossaryReference(key.to_string().into()));
+        let events = src
+            .contents
+            .iter()
+            .filter_map(|event| match event {
+                BookEvent::Event(e) => Some(e.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let marked = events
+            .iter()
+            .filter(|e| matches!(e, Event::Start(Tag::GlossaryReference(_))))
+            .collect::<Vec<_>>();
+        assert_eq!(marked, vec![&reference("hobbit"), &reference("nasa")]);
+        assert!(events.contains(&Event::Text(
+            "National Aeronautics and Space Administration (NASA)".into()
+        )));
+        assert!(events.contains(&Event::Text(". Another hobbit met NASA.".into())));
+
+        let terms = events
+            .windows(2)
+            .filter_map(|pair| match pair {
+                [Event::Start(Tag::GlossaryTerm(_)), Event::Text(term)] => Some(term.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec!["Hobbit", "NASA"]);
+        assert!(src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::Glossary)));
+    }
+
+    #[test]
+    fn speakers_are_listed_in_a_dramatis_personae() {
+        let play = "# Act I\n\n<div class=\"drama\" data-personae=\"FIRST WITCH, SECOND WITCH\">\n\nFIRST WITCH. When shall we three meet again?\n\nSECOND WITCH. When the hurlyburly's done.\n\nFIRST WITCH. That will be ere the set of sun.\n\n</div>\n";
+        let src = BookSrcBuilder::new("Macbeth")
+            .do_not_generate_halftitle()
+            .do_not_generate_titlepage()
+            .do_not_generate_copyrightpage()
+            .add_mainmatter(play)
+            .process();
+        let start = src
+            .contents
+            .iter()
+            .position(|e| e == &BookEvent::BeginSemantic(SemanticRole::DramatisPersonae))
+            .unwrap();
+        let mainmatter = src
+            .contents
+            .iter()
+            .position(|e| e == &BookEvent::BeginMainmatter)
+            .unwrap();
+        assert!(start < mainmatter);
+        let names = src.contents[start..mainmatter]
+            .iter()
+            .filter_map(|event| match event {
+                BookEvent::Event(Event::Text(name)) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["First Witch", "Second Witch"]);
+
+        let src = BookSrcBuilder::new("Macbeth")
+            .do_not_generate_dramatis_personae()
+            .add_mainmatter(play)
+            .process();
+        assert!(!src
+            .contents
+            .contains(&BookEvent::BeginSemantic(SemanticRole::DramatisPersonae)));
+    }
+
+    #[test]
+    fn openings_pass_over_epigraphs_and_images() {
+        let chapter = "# One\n\n<div class=\"epigraph\">\n\n> Call me Ishmael.\n\n</div>\n\n![](whale.png)\n\n'Twas brillig.\n\nAnd the slithy toves.\n\n# Two\n\n> A quotation.\n\nNot an opening.";
+        let mut src = BookSrcBuilder::new("A Book")
+            .add_mainmatter(chapter)
+            .process();
+        let style = OpeningStyle {
+            drop_cap: Some(1),
+            small_caps: SmallCapsOpening::FirstLine,
+        };
+        style.transform(&mut src.contents, &mut src.metadata);
+
+        let openings = src
+            .contents
+            .iter()
+            .filter(|e| matches!(e, BookEvent::Event(Event::Start(Tag::OpeningParagraph(..)))))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            openings,
+            vec![&BookEvent::Event(Event::Start(Tag::OpeningParagraph(
+                Some(2),
+                true
+            )))]
+        );
+        let drop_cap = src
+            .contents
+            .iter()
+            .skip_while(|e| **e != BookEvent::Event(Event::Start(Tag::DropCap)))
+            .nth(1);
+        assert_eq!(drop_cap, Some(&BookEvent::Event(Event::Text("‘T".into()))));
+    }
+
+    #[test]
+    fn generated_pages_follow_their_templates() {
+        let page_text = |builder: &mut BookSrcBuilder<'static>| {
+            let src = builder.add_mainmatter("# One\n\nText.").process();
+            src.contents
+                .iter()
+                .skip_while(|e| **e != BookEvent::BeginSemantic(SemanticRole::Copyrightpage))
+                .take_while(|e| **e != BookEvent::EndSemantic(SemanticRole::Copyrightpage))
+                .filter_map(|e| match e {
+                    BookEvent::Event(Event::Text(t)) => Some(t.to_string()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let year = bookbinder_common::get_current_year();
+
+        let text = page_text(
+            BookSrcBuilder::new("A Book")
+                .author("A. Writer")
+                .author("B. Writer")
+                .publisher("Publisher")
+                .first_published("1999")
+                .publication_date("2021-06-01")
+                .do_not_generate_titlepage(),
+        );
+        assert_eq!(
+            text,
+            vec![
+                "A BOOK".to_string(),
+                "Copyright © 1999 A. Writer and B. Writer".to_string(),
+                "The moral rights of the authors have been asserted".to_string(),
+                "First published 1999, this edition published 2021 by Publisher".to_string(),
+            ]
+        );
+
+        let templates = PageTemplates {
+            copyright_page: "© {copyright_year} {authors}{?print_location}\n\nImprimé en {print_location}{/print_location}".to_string(),
+            halftitle: "{title}, {?subtitle}{subtitle}{/subtitle}".to_string(),
+            and: "et".to_string(),
+            edited_by: "Sous la direction de {editors}".to_string(),
+            ..PageTemplates::default()
+        };
+        let src = BookSrcBuilder::new("Le Livre")
+            .shorttitle("Livre")
+            .subtitle("Roman")
+            .author("A. Auteur")
+            .author("B. Auteur")
+            .editor("C. Éditeur")
+            .print_location("France")
+            .set_page_templates(templates)
+            .add_mainmatter("# Un\n\nTexte.")
+            .process();
+        assert!(src
+            .contents
+            .contains(&BookEvent::Event(Event::Text("LE LIVRE, ROMAN".into()))));
+        assert!(src.contents.contains(&BookEvent::Event(Event::Text(
+            format!("© {} A. Auteur et B. Auteur", year).into()
+        ))));
+        assert!(src
+            .contents
+            .contains(&BookEvent::Event(Event::Text("Imprimé en France".into()))));
+        assert!(src
+            .contents
+            .contains(&BookEvent::TitlePageContributors(vec![
+                (None, vec!["A. Auteur".into(), "B. Auteur".into()]),
+                (
+                    Some("Sous la direction de C. Éditeur".into()),
+                    vec!["C. Éditeur".into()]
+                ),
+            ])));
+    }
 }
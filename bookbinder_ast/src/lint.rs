@@ -0,0 +1,537 @@
+use crate::{BookEvent, Metadata, SemanticRole};
+use extended_pulldown::{Event, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+/// A check of the typography or consistency of a processed book,
+/// with an id by which it can be disabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// `straight-quote`: a straight quote left in text, where the smartener could not decide which way it should face
+    StraightQuote,
+    /// `unbalanced-quotes`: a paragraph with more opening than closing double quotes, or the reverse
+    UnbalancedQuotes,
+    /// `doubled-space`: two or more spaces in a row
+    DoubledSpace,
+    /// `hyphen-in-range`: a hyphen between two numbers, as in `10-20`, rather than an en dash
+    HyphenInRange,
+    /// `empty-chapter`: a chapter with a heading and nothing else
+    EmptyChapter,
+    /// `image-without-alt`: an image with no alt text to describe it
+    ImageWithoutAlt,
+    /// `heading-punctuation`: a heading ending in a full stop, comma, colon or semicolon
+    HeadingPunctuation,
+    /// `inconsistent-name`: a name in the text spelled almost, but not quite, like a contributor in the metadata
+    InconsistentName,
+}
+
+impl LintRule {
+    /// Every rule, in the order they are documented
+    pub const ALL: [LintRule; 8] = [
+        LintRule::StraightQuote,
+        LintRule::UnbalancedQuotes,
+        LintRule::DoubledSpace,
+        LintRule::HyphenInRange,
+        LintRule::EmptyChapter,
+        LintRule::ImageWithoutAlt,
+        LintRule::HeadingPunctuation,
+        LintRule::InconsistentName,
+    ];
+
+    /// The id by which this rule is referred to, such as `straight-quote`
+    pub const fn id(self) -> &'static str {
+        match self {
+            LintRule::StraightQuote => "straight-quote",
+            LintRule::UnbalancedQuotes => "unbalanced-quotes",
+            LintRule::DoubledSpace => "doubled-space",
+            LintRule::HyphenInRange => "hyphen-in-range",
+            LintRule::EmptyChapter => "empty-chapter",
+            LintRule::ImageWithoutAlt => "image-without-alt",
+            LintRule::HeadingPunctuation => "heading-punctuation",
+            LintRule::InconsistentName => "inconsistent-name",
+        }
+    }
+}
+
+impl Display for LintRule {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        formatter.write_str(self.id())
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintRule::ALL
+            .iter()
+            .find(|rule| rule.id() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown lint rule {:?}", s))
+    }
+}
+
+/// Which lint rules are checked; by default, all of them are
+///
+/// ```
+/// use bookbinder_ast::{BookSrcBuilder, LintOptions, LintRule};
+///
+/// let src = BookSrcBuilder::new("A Book")
+///     .add_mainmatter("# A Chapter.\n\nSee pages 10-20.  Then stop.")
+///     .process();
+/// let rules = |options: &LintOptions| {
+///     src.lint(options).into_iter().map(|lint| lint.rule).collect::<Vec<_>>()
+/// };
+/// assert_eq!(
+///     rules(&LintOptions::default()),
+///     vec![LintRule::HeadingPunctuation, LintRule::DoubledSpace, LintRule::HyphenInRange]
+/// );
+///
+/// let mut options = LintOptions::default();
+/// options.disable("doubled-space".parse().unwrap());
+/// assert_eq!(
+///     rules(&options),
+///     vec![LintRule::HeadingPunctuation, LintRule::HyphenInRange]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintOptions {
+    disabled: HashSet<LintRule>,
+}
+
+impl LintOptions {
+    /// Do not check `rule`
+    pub fn disable(&mut self, rule: LintRule) -> &mut Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    /// Whether `rule` is checked
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// A problem found by a lint rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Lint {
+    /// The rule which found the problem
+    pub rule: LintRule,
+    /// What the problem is
+    pub message: String,
+    /// The title of the division the problem was found in, or its role if it has no title
+    pub division: Option<String>,
+}
+
+impl Display for Lint {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+        if let Some(ref division) = self.division {
+            write!(formatter, "{}: ", division)?;
+        }
+        write!(formatter, "{} [{}]", self.message, self.rule)
+    }
+}
+
+/// the text around the byte `offset` in `text`, to show where a problem is
+fn excerpt(text: &str, offset: usize) -> String {
+    const CONTEXT: usize = 20;
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let end = text[offset..]
+        .char_indices()
+        .nth(CONTEXT)
+        .map(|(i, _)| offset + i)
+        .unwrap_or_else(|| text.len());
+    format!(
+        "{}{}{}",
+        if start > 0 { "…" } else { "" },
+        text[start..end].trim(),
+        if end < text.len() { "…" } else { "" }
+    )
+}
+
+/// the offset of the first straight quote in `text`,
+/// other than an apostrophe within a word, which the smartener leaves alone
+fn straight_quote(text: &str) -> Option<usize> {
+    let is_alphanumeric = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    text.char_indices()
+        .find(|&(i, c)| {
+            c == '"'
+                || (c == '\''
+                    && !(is_alphanumeric(text[..i].chars().next_back())
+                        && is_alphanumeric(text[i + 1..].chars().next())))
+        })
+        .map(|(i, _)| i)
+}
+
+/// the offset of the first hyphen between two numbers in `text`,
+/// ignoring numbers joined by several hyphens, such as an ISBN or a date
+fn hyphenated_range(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let run_end = |mut i: usize, forwards: bool| {
+        while (forwards && i < bytes.len() && bytes[i].is_ascii_digit())
+            || (!forwards && i > 0 && bytes[i - 1].is_ascii_digit())
+        {
+            i = if forwards { i + 1 } else { i - 1 };
+        }
+        i
+    };
+    text.match_indices('-').map(|(i, _)| i).find(|&i| {
+        let before = run_end(i, false);
+        let after = run_end(i + 1, true);
+        before < i
+            && after > i + 1
+            && !matches!(before.checked_sub(1).map(|b| bytes[b]), Some(b'-'))
+            && !matches!(bytes.get(after), Some(b'-'))
+    })
+}
+
+/// the number of single-character edits needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// whether `candidate` looks like a misspelling of `name`, rather than the same name or a different one
+fn is_near_miss(candidate: &str, name: &str) -> bool {
+    if candidate == name || name.chars().count() < 5 {
+        return false;
+    }
+    edit_distance(&candidate.to_lowercase(), &name.to_lowercase()) <= 2
+}
+
+/// a word of text as it might appear in a name, without surrounding punctuation or a possessive
+fn name_word(word: &str) -> &str {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '.');
+    word.strip_suffix("’s")
+        .or_else(|| word.strip_suffix("'s"))
+        .unwrap_or(word)
+        .trim_end_matches('.')
+}
+
+/// a name as it is compared with others, ignoring punctuation which can follow it in text
+fn normalized_name(name: &str) -> String {
+    name.split_whitespace()
+        .map(name_word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checks a book against the lint rules, event by event
+struct Linter<'m> {
+    options: &'m LintOptions,
+    /// the names of contributors in the metadata, as given and as compared
+    names: Vec<(String, String)>,
+    lints: Vec<(LintRule, String, Option<usize>)>,
+    /// the titles and roles of the divisions seen so far
+    divisions: Vec<(Option<String>, SemanticRole)>,
+    /// the divisions we are within, innermost last
+    open: Vec<usize>,
+    in_code: bool,
+    /// the text of the heading being read, if any
+    heading: Option<String>,
+    /// the opening and closing double quotes seen in the current paragraph
+    quotes: Option<(usize, usize)>,
+    /// whether the last paragraph left a quotation open, as happens when it continues into the next
+    quotation_continues: bool,
+    /// the text of the image being read, if any, with where it is found
+    image: Option<(String, String)>,
+    /// whether the chapter being read has anything after its heading
+    chapter_has_content: bool,
+    /// variant spellings of names already reported
+    reported_names: HashSet<String>,
+}
+
+impl<'m> Linter<'m> {
+    fn new(options: &'m LintOptions, metadata: &Metadata<'_>) -> Self {
+        let names = metadata
+            .authors
+            .iter()
+            .chain(metadata.editors.iter())
+            .chain(metadata.translators.iter())
+            .chain(metadata.foreword_authors.iter())
+            .chain(metadata.introduction_authors.iter())
+            .chain(metadata.afterword_authors.iter())
+            .chain(metadata.introduction_and_notes_authors.iter())
+            .chain(metadata.chapter_authors.iter())
+            .map(|name| (name.trim().to_string(), normalized_name(name)))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        Linter {
+            options,
+            names,
+            lints: Vec::new(),
+            divisions: Vec::new(),
+            open: Vec::new(),
+            in_code: false,
+            heading: None,
+            quotes: None,
+            quotation_continues: false,
+            image: None,
+            chapter_has_content: true,
+            reported_names: HashSet::new(),
+        }
+    }
+
+    fn report(&mut self, rule: LintRule, message: String) {
+        if self.options.is_enabled(rule) {
+            self.lints.push((rule, message, self.open.last().copied()));
+        }
+    }
+
+    fn check_name(&mut self, candidate: &str) {
+        let normalized = normalized_name(candidate);
+        let near_miss = self
+            .names
+            .iter()
+            .find(|(_, name)| is_near_miss(&normalized, name))
+            .map(|(name, _)| name.clone());
+        if let Some(name) = near_miss {
+            if self.reported_names.insert(candidate.to_string()) {
+                self.report(
+                    LintRule::InconsistentName,
+                    format!("{:?} is spelled {:?} in the metadata", candidate, name),
+                );
+            }
+        }
+    }
+
+    /// look for near misses of contributors' names among the capitalised words of `text`
+    fn check_names_in(&mut self, text: &str) {
+        let words = text.split_whitespace().map(name_word).collect::<Vec<_>>();
+        let lengths = self
+            .names
+            .iter()
+            .map(|(_, name)| name.split_whitespace().count())
+            .collect::<HashSet<_>>();
+        for length in lengths.into_iter() {
+            for window in words.windows(length) {
+                if window.iter().all(|w| w.starts_with(char::is_uppercase)) {
+                    self.check_name(&window.join(" "));
+                }
+            }
+        }
+    }
+
+    fn check_text(&mut self, text: &str) {
+        if let Some((_, ref mut alt)) = self.image {
+            alt.push_str(text);
+            return;
+        }
+        if let Some(ref mut heading) = self.heading {
+            heading.push_str(text);
+        }
+        if let Some((ref mut opening, ref mut closing)) = self.quotes {
+            *opening += text.matches('“').count();
+            *closing += text.matches('”').count();
+            if self.quotation_continues {
+                self.quotation_continues = false;
+                if !text.trim_start().starts_with('“') {
+                    self.unclosed_quotation();
+                }
+            }
+        }
+        if let Some(i) = straight_quote(text) {
+            self.report(
+                LintRule::StraightQuote,
+                format!("straight quote in “{}”", excerpt(text, i)),
+            );
+        }
+        if let Some(i) = text.find("  ") {
+            self.report(
+                LintRule::DoubledSpace,
+                format!("doubled space in “{}”", excerpt(text, i)),
+            );
+        }
+        if let Some(i) = hyphenated_range(text) {
+            self.report(
+                LintRule::HyphenInRange,
+                format!("hyphen rather than en dash in “{}”", excerpt(text, i)),
+            );
+        }
+        self.check_names_in(text);
+    }
+
+    fn unclosed_quotation(&mut self) {
+        self.report(
+            LintRule::UnbalancedQuotes,
+            "quotation is opened but never closed".to_string(),
+        );
+    }
+
+    fn end_heading(&mut self) {
+        let heading = match self.heading.take() {
+            Some(heading) => heading,
+            None => return,
+        };
+        let heading = heading.trim();
+        if heading.ends_with(['.', ',', ':', ';']) && !heading.ends_with("...") {
+            self.report(
+                LintRule::HeadingPunctuation,
+                format!("heading “{}” ends in punctuation", heading),
+            );
+        }
+        if let Some(&i) = self.open.last() {
+            if self.divisions[i].0.is_none() && !heading.is_empty() {
+                self.divisions[i].0 = Some(heading.to_string());
+            }
+        }
+    }
+
+    fn check(&mut self, event: &BookEvent<'_>) {
+        let in_chapter = matches!(
+            self.open.last().map(|&i| self.divisions[i].1),
+            Some(SemanticRole::Chapter)
+        );
+        if self.quotation_continues
+            && matches!(
+                event,
+                BookEvent::BeginSemantic(_) | BookEvent::EndSemantic(_)
+            )
+        {
+            self.quotation_continues = false;
+            self.unclosed_quotation();
+        }
+        match event {
+            BookEvent::BeginSemantic(role) => {
+                self.open.push(self.divisions.len());
+                self.divisions.push((None, *role));
+                if *role == SemanticRole::Chapter {
+                    self.chapter_has_content = false;
+                }
+            }
+            BookEvent::EndSemantic(role) => {
+                if *role == SemanticRole::Chapter && !self.chapter_has_content {
+                    self.report(LintRule::EmptyChapter, "chapter has no text".to_string());
+                    self.chapter_has_content = true;
+                }
+                self.open.pop();
+            }
+            BookEvent::BeginDivisionHeader(_) => self.heading = Some(String::new()),
+            BookEvent::EndDivisionHeader(_) => self.end_heading(),
+            BookEvent::DivisionAuthors(authors) => {
+                for author in authors.iter() {
+                    self.check_name(author.trim());
+                }
+            }
+            BookEvent::Event(event) => {
+                if in_chapter && self.heading.is_none() {
+                    self.chapter_has_content = true;
+                }
+                match event {
+                    Event::Start(Tag::CodeBlock(_)) => self.in_code = true,
+                    Event::End(Tag::CodeBlock(_)) => self.in_code = false,
+                    Event::Start(Tag::Heading(_)) => self.heading = Some(String::new()),
+                    Event::End(Tag::Heading(_)) => self.end_heading(),
+                    // a stanza of verse, a speech or a postscript is checked as a whole, like a paragraph
+                    Event::Start(Tag::Paragraph)
+                    | Event::Start(Tag::UnindentedParagraph)
+                    | Event::Start(Tag::Stanza)
+                    | Event::Start(Tag::Speech(_))
+                    | Event::Start(Tag::StageDirection)
+                    | Event::Start(Tag::Postscript) => {
+                        self.quotes = Some((0, 0));
+                    }
+                    Event::End(Tag::Paragraph)
+                    | Event::End(Tag::UnindentedParagraph)
+                    | Event::End(Tag::Stanza)
+                    | Event::End(Tag::Speech(_))
+                    | Event::End(Tag::StageDirection)
+                    | Event::End(Tag::Postscript) => {
+                        if let Some((opening, closing)) = self.quotes.take() {
+                            if opening > closing {
+                                self.quotation_continues = true;
+                            } else if opening < closing {
+                                self.report(
+                                    LintRule::UnbalancedQuotes,
+                                    format!(
+                                        "paragraph has {} opening but {} closing double quotes",
+                                        opening, closing
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    Event::Start(Tag::Image(_, dest, _)) => {
+                        self.image = Some((dest.to_string(), String::new()));
+                    }
+                    Event::End(Tag::Image(..)) => {
+                        if let Some((dest, alt)) = self.image.take() {
+                            if alt.trim().is_empty() {
+                                self.report(
+                                    LintRule::ImageWithoutAlt,
+                                    format!("image {:?} has no alt text", dest),
+                                );
+                            }
+                        }
+                    }
+                    Event::Text(text) if !self.in_code => self.check_text(text),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> Vec<Lint> {
+        let divisions = self.divisions;
+        self.lints
+            .into_iter()
+            .map(|(rule, message, division)| Lint {
+                rule,
+                message,
+                division: division.and_then(|i| {
+                    let (ref title, role) = divisions[i];
+                    title.clone().or_else(|| role.get_label().map(String::from))
+                }),
+            })
+            .collect()
+    }
+}
+
+/// Check `events` against every rule enabled in `options`
+pub(crate) fn lint(
+    events: &[BookEvent<'_>],
+    metadata: &Metadata<'_>,
+    options: &LintOptions,
+) -> Vec<Lint> {
+    let mut linter = Linter::new(options, metadata);
+    for event in events.iter() {
+        linter.check(event);
+    }
+    linter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_names() {
+        assert_eq!(hyphenated_range("pages 10-20."), Some(8));
+        assert_eq!(hyphenated_range("ISBN 978-0-14-118776-1"), None);
+        assert_eq!(hyphenated_range("on 2020-01-01"), None);
+        assert_eq!(hyphenated_range("a well-known 3-D film"), None);
+        assert!(is_near_miss("Jon Smith", "John Smith"));
+        assert!(is_near_miss("John smith", "John Smith"));
+        assert!(!is_near_miss("Jane Smith", "John Smith"));
+        assert_eq!(excerpt("short", 2), "short");
+    }
+}
@@ -1,11 +1,17 @@
 use crate::{ContributorSource, TitlePageContributorRole};
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
 /// The metadata of a particular book
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metadata<'a> {
     do_not_assert_moral_rights: bool,
     is_not_first_publication: bool,
+    first_published: Option<Cow<'a, str>>,
+    publication_date: Option<Cow<'a, str>>,
+    /// The language of this work, as an IETF language tag such as `en` or `fr-CA`
+    pub language: Option<Cow<'a, str>>,
     /// The main title of this work
     pub title: Cow<'a, str>,
     pub(crate) shorttitle: Option<Cow<'a, str>>,
@@ -25,6 +31,8 @@ pub struct Metadata<'a> {
     pub afterword_authors: Vec<Cow<'a, str>>,
     /// Authors of introductions to and in-text notes or commentary on this work
     pub introduction_and_notes_authors: Vec<Cow<'a, str>>,
+    /// Authors of individual chapters of this work, such as the contributors to a collection of essays
+    pub chapter_authors: Vec<Cow<'a, str>>,
     cover_designer: Option<Cow<'a, str>>,
     author_photo_copyright_holder: Option<Cow<'a, str>>,
     paperback_isbn: Option<Cow<'a, str>>,
@@ -118,148 +126,102 @@ impl<'a> Metadata<'a> {
         }
     }
 
-    pub(crate) fn get_copyright_page_text(&self) -> String {
-        let year = bookbinder_common::get_current_year();
+    /// The date on which this edition is published, as a number of seconds since the unix epoch:
+    /// the publication date, if one is set, or otherwise the time given by `SOURCE_DATE_EPOCH`,
+    /// if that is set. Dates in generated output are taken from this, so that they do not change
+    /// each time a book is rendered.
+    /// A publication date which cannot be read is passed over here,
+    /// but reported as an error by `BookSrcBuilder::process_checked`.
+    pub fn get_publication_epoch(&self) -> Option<i64> {
+        self.publication_date
+            .as_deref()
+            .and_then(bookbinder_common::date_to_epoch)
+            .or_else(bookbinder_common::source_date_epoch_from_env)
+    }
 
-        // get an optional value from metadata;
-        // optionally, wrap this in a prefix or suffix
-        macro_rules! parse_optional_value {
-            ($pre:expr, $field:ident, $post:expr) => {
-                if let Some(ref x) = self.$field {
-                    Some(format!("{}{}{}", $pre, x, $post))
-                } else {
-                    None
-                }
-            };
-            ($pre:expr, $field:ident) => {
-                if let Some(ref x) = self.$field {
-                    Some(format!("{}{}", $pre, x))
-                } else {
-                    None
-                }
-            };
+    /// The publication date, if one is set but cannot be read as a date of the form `YYYY-MM-DD`
+    pub fn invalid_publication_date(&self) -> Option<&str> {
+        self.publication_date
+            .as_deref()
+            .filter(|date| bookbinder_common::date_to_epoch(date).is_none())
+    }
+
+    /// The value of the field `name` for use in a template, where the names of several people
+    /// are joined as `A, B and C` using `and`. This is `None` if there is no such field,
+    /// and `Some(None)` if it has no value, or -- for a field which is only a condition -- is not set.
+    pub(crate) fn get_template_field(&self, name: &str, and: &str) -> Option<Option<String>> {
+        let year = self
+            .get_publication_epoch()
+            .map(bookbinder_common::epoch_to_year)
+            .unwrap_or_else(bookbinder_common::get_current_year);
+        let first_published = self
+            .first_published
+            .as_ref()
+            .map(|y| y.to_string())
+            .filter(|y| *y != year);
+
+        // get an optional value from metadata
+        macro_rules! optional_value {
             ($field:ident) => {
-                if let Some(ref x) = self.$field {
-                    Some(x)
-                } else {
-                    None
-                }
+                self.$field
+                    .as_ref()
+                    .map(|x| x.to_string())
+                    .filter(|x| !x.trim().is_empty())
             };
         }
 
-        let copyright_statement = parse_optional_value!(copyright_statement);
-        let publisher_name = parse_optional_value!(publisher_name);
-        let publisher_address = parse_optional_value!(publisher_address);
-        let publisher_url = parse_optional_value!(publisher_url);
-        let cover_designer = parse_optional_value!("Cover design by ", cover_designer);
-        let author_photo_copyright_holder =
-            parse_optional_value!("Author photo © ", author_photo_copyright_holder);
-        let print_loc = parse_optional_value!("Printed in ", print_loc);
+        // get a list of names from metadata
+        macro_rules! names {
+            ($field:ident) => {
+                join_names(&self.$field, and)
+            };
+        }
 
         macro_rules! get_isbn {
-            ($field:ident, $name:expr) => {
+            ($field:ident) => {
                 self.$field
                     .as_ref()
-                    .map(|i| bookbinder_common::display_isbn(i, Some($name)))
+                    .map(|i| bookbinder_common::display_isbn(i, None))
                     .transpose()
                     .ok()
                     .flatten()
             };
         }
 
-        let epub_isbn = get_isbn!(epub_isbn, "epub");
-        let hardback_isbn = get_isbn!(hardback_isbn, "hardback");
-        let paperback_isbn = get_isbn!(paperback_isbn, "paperback");
-
-        let copyright_statement = copyright_statement.map(|s| s.to_string()).or_else(|| {
-            let authors = self.get_authors();
-            if let Some(authors) = authors {
-                Some(format!("Copyright © {} {}", &year, authors))
-            } else {
-                None
-            }
-        });
-
-        let mut text = String::new();
-
-        if let Some(ref copyright_statement) = copyright_statement {
-            text.push_str(copyright_statement);
-            text.push_str("  \n");
-        };
-
-        if !self.do_not_assert_moral_rights {
-            match self.authors.len() {
-                0 => {}
-                1 => {
-                    text.push_str("The author's moral rights have been asserted");
-                    text.push_str("  \n");
-                }
-                _ => {
-                    text.push_str("The moral rights of the authors have been asserted");
-                    text.push_str("  \n");
-                }
-            }
-        };
-        if !text.is_empty() {
-            text.push_str("\n\n* * *\n\n");
-        }
-
-        let pub_statement = match (self.is_not_first_publication, publisher_name) {
-            (false, Some(publisher_name)) => {
-                format!("First published {} by {}", &year, publisher_name)
-            }
-            (false, None) => format!("First published {}", &year),
-            (true, Some(publisher_name)) => {
-                format!("This edition published {} by {}", &year, publisher_name)
-            }
-            (true, None) => format!("This edition published {}", &year),
+        // a field which is only a condition
+        let flag = |set: bool| if set { Some(String::new()) } else { None };
+
+        let value = match name {
+            "title" => Some(self.title.to_string()),
+            "shorttitle" => Some(self.get_short_title().to_string()),
+            "subtitle" => optional_value!(subtitle),
+            "authors" => names!(authors),
+            "editors" => names!(editors),
+            "translators" => names!(translators),
+            "foreword_authors" => names!(foreword_authors),
+            "introduction_authors" => names!(introduction_authors),
+            "afterword_authors" => names!(afterword_authors),
+            "introduction_and_notes_authors" => names!(introduction_and_notes_authors),
+            "chapter_authors" => names!(chapter_authors),
+            "publisher" => optional_value!(publisher_name),
+            "publisher_address" => optional_value!(publisher_address),
+            "publisher_url" => optional_value!(publisher_url),
+            "print_location" => optional_value!(print_loc),
+            "cover_designer" => optional_value!(cover_designer),
+            "author_photo_copyright_holder" => optional_value!(author_photo_copyright_holder),
+            "copyright_statement" => optional_value!(copyright_statement),
+            "epub_isbn" => get_isbn!(epub_isbn),
+            "paperback_isbn" => get_isbn!(paperback_isbn),
+            "hardback_isbn" => get_isbn!(hardback_isbn),
+            "copyright_year" => first_published.clone().or_else(|| Some(year.clone())),
+            "first_published" => first_published,
+            "year" => Some(year),
+            "reissue" => flag(self.is_not_first_publication || first_published.is_some()),
+            "moral_rights" => flag(!self.do_not_assert_moral_rights && !self.authors.is_empty()),
+            "several_authors" => flag(self.authors.len() > 1),
+            _ => return None,
         };
-        text.push_str(&pub_statement);
-        text.push_str("  \n");
-        if let Some(ref publisher_address) = publisher_address {
-            text.push_str(publisher_address);
-            if publisher_url.is_some() {
-                text.push_str("  \n");
-            }
-        }
-        if let Some(ref x) = publisher_url {
-            text.push_str("```");
-            text.push_str(x);
-            text.push_str("```");
-        }
-
-        text.push_str("\n\n* * *\n\n");
-
-        macro_rules! push_misc {
-            ($misc:ident) => {
-                if let Some(ref x) = $misc {
-                    text.push_str(x);
-                    text.push_str("  \n");
-                }
-            };
-        }
-
-        push_misc!(epub_isbn);
-        push_misc!(hardback_isbn);
-        push_misc!(paperback_isbn);
-        push_misc!(cover_designer);
-        push_misc!(author_photo_copyright_holder);
-
-        if text.ends_with("  \n") {
-            text.drain(text.len() - 3..);
-        }
-
-        if let Some(ref print_loc) = print_loc {
-            if !text.ends_with("*\n\n") {
-                text.push_str("\n\n* * *\n\n");
-            }
-            text.push_str(print_loc);
-        }
-
-        if text.ends_with("\n\n* * *\n\n") {
-            text.drain(text.len() - 9..);
-        }
-        text
+        Some(value)
     }
 
     pub(crate) fn get_titlepage_contributors(
@@ -297,6 +259,15 @@ impl<'a> Metadata<'a> {
     }
 }
 
+/// `names`, in the format `A, B and C`, where `and` is the word joining the last two
+fn join_names(names: &[Cow<'_, str>], and: &str) -> Option<String> {
+    match names {
+        [] => None,
+        [name] => Some(name.to_string()),
+        [rest @ .., last] => Some(format!("{} {} {}", rest.join(", "), and, last)),
+    }
+}
+
 macro_rules! metadata_add_contributor {
     ($fnname:ident, $field:ident, $d:meta) => {
         #[$d]
@@ -403,6 +374,21 @@ impl<'a> Metadata<'a> {
         doc = "Set a custom copyright statement"
     );
     meta_bool!(do_not_assert_moral_rights, do_not_assert_moral_rights, doc="Set this flag if you do not wish to assert the moral rights of the author on the copyright page");
+    add_metadata_value!(
+        first_published,
+        first_published,
+        doc = "The year in which this book was first published, if this edition is a later one"
+    );
+    add_metadata_value!(
+        publication_date,
+        publication_date,
+        doc = "The date on which this edition is published, as `YYYY-MM-DD`"
+    );
+    add_metadata_value!(
+        language,
+        language,
+        doc = "The language of this work, as an IETF language tag such as `en` or `fr-CA`"
+    );
     meta_bool!(
         is_not_first_publication,
         is_not_first_publication,
@@ -411,4 +397,5 @@ impl<'a> Metadata<'a> {
     metadata_add_bulk_crate_contributor!(add_foreword_authors, foreword_authors);
     metadata_add_bulk_crate_contributor!(add_afterword_authors, afterword_authors);
     metadata_add_bulk_crate_contributor!(add_introduction_authors, introduction_authors);
+    metadata_add_bulk_crate_contributor!(add_chapter_authors, chapter_authors);
 }
@@ -0,0 +1,229 @@
+use crate::{BookEvent, BookTransform, Event, Metadata, SemanticRole, Tag};
+use serde::{Deserialize, Serialize};
+
+/// the quotation marks, and apostrophes of elision, which can come before the initial letter of a paragraph
+const OPENING_QUOTES: &[char] = &['"', '\'', '“', '‘', '’', '«', '‹', '„', '‚'];
+
+/// What is set in small caps at the start of the opening paragraph of a chapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SmallCapsOpening {
+    /// Nothing
+    #[default]
+    None,
+    /// The whole of the first line
+    FirstLine,
+    /// The given number of words
+    FirstWords(u8),
+}
+
+/// How the opening paragraph of each chapter, prologue and epilogue begins:
+/// with a drop cap, with its first line or first few words in small caps, or both.
+///
+/// Any quotation marks before the initial letter are set with the drop cap.
+/// Where a chapter begins with an epigraph or an image, the first paragraph of text after it is the opening;
+/// where it begins with something else, such as a quotation or a list, it is left as it is.
+///
+/// In print, a drop cap falling through any number of lines is set with the `lettrine` package;
+/// in an epub, a drop cap falls through two, three or four lines.
+/// Because `lettrine` cannot tell where the first line ends,
+/// a first line in small caps after a drop cap is set in print as its first four words instead.
+///
+/// ```
+/// use bookbinder_ast::{BookEvent, BookSrcBuilder, BookTransform, Event, OpeningStyle, SmallCapsOpening, Tag};
+///
+/// let mut src = BookSrcBuilder::new("A Book")
+///     .add_mainmatter("# One\n\n\"It was a dark and stormy night,\" she said.")
+///     .process();
+/// let style = OpeningStyle {
+///     drop_cap: Some(3),
+///     small_caps: SmallCapsOpening::FirstWords(3),
+/// };
+/// style.transform(&mut src.contents, &mut src.metadata);
+/// let opening = src
+///     .contents
+///     .iter()
+///     .skip_while(|e| **e != BookEvent::Event(Event::Start(Tag::OpeningParagraph(Some(3), false))))
+///     .filter_map(|e| match e {
+///         BookEvent::Event(e) => Some(e.clone()),
+///         _ => None,
+///     })
+///     .take(8)
+///     .collect::<Vec<_>>();
+/// assert_eq!(
+///     opening,
+///     vec![
+///         Event::Start(Tag::OpeningParagraph(Some(3), false)),
+///         Event::Start(Tag::DropCap),
+///         Event::Text("“I".into()),
+///         Event::End(Tag::DropCap),
+///         Event::Start(Tag::SmallCaps),
+///         Event::Text("t was a".into()),
+///         Event::End(Tag::SmallCaps),
+///         Event::Text(" dark and stormy night,” she said.".into()),
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpeningStyle {
+    /// The number of lines through which a drop cap falls, if there is to be one
+    pub drop_cap: Option<u8>,
+    /// What is set in small caps
+    pub small_caps: SmallCapsOpening,
+}
+
+impl OpeningStyle {
+    /// Whether chapters open just as any other paragraph would
+    pub fn is_plain(&self) -> bool {
+        self.drop_cap.is_none() && self.small_caps == SmallCapsOpening::None
+    }
+
+    /// Mark the paragraph beginning at `start` as an opening paragraph,
+    /// returning the number of events added to `events`
+    fn mark_opening(&self, events: &mut Vec<BookEvent<'_>>, start: usize) -> usize {
+        let drop_cap = self.drop_cap.map(|lines| lines.max(2));
+        let first_line = self.small_caps == SmallCapsOpening::FirstLine;
+        let tag = Tag::OpeningParagraph(drop_cap, first_line);
+        events[start] = BookEvent::Event(Event::Start(tag.clone()));
+        if let Some(end) = events[start..]
+            .iter()
+            .position(|e| matches!(e, BookEvent::Event(Event::End(Tag::Paragraph))))
+        {
+            events[start + end] = BookEvent::Event(Event::End(tag));
+        }
+
+        let text = match events.get(start + 1) {
+            Some(BookEvent::Event(Event::Text(text))) => text.to_string(),
+            _ => return 0,
+        };
+        let mut marked = Vec::new();
+        let mut rest = text.as_str();
+        let mut words = match self.small_caps {
+            SmallCapsOpening::FirstWords(n) => n as usize,
+            _ => 0,
+        };
+        if drop_cap.is_some() {
+            if let Some(initial_len) = initial_len(rest) {
+                marked.push(Event::Start(Tag::DropCap));
+                marked.push(Event::Text(rest[..initial_len].to_string().into()));
+                marked.push(Event::End(Tag::DropCap));
+                rest = &rest[initial_len..];
+                // a word of a single letter ends with its drop cap
+                if rest.starts_with(char::is_whitespace) {
+                    words = words.saturating_sub(1);
+                }
+            }
+        }
+        if words > 0 {
+            let end = words_len(rest, words);
+            marked.push(Event::Start(Tag::SmallCaps));
+            marked.push(Event::Text(rest[..end].to_string().into()));
+            marked.push(Event::End(Tag::SmallCaps));
+            rest = &rest[end..];
+        }
+        if marked.is_empty() {
+            return 0;
+        }
+        if !rest.is_empty() {
+            marked.push(Event::Text(rest.to_string().into()));
+        }
+        let added = marked.len() - 1;
+        events.splice(
+            start + 1..start + 2,
+            marked.into_iter().map(BookEvent::Event),
+        );
+        added
+    }
+}
+
+/// the length of the initial letter of `text`, with any quotation marks before it
+fn initial_len(text: &str) -> Option<usize> {
+    let quotes_len = text.len() - text.trim_start_matches(OPENING_QUOTES).len();
+    let initial = text[quotes_len..].chars().next()?;
+    if initial.is_alphanumeric() {
+        Some(quotes_len + initial.len_utf8())
+    } else {
+        None
+    }
+}
+
+/// the length of the first `n` words of `text`, without the whitespace after them
+fn words_len(text: &str, n: usize) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if in_word && words == n {
+                return i;
+            }
+            in_word = false;
+        } else if !in_word {
+            in_word = true;
+            words += 1;
+        }
+    }
+    text.len()
+}
+
+/// if the paragraph beginning at `start` holds only an image, the index of its end
+fn figure_end(events: &[BookEvent<'_>], start: usize) -> Option<usize> {
+    if !matches!(
+        events.get(start + 1),
+        Some(BookEvent::Event(Event::Start(Tag::Image(..))))
+    ) {
+        return None;
+    }
+    let end = start
+        + events[start..]
+            .iter()
+            .position(|e| matches!(e, BookEvent::Event(Event::End(Tag::Image(..)))))?
+        + 1;
+    match events.get(end) {
+        Some(BookEvent::Event(Event::End(Tag::Paragraph))) => Some(end),
+        _ => None,
+    }
+}
+
+impl BookTransform for OpeningStyle {
+    fn transform<'a>(&self, events: &mut Vec<BookEvent<'a>>, _metadata: &mut Metadata<'a>) {
+        if self.is_plain() {
+            return;
+        }
+        let mut division = None;
+        // whether the opening paragraph of the current division is still to come
+        let mut pending = false;
+        let mut in_epigraph = false;
+        let mut i = 0;
+        while i < events.len() {
+            match events[i] {
+                BookEvent::BeginSemantic(SemanticRole::Epigraph) => in_epigraph = true,
+                BookEvent::EndSemantic(SemanticRole::Epigraph) => in_epigraph = false,
+                BookEvent::BeginSemantic(role) => {
+                    division = Some(role);
+                    pending = false;
+                }
+                BookEvent::EndSemantic(_) => pending = false,
+                BookEvent::EndDivisionHeader(_) => {
+                    pending = matches!(
+                        division,
+                        Some(SemanticRole::Chapter)
+                            | Some(SemanticRole::Prologue)
+                            | Some(SemanticRole::Epilogue)
+                    );
+                }
+                _ if in_epigraph || !pending => {}
+                BookEvent::Event(Event::Start(Tag::Paragraph)) => match figure_end(events, i) {
+                    Some(end) => i = end,
+                    None => {
+                        i += self.mark_opening(events, i);
+                        pending = false;
+                    }
+                },
+                // a chapter which opens with a quotation, a list or the like is left as it is
+                BookEvent::Event(Event::Start(_)) => pending = false,
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{BookEvent, RenderTarget, SemanticRole};
+use extended_pulldown::{Event, Tag};
+use serde::Serialize;
+
+/// Counts of what makes up a division of a book, such as a chapter or a foreword
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DivisionStatistics {
+    /// The role of the division
+    pub role: SemanticRole,
+    /// The text of its heading, if it has one
+    pub title: Option<String>,
+    /// The number of words in it, including those of any divisions within it
+    pub words: usize,
+    /// The number of footnotes in it
+    pub footnotes: usize,
+    /// The number of images in it
+    pub images: usize,
+}
+
+/// Counts of what makes up a book, overall, by matter and by division.
+///
+/// ```
+/// use bookbinder_ast::BookSrcBuilder;
+///
+/// let src = BookSrcBuilder::new("A Book")
+///     .set_dedication("For my mother.")
+///     .add_mainmatter("# One\n\nThe first chapter.[^1]\n\n[^1]: A note.\n\n# Two\n\nThe second.")
+///     .process();
+/// let statistics = src.statistics();
+/// let chapters = statistics
+///     .divisions
+///     .iter()
+///     .filter(|d| d.title.as_deref() == Some("One") || d.title.as_deref() == Some("Two"))
+///     .map(|d| d.words)
+///     .collect::<Vec<_>>();
+/// assert_eq!(chapters, vec![6, 3]);
+/// assert_eq!(statistics.mainmatter_words, 9);
+/// assert_eq!(statistics.footnotes, 1);
+/// assert_eq!(statistics.estimated_pages(300), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Statistics {
+    /// The number of words in the whole book
+    pub words: usize,
+    /// The number of words before the mainmatter, including any generated titlepage and copyright page
+    pub frontmatter_words: usize,
+    /// The number of words in the mainmatter
+    pub mainmatter_words: usize,
+    /// The number of words after the mainmatter
+    pub backmatter_words: usize,
+    /// The number of footnotes in the whole book
+    pub footnotes: usize,
+    /// The number of images in the whole book
+    pub images: usize,
+    /// Counts for each division, in the order they appear
+    pub divisions: Vec<DivisionStatistics>,
+}
+
+impl Statistics {
+    /// The average number of words an adult reads silently in a minute
+    pub const AVERAGE_WORDS_PER_MINUTE: usize = 238;
+
+    /// Roughly how many minutes reading the whole book takes at `words_per_minute`
+    pub fn reading_minutes(&self, words_per_minute: usize) -> usize {
+        self.words.div_ceil(words_per_minute.max(1))
+    }
+
+    /// Roughly how many pages the book fills when `words_per_page` fit on each;
+    /// this does not allow for blank pages or those which begin a division
+    pub fn estimated_pages(&self, words_per_page: usize) -> usize {
+        self.words.div_ceil(words_per_page.max(1))
+    }
+}
+
+/// whether an element begun or ended by `tag` can fall within a word
+fn is_inline(tag: &Tag<'_>) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Link(..)
+            | Tag::Sans
+            | Tag::SmallCaps
+            | Tag::Superscript
+            | Tag::Subscript
+            | Tag::GlossaryReference(_)
+            | Tag::Parenthetical
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Matter {
+    Front,
+    Main,
+    Back,
+}
+
+/// Walk `events`, counting their words, footnotes and images
+/// as they are rendered to `target`;
+/// if there is no target, content marked as only for some format is not counted at all
+pub(crate) fn count(events: &[BookEvent<'_>], target: Option<RenderTarget>) -> Statistics {
+    let mut statistics = Statistics::default();
+    // whether each format-only element we are within is left out of `target`
+    let mut excluded: Vec<bool> = Vec::new();
+    let mut matter = Matter::Front;
+    // the indices of the divisions we are within, outermost first
+    let mut open: Vec<usize> = Vec::new();
+    let mut in_header = false;
+    // whether the last text ended partway through a word,
+    // since a word can be split between events
+    let mut in_word = false;
+
+    for event in events.iter() {
+        let mut words = 0;
+        let mut footnotes = 0;
+        let mut images = 0;
+        match event {
+            BookEvent::Event(Event::Start(Tag::FormatOnly(format))) => {
+                excluded.push(!matches!(target, Some(t) if t.is_called(format)));
+            }
+            BookEvent::Event(Event::End(Tag::FormatOnly(_))) => {
+                excluded.pop();
+            }
+            _ if excluded.contains(&true) => {}
+            BookEvent::BeginFrontmatter => matter = Matter::Front,
+            BookEvent::BeginMainmatter => matter = Matter::Main,
+            BookEvent::BeginBackmatter => matter = Matter::Back,
+            BookEvent::BeginSemantic(role) => {
+                open.push(statistics.divisions.len());
+                statistics.divisions.push(DivisionStatistics {
+                    role: *role,
+                    title: None,
+                    words: 0,
+                    footnotes: 0,
+                    images: 0,
+                });
+            }
+            BookEvent::EndSemantic(_) => {
+                open.pop();
+            }
+            BookEvent::BeginDivisionHeader(_) => in_header = true,
+            BookEvent::EndDivisionHeader(_) => in_header = false,
+            BookEvent::Event(Event::Text(text)) | BookEvent::Event(Event::Code(text)) => {
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        in_word = false;
+                    } else if !in_word && c.is_alphanumeric() {
+                        words += 1;
+                        in_word = true;
+                    }
+                }
+                if in_header {
+                    if let Some(&i) = open.last() {
+                        statistics.divisions[i]
+                            .title
+                            .get_or_insert_with(String::new)
+                            .push_str(text);
+                    }
+                }
+            }
+            BookEvent::Event(Event::Start(Tag::FlattenedFootnote))
+            | BookEvent::Event(Event::Start(Tag::FootnoteDefinition(_))) => {
+                footnotes = 1;
+                in_word = false;
+            }
+            BookEvent::Event(Event::Start(Tag::Image(..))) => {
+                images = 1;
+                in_word = false;
+            }
+            BookEvent::Event(Event::Start(tag)) | BookEvent::Event(Event::End(tag))
+                if is_inline(tag) => {}
+            _ => in_word = false,
+        }
+        if words + footnotes + images == 0 {
+            continue;
+        }
+
+        statistics.words += words;
+        statistics.footnotes += footnotes;
+        statistics.images += images;
+        match matter {
+            Matter::Front => statistics.frontmatter_words += words,
+            Matter::Main => statistics.mainmatter_words += words,
+            Matter::Back => statistics.backmatter_words += words,
+        }
+        for &i in open.iter() {
+            let division = &mut statistics.divisions[i];
+            division.words += words;
+            division.footnotes += footnotes;
+            division.images += images;
+        }
+    }
+    statistics
+}
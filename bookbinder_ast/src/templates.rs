@@ -0,0 +1,384 @@
+use crate::{Metadata, TitlePageContributorRole};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// what the blank lines between paragraphs of a template are marked with while it is filled
+const PARAGRAPH_BREAK: &str = "\u{1e}";
+
+/// The layout of the copyright page generated by default
+const COPYRIGHT_PAGE: &str = concat!(
+    "{?copyright_statement}{copyright_statement}{/copyright_statement}",
+    "{!copyright_statement}{?authors}Copyright © {copyright_year} {authors}{/authors}{/copyright_statement}\n",
+    "{?moral_rights}",
+    "{?several_authors}The moral rights of the authors have been asserted{/several_authors}",
+    "{!several_authors}The author's moral rights have been asserted{/several_authors}",
+    "{/moral_rights}\n",
+    "\n",
+    "* * *\n",
+    "\n",
+    "{!reissue}First published {year}{/reissue}",
+    "{?reissue}{?first_published}First published {first_published}, this edition published {year}{/first_published}",
+    "{!first_published}This edition published {year}{/first_published}{/reissue}",
+    "{?publisher} by {publisher}{/publisher}\n",
+    "{publisher_address}\n",
+    "{?publisher_url}`{publisher_url}`{/publisher_url}\n",
+    "\n",
+    "* * *\n",
+    "\n",
+    "{?epub_isbn}{epub_isbn} (epub){/epub_isbn}\n",
+    "{?hardback_isbn}{hardback_isbn} (hardback){/hardback_isbn}\n",
+    "{?paperback_isbn}{paperback_isbn} (paperback){/paperback_isbn}\n",
+    "{?cover_designer}Cover design by {cover_designer}{/cover_designer}\n",
+    "{?author_photo_copyright_holder}Author photo © {author_photo_copyright_holder}{/author_photo_copyright_holder}\n",
+    "\n",
+    "* * *\n",
+    "\n",
+    "{?print_location}Printed in {print_location}{/print_location}\n",
+);
+
+/// Templates for the text of the pages generated from a book's metadata:
+/// its copyright page, its halftitle and the words introducing contributors on its titlepage.
+/// Setting them allows these pages to be written in another language, or laid out differently.
+///
+/// A template is markdown in which `{field}` is replaced by the value of that field of the book's metadata:
+///
+/// - `title`, `shorttitle` (or the title, if there is no short title) and `subtitle`;
+/// - `authors`, `editors`, `translators`, `foreword_authors`, `introduction_authors`, `afterword_authors`,
+///   `introduction_and_notes_authors` and `chapter_authors`, each listing names as `A, B and C`;
+/// - `publisher`, `publisher_address`, `publisher_url`, `print_location`, `cover_designer`,
+///   `author_photo_copyright_holder` and `copyright_statement`;
+/// - `epub_isbn`, `hardback_isbn` and `paperback_isbn`, as `ISBN-13: 9780141439518`;
+/// - `year`, the year of this edition; `first_published`, the year of the first edition, if it was earlier;
+///   and `copyright_year`, which is the first of these.
+///
+/// `{?field}...{/field}` is kept only if the field has a value, and `{!field}...{/field}` only if it does not.
+/// Besides the fields above, these conditional sections can test `reissue`, which is set if this is not the first edition,
+/// `moral_rights`, which is set if the moral rights of the authors are asserted, and `several_authors`.
+/// Anything else in braces, such as a cross-reference, is left as it is.
+///
+/// Each line of a generated page is set on a line of its own; lines left empty are dropped,
+/// as are paragraphs left empty and scene breaks left with nothing to separate.
+///
+/// ```
+/// use bookbinder_ast::{BookSrcBuilder, PageTemplates};
+///
+/// let mut templates = PageTemplates::default();
+/// templates.copyright_page = "© {copyright_year} {authors}\n{?publisher}Publié par {publisher}{/publisher}\n\nImprimé en {print_location}".to_string();
+/// templates.halftitle = "{title}".to_string();
+/// templates.and = "et".to_string();
+/// let src = BookSrcBuilder::new("Le Livre")
+///     .author("A. Auteur")
+///     .author("B. Auteur")
+///     .set_page_templates(templates)
+///     .add_mainmatter("# Un\n\nLe texte...")
+///     .process();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageTemplates {
+    /// The text of the copyright page
+    pub copyright_page: String,
+    /// The text of the halftitle, which is set in capitals
+    pub halftitle: String,
+    /// The words introducing editors on the titlepage
+    pub edited_by: String,
+    /// The words introducing translators on the titlepage
+    pub translated_by: String,
+    /// The words introducing the authors of forewords on the titlepage
+    pub foreword_by: String,
+    /// The words introducing the authors of afterwords on the titlepage
+    pub afterword_by: String,
+    /// The words introducing the authors of introductions on the titlepage
+    pub introduction_by: String,
+    /// The words introducing the authors of introductions and notes on the titlepage
+    pub introduction_and_notes_by: String,
+    /// The word joining the last two names in a list of several
+    pub and: String,
+}
+
+impl Default for PageTemplates {
+    /// Pages in English, as generated when no templates are set
+    fn default() -> Self {
+        PageTemplates {
+            copyright_page: COPYRIGHT_PAGE.to_string(),
+            halftitle: "{shorttitle}".to_string(),
+            edited_by: "Edited by".to_string(),
+            translated_by: "Translated by".to_string(),
+            foreword_by: "With a foreword by".to_string(),
+            afterword_by: "With an afterword by".to_string(),
+            introduction_by: "With an introduction by".to_string(),
+            introduction_and_notes_by: "With an introduction and notes by".to_string(),
+            and: "and".to_string(),
+        }
+    }
+}
+
+/// Errors possible while reading page templates from a data file
+#[derive(Debug)]
+pub enum PageTemplatesError {
+    /// The toml could not be read
+    Toml(toml::de::Error),
+    /// The yaml could not be read
+    Yaml(serde_yaml::Error),
+    /// The json could not be read
+    Json(serde_json::Error),
+}
+
+impl From<toml::de::Error> for PageTemplatesError {
+    fn from(src: toml::de::Error) -> Self {
+        PageTemplatesError::Toml(src)
+    }
+}
+
+impl From<serde_yaml::Error> for PageTemplatesError {
+    fn from(src: serde_yaml::Error) -> Self {
+        PageTemplatesError::Yaml(src)
+    }
+}
+
+impl From<serde_json::Error> for PageTemplatesError {
+    fn from(src: serde_json::Error) -> Self {
+        PageTemplatesError::Json(src)
+    }
+}
+
+impl std::fmt::Display for PageTemplatesError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            PageTemplatesError::Toml(e) => write!(formatter, "invalid toml page templates: {}", e),
+            PageTemplatesError::Yaml(e) => write!(formatter, "invalid yaml page templates: {}", e),
+            PageTemplatesError::Json(e) => write!(formatter, "invalid json page templates: {}", e),
+        }
+    }
+}
+
+impl Error for PageTemplatesError {}
+
+impl From<PageTemplatesError> for std::io::Error {
+    fn from(src: PageTemplatesError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+impl PageTemplates {
+    /// Read templates from toml, with a key for each template to set;
+    /// any not given are left as the default
+    pub fn from_toml(src: &str) -> Result<Self, PageTemplatesError> {
+        Ok(toml::from_str(src)?)
+    }
+
+    /// Read templates from yaml, laid out as for `from_toml`
+    pub fn from_yaml(src: &str) -> Result<Self, PageTemplatesError> {
+        Ok(serde_yaml::from_str(src)?)
+    }
+
+    /// Read templates from json, laid out as for `from_toml`
+    pub fn from_json(src: &str) -> Result<Self, PageTemplatesError> {
+        Ok(serde_json::from_str(src)?)
+    }
+
+    /// The markdown of the copyright page of the book described by `metadata`
+    pub(crate) fn copyright_page(&self, metadata: &Metadata<'_>) -> String {
+        fill_page(&self.copyright_page, &|name| {
+            metadata.get_template_field(name, &self.and)
+        })
+    }
+
+    /// The markdown of the halftitle of the book described by `metadata`
+    pub(crate) fn halftitle(&self, metadata: &Metadata<'_>) -> String {
+        fill_page(&self.halftitle, &|name| {
+            metadata.get_template_field(name, &self.and)
+        })
+    }
+
+    /// The words introducing contributors in `role` on the titlepage of the book described by `metadata`
+    pub(crate) fn contributor_label(
+        &self,
+        role: TitlePageContributorRole,
+        metadata: &Metadata<'_>,
+    ) -> Option<String> {
+        use TitlePageContributorRole::*;
+        let template = match role {
+            Author => return None,
+            Editor => &self.edited_by,
+            Translator => &self.translated_by,
+            ForewordAuthor => &self.foreword_by,
+            AfterwordAuthor => &self.afterword_by,
+            IntroductionAuthor => &self.introduction_by,
+            IntroductionAndNotesAuthor => &self.introduction_and_notes_by,
+        };
+        let label = fill(template, &|name| {
+            metadata.get_template_field(name, &self.and)
+        });
+        let label = label.trim();
+        if label.is_empty() {
+            None
+        } else {
+            Some(label.to_string())
+        }
+    }
+}
+
+/// the end of the conditional section named `name` whose body begins `text`:
+/// the index at which the body ends, and the index after its closing tag
+fn section_end(text: &str, name: &str) -> Option<(usize, usize)> {
+    let opens = [format!("{{?{}}}", name), format!("{{!{}}}", name)];
+    let close = format!("{{/{}}}", name);
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with(&close) {
+            if depth == 0 {
+                return Some((i, i + close.len()));
+            }
+            depth -= 1;
+            i += close.len();
+        } else if let Some(open) = opens.iter().find(|open| rest.starts_with(open.as_str())) {
+            depth += 1;
+            i += open.len();
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// `template`, with its placeholders and conditional sections filled using `field`,
+/// which gives the value of a field, as for `Metadata::get_template_field`
+fn fill<F: Fn(&str) -> Option<Option<String>>>(template: &str, field: &F) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let inner = match after.find('}') {
+            Some(close) => &after[..close],
+            None => break,
+        };
+        let tail = &after[inner.len() + 1..];
+
+        let condition = match inner.chars().next() {
+            Some('?') => Some((&inner[1..], true)),
+            Some('!') => Some((&inner[1..], false)),
+            _ => None,
+        };
+        if let Some((name, wanted)) = condition {
+            if let (Some(value), Some((body_end, section_end))) =
+                (field(name), section_end(tail, name))
+            {
+                if value.is_some() == wanted {
+                    filled.push_str(&fill(&tail[..body_end], field));
+                }
+                rest = &tail[section_end..];
+                continue;
+            }
+        } else if let Some(value) = field(inner) {
+            filled.push_str(value.as_deref().unwrap_or_default());
+            rest = tail;
+            continue;
+        }
+
+        // anything else in braces is not a placeholder
+        filled.push('{');
+        rest = after;
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// whether `paragraph` is a thematic break
+fn is_scene_break(paragraph: &str) -> bool {
+    let marks = paragraph.chars().filter(|c| !c.is_whitespace());
+    marks.clone().count() >= 3 && marks.clone().all(|c| c == '*' || c == '-' || c == '_')
+}
+
+/// the markdown of a generated page from `template`,
+/// with each line set on a line of its own and what is left empty dropped
+fn fill_page<F: Fn(&str) -> Option<Option<String>>>(template: &str, field: &F) -> String {
+    // the blank lines of the template are marked before it is filled,
+    // so that they are not confused with lines left empty
+    let marked = template
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                PARAGRAPH_BREAK
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut paragraphs: Vec<String> = Vec::new();
+    for paragraph in fill(&marked, field).split(PARAGRAPH_BREAK) {
+        let filled = paragraph
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("  \n");
+        if filled.is_empty() {
+            continue;
+        }
+        // a scene break is only kept between two paragraphs
+        if is_scene_break(&filled) && paragraphs.last().is_none_or(|p| is_scene_break(p)) {
+            continue;
+        }
+        paragraphs.push(filled);
+    }
+    if paragraphs.last().is_some_and(|p| is_scene_break(p)) {
+        paragraphs.pop();
+    }
+    paragraphs.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str) -> Option<Option<String>> {
+        match name {
+            "title" => Some(Some("A Book".to_string())),
+            "subtitle" => Some(None),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn placeholders_and_conditions() {
+        assert_eq!(fill("{title}: {subtitle}", &field), "A Book: ");
+        assert_eq!(
+            fill(
+                "{?subtitle}{subtitle}{/subtitle}{!subtitle}None{/subtitle}",
+                &field
+            ),
+            "None"
+        );
+        assert_eq!(
+            fill("{?title}{?title}Nested {title}{/title}{/title}", &field),
+            "Nested A Book"
+        );
+        assert_eq!(
+            fill("{@label} {unknown} {?unknown}", &field),
+            "{@label} {unknown} {?unknown}"
+        );
+        assert_eq!(
+            fill_page(
+                "* * *\n\n{title}\n{subtitle}\n\n* * *\n\n{subtitle}\n\n* * *",
+                &field
+            ),
+            "A Book"
+        );
+        assert_eq!(
+            fill_page("{title}\n{title}\n\n---\n\n{title}", &field),
+            "A Book  \nA Book\n\n---\n\nA Book"
+        );
+        assert_eq!(
+            fill_page(
+                "{?subtitle}A\n\nB{/subtitle}\n\n{!subtitle}{title}\n\n{title}{/subtitle}",
+                &field
+            ),
+            "A Book\n\nA Book"
+        );
+    }
+}
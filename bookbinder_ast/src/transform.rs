@@ -0,0 +1,170 @@
+use crate::{BookEvent, BookSrc, Event, Metadata, Tag};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// An output format which a `BookSrc` can be rendered to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderTarget {
+    /// Rendering to an epub
+    Epub,
+    /// Rendering to LaTeX, or to a pdf by way of LaTeX
+    Latex,
+}
+
+impl RenderTarget {
+    /// Whether content marked as only for the format called `name`,
+    /// as by `<span class="pdf-only">`, is rendered to this target;
+    /// `epub` names the epub target, and `pdf` or `latex` the LaTeX target
+    pub fn is_called(self, name: &str) -> bool {
+        let names: &[&str] = match self {
+            RenderTarget::Epub => &["epub"],
+            RenderTarget::Latex => &["pdf", "latex"],
+        };
+        names.iter().any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A transformation applied to a processed book immediately before it is rendered,
+/// for example to enforce a house style.
+///
+/// Transforms can be registered on a `BookSrcBuilder` (and so carried by the `BookSrc` it produces)
+/// or added to the options of a particular renderer.
+/// Transforms registered on the source run first, followed by those added to the renderer's options;
+/// within each group they run in the order in which they were added.
+/// All of them run before any format-specific processing, such as the reformatting of headers.
+///
+/// ```
+/// use bookbinder_ast::{BookEvent, BookSrcBuilder, BookTransform, Event, Metadata, RenderTarget};
+///
+/// /// Replace one name with another throughout
+/// #[derive(Debug)]
+/// struct Rename;
+///
+/// impl BookTransform for Rename {
+///     fn transform<'a>(&self, events: &mut Vec<BookEvent<'a>>, _metadata: &mut Metadata<'a>) {
+///         for event in events.iter_mut() {
+///             if let BookEvent::Event(Event::Text(text)) = event {
+///                 if text.contains("Smith") {
+///                     *text = text.replace("Smith", "Smyth").into();
+///                 }
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut src = BookSrcBuilder::new("A Book")
+///     .add_mainmatter("Mr Smith went to town.")
+///     .add_transform(Rename)
+///     .process();
+/// src.apply_transforms(RenderTarget::Epub, &Default::default());
+/// assert!(src.contents.contains(&BookEvent::Event(Event::Text("Mr Smyth went to town.".into()))));
+/// ```
+pub trait BookTransform: Debug + Send + Sync {
+    /// Transform the events and metadata of a book
+    fn transform<'a>(&self, events: &mut Vec<BookEvent<'a>>, metadata: &mut Metadata<'a>);
+}
+
+#[derive(Debug, Clone)]
+struct RegisteredTransform {
+    transform: Arc<dyn BookTransform>,
+    /// the only target this applies to, if restricted
+    target: Option<RenderTarget>,
+}
+
+/// An ordered sequence of transforms, each of which may be restricted to a particular `RenderTarget`
+#[derive(Debug, Clone, Default)]
+pub struct TransformPipeline {
+    transforms: Vec<RegisteredTransform>,
+}
+
+impl TransformPipeline {
+    /// Add a transform to apply whatever the output format
+    pub fn add<T: BookTransform + 'static>(&mut self, transform: T) -> &mut Self {
+        self.transforms.push(RegisteredTransform {
+            transform: Arc::new(transform),
+            target: None,
+        });
+        self
+    }
+
+    /// Add a transform to apply only when rendering to `target`
+    pub fn add_for<T: BookTransform + 'static>(
+        &mut self,
+        transform: T,
+        target: RenderTarget,
+    ) -> &mut Self {
+        self.transforms.push(RegisteredTransform {
+            transform: Arc::new(transform),
+            target: Some(target),
+        });
+        self
+    }
+
+    /// Move all the transforms of `other` to the end of this pipeline
+    pub(crate) fn append(&mut self, other: &mut TransformPipeline) {
+        self.transforms.append(&mut other.transforms);
+    }
+
+    /// Whether this pipeline contains no transforms at all
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Apply, in order, each transform enabled for `target`
+    pub fn apply<'a>(
+        &self,
+        target: RenderTarget,
+        events: &mut Vec<BookEvent<'a>>,
+        metadata: &mut Metadata<'a>,
+    ) {
+        let enabled = self
+            .transforms
+            .iter()
+            .filter(|t| t.target.is_none() || t.target == Some(target));
+        for registered in enabled {
+            registered.transform.transform(events, metadata);
+        }
+    }
+}
+
+impl BookSrc<'_> {
+    /// Remove any content marked as only for formats other than `target`,
+    /// keeping that marked for `target` itself without its markers.
+    ///
+    /// ```
+    /// use bookbinder_ast::{BookEvent, BookSrcBuilder, Event, RenderTarget};
+    ///
+    /// let mut src = BookSrcBuilder::new("A Book")
+    ///     .add_mainmatter(r#"See <span class="pdf-only">page 32</span><span class="epub-only">the next chapter</span>."#)
+    ///     .process();
+    /// src.select_format(RenderTarget::Epub);
+    /// assert!(src.contents.contains(&BookEvent::Event(Event::Text("the next chapter".into()))));
+    /// assert!(!src.contents.contains(&BookEvent::Event(Event::Text("page 32".into()))));
+    /// ```
+    pub fn select_format(&mut self, target: RenderTarget) {
+        // whether each format-only element we are within is for another format
+        let mut excluded: Vec<bool> = Vec::new();
+        self.contents.retain(|event| match event {
+            BookEvent::Event(Event::Start(Tag::FormatOnly(format))) => {
+                excluded.push(!target.is_called(format));
+                false
+            }
+            BookEvent::Event(Event::End(Tag::FormatOnly(_))) => {
+                excluded.pop();
+                false
+            }
+            _ => !excluded.contains(&true),
+        });
+    }
+
+    /// Apply the transforms registered on this source, followed by `additional` transforms
+    /// (typically those set in a renderer's options), for rendering to `target`.
+    ///
+    /// The transforms registered on this source are consumed,
+    /// so calling this again will only apply `additional`.
+    pub fn apply_transforms(&mut self, target: RenderTarget, additional: &TransformPipeline) {
+        let own = std::mem::take(&mut self.transforms);
+        own.apply(target, &mut self.contents, &mut self.metadata);
+        additional.apply(target, &mut self.contents, &mut self.metadata);
+    }
+}
@@ -87,7 +87,7 @@ fn load_system_fonts(db: &mut Database) {
     db.load_fonts_dir("/usr/local/share/fonts/");
 
     if let Ok(ref home) = std::env::var("HOME") {
-        let path = std::path::Path::new(home).join(".local/share/fonts");
+        let path = Path::new(home).join(".local/share/fonts");
         db.load_fonts_dir(path);
     }
 }
@@ -103,7 +103,7 @@ fn load_system_fonts(db: &mut Database) {
     db.load_fonts_dir("/System/Library/Fonts");
 
     if let Ok(ref home) = std::env::var("HOME") {
-        let path = std::path::Path::new(home).join("Library/Fonts");
+        let path = Path::new(home).join("Library/Fonts");
         db.load_fonts_dir(path);
     }
 }
@@ -129,17 +129,88 @@ pub fn escape_to_latex<'a, S: Into<Cow<'a, str>>>(input: S) -> Cow<'a, str> {
 
 /// call lualatex on a particular str and return the pdf
 pub fn call_latex(tex: &str) -> Result<Vec<u8>, std::io::Error> {
-    _call_latex(tex, false)
+    _call_latex(tex, false, None)
 }
 
 /// call lualatex on a particular str and return the pdf,
 /// displaying lualatex's output as it goes
 pub fn call_latex_verbose(tex: &str) -> Result<Vec<u8>, std::io::Error> {
-    _call_latex(tex, true)
+    _call_latex(tex, true, None)
 }
 
-/// call a latex engine on a particular str and return the pdf
-fn _call_latex(tex: &str, verbose: bool) -> Result<Vec<u8>, std::io::Error> {
+/// call lualatex on a particular str and return the pdf,
+/// dating it -- and any date given by `\today` -- to `source_date_epoch`,
+/// a number of seconds since the unix epoch, so that the same tex always gives the same pdf
+pub fn call_latex_with_source_date(
+    tex: &str,
+    source_date_epoch: i64,
+) -> Result<Vec<u8>, std::io::Error> {
+    _call_latex(tex, false, Some(source_date_epoch))
+}
+
+/// The name by which xindy knows the language of the IETF language tag `tag`, such as `en-GB` or `fr`,
+/// for sorting an index; languages it does not know are sorted as English
+pub fn xindy_language(tag: &str) -> &'static str {
+    let primary = tag.split(['-', '_']).next().unwrap_or_default();
+    match primary.to_ascii_lowercase().as_str() {
+        "sq" => "albanian",
+        "be" => "belarusian",
+        "bg" => "bulgarian",
+        "hr" => "croatian",
+        "cs" => "czech",
+        "da" => "danish",
+        "nl" => "dutch",
+        "eo" => "esperanto",
+        "et" => "estonian",
+        "fi" => "finnish",
+        "fr" => "french",
+        "ka" => "georgian",
+        "de" => "german-din",
+        "el" => "greek",
+        "he" => "hebrew",
+        "hu" => "hungarian",
+        "is" => "icelandic",
+        "it" => "italian",
+        "la" => "latin",
+        "lv" => "latvian",
+        "lt" => "lithuanian",
+        "mk" => "macedonian",
+        "mn" => "mongolian",
+        "no" | "nb" | "nn" => "norwegian",
+        "pl" => "polish",
+        "pt" => "portuguese",
+        "ro" => "romanian",
+        "ru" => "russian",
+        "sr" => "serbian",
+        "sk" => "slovak-small",
+        "sl" => "slovenian",
+        "es" => "spanish-modern",
+        "sv" => "swedish",
+        "tr" => "turkish",
+        "uk" => "ukrainian",
+        "vi" => "vietnamese",
+        _ => "english",
+    }
+}
+
+/// the language in which `tex` asks for its index to be sorted by xindy,
+/// as given to `\makeindex`; English if it does not say
+fn index_language(tex: &str) -> &str {
+    let option = "\\makeindex[options=-L ";
+    tex.find(option)
+        .map(|start| &tex[start + option.len()..])
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or("english")
+}
+
+/// call a latex engine on a particular str and return the pdf;
+/// where a source date is given, or set in `SOURCE_DATE_EPOCH`,
+/// the engine is made to use it in place of the current time
+fn _call_latex(
+    tex: &str,
+    verbose: bool,
+    source_date_epoch: Option<i64>,
+) -> Result<Vec<u8>, std::io::Error> {
     let filename_base = tex.hash_to_string();
     let mut outdir = std::env::temp_dir();
     outdir = outdir.join("bookbinder");
@@ -152,22 +223,24 @@ fn _call_latex(tex: &str, verbose: bool) -> Result<Vec<u8>, std::io::Error> {
     std::fs::write(&texpath, tex)?;
 
     let odir_arg = format!("-output-directory={}", &outdir.to_string_lossy());
+    // an index is sorted by xindy rather than makeindex, in the language the document asks for
+    let index_arg = format!(
+        "$makeindex = q/texindy -L {} -C utf8 %O -o %D %S/",
+        index_language(tex)
+    );
 
-    let mut ltx = if !verbose {
-        Command::new("latexmk")
-            .args(&[
-                &odir_arg,
-                "-xelatex",
-                "-interaction=batchmode",
-                "-halt-on-error",
-                texpath.to_string_lossy().as_ref(),
-            ])
-            .spawn()?
-    } else {
-        Command::new("latexmk")
-            .args(&[&odir_arg, "-xelatex", texpath.to_string_lossy().as_ref()])
-            .spawn()?
-    };
+    let mut latexmk = Command::new("latexmk");
+    let _ = latexmk.args(&[&odir_arg, "-e", &index_arg, "-xelatex"]);
+    if !verbose {
+        let _ = latexmk.args(&["-interaction=batchmode", "-halt-on-error"]);
+    }
+    let _ = latexmk.arg(texpath.to_string_lossy().as_ref());
+    if let Some(epoch) = source_date_epoch.or_else(source_date_epoch_from_env) {
+        let _ = latexmk
+            .env("SOURCE_DATE_EPOCH", epoch.to_string())
+            .env("FORCE_SOURCE_DATE", "1");
+    }
+    let mut ltx = latexmk.spawn()?;
 
     let _ = ltx.wait()?;
 
@@ -264,20 +337,81 @@ pub fn convert_pdf_to_svg(pdf: &[u8], dpi: Option<usize>) -> Result<String, Box<
     Ok(svg)
 }
 
-/// get the current year as a string
+/// The time given by the `SOURCE_DATE_EPOCH` environment variable,
+/// as a number of seconds since the unix epoch, if it is set;
+/// see <https://reproducible-builds.org/specs/source-date-epoch/>
+pub fn source_date_epoch_from_env() -> Option<i64> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+}
+
+/// The time to treat as the present when producing output,
+/// as a number of seconds since the unix epoch:
+/// that given by `SOURCE_DATE_EPOCH`, if it is set, or otherwise now
+pub fn get_source_date_epoch() -> i64 {
+    source_date_epoch_from_env().unwrap_or_else(|| time::get_time().sec)
+}
+
+/// The time, in utc, of a number of seconds since the unix epoch
+pub fn epoch_to_tm(epoch: i64) -> time::Tm {
+    time::at_utc(time::Timespec::new(epoch, 0))
+}
+
+/// The number of seconds since the unix epoch at midnight, utc, on a date
+/// given as `YYYY-MM-DD` or, for the first of January, just `YYYY`
+/// ```
+/// # use bookbinder_common::date_to_epoch;
+/// assert_eq!(date_to_epoch("2020-01-01"), Some(1577836800));
+/// assert_eq!(date_to_epoch("2020"), Some(1577836800));
+/// assert_eq!(date_to_epoch("1 January 2020"), None);
+/// ```
+pub fn date_to_epoch(date: &str) -> Option<i64> {
+    let date = date.trim();
+    let date = if date.len() == 4 {
+        format!("{}-01-01", date)
+    } else {
+        date.to_string()
+    };
+    time::strptime(&date, "%Y-%m-%d")
+        .ok()
+        .map(|tm| tm.to_timespec().sec)
+}
+
+/// The year, in utc, of a number of seconds since the unix epoch
+pub fn epoch_to_year(epoch: i64) -> String {
+    time::strftime("%Y", &epoch_to_tm(epoch)).unwrap()
+}
+
+/// get the current year as a string;
+/// where `SOURCE_DATE_EPOCH` is set, this is the year it gives
 pub fn get_current_year() -> String {
-    let now = time::now_utc();
-    time::strftime("%Y", &now).unwrap()
+    epoch_to_year(get_source_date_epoch())
+}
+
+/// given a number, return the corresponding letters,
+/// continuing with double letters after Z
+/// e.g. 1 -> A, 2 -> B, 26 -> Z, 27 -> AA, 28 -> AB.
+/// Returns an empty string for 0
+/// ```
+/// # use bookbinder_common::number_to_letters;
+/// let number = 2;
+/// assert_eq!(number_to_letters(number), "B");
+/// ```
+pub fn number_to_letters(n: usize) -> String {
+    num_conversions::number_to_letters(n)
 }
 
 /// given a number, return the corresponding letter
 /// e.g. 0 -> A, 1 -> B, 2 -> C.
 /// Returns an error if the number is greater than 25
 /// ```
+/// # #![allow(deprecated)]
 /// # use bookbinder_common::number_to_letter;
 /// let number = 1;
 /// assert_eq!(number_to_letter(number), Ok('B'));
 /// ```
+#[deprecated(note = "use `number_to_letters`, which continues past Z and counts from 1")]
 pub const fn number_to_letter(n: u8) -> Result<char, ()> {
     if n > 25 {
         Err(())
@@ -289,40 +423,84 @@ pub const fn number_to_letter(n: u8) -> Result<char, ()> {
 }
 
 /// given a number, return it in roman format
-/// e.g. 1 -> I, 10 -> X, etc
+/// e.g. 1 -> I, 10 -> X, etc.
+/// Numbers greater than 3999, which have no roman form in ordinary use,
+/// are returned in arabic numerals
 /// ```
 /// # use bookbinder_common::number_to_roman;
 /// let number = 1;
 /// assert_eq!(number_to_roman(number), "I");
+/// assert_eq!(number_to_roman(4000), "4000");
 /// ```
-pub const fn number_to_roman(n: u8) -> &'static str {
+pub fn number_to_roman(n: usize) -> String {
     num_conversions::number_to_roman(n)
 }
 
-/// given a number, return its equivalent in words
+/// given a number, return its equivalent in uppercase words
 /// ```
 /// # use bookbinder_common::number_to_words;
 /// let number = 1;
 /// assert_eq!(number_to_words(number), "ONE");
 /// ```
-pub const fn number_to_words(n: u8) -> &'static str {
+pub fn number_to_words(n: usize) -> String {
     num_conversions::number_to_words(n)
 }
 
+/// given a number, return its ordinal equivalent in uppercase words
+/// ```
+/// # use bookbinder_common::number_to_ordinal_words;
+/// let number = 21;
+/// assert_eq!(number_to_ordinal_words(number), "TWENTY-FIRST");
+/// ```
+pub fn number_to_ordinal_words(n: usize) -> String {
+    num_conversions::number_to_ordinal_words(n)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(deprecated)]
     fn test_numbers_to_letter() {
         assert_eq!(number_to_letter(0), Ok('A'));
         assert_eq!(number_to_letter(25), Ok('Z'));
         assert_eq!(number_to_letter(27), Err(()));
     }
 
+    #[test]
+    fn test_numbers_to_letters() {
+        assert_eq!(number_to_letters(0), "");
+        assert_eq!(number_to_letters(1), "A");
+        assert_eq!(number_to_letters(26), "Z");
+        assert_eq!(number_to_letters(27), "AA");
+        assert_eq!(number_to_letters(28), "AB");
+        assert_eq!(number_to_letters(53), "BA");
+        assert_eq!(number_to_letters(703), "AAA");
+    }
+
     #[test]
     fn test_get_current_year() {
-        assert_eq!(get_current_year(), "2020".to_string());
+        assert_eq!(epoch_to_year(1577836800), "2020".to_string());
+        assert_eq!(epoch_to_year(1577836799), "2019".to_string());
+        assert_eq!(
+            date_to_epoch("2020-06-01").map(epoch_to_year),
+            Some("2020".to_string())
+        );
+        assert_eq!(date_to_epoch("1 June 2020"), None);
+        assert_eq!(date_to_epoch("2020-13-01"), None);
+    }
+
+    #[test]
+    fn test_index_language() {
+        assert_eq!(xindy_language("en-GB"), "english");
+        assert_eq!(xindy_language("fr"), "french");
+        assert_eq!(xindy_language("DE_at"), "german-din");
+        assert_eq!(xindy_language("nb"), "norwegian");
+        assert_eq!(xindy_language("tlh"), "english");
+        let tex = "\\usepackage[xindy]{imakeidx}\n\\makeindex[options=-L french -C utf8, intoc]\n";
+        assert_eq!(index_language(tex), "french");
+        assert_eq!(index_language("\\begin{document}"), "english");
     }
 
     #[test]
@@ -364,11 +542,32 @@ mod tests {
         assert_eq!(number_to_words(12), "TWELVE");
         assert_eq!(number_to_words(25), "TWENTY-FIVE");
         assert_eq!(number_to_words(125), "ONE HUNDRED AND TWENTY-FIVE");
+        assert_eq!(number_to_words(300), "THREE HUNDRED");
+        assert_eq!(number_to_words(1005), "ONE THOUSAND AND FIVE");
+        assert_eq!(
+            number_to_words(2_340_017),
+            "TWO MILLION THREE HUNDRED AND FORTY THOUSAND AND SEVENTEEN"
+        );
+    }
+
+    #[test]
+    fn test_numbers_to_ordinal_words() {
+        assert_eq!(number_to_ordinal_words(1), "FIRST");
+        assert_eq!(number_to_ordinal_words(12), "TWELFTH");
+        assert_eq!(number_to_ordinal_words(20), "TWENTIETH");
+        assert_eq!(number_to_ordinal_words(42), "FORTY-SECOND");
+        assert_eq!(number_to_ordinal_words(100), "ONE HUNDREDTH");
+        assert_eq!(number_to_ordinal_words(258), "TWO HUNDRED AND FIFTY-EIGHTH");
     }
 
     #[test]
     fn test_numbers_to_roman() {
         assert_eq!(number_to_roman(0), "");
         assert_eq!(number_to_roman(1), "I");
+        assert_eq!(number_to_roman(49), "XLIX");
+        assert_eq!(number_to_roman(300), "CCC");
+        assert_eq!(number_to_roman(1994), "MCMXCIV");
+        assert_eq!(number_to_roman(3999), "MMMCMXCIX");
+        assert_eq!(number_to_roman(usize::MAX), usize::MAX.to_string());
     }
 }
@@ -1,528 +1,158 @@
-// We don't care about final binary size,
-// we don't need to do anything fancy,
-// and no-one wants more than 255 chapters anyway
+// Conversions of numbers into words, roman numerals and letters,
+// all in uppercase; any other casing is left to the caller.
 //
-// Therefore, resist the temptation to write clever algorithms here;
-// this is fast, well-controlled, and reduces external dependencies.
+// Words follow British usage, so that 125 is `ONE HUNDRED AND TWENTY-FIVE`.
 
-pub(super) const fn number_to_words(num: u8) -> &'static str {
-    match num {
-        0 => "ZERO",
-        1 => "ONE",
-        2 => "TWO",
-        3 => "THREE",
-        4 => "FOUR",
-        5 => "FIVE",
-        6 => "SIX",
-        7 => "SEVEN",
-        8 => "EIGHT",
-        9 => "NINE",
-        10 => "TEN",
-        11 => "ELEVEN",
-        12 => "TWELVE",
-        13 => "THIRTEEN",
-        14 => "FOURTEEN",
-        15 => "FIFTEEN",
-        16 => "SIXTEEN",
-        17 => "SEVENTEEN",
-        18 => "EIGHTEEN",
-        19 => "NINETEEN",
-        20 => "TWENTY",
-        21 => "TWENTY-ONE",
-        22 => "TWENTY-TWO",
-        23 => "TWENTY-THREE",
-        24 => "TWENTY-FOUR",
-        25 => "TWENTY-FIVE",
-        26 => "TWENTY-SIX",
-        27 => "TWENTY-SEVEN",
-        28 => "TWENTY-EIGHT",
-        29 => "TWENTY-NINE",
-        30 => "THIRTY",
-        31 => "THIRTY-ONE",
-        32 => "THIRTY-TWO",
-        33 => "THIRTY-THREE",
-        34 => "THIRTY-FOUR",
-        35 => "THIRTY-FIVE",
-        36 => "THIRTY-SIX",
-        37 => "THIRTY-SEVEN",
-        38 => "THIRTY-EIGHT",
-        39 => "THIRTY-NINE",
-        40 => "FORTY",
-        41 => "FORTY-ONE",
-        42 => "FORTY-TWO",
-        43 => "FORTY-THREE",
-        44 => "FORTY-FOUR",
-        45 => "FORTY-FIVE",
-        46 => "FORTY-SIX",
-        47 => "FORTY-SEVEN",
-        48 => "FORTY-EIGHT",
-        49 => "FORTY-NINE",
-        50 => "FIFTY",
-        51 => "FIFTY-ONE",
-        52 => "FIFTY-TWO",
-        53 => "FIFTY-THREE",
-        54 => "FIFTY-FOUR",
-        55 => "FIFTY-FIVE",
-        56 => "FIFTY-SIX",
-        57 => "FIFTY-SEVEN",
-        58 => "FIFTY-EIGHT",
-        59 => "FIFTY-NINE",
-        60 => "SIXTY",
-        61 => "SIXTY-ONE",
-        62 => "SIXTY-TWO",
-        63 => "SIXTY-THREE",
-        64 => "SIXTY-FOUR",
-        65 => "SIXTY-FIVE",
-        66 => "SIXTY-SIX",
-        67 => "SIXTY-SEVEN",
-        68 => "SIXTY-EIGHT",
-        69 => "SIXTY-NINE",
-        70 => "SEVENTY",
-        71 => "SEVENTY-ONE",
-        72 => "SEVENTY-TWO",
-        73 => "SEVENTY-THREE",
-        74 => "SEVENTY-FOUR",
-        75 => "SEVENTY-FIVE",
-        76 => "SEVENTY-SIX",
-        77 => "SEVENTY-SEVEN",
-        78 => "SEVENTY-EIGHT",
-        79 => "SEVENTY-NINE",
-        80 => "EIGHTY",
-        81 => "EIGHTY-ONE",
-        82 => "EIGHTY-TWO",
-        83 => "EIGHTY-THREE",
-        84 => "EIGHTY-FOUR",
-        85 => "EIGHTY-FIVE",
-        86 => "EIGHTY-SIX",
-        87 => "EIGHTY-SEVEN",
-        88 => "EIGHTY-EIGHT",
-        89 => "EIGHTY-NINE",
-        90 => "NINETY",
-        91 => "NINETY-ONE",
-        92 => "NINETY-TWO",
-        93 => "NINETY-THREE",
-        94 => "NINETY-FOUR",
-        95 => "NINETY-FIVE",
-        96 => "NINETY-SIX",
-        97 => "NINETY-SEVEN",
-        98 => "NINETY-EIGHT",
-        99 => "NINETY-NINE",
-        100 => "ONE HUNDRED",
-        101 => "ONE HUNDRED AND ONE",
-        102 => "ONE HUNDRED AND TWO",
-        103 => "ONE HUNDRED AND THREE",
-        104 => "ONE HUNDRED AND FOUR",
-        105 => "ONE HUNDRED AND FIVE",
-        106 => "ONE HUNDRED AND SIX",
-        107 => "ONE HUNDRED AND SEVEN",
-        108 => "ONE HUNDRED AND EIGHT",
-        109 => "ONE HUNDRED AND NINE",
-        110 => "ONE HUNDRED AND TEN",
-        111 => "ONE HUNDRED AND ELEVEN",
-        112 => "ONE HUNDRED AND TWELVE",
-        113 => "ONE HUNDRED AND THIRTEEN",
-        114 => "ONE HUNDRED AND FOURTEEN",
-        115 => "ONE HUNDRED AND FIFTEEN",
-        116 => "ONE HUNDRED AND SIXTEEN",
-        117 => "ONE HUNDRED AND SEVENTEEN",
-        118 => "ONE HUNDRED AND EIGHTEEN",
-        119 => "ONE HUNDRED AND NINETEEN",
-        120 => "ONE HUNDRED AND TWENTY",
-        121 => "ONE HUNDRED AND TWENTY-ONE",
-        122 => "ONE HUNDRED AND TWENTY-TWO",
-        123 => "ONE HUNDRED AND TWENTY-THREE",
-        124 => "ONE HUNDRED AND TWENTY-FOUR",
-        125 => "ONE HUNDRED AND TWENTY-FIVE",
-        126 => "ONE HUNDRED AND TWENTY-SIX",
-        127 => "ONE HUNDRED AND TWENTY-SEVEN",
-        128 => "ONE HUNDRED AND TWENTY-EIGHT",
-        129 => "ONE HUNDRED AND TWENTY-NINE",
-        130 => "ONE HUNDRED AND THIRTY",
-        131 => "ONE HUNDRED AND THIRTY-ONE",
-        132 => "ONE HUNDRED AND THIRTY-TWO",
-        133 => "ONE HUNDRED AND THIRTY-THREE",
-        134 => "ONE HUNDRED AND THIRTY-FOUR",
-        135 => "ONE HUNDRED AND THIRTY-FIVE",
-        136 => "ONE HUNDRED AND THIRTY-SIX",
-        137 => "ONE HUNDRED AND THIRTY-SEVEN",
-        138 => "ONE HUNDRED AND THIRTY-EIGHT",
-        139 => "ONE HUNDRED AND THIRTY-NINE",
-        140 => "ONE HUNDRED AND FORTY",
-        141 => "ONE HUNDRED AND FORTY-ONE",
-        142 => "ONE HUNDRED AND FORTY-TWO",
-        143 => "ONE HUNDRED AND FORTY-THREE",
-        144 => "ONE HUNDRED AND FORTY-FOUR",
-        145 => "ONE HUNDRED AND FORTY-FIVE",
-        146 => "ONE HUNDRED AND FORTY-SIX",
-        147 => "ONE HUNDRED AND FORTY-SEVEN",
-        148 => "ONE HUNDRED AND FORTY-EIGHT",
-        149 => "ONE HUNDRED AND FORTY-NINE",
-        150 => "ONE HUNDRED AND FIFTY",
-        151 => "ONE HUNDRED AND FIFTY-ONE",
-        152 => "ONE HUNDRED AND FIFTY-TWO",
-        153 => "ONE HUNDRED AND FIFTY-THREE",
-        154 => "ONE HUNDRED AND FIFTY-FOUR",
-        155 => "ONE HUNDRED AND FIFTY-FIVE",
-        156 => "ONE HUNDRED AND FIFTY-SIX",
-        157 => "ONE HUNDRED AND FIFTY-SEVEN",
-        158 => "ONE HUNDRED AND FIFTY-EIGHT",
-        159 => "ONE HUNDRED AND FIFTY-NINE",
-        160 => "ONE HUNDRED AND SIXTY",
-        161 => "ONE HUNDRED AND SIXTY-ONE",
-        162 => "ONE HUNDRED AND SIXTY-TWO",
-        163 => "ONE HUNDRED AND SIXTY-THREE",
-        164 => "ONE HUNDRED AND SIXTY-FOUR",
-        165 => "ONE HUNDRED AND SIXTY-FIVE",
-        166 => "ONE HUNDRED AND SIXTY-SIX",
-        167 => "ONE HUNDRED AND SIXTY-SEVEN",
-        168 => "ONE HUNDRED AND SIXTY-EIGHT",
-        169 => "ONE HUNDRED AND SIXTY-NINE",
-        170 => "ONE HUNDRED AND SEVENTY",
-        171 => "ONE HUNDRED AND SEVENTY-ONE",
-        172 => "ONE HUNDRED AND SEVENTY-TWO",
-        173 => "ONE HUNDRED AND SEVENTY-THREE",
-        174 => "ONE HUNDRED AND SEVENTY-FOUR",
-        175 => "ONE HUNDRED AND SEVENTY-FIVE",
-        176 => "ONE HUNDRED AND SEVENTY-SIX",
-        177 => "ONE HUNDRED AND SEVENTY-SEVEN",
-        178 => "ONE HUNDRED AND SEVENTY-EIGHT",
-        179 => "ONE HUNDRED AND SEVENTY-NINE",
-        180 => "ONE HUNDRED AND EIGHTY",
-        181 => "ONE HUNDRED AND EIGHTY-ONE",
-        182 => "ONE HUNDRED AND EIGHTY-TWO",
-        183 => "ONE HUNDRED AND EIGHTY-THREE",
-        184 => "ONE HUNDRED AND EIGHTY-FOUR",
-        185 => "ONE HUNDRED AND EIGHTY-FIVE",
-        186 => "ONE HUNDRED AND EIGHTY-SIX",
-        187 => "ONE HUNDRED AND EIGHTY-SEVEN",
-        188 => "ONE HUNDRED AND EIGHTY-EIGHT",
-        189 => "ONE HUNDRED AND EIGHTY-NINE",
-        190 => "ONE HUNDRED AND NINETY",
-        191 => "ONE HUNDRED AND NINETY-ONE",
-        192 => "ONE HUNDRED AND NINETY-TWO",
-        193 => "ONE HUNDRED AND NINETY-THREE",
-        194 => "ONE HUNDRED AND NINETY-FOUR",
-        195 => "ONE HUNDRED AND NINETY-FIVE",
-        196 => "ONE HUNDRED AND NINETY-SIX",
-        197 => "ONE HUNDRED AND NINETY-SEVEN",
-        198 => "ONE HUNDRED AND NINETY-EIGHT",
-        199 => "ONE HUNDRED AND NINETY-NINE",
-        200 => "TWO HUNDRED",
-        201 => "TWO HUNDRED AND ONE",
-        202 => "TWO HUNDRED AND TWO",
-        203 => "TWO HUNDRED AND THREE",
-        204 => "TWO HUNDRED AND FOUR",
-        205 => "TWO HUNDRED AND FIVE",
-        206 => "TWO HUNDRED AND SIX",
-        207 => "TWO HUNDRED AND SEVEN",
-        208 => "TWO HUNDRED AND EIGHT",
-        209 => "TWO HUNDRED AND NINE",
-        210 => "TWO HUNDRED AND TEN",
-        211 => "TWO HUNDRED AND ELEVEN",
-        212 => "TWO HUNDRED AND TWELVE",
-        213 => "TWO HUNDRED AND THIRTEEN",
-        214 => "TWO HUNDRED AND FOURTEEN",
-        215 => "TWO HUNDRED AND FIFTEEN",
-        216 => "TWO HUNDRED AND SIXTEEN",
-        217 => "TWO HUNDRED AND SEVENTEEN",
-        218 => "TWO HUNDRED AND EIGHTEEN",
-        219 => "TWO HUNDRED AND NINETEEN",
-        220 => "TWO HUNDRED AND TWENTY",
-        221 => "TWO HUNDRED AND TWENTY-ONE",
-        222 => "TWO HUNDRED AND TWENTY-TWO",
-        223 => "TWO HUNDRED AND TWENTY-THREE",
-        224 => "TWO HUNDRED AND TWENTY-FOUR",
-        225 => "TWO HUNDRED AND TWENTY-FIVE",
-        226 => "TWO HUNDRED AND TWENTY-SIX",
-        227 => "TWO HUNDRED AND TWENTY-SEVEN",
-        228 => "TWO HUNDRED AND TWENTY-EIGHT",
-        229 => "TWO HUNDRED AND TWENTY-NINE",
-        230 => "TWO HUNDRED AND THIRTY",
-        231 => "TWO HUNDRED AND THIRTY-ONE",
-        232 => "TWO HUNDRED AND THIRTY-TWO",
-        233 => "TWO HUNDRED AND THIRTY-THREE",
-        234 => "TWO HUNDRED AND THIRTY-FOUR",
-        235 => "TWO HUNDRED AND THIRTY-FIVE",
-        236 => "TWO HUNDRED AND THIRTY-SIX",
-        237 => "TWO HUNDRED AND THIRTY-SEVEN",
-        238 => "TWO HUNDRED AND THIRTY-EIGHT",
-        239 => "TWO HUNDRED AND THIRTY-NINE",
-        240 => "TWO HUNDRED AND FORTY",
-        241 => "TWO HUNDRED AND FORTY-ONE",
-        242 => "TWO HUNDRED AND FORTY-TWO",
-        243 => "TWO HUNDRED AND FORTY-THREE",
-        244 => "TWO HUNDRED AND FORTY-FOUR",
-        245 => "TWO HUNDRED AND FORTY-FIVE",
-        246 => "TWO HUNDRED AND FORTY-SIX",
-        247 => "TWO HUNDRED AND FORTY-SEVEN",
-        248 => "TWO HUNDRED AND FORTY-EIGHT",
-        249 => "TWO HUNDRED AND FORTY-NINE",
-        250 => "TWO HUNDRED AND FIFTY",
-        251 => "TWO HUNDRED AND FIFTY-ONE",
-        252 => "TWO HUNDRED AND FIFTY-TWO",
-        253 => "TWO HUNDRED AND FIFTY-THREE",
-        254 => "TWO HUNDRED AND FIFTY-FOUR",
-        255 => "TWO HUNDRED AND FIFTY-FIVE",
+const ONES: [&str; 20] = [
+    "ZERO",
+    "ONE",
+    "TWO",
+    "THREE",
+    "FOUR",
+    "FIVE",
+    "SIX",
+    "SEVEN",
+    "EIGHT",
+    "NINE",
+    "TEN",
+    "ELEVEN",
+    "TWELVE",
+    "THIRTEEN",
+    "FOURTEEN",
+    "FIFTEEN",
+    "SIXTEEN",
+    "SEVENTEEN",
+    "EIGHTEEN",
+    "NINETEEN",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "TWENTY", "THIRTY", "FORTY", "FIFTY", "SIXTY", "SEVENTY", "EIGHTY", "NINETY",
+];
+
+// enough for usize::MAX on a 64 bit platform
+const SCALES: [&str; 7] = [
+    "",
+    "THOUSAND",
+    "MILLION",
+    "BILLION",
+    "TRILLION",
+    "QUADRILLION",
+    "QUINTILLION",
+];
+
+/// the largest number with a roman form in ordinary use
+const MAX_ROMAN: usize = 3999;
+
+const ROMAN: [(usize, &str); 13] = [
+    (1000, "M"),
+    (900, "CM"),
+    (500, "D"),
+    (400, "CD"),
+    (100, "C"),
+    (90, "XC"),
+    (50, "L"),
+    (40, "XL"),
+    (10, "X"),
+    (9, "IX"),
+    (5, "V"),
+    (4, "IV"),
+    (1, "I"),
+];
+
+fn below_hundred_to_words(num: usize) -> String {
+    if num < 20 {
+        return ONES[num].to_string();
+    }
+    match (num / 10, num % 10) {
+        (tens, 0) => TENS[tens].to_string(),
+        (tens, ones) => format!("{}-{}", TENS[tens], ONES[ones]),
+    }
+}
+
+fn below_thousand_to_words(num: usize) -> String {
+    match (num / 100, num % 100) {
+        (0, rest) => below_hundred_to_words(rest),
+        (hundreds, 0) => format!("{} HUNDRED", ONES[hundreds]),
+        (hundreds, rest) => format!(
+            "{} HUNDRED AND {}",
+            ONES[hundreds],
+            below_hundred_to_words(rest)
+        ),
+    }
+}
+
+pub(super) fn number_to_words(num: usize) -> String {
+    if num == 0 {
+        return ONES[0].to_string();
+    }
+
+    let mut groups = Vec::with_capacity(SCALES.len());
+    let mut remaining = num;
+    while remaining > 0 {
+        groups.push(remaining % 1000);
+        remaining /= 1000;
+    }
+
+    let mut words = Vec::with_capacity(groups.len());
+    for (scale, group) in groups.into_iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        let mut group_words = below_thousand_to_words(group);
+        if scale > 0 {
+            group_words.push(' ');
+            group_words.push_str(SCALES[scale]);
+        } else if group < 100 && num >= 1000 {
+            // e.g. ONE THOUSAND AND FIVE
+            group_words.insert_str(0, "AND ");
+        }
+        words.push(group_words);
+    }
+    words.join(" ")
+}
+
+pub(super) fn number_to_ordinal_words(num: usize) -> String {
+    let words = number_to_words(num);
+    let last_word_start = words.rfind(&[' ', '-'][..]).map(|i| i + 1).unwrap_or(0);
+    let (start, last_word) = words.split_at(last_word_start);
+    let ordinal = match last_word {
+        "ONE" => "FIRST".to_string(),
+        "TWO" => "SECOND".to_string(),
+        "THREE" => "THIRD".to_string(),
+        "FIVE" => "FIFTH".to_string(),
+        "EIGHT" => "EIGHTH".to_string(),
+        "NINE" => "NINTH".to_string(),
+        "TWELVE" => "TWELFTH".to_string(),
+        w if w.ends_with('Y') => format!("{}IETH", &w[..w.len() - 1]),
+        w => format!("{}TH", w),
+    };
+    format!("{}{}", start, ordinal)
+}
+
+pub(super) fn number_to_roman(num: usize) -> String {
+    if num > MAX_ROMAN {
+        return num.to_string();
+    }
+    let mut remaining = num;
+    let mut roman = String::new();
+    for (value, numeral) in ROMAN.iter() {
+        while remaining >= *value {
+            roman.push_str(numeral);
+            remaining -= value;
+        }
     }
+    roman
 }
 
-pub(super) const fn number_to_roman(num: u8) -> &'static str {
-    match num {
-        0 => "",
-        1 => "I",
-        2 => "II",
-        3 => "III",
-        4 => "IV",
-        5 => "V",
-        6 => "VI",
-        7 => "VII",
-        8 => "VIII",
-        9 => "IX",
-        10 => "X",
-        11 => "XI",
-        12 => "XII",
-        13 => "XIII",
-        14 => "XIV",
-        15 => "XV",
-        16 => "XVI",
-        17 => "XVII",
-        18 => "XVIII",
-        19 => "XIX",
-        20 => "XX",
-        21 => "XXI",
-        22 => "XXII",
-        23 => "XXIII",
-        24 => "XXIV",
-        25 => "XXV",
-        26 => "XXVI",
-        27 => "XXVII",
-        28 => "XXVIII",
-        29 => "XXIX",
-        30 => "XXX",
-        31 => "XXXI",
-        32 => "XXXII",
-        33 => "XXXIII",
-        34 => "XXXIV",
-        35 => "XXXV",
-        36 => "XXXVI",
-        37 => "XXXVII",
-        38 => "XXXVIII",
-        39 => "XXXIX",
-        40 => "XL",
-        41 => "XLI",
-        42 => "XLII",
-        43 => "XLIII",
-        44 => "XLIV",
-        45 => "XLV",
-        46 => "XLVI",
-        47 => "XLVII",
-        48 => "XLVIII",
-        49 => "XLIX",
-        50 => "L",
-        51 => "LI",
-        52 => "LII",
-        53 => "LIII",
-        54 => "LIV",
-        55 => "LV",
-        56 => "LVI",
-        57 => "LVII",
-        58 => "LVIII",
-        59 => "LIX",
-        60 => "LX",
-        61 => "LXI",
-        62 => "LXII",
-        63 => "LXIII",
-        64 => "LXIV",
-        65 => "LXV",
-        66 => "LXVI",
-        67 => "LXVII",
-        68 => "LXVIII",
-        69 => "LXIX",
-        70 => "LXX",
-        71 => "LXXI",
-        72 => "LXXII",
-        73 => "LXXIII",
-        74 => "LXXIV",
-        75 => "LXXV",
-        76 => "LXXVI",
-        77 => "LXXVII",
-        78 => "LXXVIII",
-        79 => "LXXIX",
-        80 => "LXXX",
-        81 => "LXXXI",
-        82 => "LXXXII",
-        83 => "LXXXIII",
-        84 => "LXXXIV",
-        85 => "LXXXV",
-        86 => "LXXXVI",
-        87 => "LXXXVII",
-        88 => "LXXXVIII",
-        89 => "LXXXIX",
-        90 => "XC",
-        91 => "XCI",
-        92 => "XCII",
-        93 => "XCIII",
-        94 => "XCIV",
-        95 => "XCV",
-        96 => "XCVI",
-        97 => "XCVII",
-        98 => "XCVIII",
-        99 => "XCIX",
-        100 => "C",
-        101 => "CI",
-        102 => "CII",
-        103 => "CIII",
-        104 => "CIV",
-        105 => "CV",
-        106 => "CVI",
-        107 => "CVII",
-        108 => "CVIII",
-        109 => "CIX",
-        110 => "CX",
-        111 => "CXI",
-        112 => "CXII",
-        113 => "CXIII",
-        114 => "CXIV",
-        115 => "CXV",
-        116 => "CXVI",
-        117 => "CXVII",
-        118 => "CXVIII",
-        119 => "CXIX",
-        120 => "CXX",
-        121 => "CXXI",
-        122 => "CXXII",
-        123 => "CXXIII",
-        124 => "CXXIV",
-        125 => "CXXV",
-        126 => "CXXVI",
-        127 => "CXXVII",
-        128 => "CXXVIII",
-        129 => "CXXIX",
-        130 => "CXXX",
-        131 => "CXXXI",
-        132 => "CXXXII",
-        133 => "CXXXIII",
-        134 => "CXXXIV",
-        135 => "CXXXV",
-        136 => "CXXXVI",
-        137 => "CXXXVII",
-        138 => "CXXXVIII",
-        139 => "CXXXIX",
-        140 => "CXL",
-        141 => "CXLI",
-        142 => "CXLII",
-        143 => "CXLIII",
-        144 => "CXLIV",
-        145 => "CXLV",
-        146 => "CXLVI",
-        147 => "CXLVII",
-        148 => "CXLVIII",
-        149 => "CXLIX",
-        150 => "CL",
-        151 => "CLI",
-        152 => "CLII",
-        153 => "CLIII",
-        154 => "CLIV",
-        155 => "CLV",
-        156 => "CLVI",
-        157 => "CLVII",
-        158 => "CLVIII",
-        159 => "CLIX",
-        160 => "CLX",
-        161 => "CLXI",
-        162 => "CLXII",
-        163 => "CLXIII",
-        164 => "CLXIV",
-        165 => "CLXV",
-        166 => "CLXVI",
-        167 => "CLXVII",
-        168 => "CLXVIII",
-        169 => "CLXIX",
-        170 => "CLXX",
-        171 => "CLXXI",
-        172 => "CLXXII",
-        173 => "CLXXIII",
-        174 => "CLXXIV",
-        175 => "CLXXV",
-        176 => "CLXXVI",
-        177 => "CLXXVII",
-        178 => "CLXXVIII",
-        179 => "CLXXIX",
-        180 => "CLXXX",
-        181 => "CLXXXI",
-        182 => "CLXXXII",
-        183 => "CLXXXIII",
-        184 => "CLXXXIV",
-        185 => "CLXXXV",
-        186 => "CLXXXVI",
-        187 => "CLXXXVII",
-        188 => "CLXXXVIII",
-        189 => "CLXXXIX",
-        190 => "CXC",
-        191 => "CXCI",
-        192 => "CXCII",
-        193 => "CXCIII",
-        194 => "CXCIV",
-        195 => "CXCV",
-        196 => "CXCVI",
-        197 => "CXCVII",
-        198 => "CXCVIII",
-        199 => "CXCIX",
-        200 => "CC",
-        201 => "CCI",
-        202 => "CCII",
-        203 => "CCIII",
-        204 => "CCIV",
-        205 => "CCV",
-        206 => "CCVI",
-        207 => "CCVII",
-        208 => "CCVIII",
-        209 => "CCIX",
-        210 => "CCX",
-        211 => "CCXI",
-        212 => "CCXII",
-        213 => "CCXIII",
-        214 => "CCXIV",
-        215 => "CCXV",
-        216 => "CCXVI",
-        217 => "CCXVII",
-        218 => "CCXVIII",
-        219 => "CCXIX",
-        220 => "CCXX",
-        221 => "CCXXI",
-        222 => "CCXXII",
-        223 => "CCXXIII",
-        224 => "CCXXIV",
-        225 => "CCXXV",
-        226 => "CCXXVI",
-        227 => "CCXXVII",
-        228 => "CCXXVIII",
-        229 => "CCXXIX",
-        230 => "CCXXX",
-        231 => "CCXXXI",
-        232 => "CCXXXII",
-        233 => "CCXXXIII",
-        234 => "CCXXXIV",
-        235 => "CCXXXV",
-        236 => "CCXXXVI",
-        237 => "CCXXXVII",
-        238 => "CCXXXVIII",
-        239 => "CCXXXIX",
-        240 => "CCXL",
-        241 => "CCXLI",
-        242 => "CCXLII",
-        243 => "CCXLIII",
-        244 => "CCXLIV",
-        245 => "CCXLV",
-        246 => "CCXLVI",
-        247 => "CCXLVII",
-        248 => "CCXLVIII",
-        249 => "CCXLIX",
-        250 => "CCL",
-        251 => "CCLI",
-        252 => "CCLII",
-        253 => "CCLIII",
-        254 => "CCLIV",
-        255 => "CCLV",
+pub(super) fn number_to_letters(num: usize) -> String {
+    // bijective base 26: A to Z, then AA, AB and so on
+    let mut letters = Vec::new();
+    let mut remaining = num;
+    while remaining > 0 {
+        remaining -= 1;
+        letters.push((b'A' + (remaining % 26) as u8) as char);
+        remaining /= 26;
     }
+    letters.into_iter().rev().collect()
 }
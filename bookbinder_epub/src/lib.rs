@@ -10,16 +10,19 @@
 
 use bookbinder_ast::helpers::{BookEventIteratorHelper, EpubMarker};
 use bookbinder_ast::Metadata;
-use bookbinder_ast::{BookEvent, BookSrc, SemanticRole, TextHeaderOptions};
+use bookbinder_ast::{
+    BookEvent, BookSrc, BookTransform, CrossReferences, Index, IndexEntry, IndexHeading,
+    NumberFormat, OpeningStyle, RenderTarget, SceneBreak, SemanticRole, TextHeaderOptions,
+    TransformPipeline,
+};
 use bookbinder_common::MimeTypeHelper;
 use epub_bundler::{EpubBundlingError, EpubContent, EpubResource, EpubSource};
-use extended_pulldown::{CodeBlockKind, CowStr, Event, Tag};
+use extended_pulldown::{CodeBlockKind, CowStr, Event, MakeStatic, Tag};
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use temp_file_name::TempFilePath;
-use uuid::Uuid;
 mod svg_titlepage_generator;
 mod text_splitting;
 use bookbinder_ast::helpers::{CollatedEpigraph, CollatedHeader, CollatedTitlePage};
@@ -64,6 +67,13 @@ pub struct Options {
     pub header_options: TextHeaderOptions,
     /// A new label for chapters -- e.g. `Letter 1` instead of `Chapter 1`
     pub chapter_label: Option<Cow<'static, str>>,
+    /// How scene breaks are shown; by default, as a short rule
+    pub scene_break: Option<SceneBreak>,
+    /// How the opening paragraph of each chapter begins; by default, as any other paragraph
+    pub opening_style: OpeningStyle,
+    /// Transforms to apply before rendering,
+    /// after any registered on the `BookSrc` itself
+    pub transforms: TransformPipeline,
 }
 
 impl Options {
@@ -79,6 +89,25 @@ impl Options {
         self
     }
 
+    /// Set how scene breaks are shown
+    pub fn scene_break(&mut self, scene_break: SceneBreak) -> &mut Self {
+        self.scene_break = Some(scene_break);
+        self
+    }
+
+    /// Set how the opening paragraph of each chapter begins,
+    /// as with a drop cap or its first line in small caps
+    pub fn opening_style(&mut self, opening_style: OpeningStyle) -> &mut Self {
+        self.opening_style = opening_style;
+        self
+    }
+
+    /// Add a transform to apply to the book before rendering
+    pub fn add_transform<T: BookTransform + 'static>(&mut self, transform: T) -> &mut Self {
+        self.transforms.add(transform);
+        self
+    }
+
     /// Set a cover image
     pub fn cover_image<P: Into<PathBuf>>(&mut self, cover_image: P) -> &mut Self {
         self.cover_image = Some(cover_image.into());
@@ -147,11 +176,25 @@ impl Options {
         self
     }
 
+    /// Label chapters with numbers in `format`:
+    /// e.g. with `NumberFormat::Ordinal` a first chapter would be labelled
+    /// as `Chapter First`, or just `First` if `only_number_chapters` was called
+    pub fn set_chapter_number_format(&mut self, format: NumberFormat) -> &mut Self {
+        self.header_options.set_chapter_number_format(format);
+        self
+    }
+
+    /// Label parts with numbers in `format`, rather than uppercase roman numerals
+    pub fn set_part_number_format(&mut self, format: NumberFormat) -> &mut Self {
+        self.header_options.set_part_number_format(format);
+        self
+    }
+
     /// Modify a Vec of events in accordance with these options
     fn modify_events(&self, events: &mut Vec<BookEvent<'_>>) {
         // replace chapter labels if necessary
         if self.chapter_label.is_some() {
-            let default_chapter_label = bookbinder_ast::SemanticRole::Chapter.get_label();
+            let default_chapter_label = SemanticRole::Chapter.get_label();
             let labels = events.iter_mut().filter_map(|event| {
                 if let BookEvent::DivisionHeaderLabel { text, .. } = event {
                     match text {
@@ -215,6 +258,116 @@ enum TocFormat {
     Provided(&'static str),
 }
 
+/// the start of a link to an anchor, which is completed with the file holding the anchor
+/// once every page has been written
+static CROSS_REFERENCE_PREFIX: &str = "bookbinder-xref:";
+
+/// the place in the index page where its entries are listed once every page has been written
+static INDEX_PLACEHOLDER: &str = "<!--bookbinder-index-->";
+
+/// the id of the anchor for a label;
+/// since a label can contain a colon, which an id cannot, it is written as `_-`,
+/// and any underscore is doubled so that no two labels share an id
+fn anchor_id(label: &str) -> String {
+    format!("ref-{}", label.replace('_', "__").replace(':', "_-"))
+}
+
+/// the id of the anchor for the definition of a glossary term
+fn glossary_anchor_id(key: &str) -> String {
+    format!("gloss-{}", key)
+}
+
+/// complete links to anchors with the files in which the anchors are found
+fn resolve_cross_reference_links(xhtml: &str, files: &HashMap<String, String>) -> String {
+    let mut resolved = String::with_capacity(xhtml.len());
+    let mut rest = xhtml;
+    while let Some(i) = rest.find(CROSS_REFERENCE_PREFIX) {
+        resolved.push_str(&rest[..i]);
+        rest = &rest[i + CROSS_REFERENCE_PREFIX.len()..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        let anchor = &rest[..end];
+        if let Some(file) = files.get(anchor) {
+            resolved.push_str(file);
+        }
+        resolved.push('#');
+        resolved.push_str(anchor);
+        rest = &rest[end..];
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+/// a place where a term is marked for the index
+#[derive(Debug)]
+struct IndexLocator {
+    href: String,
+    /// the title of the page holding the term
+    text: Option<String>,
+}
+
+fn write_index_headings(xhtml: &mut String, headings: &[IndexHeading<IndexLocator>]) {
+    xhtml.push_str("\n<ul epub:type=\"index-entry-list\">");
+    for heading in headings.iter() {
+        xhtml.push_str("\n<li epub:type=\"index-entry\"><span epub:type=\"index-term\">");
+        xhtml.push_str(&bookbinder_common::escape_to_html(&heading.term));
+        xhtml.push_str("</span>");
+        // how many places on each page have been listed so far
+        let mut places_on_page: HashMap<&str, usize> = HashMap::new();
+        for (i, locator) in heading.locations.iter().enumerate() {
+            // a page without a title is instead given the number of the place in the list,
+            // and a page holding the term more than once is numbered after its first place
+            let text = match locator.text {
+                Some(ref text) => {
+                    let place = places_on_page.entry(text).or_insert(0);
+                    *place += 1;
+                    let escaped = bookbinder_common::escape_to_html(text);
+                    match *place {
+                        1 => escaped.to_string(),
+                        n => format!("{} ({})", escaped, n),
+                    }
+                }
+                None => (i + 1).to_string(),
+            };
+            xhtml.push_str(&format!(
+                ", <a epub:type=\"index-locator\" href=\"{}\">{}</a>",
+                locator.href, text
+            ));
+        }
+        for (phrase, epub_type, terms) in [
+            ("see", "index-xref-preferred", &heading.see),
+            ("see also", "index-xref-related", &heading.see_also),
+        ]
+        .iter()
+        {
+            if !terms.is_empty() {
+                let terms = terms
+                    .iter()
+                    .map(|term| {
+                        format!(
+                            "<span epub:type=\"{}\">{}</span>",
+                            epub_type,
+                            bookbinder_common::escape_to_html(term)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                xhtml.push_str(&format!(". <em>{}</em> {}", phrase, terms.join("; ")));
+            }
+        }
+        if !heading.subheadings.is_empty() {
+            write_index_headings(xhtml, &heading.subheadings);
+        }
+        xhtml.push_str("</li>");
+    }
+    xhtml.push_str("\n</ul>\n");
+}
+
+/// list the entries of an index, linking to each place their terms are marked
+fn index_to_xhtml(headings: &[IndexHeading<IndexLocator>]) -> String {
+    let mut xhtml = String::new();
+    write_index_headings(&mut xhtml, headings);
+    xhtml
+}
+
 /// escape a CowStr<'_> for use in html
 fn escape_cowstr_for_html(cowstr: CowStr<'_>) -> Cow<'_, str> {
     match cowstr {
@@ -231,7 +384,7 @@ where
 {
     title_events: Vec<TitleEvent<'a>>,
     subtitle_events: Option<Vec<TitleEvent<'a>>>,
-    contributors: Option<Vec<(Option<&'a str>, Vec<S>)>>,
+    contributors: Option<Vec<(Option<S>, Vec<S>)>>,
     logo: Option<&'a Path>,
     typeface: Option<&'a str>,
 }
@@ -289,22 +442,52 @@ impl EpubRenderer for BookSrc<'_> {
     fn render_to_epub(mut self, mut options: Options) -> Result<Vec<u8>, RenderingError> {
         let pages = self.get_pages(&mut options)?;
 
-        let mut resources = HashSet::new();
+        // kept in the order in which they are first seen, so that the epub is always bundled the same way
+        let mut resources = Vec::new();
         let mut contents = Vec::with_capacity(pages.len());
+        // the file in which each anchor is found
+        let mut anchor_files = HashMap::new();
+        let mut index = Index::default();
 
         for page in pages.into_iter() {
             for resource in page.associated_resources.into_iter() {
-                resources.insert(resource);
+                if !resources.contains(&resource) {
+                    resources.push(resource);
+                }
             }
             let mut content = EpubContent::new(page.xhtml);
-            if let Some(toc_title) = page.toc_title {
+            if let Some(ref toc_title) = page.toc_title {
                 content
-                    .set_toc_title(toc_title, page.toc_level.unwrap_or(1))
+                    .set_toc_title(toc_title.clone(), page.toc_level.unwrap_or(1))
                     .unwrap();
             }
+            let filename = content.output_path.to_string_lossy().to_string();
+            for anchor in page.anchors.into_iter() {
+                anchor_files.insert(anchor, filename.clone());
+            }
+            for (anchor, entry) in page.index_entries.into_iter() {
+                let locator = IndexLocator {
+                    href: format!("{}#{}", filename, anchor),
+                    text: page.title.clone(),
+                };
+                index.add(&entry, locator);
+            }
             contents.push(content);
         }
 
+        // a page's filename is derived from its contents,
+        // so links between pages can only be completed once every page exists
+        for content in contents.iter_mut() {
+            if content.data.contains(CROSS_REFERENCE_PREFIX) {
+                content.data = resolve_cross_reference_links(&content.data, &anchor_files);
+            }
+            if content.data.contains(INDEX_PLACEHOLDER) {
+                content.data = content
+                    .data
+                    .replace(INDEX_PLACEHOLDER, &index_to_xhtml(index.headings()));
+            }
+        }
+
         let resources = resources
             .into_iter()
             .map(EpubResource::from_file)
@@ -400,6 +583,8 @@ pub enum RenderingError {
     ResourceError(String),
     /// There was an error bundling the epub
     BundlingError(EpubBundlingError),
+    /// There were references to labels which do not exist
+    DanglingReferences(Vec<String>),
 }
 
 impl From<&'static str> for RenderingError {
@@ -430,6 +615,7 @@ impl Error for RenderingError {}
 fn preprocess<'a>(src: Vec<BookEvent<'a>>) -> Result<(bool, Vec<BookEvent<'a>>), RenderingError> {
     let mut events = Vec::with_capacity(src.len());
     let mut footnotes = Vec::new();
+    let mut footnote_count = 0;
     let mut current_footnote = Vec::new();
     let mut in_footnote = false;
     let mut has_parts = false;
@@ -451,8 +637,8 @@ fn preprocess<'a>(src: Vec<BookEvent<'a>>) -> Result<(bool, Vec<BookEvent<'a>>),
                 in_footnote = true;
             }
             BookEvent::Event(Event::End(Tag::FlattenedFootnote)) => {
-                let marker = Uuid::new_v4().to_string();
-                let marker: CowStr = marker.into();
+                footnote_count += 1;
+                let marker: CowStr = format!("footnote_{}", footnote_count).into();
 
                 footnotes.push(Event::Start(Tag::FootnoteDefinition(marker.clone())).into());
                 footnotes.append(&mut current_footnote);
@@ -501,6 +687,13 @@ struct EpubPage {
     associated_resources: Vec<PathBuf>,
     toc_level: Option<usize>,
     toc_title: Option<String>,
+    /// the page's own title, or its label if it has none,
+    /// without the authors a table of contents may add; the index names the page by this
+    title: Option<String>,
+    /// the anchors which can be linked to on this page
+    anchors: Vec<String>,
+    /// the terms marked for the index on this page, with the anchors marking them
+    index_entries: Vec<(String, IndexEntry<'static>)>,
 }
 
 impl EpubPage {
@@ -509,7 +702,10 @@ impl EpubPage {
 			xhtml: format!("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n<head><title>{}</title></head><body></body></html>", title),
 			associated_resources: Vec::new(),
 			toc_title: Some(title.into()),
-			toc_level: Some(0)
+			title: Some(title.into()),
+			toc_level: Some(0),
+			anchors: Vec::new(),
+			index_entries: Vec::new()
 		}
     }
 }
@@ -536,6 +732,17 @@ impl<'a> MetadataAdder for Metadata<'a> {
             epub_src.set_isbn(epub_isbn).unwrap();
         }
 
+        // a tag which is not valid leaves the epub declared as english
+        if let Some(ref language) = self.language {
+            let _ = epub_src.set_language(language);
+        }
+
+        if let Some(epoch) = self.get_publication_epoch() {
+            epub_src
+                .set_modification_date(bookbinder_common::epoch_to_tm(epoch))
+                .unwrap();
+        }
+
         add_contributors!(authors, add_author);
         add_contributors!(editors, add_editor);
         add_contributors!(translators, add_translator);
@@ -546,6 +753,7 @@ impl<'a> MetadataAdder for Metadata<'a> {
             introduction_and_notes_authors,
             add_author_of_introduction_and_notes
         );
+        add_contributors!(chapter_authors, add_contributor);
     }
 }
 
@@ -560,6 +768,8 @@ struct XhtmlWriter {
     observed_label: Option<String>,
     /// any title seen for this page
     observed_title: Option<String>,
+    /// the authors of this page, if it is a chapter with authors of its own
+    observed_authors: Option<String>,
     associated_resources: Vec<PathBuf>,
     /// whether we have started writing footnote definitions;
     /// a flag to check whether a 'Notes' header needs to be written
@@ -569,9 +779,18 @@ struct XhtmlWriter {
     /// which means a following paragraph should not
     /// be indented
     do_not_indent_next_para: bool,
+    scene_break: SceneBreak,
     css_path: Option<PathBuf>,
     in_heading: bool,
     in_para: bool,
+    cross_references: Rc<CrossReferences>,
+    /// the anchors of labels seen on this page
+    anchors: Vec<String>,
+    /// the terms marked for the index on this page, with the anchors marking them
+    index_entries: Vec<(String, IndexEntry<'static>)>,
+    /// how often the lines of the verse being written are numbered, if at all,
+    /// and how many lines of it have been written
+    verse_lines: (Option<u32>, u32),
 }
 
 impl XhtmlWriter {
@@ -584,15 +803,80 @@ impl XhtmlWriter {
             inline_image_alt_buffer: String::new(),
             observed_label: None,
             observed_title: None,
+            observed_authors: None,
             associated_resources: Vec::new(),
             in_footnote_definitions: false,
             do_not_indent_next_para: false,
+            scene_break: SceneBreak::Rule,
             css_path: None,
             in_heading: false,
             in_para: false,
+            cross_references: Rc::default(),
+            anchors: Vec::new(),
+            index_entries: Vec::new(),
+            verse_lines: (None, 0),
         }
     }
 
+    fn write_scene_break(&mut self) {
+        let glyph = match self.scene_break {
+            SceneBreak::Rule => {
+                self.target.push_str("<hr/>\n");
+                return;
+            }
+            SceneBreak::BlankLine => {
+                self.target.push_str("<hr class=\"blank\"/>\n");
+                return;
+            }
+            SceneBreak::Asterism => Cow::Borrowed("\u{2042}"),
+            SceneBreak::Dinkus => Cow::Borrowed("* * *"),
+            SceneBreak::Glyph(ref glyph) => escape_cowstr_for_html(glyph.as_str().into())
+                .into_owned()
+                .into(),
+            SceneBreak::Ornament(ref p) => {
+                let filename = p.file_name().unwrap_or_default().to_string_lossy();
+                let img = format!("<img alt=\"\" src=\"{}\"/>", filename);
+                self.associated_resources.push(p.clone());
+                img.into()
+            }
+        };
+        self.target
+            .push_str("<p class=\"scene-break\" role=\"separator\">");
+        self.target.push_str(&glyph);
+        self.target.push_str("</p>\n");
+    }
+
+    fn push_label(&mut self, label: CowStr<'_>) {
+        let anchor = anchor_id(&label);
+        self.target
+            .push_str(&format!("<span id=\"{}\"></span>", anchor));
+        self.anchors.push(anchor);
+    }
+
+    /// link to the place `label` names, showing the text it resolves to;
+    /// a label which resolves to no text, as in running text before any heading,
+    /// is shown by its name so that the link can still be seen
+    fn push_reference(&mut self, label: CowStr<'_>) {
+        let text = match self.cross_references.resolve(&label) {
+            Some(text) if !text.is_empty() => text,
+            _ => &label,
+        };
+        let link = format!(
+            "<a href=\"{}{}\">{}</a>",
+            CROSS_REFERENCE_PREFIX,
+            anchor_id(&label),
+            bookbinder_common::escape_to_html(text)
+        );
+        self.target.push_str(&link);
+    }
+
+    fn push_index_entry(&mut self, entry: IndexEntry<'_>) {
+        let anchor = format!("index-{}", self.index_entries.len() + 1);
+        self.target
+            .push_str(&format!("<span id=\"{}\"></span>", anchor));
+        self.index_entries.push((anchor, entry.into_static()));
+    }
+
     fn write_epigraph(&mut self, epigraph: CollatedEpigraph<'_>) {
         let text = epigraph.text;
         let source = epigraph.source;
@@ -610,11 +894,38 @@ impl XhtmlWriter {
         }
     }
 
-    fn write_division_header(&mut self, header: CollatedHeader<'_, EpubMarker>) {
+    fn write_division_epigraph(&mut self, epigraph: CollatedEpigraph<'_>) {
+        self.target.push_str("\n<div class=\"division_epigraph\">");
+        self.write_epigraph(epigraph);
+        self.target.push_str("\n</div>\n");
+    }
+
+    fn write_division_header(&mut self, mut header: CollatedHeader<'_, EpubMarker>) {
+        for label in std::mem::take(&mut header.labels).into_iter() {
+            self.push_label(label);
+        }
+        for entry in std::mem::take(&mut header.index_entries).into_iter() {
+            self.push_index_entry(entry);
+        }
         let label_and_title = header.reconcile_joined_label_and_title();
 
         if let Some((label, title)) = label_and_title {
             let authors = header.get_authors();
+            self.observed_label = label.as_ref().map(|l| l.to_string());
+            // a short title, where there is one, is what appears in the table of contents
+            self.observed_title = header
+                .get_short_title()
+                .or_else(|| title.clone())
+                .map(|t| t.to_string());
+            // the chapters of a collection are listed with their authors
+            if get_matter(self.current_division) == "mainmatter"
+                && !matches!(
+                    self.current_division,
+                    SemanticRole::Part | SemanticRole::Volume
+                )
+            {
+                self.observed_authors = header.get_joined_authors().map(|a| a.to_string());
+            }
 
             if let Some(label) = label {
                 self.target.push_str(&format!(
@@ -738,6 +1049,10 @@ impl XhtmlWriter {
         xhtml.push_str(&self.get_body());
         xhtml.push_str("\n</html>");
 
+        let title = self
+            .observed_title
+            .clone()
+            .or_else(|| self.observed_label.clone());
         let toc_title = match get_default_toc_format(self.current_division) {
             TocFormat::NoTocEntry => None,
             TocFormat::Provided(s) => Some(s.into()),
@@ -757,12 +1072,19 @@ impl XhtmlWriter {
                 }
             }
         };
+        let toc_title = match (toc_title, self.observed_authors) {
+            (Some(title), Some(authors)) => Some(format!("{} — {}", title, authors)),
+            (toc_title, _) => toc_title,
+        };
 
         EpubPage {
             xhtml,
             associated_resources: self.associated_resources,
             toc_title,
+            title,
             toc_level: get_default_toc_level(self.current_division),
+            anchors: self.anchors,
+            index_entries: self.index_entries,
         }
     }
 
@@ -971,13 +1293,138 @@ impl XhtmlWriter {
             FlattenedFootnote => unreachable!(),
             Superscript => self.target.push_str("<sup>"),
             Subscript => self.target.push_str("<sub>"),
+            GlossaryReference(key) => {
+                self.target.push_str(&format!(
+                    "<a epub:type=\"glossref\" href=\"{}{}\">",
+                    CROSS_REFERENCE_PREFIX,
+                    glossary_anchor_id(&key)
+                ));
+            }
+            Glossary => {
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target.push_str("<dl class=\"glossary\">\n");
+            }
+            GlossaryTerm(key) => {
+                let anchor = glossary_anchor_id(&key);
+                self.target
+                    .push_str(&format!("<dt epub:type=\"glossterm\" id=\"{}\">", anchor));
+                self.anchors.push(anchor);
+            }
+            GlossaryDefinition => self.target.push_str("<dd epub:type=\"glossdef\">"),
+            // content for other formats is removed before rendering
+            FormatOnly(_) => {}
+            Verse(numbering) => {
+                self.verse_lines = (numbering, 0);
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target
+                    .push_str("<div class=\"verse\" epub:type=\"z3998:poem\">\n");
+            }
+            Stanza => self
+                .target
+                .push_str("<div class=\"stanza\" epub:type=\"z3998:verse\">\n"),
+            VerseLine(indent) => {
+                self.verse_lines.1 += 1;
+                self.target.push_str("<p class=\"verse-line\">");
+                if let (Some(n), line) = self.verse_lines {
+                    if line % n == 0 {
+                        self.target.push_str(&format!(
+                            "<span class=\"verse-line-number\">{}</span>",
+                            line
+                        ));
+                    }
+                }
+                // only the beginning of the line is indented,
+                // so that the rest of it still hangs beneath if it is too long to fit
+                if indent > 0 {
+                    self.target.push_str(&format!(
+                        "<span class=\"verse-indent\" style=\"margin-left: {}em\">",
+                        indent as f32 / 2.0
+                    ));
+                }
+            }
+            // the speaker begins the paragraph of the speech itself,
+            // so that the two can never be separated by a page break
+            Speech(speaker) => {
+                self.in_para = true;
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target
+                    .push_str("<p class=\"speech\"><span class=\"speaker\">");
+                self.target.push_str(&escape_cowstr_for_html(speaker));
+                self.target.push_str(".</span> ");
+            }
+            StageDirection => {
+                self.in_para = true;
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target.push_str("<p class=\"stage-direction\">");
+            }
+            Parenthetical => self.target.push_str("<span class=\"parenthetical\">"),
+            SceneHeading => {
+                self.in_para = true;
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target.push_str("<p class=\"scene-heading\">");
+            }
+            Letter => {
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target.push_str("<div class=\"letter\">\n");
+            }
+            OpeningParagraph(drop_cap, first_line) => {
+                self.in_para = true;
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                self.target.push_str("<p class=\"opening");
+                // the default css has drop caps falling through two to four lines
+                if let Some(lines) = drop_cap {
+                    self.target
+                        .push_str(&format!(" drop-cap-{}", lines.clamp(2, 4)));
+                }
+                if first_line {
+                    self.target.push_str(" first-line-small-caps");
+                }
+                self.target.push_str("\">");
+            }
+            DropCap => self.target.push_str("<span class=\"drop-cap\">"),
+            Dateline | Salutation | Signature | Postscript => {
+                self.in_para = true;
+                if !self.target.ends_with('\n') {
+                    self.target.push('\n');
+                }
+                let class = match tag {
+                    Dateline => "dateline",
+                    Salutation => "salutation",
+                    Signature => "signature",
+                    _ => "postscript",
+                };
+                self.target.push_str(&format!("<p class=\"{}\">", class));
+            }
         }
     }
 
     fn push_end_tag(&mut self, tag: Tag) {
         use Tag::*;
         match tag {
-            UnindentedParagraph | Paragraph => {
+            UnindentedParagraph
+            | Paragraph
+            | Speech(_)
+            | StageDirection
+            | SceneHeading
+            | Dateline
+            | Salutation
+            | Signature
+            | Postscript
+            | OpeningParagraph(_, _) => {
                 self.in_para = false;
                 if !self.target.ends_with("</figure>") {
                     self.target.push_str("</p>\n");
@@ -1016,7 +1463,9 @@ impl XhtmlWriter {
                     self.target.push_str("</figure>");
                 }
             }
-            Sans | SmallCaps | RightAligned => self.target.push_str("</span>"),
+            Sans | SmallCaps | RightAligned | Parenthetical | DropCap => {
+                self.target.push_str("</span>")
+            }
             Centred => {
                 if !self.in_para {
                     self.target.push_str("</span>");
@@ -1026,6 +1475,23 @@ impl XhtmlWriter {
             Subscript => self.target.push_str("</sub>"),
             Table(_) | TableHead | TableRow | TableCell => {}
             FlattenedFootnote => unreachable!(),
+            GlossaryReference(_) => self.target.push_str("</a>"),
+            Glossary => self.target.push_str("</dl>\n"),
+            GlossaryTerm(_) => self.target.push_str("</dt>\n"),
+            GlossaryDefinition => self.target.push_str("</dd>\n"),
+            FormatOnly(_) => {}
+            Verse(_) => {
+                self.verse_lines = (None, 0);
+                self.target.push_str("</div>\n");
+            }
+            Stanza => self.target.push_str("</div>\n"),
+            VerseLine(indent) => {
+                if indent > 0 {
+                    self.target.push_str("</span>");
+                }
+                self.target.push_str("</p>\n");
+            }
+            Letter => self.target.push_str("</div>\n"),
         }
     }
 
@@ -1056,7 +1522,7 @@ impl XhtmlWriter {
                     if !self.target.ends_with('\n') {
                         self.target.push('\n');
                     }
-                    self.target.push_str("<hr/>\n");
+                    self.write_scene_break();
                     self.do_not_indent_next_para = true;
                 }
                 Event::FootnoteReference(name) => {
@@ -1069,6 +1535,13 @@ impl XhtmlWriter {
                     self.target.push_str(&format!("<sup>{}</sup></a>", number));
                 }
                 Event::TaskListMarker(_) => {}
+                Event::Label(label) => self.push_label(label),
+                Event::Reference(label) => self.push_reference(label),
+                // an epub has no fixed pages, so a reference to one links to its place instead
+                Event::PageReference(label) => self.push_reference(label),
+                Event::IndexEntry(entry) => self.push_index_entry(entry),
+                // citations are resolved to text when a book is processed
+                Event::Citation(_) => {}
             }
         }
     }
@@ -1082,15 +1555,31 @@ impl EpubPageGetter for BookSrc<'_> {
     /// transform the events stream into an intermediate format
     /// representing an epub page
     fn get_pages(&mut self, options: &mut Options) -> Result<Vec<EpubPage>, RenderingError> {
-        self.change_headers(options.header_options);
+        self.select_format(RenderTarget::Epub);
+        self.apply_transforms(RenderTarget::Epub, &options.transforms);
+        options
+            .opening_style
+            .transform(&mut self.contents, &mut self.metadata);
+        self.change_headers(options.header_options.clone());
         options.modify_events(&mut self.contents);
 
+        let cross_references = self.cross_references();
+        if !cross_references.dangling().is_empty() {
+            return Err(RenderingError::DanglingReferences(
+                cross_references.dangling().to_vec(),
+            ));
+        }
+        let cross_references = Rc::new(cross_references);
+
         let (has_parts, events) = preprocess(std::mem::take(&mut self.contents))?;
         let mut events = events.into_iter();
         let mut current_page: Option<XhtmlWriter> = None;
         let mut pages = Vec::new();
+        // the index of the first page within the current volume, if any
+        let mut volume_start: Option<usize> = None;
 
         let css = options.get_css()?;
+        let scene_break = options.scene_break.clone().unwrap_or(SceneBreak::Rule);
 
         #[allow(clippy::while_let_on_iterator)]
         while let Some(event) = events.next() {
@@ -1101,6 +1590,8 @@ impl EpubPageGetter for BookSrc<'_> {
                     } else {
                         let mut cp = XhtmlWriter::new(SemanticRole::Chapter);
                         cp.css_path = Some(css.clone());
+                        cp.cross_references = cross_references.clone();
+                        cp.scene_break = scene_break.clone();
                         cp.push(e);
                         current_page = Some(cp);
                     }
@@ -1108,8 +1599,15 @@ impl EpubPageGetter for BookSrc<'_> {
                 BookEvent::Null => {}
                 BookEvent::BeginTitlePage => {
                     let titlepage = events.collate_titlepage();
+                    // a volume's titlepage is always generated, since any titlepage set in options
+                    // belongs to the book as a whole
+                    let provided_titlepage = if volume_start.is_none() {
+                        options.get_titlepage()
+                    } else {
+                        None
+                    };
                     let (titlepage_filename, titlepage_filepath) =
-                        if let Some(p) = options.get_titlepage() {
+                        if let Some(p) = provided_titlepage {
                             (
                                 p.file_name()
                                     .map(|s| s.to_string_lossy().to_string())
@@ -1135,7 +1633,9 @@ impl EpubPageGetter for BookSrc<'_> {
                     let image = format!("<img alt=\"The titlepage\" src=\"{}\" style=\"display: block; width: 100%; margin: auto; page-break-after: always;\"/>", titlepage_filename);
                     writer.push(Event::Html(image.into()));
                     writer.associated_resources.push(titlepage_filepath.clone());
-                    options.titlepage = Some(titlepage_filepath);
+                    if volume_start.is_none() {
+                        options.titlepage = Some(titlepage_filepath);
+                    }
                     pages.push(writer.finish());
                 }
                 BookEvent::BeginDivisionHeader(is_starred) => {
@@ -1145,10 +1645,32 @@ impl EpubPageGetter for BookSrc<'_> {
                     } else {
                         let mut cp = XhtmlWriter::new(SemanticRole::Chapter);
                         cp.css_path = Some(css.clone());
+                        cp.cross_references = cross_references.clone();
+                        cp.scene_break = scene_break.clone();
                         current_page = Some(cp);
                         current_page.as_mut().unwrap()
                     };
                     cp.write_division_header(div_header);
+                    // a volume's heading is a page of its own, and everything up to the end of the volume
+                    // is nested beneath it
+                    if cp.current_division == SemanticRole::Volume {
+                        if let Some(cp) = current_page.take() {
+                            pages.push(cp.finish());
+                        }
+                        volume_start = Some(pages.len());
+                    }
+                }
+                // the epigraph of a chapter or part belongs on the same page as its heading
+                BookEvent::BeginSemantic(SemanticRole::Epigraph)
+                    if matches!(
+                        current_page.as_ref().map(|cp| cp.current_division),
+                        Some(SemanticRole::Chapter) | Some(SemanticRole::Part)
+                    ) =>
+                {
+                    let epigraph = events.collate_epigraph();
+                    if let Some(cp) = current_page.as_mut() {
+                        cp.write_division_epigraph(epigraph);
+                    }
                 }
                 BookEvent::BeginSemantic(SemanticRole::Epigraph) => {
                     if let Some(cp) = current_page.take() {
@@ -1156,16 +1678,36 @@ impl EpubPageGetter for BookSrc<'_> {
                     }
                     let mut writer = XhtmlWriter::new(SemanticRole::Epigraph);
                     writer.css_path = Some(css.clone());
+                    writer.cross_references = cross_references.clone();
+                    writer.scene_break = scene_break.clone();
 
                     let epigraph = events.collate_epigraph();
                     writer.write_epigraph(epigraph);
                     pages.push(writer.finish());
                 }
+                BookEvent::EndSemantic(SemanticRole::Volume) => {
+                    if let Some(cp) = current_page.take() {
+                        pages.push(cp.finish());
+                    }
+                    if let Some(start) = volume_start.take() {
+                        for page in pages[start..].iter_mut() {
+                            page.toc_level = Some(page.toc_level.unwrap_or(1) + 1);
+                        }
+                    }
+                }
                 BookEvent::BeginSemantic(role) => {
                     let mut writer = XhtmlWriter::new(role);
                     writer.css_path = Some(css.clone());
+                    writer.cross_references = cross_references.clone();
+                    writer.scene_break = scene_break.clone();
                     current_page = Some(writer);
                 }
+                BookEvent::EndSemantic(SemanticRole::Index) => {
+                    if let Some(mut cp) = current_page.take() {
+                        cp.target.push_str(INDEX_PLACEHOLDER);
+                        pages.push(cp.finish());
+                    }
+                }
                 BookEvent::EndSemantic(_) => {
                     if let Some(cp) = current_page {
                         let page = cp.finish();
@@ -1219,4 +1761,211 @@ mod tests {
         }
         assert_eq!("\n<p>Text<a href=\"#fn\" id=\"fn_ref_fn\" epub:type=\"noteref\"><sup>1</sup></a></p>\n\n<h6 class=\"notes_heading\">Notes</h6>\n\n<p id=\"fn\" epub:type=\"footnote\" class=\"footnote\"><a href=\"#fn_ref_fn\">1.</a> \n<p>Footnote text</p>\n</p>", writer.target);
     }
+
+    #[test]
+    fn test_repeated_index_locators_are_numbered() {
+        let mut index = Index::default();
+        let entry = IndexEntry {
+            terms: vec!["roses".into()],
+            cross_reference: None,
+        };
+        for (href, text) in [
+            ("one.xhtml#index-1", Some("Chapter One")),
+            ("one.xhtml#index-2", Some("Chapter One")),
+            ("two.xhtml#index-1", Some("Chapter Two")),
+            ("three.xhtml#index-1", None),
+        ] {
+            let locator = IndexLocator {
+                href: href.to_string(),
+                text: text.map(String::from),
+            };
+            index.add(&entry, locator);
+        }
+        let xhtml = index_to_xhtml(index.headings());
+        assert!(xhtml.contains(concat!(
+            "<a epub:type=\"index-locator\" href=\"one.xhtml#index-1\">Chapter One</a>, ",
+            "<a epub:type=\"index-locator\" href=\"one.xhtml#index-2\">Chapter One (2)</a>, ",
+            "<a epub:type=\"index-locator\" href=\"two.xhtml#index-1\">Chapter Two</a>, ",
+            "<a epub:type=\"index-locator\" href=\"three.xhtml#index-1\">4</a>"
+        )));
+    }
+
+    #[test]
+    fn test_pages_are_indexed_by_their_own_titles() {
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        writer.observed_title = Some("On Brevity".to_string());
+        writer.observed_authors = Some("A.N. Author".to_string());
+        let page = writer.finish();
+        assert_eq!(page.toc_title.as_deref(), Some("On Brevity — A.N. Author"));
+        assert_eq!(page.title.as_deref(), Some("On Brevity"));
+    }
+
+    #[test]
+    fn test_cross_reference_links() {
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        let mut cross_references = BookSrc::default();
+        cross_references.contents.extend(vec![
+            Event::Label("top".into()).into(),
+            Event::Start(Tag::Heading(2)).into(),
+            Event::Text("A Map".into()).into(),
+            Event::Label("fig:map".into()).into(),
+            Event::End(Tag::Heading(2)).into(),
+            Event::Start(Tag::Heading(2)).into(),
+            Event::Text("Another Map".into()).into(),
+            Event::Label("fig_map".into()).into(),
+            Event::End(Tag::Heading(2)).into(),
+        ]);
+        writer.cross_references = Rc::new(cross_references.cross_references());
+        writer.push(Event::Text("See ".into()));
+        writer.push(Event::Reference("fig:map".into()));
+        writer.push(Event::Text(" on page ".into()));
+        writer.push(Event::PageReference("fig:map".into()));
+        writer.push(Event::Text(" or ".into()));
+        writer.push(Event::Reference("top".into()));
+        writer.push(Event::Text(", not ".into()));
+        writer.push(Event::Reference("fig_map".into()));
+        writer.push(Event::Label("fig:map".into()));
+        writer.push(Event::Label("fig_map".into()));
+        assert_eq!(
+            writer.anchors,
+            vec!["ref-fig_-map".to_string(), "ref-fig__map".to_string()]
+        );
+
+        let mut files = HashMap::new();
+        files.insert("ref-fig_-map".to_string(), "map.xhtml".to_string());
+        files.insert("ref-fig__map".to_string(), "another.xhtml".to_string());
+        files.insert("ref-top".to_string(), "top.xhtml".to_string());
+        let resolved = resolve_cross_reference_links(&writer.target, &files);
+        assert_eq!(
+            resolved,
+            concat!(
+                "See <a href=\"map.xhtml#ref-fig_-map\">A Map</a>",
+                " on page <a href=\"map.xhtml#ref-fig_-map\">A Map</a>",
+                " or <a href=\"top.xhtml#ref-top\">top</a>",
+                ", not <a href=\"another.xhtml#ref-fig__map\">Another Map</a>",
+                "<span id=\"ref-fig_-map\"></span>",
+                "<span id=\"ref-fig__map\"></span>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_scene_breaks() {
+        let events = vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text("Before".into()),
+            Event::End(Tag::Paragraph),
+            Event::Rule,
+            Event::Start(Tag::Paragraph),
+            Event::Text("After".into()),
+            Event::End(Tag::Paragraph),
+        ];
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        writer.scene_break = SceneBreak::Glyph("<❦>".into());
+        for event in events.into_iter() {
+            writer.push(event);
+        }
+        assert_eq!("\n<p>Before</p>\n<p class=\"scene-break\" role=\"separator\">&lt;❦&gt;</p>\n<p class=\"noindent\">After</p>\n", writer.target);
+    }
+
+    #[test]
+    fn test_ornament_scene_breaks() {
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        writer.scene_break = SceneBreak::Ornament("ornaments/fleuron.png".into());
+        writer.push(Event::Rule);
+        assert_eq!(
+            "\n<p class=\"scene-break\" role=\"separator\"><img alt=\"\" src=\"fleuron.png\"/></p>\n",
+            writer.target
+        );
+        assert_eq!(
+            writer.associated_resources,
+            vec![PathBuf::from("ornaments/fleuron.png")]
+        );
+    }
+
+    #[test]
+    fn test_verse() {
+        let text = "```verse numbered=2\nOne\n  Two\n\nThree\n```";
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        for event in extended_pulldown::Parser::new(text) {
+            writer.push(event);
+        }
+        assert_eq!(
+            writer.target,
+            concat!(
+                "\n<div class=\"verse\" epub:type=\"z3998:poem\">\n",
+                "<div class=\"stanza\" epub:type=\"z3998:verse\">\n",
+                "<p class=\"verse-line\">One</p>\n",
+                "<p class=\"verse-line\"><span class=\"verse-line-number\">2</span>",
+                "<span class=\"verse-indent\" style=\"margin-left: 1em\">Two</span></p>\n",
+                "</div>\n",
+                "<div class=\"stanza\" epub:type=\"z3998:verse\">\n",
+                "<p class=\"verse-line\">Three</p>\n",
+                "</div>\n",
+                "</div>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_drama() {
+        let text = "<div class=\"drama\" data-personae=\"HAMLET\">\n\nINT. HALL -- NIGHT\n\nHAMLET. Who's there? (Drawing.)\n\n[Enter Ghost.]\n\n</div>\n";
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        for event in extended_pulldown::Parser::new(text) {
+            writer.push(event);
+        }
+        assert_eq!(
+            writer.target,
+            concat!(
+                "\n<p class=\"scene-heading\">INT. HALL – NIGHT</p>\n",
+                "<p class=\"speech\"><span class=\"speaker\">HAMLET.</span> ",
+                "Who’s there? <span class=\"parenthetical\">(Drawing.)</span></p>\n",
+                "<p class=\"stage-direction\">[Enter Ghost.]</p>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_letter() {
+        let text = "<div class=\"letter\">\n\nDear Lucy,\n\nAll is well.\n\nYours,\n\nMina\n\nP.S. Write soon.\n\n</div>\n";
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        for event in extended_pulldown::Parser::new(text) {
+            writer.push(event);
+        }
+        assert_eq!(
+            writer.target,
+            concat!(
+                "\n<div class=\"letter\">\n",
+                "<p class=\"salutation\">Dear Lucy,</p>\n",
+                "<p>All is well.</p>\n",
+                "<p class=\"signature\">Yours,</p>\n",
+                "<p class=\"signature\">Mina</p>\n",
+                "<p class=\"postscript\">P.S. Write soon.</p>\n",
+                "</div>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_opening() {
+        use extended_pulldown::{Event, Tag};
+        let mut writer = XhtmlWriter::new(SemanticRole::Chapter);
+        for event in [
+            Event::Start(Tag::OpeningParagraph(Some(6), true)),
+            Event::Start(Tag::DropCap),
+            Event::Text("“I".into()),
+            Event::End(Tag::DropCap),
+            Event::Text("t was.”".into()),
+            Event::End(Tag::OpeningParagraph(Some(6), true)),
+        ] {
+            writer.push(event);
+        }
+        assert_eq!(
+            writer.target,
+            concat!(
+                "\n<p class=\"opening drop-cap-4 first-line-small-caps\">",
+                "<span class=\"drop-cap\">“I</span>t was.”</p>\n"
+            )
+        );
+    }
 }
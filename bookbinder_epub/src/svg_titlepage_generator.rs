@@ -176,7 +176,6 @@ pub(crate) struct SizedText<'a> {
     pub(crate) text: Cow<'a, str>,
     pub(crate) width: i32,
     pub(crate) height_above_baseline: i32,
-    pub(crate) height_below_baseline: i32,
     pub(crate) family: Family<'a>,
     pub(crate) size: f32,
     pub(crate) weight: Weight,
@@ -191,8 +190,7 @@ impl<'a> SizedText<'a> {
         text: S,
     ) -> Self {
         let text = text.into();
-        let (width, height_above_baseline, height_below_baseline) = setter.shape(&text);
-        let height_below_baseline = -height_below_baseline;
+        let (width, height_above_baseline, _) = setter.shape(&text);
 
         let family = data.family;
         let size = setter.px_size;
@@ -202,7 +200,6 @@ impl<'a> SizedText<'a> {
             text,
             width,
             height_above_baseline,
-            height_below_baseline,
             family,
             size,
             weight,
@@ -414,7 +411,7 @@ impl SVGWriter {
 fn generate_titlepage_svg<'a, S, I, P>(
     title: I,
     subtitle: Option<I>,
-    contributors: Option<Vec<(Option<&'a str>, Vec<S>)>>,
+    contributors: Option<Vec<(Option<S>, Vec<S>)>>,
     logo: Option<P>,
     typeface: Option<&'a str>,
 ) -> Result<String, ()>
@@ -508,7 +505,7 @@ where
                 let sized_role = SizedText::new(
                     &normal_font_data,
                     &contributor_ancillary_setter,
-                    role.to_uppercase(),
+                    role.as_ref().to_uppercase(),
                 );
                 writer.add_text_and_move_down(sized_role);
                 writer.move_y(CONTRIBUTOR_INTRO_SPACING);
@@ -9,15 +9,19 @@
 #![deny(missing_debug_implementations)]
 
 use bookbinder_ast::Metadata;
-use bookbinder_ast::{BookEvent, BookSrc, SemanticRole, TextHeaderOptions};
+use bookbinder_ast::{
+    BookEvent, BookSrc, BookTransform, CrossReferences, NumberFormat, RenderTarget, SemanticRole,
+    TextHeaderOptions, TransformPipeline,
+};
 use bookbinder_common::MimeTypeHelper;
-use extended_pulldown::{Event, Tag};
+use extended_pulldown::{Event, IndexCrossReference, IndexEntry, MakeStatic, Tag};
 use std::path::PathBuf;
 use temp_file_name::TempFilePath;
 mod preamble_options;
 use bookbinder_ast::helpers::{
-    BookEventIteratorHelper, CollatedHeader, CollatedImage, LatexMarker,
+    BookEventIteratorHelper, CollatedEpigraph, CollatedHeader, CollatedImage, LatexMarker,
 };
+use preamble_options::counter_representation;
 pub use preamble_options::{PaperSize, PreambleOptions};
 
 /// Options with a prerendered preamble;
@@ -31,6 +35,7 @@ pub struct OptionsWithRenderedPreamble {
     contributor_identifier: Option<String>,
     latex_secnumdepth: LatexSecNumDepth,
     include_toc: bool,
+    transforms: TransformPipeline,
 }
 
 impl OptionsWithRenderedPreamble {
@@ -116,6 +121,13 @@ impl OptionsWithRenderedPreamble {
         self
     }
 
+    /// Add a transform to apply to the book before rendering,
+    /// after any registered on the `BookSrc` itself
+    pub fn add_transform<T: BookTransform + 'static>(&mut self, transform: T) -> &mut Self {
+        self.transforms.add(transform);
+        self
+    }
+
     /// Set the secnumdepth
     pub fn set_secnumdepth(&mut self, secnumdepth: LatexSecNumDepth) -> &mut Self {
         self.latex_secnumdepth = secnumdepth;
@@ -262,19 +274,84 @@ struct LatexWriter {
     output: String,
     current_matter: Option<Matter>,
     current_division: Option<SemanticRole>,
-    expected_epigraphs_count: usize,
-    seen_epigraphs: usize,
-    expected_appendices_count: usize,
+    in_epigraphs: bool,
     seen_appendices: usize,
+    in_volume: bool,
     in_code: bool,
     mainmatter_toggled: bool,
-    chapter_count: usize,
-    frontmatter_had_toc_contents: bool,
+    /// whether the running footer currently names the authors of a chapter
+    /// rather than those of the book
+    chapter_contributor_set: bool,
     publisher_imprint_logo: Option<PathBuf>,
     include_toc: bool,
+    cross_references: CrossReferences,
+    in_heading: bool,
+    /// labels and index entries within a heading, which must be set after it rather than in its argument
+    heading_markers: Vec<Event<'static>>,
+    /// what ends the last line of verse written, once it is known
+    /// whether the line ends a stanza or the poem itself
+    verse_line_end: Option<&'static str>,
 }
 
 impl LatexWriter {
+    /// set a label which refers to the text it resolves to,
+    /// rather than the value of whatever counter was last stepped
+    fn write_label(&mut self, label: &str) {
+        let text = self.cross_references.resolve(label).unwrap_or_default();
+        self.output.push_str(&format!(
+            "\\crossreflabel{{{}}}{{{}}}",
+            label,
+            bookbinder_common::escape_to_latex(text)
+        ));
+    }
+
+    /// mark a term for the index, quoting any characters which xindy would read as markup
+    fn write_index_entry(&mut self, entry: &IndexEntry<'_>) {
+        let quote = |term: &str| {
+            let mut quoted = String::with_capacity(term.len());
+            for c in bookbinder_common::escape_to_latex(term).chars() {
+                if matches!(c, '!' | '@' | '|' | '"') {
+                    quoted.push('"');
+                }
+                quoted.push(c);
+            }
+            quoted
+        };
+        let terms = entry
+            .terms
+            .iter()
+            .map(|term| quote(term))
+            .collect::<Vec<_>>();
+        self.output.push_str("\\index{");
+        self.output.push_str(&terms.join("!"));
+        match entry.cross_reference {
+            Some(IndexCrossReference::See(ref other)) => {
+                self.output.push_str(&format!("|see{{{}}}", quote(other)));
+            }
+            Some(IndexCrossReference::SeeAlso(ref other)) => {
+                self.output
+                    .push_str(&format!("|seealso{{{}}}", quote(other)));
+            }
+            None => {}
+        }
+        self.output.push('}');
+    }
+
+    fn write_division_epigraph(&mut self, epigraph: CollatedEpigraph<'_>) {
+        self.output.begin_environment("divisionepigraph");
+        for event in epigraph.text.into_iter() {
+            self.write_plain(event);
+        }
+        if !epigraph.source.is_empty() {
+            self.output.push_str("\\par\n\\epigraphsource{");
+            for event in epigraph.source.into_iter() {
+                self.write_plain(event);
+            }
+            self.output.push_str("}\n");
+        }
+        self.output.end_environment("divisionepigraph");
+    }
+
     fn write_plain(&mut self, event: Event<'_>) {
         use Event::*;
         use Tag::*;
@@ -327,13 +404,16 @@ impl LatexWriter {
             Start(UnindentedParagraph) => {
                 self.output.push_str("\n\\noindent ");
             }
-            Start(Heading(l)) => match l {
-                0 | 1 => self.output.push_str("\n\\section{"),
-                2 => self.output.push_str("\n\\subsection{"),
-                3 => self.output.push_str("\n\\subsubsection{"),
-                4 => self.output.push_str("\n\\paragraph{"),
-                _ => self.output.push_str("\n\\subparagraph{"),
-            },
+            Start(Heading(l)) => {
+                self.in_heading = true;
+                match l {
+                    0 | 1 => self.output.push_str("\n\\section{"),
+                    2 => self.output.push_str("\n\\subsection{"),
+                    3 => self.output.push_str("\n\\subsubsection{"),
+                    4 => self.output.push_str("\n\\paragraph{"),
+                    _ => self.output.push_str("\n\\subparagraph{"),
+                }
+            }
             Start(BlockQuote) => self.output.begin_environment("quote"),
             Start(BlockQuotation) => self.output.begin_environment("quotation"),
             Start(CodeBlock(_)) => {
@@ -380,7 +460,14 @@ impl LatexWriter {
                     self.output.push('\n');
                 }
             }
-            End(Heading(_)) => self.output.push_str("}\n"),
+            End(Heading(_)) => {
+                self.output.push('}');
+                self.in_heading = false;
+                for marker in std::mem::take(&mut self.heading_markers).into_iter() {
+                    self.write_plain(marker);
+                }
+                self.output.push('\n');
+            }
             End(BlockQuote) => self.output.end_environment("quote"),
             End(BlockQuotation) => self.output.end_environment("quotation"),
             End(CodeBlock(_)) => {
@@ -412,6 +499,104 @@ impl LatexWriter {
             Start(Table(_)) => {}
             Start(FlattenedFootnote) => {}
             End(FlattenedFootnote) => {}
+            Label(label) if self.in_heading => {
+                self.heading_markers.push(Label(label).into_static())
+            }
+            Label(label) => self.write_label(&label),
+            IndexEntry(entry) if self.in_heading => {
+                self.heading_markers.push(IndexEntry(entry).into_static())
+            }
+            IndexEntry(entry) => self.write_index_entry(&entry),
+            Reference(label) => {
+                self.output.push_str("\\ref{");
+                self.output.push_str(&label);
+                self.output.push('}');
+            }
+            PageReference(label) => {
+                self.output.push_str("\\pageref{");
+                self.output.push_str(&label);
+                self.output.push('}');
+            }
+            // citations are resolved to text when a book is processed
+            Citation(_) => {}
+            Start(GlossaryReference(key)) => {
+                self.output.push_str("\\glslink{");
+                self.output.push_str(&key);
+                self.output.push_str("}{");
+            }
+            End(GlossaryReference(_)) => self.output.push('}'),
+            // glossaries are set by the glossaries package from the entries defined in the preamble
+            Start(Glossary) | End(Glossary) => {}
+            Start(GlossaryTerm(_)) | End(GlossaryTerm(_)) => {}
+            Start(GlossaryDefinition) | End(GlossaryDefinition) => {}
+            // content for other formats is removed before rendering
+            Start(FormatOnly(_)) | End(FormatOnly(_)) => {}
+            Start(Verse(numbering)) => {
+                self.output.begin_environment("verse");
+                if let Some(n) = numbering {
+                    self.output.push_str(&format!(
+                        "\\poemlines{{{}}}\\setverselinenums{{1}}{{{}}}\n",
+                        n, n
+                    ));
+                }
+            }
+            End(Verse(_)) => {
+                self.verse_line_end = None;
+                self.output.end_environment("verse");
+            }
+            Start(Stanza) => {}
+            End(Stanza) => self.verse_line_end = Some("\\\\!\n\n"),
+            Start(VerseLine(indent)) => {
+                if let Some(end) = self.verse_line_end.take() {
+                    self.output.push_str(end);
+                }
+                if indent > 0 {
+                    self.output
+                        .push_str(&format!("\\hspace*{{{}em}}", indent as f32 / 2.0));
+                }
+            }
+            End(VerseLine(_)) => self.verse_line_end = Some("\\\\\n"),
+            // the speaker's name hangs at the start of the speech, which is indented beneath it
+            Start(Speech(speaker)) => {
+                self.output.push_str("\n\\speech{");
+                self.output
+                    .push_str(&bookbinder_common::escape_to_latex(speaker.as_ref()));
+                self.output.push('}');
+            }
+            End(Speech(_)) => {
+                if !self.output.ends_with("\n\n") {
+                    self.output.push('\n');
+                }
+            }
+            Start(StageDirection) => self.output.begin_environment("stagedirection"),
+            End(StageDirection) => self.output.end_environment("stagedirection"),
+            Start(Parenthetical) => self.output.push_str("\\textit{"),
+            End(Parenthetical) => self.output.push('}'),
+            Start(SceneHeading) => self.output.push_str("\n\\sceneheading{"),
+            End(SceneHeading) => self.output.push_str("}\n"),
+            // the environments of a letter's parts keep their alignment within its narrower margins
+            Start(Letter) => self.output.begin_environment("embeddedletter"),
+            End(Letter) => self.output.end_environment("embeddedletter"),
+            Start(Dateline) => self.output.begin_environment("letterdateline"),
+            End(Dateline) => self.output.end_environment("letterdateline"),
+            Start(Salutation) => self.output.begin_environment("lettersalutation"),
+            End(Salutation) => self.output.end_environment("lettersalutation"),
+            Start(Signature) => self.output.begin_environment("lettersignature"),
+            End(Signature) => self.output.end_environment("lettersignature"),
+            Start(Postscript) => self.output.begin_environment("letterpostscript"),
+            End(Postscript) => self.output.end_environment("letterpostscript"),
+            // an opening paragraph with a drop cap is begun in `write`, where what follows it can be seen
+            Start(OpeningParagraph(None, true)) => self.output.push_str("\n\\FirstLine{"),
+            Start(OpeningParagraph(_, _)) => self.output.push('\n'),
+            End(OpeningParagraph(drop_cap, first_line)) => {
+                if drop_cap.is_none() && first_line {
+                    self.output.push('}');
+                }
+                if !self.output.ends_with("\n\n") {
+                    self.output.push('\n');
+                }
+            }
+            Start(DropCap) | End(DropCap) => {}
         }
     }
 
@@ -419,7 +604,7 @@ impl LatexWriter {
         use extended_pulldown::Event::*;
         use BookEvent::*;
 
-        let mut events = events.into_iter();
+        let mut events = events.into_iter().peekable();
 
         macro_rules! drop_until {
             ($break:pat) => {
@@ -433,12 +618,19 @@ impl LatexWriter {
 
         while let Some(event) = events.next() {
             match event {
+                // the epigraph of a chapter is set beneath its heading
+                BeginSemantic(SemanticRole::Epigraph)
+                    if self.current_division == Some(SemanticRole::Chapter) =>
+                {
+                    let epigraph_src = events.collate_epigraph();
+                    self.write_division_epigraph(epigraph_src);
+                }
                 BeginSemantic(SemanticRole::Epigraph) => {
                     self.current_division = Some(SemanticRole::Epigraph);
-                    if self.seen_epigraphs == 0 {
+                    if !self.in_epigraphs {
                         self.output.begin_environment("epigraphs");
+                        self.in_epigraphs = true;
                     }
-                    self.seen_epigraphs += 1;
                     let epigraph_src = events.collate_epigraph();
 
                     for event in epigraph_src.text.into_iter() {
@@ -452,10 +644,11 @@ impl LatexWriter {
                         }
                         self.output.push_str("}\n");
                     }
-                    if self.seen_epigraphs == self.expected_epigraphs_count {
-                        self.output.end_environment("epigraphs");
-                    } else {
+                    if let Some(BeginSemantic(SemanticRole::Epigraph)) = events.peek() {
                         self.output.push_str("\\bigskip\n");
+                    } else {
+                        self.output.end_environment("epigraphs");
+                        self.in_epigraphs = false;
                     }
                     self.current_division = None;
                 }
@@ -463,57 +656,191 @@ impl LatexWriter {
                     let header_src: CollatedHeader<LatexMarker> =
                         events.collate_division_header(is_starred);
                     let label_and_title = header_src.reconcile_joined_label_and_title();
+                    // the numbering of chapters and parts in a volume can continue from an earlier volume,
+                    // so follow the number given rather than the counter
+                    if self.in_volume {
+                        let counter = match self.current_division {
+                            Some(SemanticRole::Chapter) => Some("chapter"),
+                            Some(SemanticRole::Part) => Some("part"),
+                            _ => None,
+                        };
+                        if let (Some(counter), Some(number)) = (counter, header_src.label_number) {
+                            self.output.set_counter(counter, number as isize - 1);
+                        }
+                    }
                     if let Some((label, title)) = label_and_title {
                         match self.current_matter {
-                            Some(Matter::Main) => match self.current_division {
-                                Some(SemanticRole::Part) => {
-                                    if let Some(title) = title {
-                                        if is_starred {
-                                            self.output
-                                                .push_str(&format!("\n\\part*{{{}}}", title));
-                                        } else {
-                                            self.output.push_str(&format!("\n\\part{{{}}}", title));
-                                        }
+                            _ if self.current_division == Some(SemanticRole::Volume) => {
+                                let authors = match header_src.get_authors() {
+                                    None => None,
+                                    Some((first, None)) => Some(first),
+                                    Some((first, Some(second))) => {
+                                        Some(format!("{} and {}", first, second).into())
                                     }
+                                };
+                                self.output.push_str(&format!(
+                                    "\n\\volumeheader{{{}}}{{{}}}{{{}}}\n",
+                                    label.unwrap_or_default(),
+                                    title.unwrap_or_default(),
+                                    authors.unwrap_or_default()
+                                ));
+                            }
+                            Some(Matter::Main) => {
+                                let chapter_authors = match self.current_division {
+                                    Some(SemanticRole::Part) => None,
+                                    _ => header_src.get_joined_authors(),
+                                };
+                                if let Some(ref authors) = chapter_authors {
+                                    self.output
+                                        .push_str(&format!("\n\\setchapterauthors{{{}}}", authors));
                                 }
-                                Some(SemanticRole::Chapter) => match (label, title) {
-                                    (Some(_), Some(title)) => {
-                                        self.output.push_str(&format!("\n\\chapter{{{}}}", title));
-                                    }
-                                    (Some(_), None) => {
-                                        self.output.push_str(
-                                            "\n\\chapter[\\chaptername{} \\thechapter]{}",
-                                        );
-                                    }
-                                    (None, Some(title)) => {
-                                        self.output.step_counter("chapter");
-                                        self.output.push_str("\n\\addcontentsline{toc}{chapter}{\\numberline{\\thechapter} ");
-                                        self.output.push_str(&title);
-                                        self.output.push('}');
-                                        self.output.push_str(&format!("\n\\chapter*{{{}}}", title));
+                                match self.current_division {
+                                    Some(SemanticRole::Part) => {
+                                        // a part heading is a page of its own,
+                                        // so any epigraphs must be set before the page is finished
+                                        let mut part_epigraphs = String::new();
+                                        while let Some(BeginSemantic(SemanticRole::Epigraph)) =
+                                            events.peek()
+                                        {
+                                            let _begin = events.next();
+                                            let epigraph_src = events.collate_epigraph();
+                                            let output = std::mem::take(&mut self.output);
+                                            self.write_division_epigraph(epigraph_src);
+                                            part_epigraphs.push_str(&std::mem::replace(
+                                                &mut self.output,
+                                                output,
+                                            ));
+                                        }
+                                        if let Some(title) = title {
+                                            if !part_epigraphs.is_empty() {
+                                                self.output.push_str(&format!(
+                                                    "\n\\renewcommand{{\\partepigraph}}{{{}}}",
+                                                    part_epigraphs
+                                                ));
+                                            }
+                                            let short_title = header_src.get_short_title();
+                                            if is_starred {
+                                                self.output
+                                                    .push_str(&format!("\n\\part*{{{}}}", title));
+                                                self.output.push_str(&format!(
+                                                    "\n\\addcontentsline{{toc}}{{part}}{{{}}}",
+                                                    short_title.unwrap_or_else(|| title.clone())
+                                                ));
+                                            } else if let Some(short_title) = short_title {
+                                                self.output.push_str(&format!(
+                                                    "\n\\part[{}]{{{}}}",
+                                                    short_title, title
+                                                ));
+                                            } else {
+                                                self.output
+                                                    .push_str(&format!("\n\\part{{{}}}", title));
+                                            }
+                                        }
                                     }
-                                    (None, None) => {
-                                        self.output.step_counter("chapter");
-                                        self.output.push_str("\n\\addcontentsline{toc}{chapter}{\\numberline{\\thechapter}  \\chaptername{} \\thechapter}");
-                                        self.output.push_str("\n\\chapter*{}");
+                                    Some(SemanticRole::Chapter) => match (label, title) {
+                                        (_, Some(title)) if is_starred => {
+                                            self.output
+                                                .push_str(&format!("\n\\chapter*{{{}}}", title));
+                                            self.output.push_str(&format!(
+                                                "\n\\addcontentsline{{toc}}{{chapter}}{{{}}}",
+                                                header_src.get_short_title().unwrap_or(title)
+                                            ));
+                                        }
+                                        (Some(_), Some(title)) => {
+                                            match header_src.get_short_title() {
+                                                Some(short_title) => {
+                                                    self.output.push_str(&format!(
+                                                        "\n\\chapter[{}]{{{}}}",
+                                                        short_title, title
+                                                    ))
+                                                }
+                                                None => self
+                                                    .output
+                                                    .push_str(&format!("\n\\chapter{{{}}}", title)),
+                                            }
+                                        }
+                                        (Some(_), None) => {
+                                            self.output.push_str(
+                                                "\n\\chapter[\\chaptername{} \\thechapter]{}",
+                                            );
+                                        }
+                                        (None, Some(title)) => {
+                                            self.output.step_counter("chapter");
+                                            self.output.push_str("\n\\addcontentsline{toc}{chapter}{\\numberline{\\thechapter} ");
+                                            self.output.push_str(
+                                                &header_src
+                                                    .get_short_title()
+                                                    .unwrap_or_else(|| title.clone()),
+                                            );
+                                            self.output.push('}');
+                                            self.output
+                                                .push_str(&format!("\n\\chapter*{{{}}}", title));
+                                        }
+                                        (None, None) => {
+                                            self.output.step_counter("chapter");
+                                            self.output.push_str("\n\\addcontentsline{toc}{chapter}{\\numberline{\\thechapter}  \\chaptername{} \\thechapter}");
+                                            self.output.push_str("\n\\chapter*{}");
+                                        }
+                                    },
+                                    // prologues, epilogues and any other unnumbered divisions
+                                    Some(_) => {
+                                        if let Some(title) = title {
+                                            self.output.push_str(&format!(
+                                                "\n\\unnumberedmainmatterchapter{{{}}}{{{}}}\n",
+                                                label.unwrap_or_default(),
+                                                title
+                                            ));
+                                        }
                                     }
-                                },
-                                _ => {}
-                            },
+                                    _ => {}
+                                }
+                                // the running footer names a chapter's own authors until the next chapter
+                                if let Some(authors) = chapter_authors {
+                                    self.output.push_str("\n\\unsetchapterauthors");
+                                    self.output.push_str(&format!(
+                                        "\n\\setchaptercontributor{{{}}}\n",
+                                        authors
+                                    ));
+                                    self.chapter_contributor_set = true;
+                                } else if self.chapter_contributor_set {
+                                    self.output.push_str("\n\\unsetchaptercontributor\n");
+                                    self.chapter_contributor_set = false;
+                                }
+                            }
                             _ => match self.current_division {
                                 Some(SemanticRole::Appendix) => {
                                     if self.seen_appendices == 1 {
                                         self.output
                                             .push_str("\\addcontentsline{toc}{part}{Appendices}\n");
                                         self.output.set_counter("chapter", 0);
-                                        self.output.push_str(
-                                            "\\renewcommand{\\thechapter}{\\Alph{chapter}}\n",
-                                        );
+                                        // keep the numbering of chapters, in case a volume's appendices
+                                        // are followed by further chapters
+                                        self.output
+                                            .push_str("\\let\\mainmatterthechapter\\thechapter\n");
+                                        self.output.push_str(&format!(
+                                            "\\renewcommand{{\\thechapter}}{{{}}}\n",
+                                            counter_representation(
+                                                &NumberFormat::Letter,
+                                                "chapter"
+                                            )
+                                        ));
                                     }
                                     let label_and_title =
                                         header_src.reconcile_joined_label_and_title();
                                     if let Some((label, title)) = label_and_title {
                                         let header = match (label, title) {
+                                            (_, Some(title)) if is_starred => {
+                                                let mut header = String::new();
+                                                header.push_str(&format!(
+                                                    "\\chapter*{{{}}}\n",
+                                                    &title
+                                                ));
+                                                header.push_str(&format!(
+                                                    "\\addcontentsline{{toc}}{{chapter}}{{{}}}",
+                                                    title
+                                                ));
+                                                header
+                                            }
                                             (Some(label), Some(title)) => {
                                                 let mut header = String::new();
                                                 header.push_str("\\setchapterlabel{");
@@ -602,6 +929,14 @@ impl LatexWriter {
                             },
                         }
                     }
+                    for label in header_src.labels.iter() {
+                        self.write_label(label);
+                        self.output.push('\n');
+                    }
+                    for entry in header_src.index_entries.iter() {
+                        self.write_index_entry(entry);
+                        self.output.push('\n');
+                    }
                 }
                 BeginTitlePage => {
                     let titlepage_src = events.collate_titlepage();
@@ -627,8 +962,10 @@ impl LatexWriter {
                         for (role, names) in contributors.into_iter() {
                             self.output.begin_environment("contributorgroup");
                             if let Some(role) = role {
-                                self.output
-                                    .push_str(&format!("\n\\contributorintro{{{}}}", role));
+                                self.output.push_str(&format!(
+                                    "\n\\contributorintro{{{}}}",
+                                    bookbinder_common::escape_to_latex(role)
+                                ));
                             }
 
                             let mut names = names
@@ -688,6 +1025,64 @@ impl LatexWriter {
                     }
                     self.output.push('}');
                 }
+                Event(Start(Tag::OpeningParagraph(Some(lines), first_line))) => {
+                    self.output.push('\n');
+                    if let Some(Event(Start(Tag::DropCap))) = events.peek() {
+                        events.next();
+                        let mut initial = String::new();
+                        for event in events.by_ref() {
+                            match event {
+                                Event(Text(text)) => initial.push_str(&text),
+                                Event(End(Tag::DropCap)) => break,
+                                _ => {}
+                            }
+                        }
+                        // quotation marks before the initial letter are set before it in the margin
+                        let letter_start = initial.char_indices().next_back().map_or(0, |(i, _)| i);
+                        let ante = &initial[..letter_start];
+                        self.output.push_str(&format!("\\lettrine[lines={}", lines));
+                        if !ante.is_empty() {
+                            self.output.push_str(", ante=");
+                            self.output
+                                .push_str(&bookbinder_common::escape_to_latex(ante));
+                        }
+                        self.output.push_str("]{");
+                        self.output.push_str(&bookbinder_common::escape_to_latex(
+                            &initial[letter_start..],
+                        ));
+                        self.output.push_str("}{");
+
+                        // the text set beside the drop cap is the rest of its word,
+                        // the words in small caps or, for a first line in small caps, the first few words
+                        match events.peek() {
+                            Some(Event(Start(Tag::SmallCaps))) => {
+                                events.next();
+                                for event in events.by_ref() {
+                                    match event {
+                                        Event(End(Tag::SmallCaps)) => break,
+                                        Event(e) => self.write_plain(e),
+                                        _ => {}
+                                    }
+                                }
+                                self.output.push('}');
+                            }
+                            Some(Event(Text(_))) => {
+                                if let Some(Event(Text(text))) = events.next() {
+                                    let words = if first_line { 4 } else { 1 };
+                                    let end = lettrine_text_len(&text, words);
+                                    self.output.push_str(&bookbinder_common::escape_to_latex(
+                                        &text[..end],
+                                    ));
+                                    self.output.push('}');
+                                    self.output.push_str(&bookbinder_common::escape_to_latex(
+                                        &text[end..],
+                                    ));
+                                }
+                            }
+                            _ => self.output.push('}'),
+                        }
+                    }
+                }
                 Event(Start(Tag::Image(_, dest, alt))) => {
                     let collated_image = events.collate_image(dest, alt);
                     if let Ok(p) = collated_image.get_latex_image_path() {
@@ -698,15 +1093,32 @@ impl LatexWriter {
                         self.output.push_str(&p);
                         self.output.push_str("}\n");
                         if let Some(caption) = collated_image.caption {
+                            let (markers, caption): (Vec<_>, Vec<_>) = caption
+                                .into_iter()
+                                .partition(|e| matches!(e, Label(_) | IndexEntry(_)));
                             self.output.push_str("\\caption{");
                             for event in caption.into_iter() {
                                 self.write_plain(event);
                             }
-                            self.output.push_str("}\n");
+                            self.output.push('}');
+                            for marker in markers.into_iter() {
+                                self.write_plain(marker);
+                            }
+                            self.output.push('\n');
                         }
                         self.output.end_environment("figure");
                     }
                 }
+                BeginFrontmatter if self.in_volume => {
+                    self.current_matter = Some(Matter::Front);
+                    self.output.push_str("\n\\suppresschapternumbersintoc\n");
+                }
+                BeginMainmatter if self.in_volume => {
+                    self.current_matter = Some(Matter::Main);
+                    self.output.push_str("\n\\clearpage");
+                    self.output.set_counter("chapter", 0);
+                    self.output.push_str("\n\\restorechapternumbersintoc{}\n");
+                }
                 BeginFrontmatter => {
                     self.current_matter = Some(Matter::Front);
                     self.output.push_str("\n\\frontmatter\n");
@@ -728,7 +1140,11 @@ impl LatexWriter {
                 }
                 BeginBackmatter => {
                     self.current_matter = Some(Matter::Back);
-                    if self.expected_appendices_count == 0 {
+                    if self.chapter_contributor_set {
+                        self.output.push_str("\n\\unsetchaptercontributor");
+                        self.chapter_contributor_set = false;
+                    }
+                    if !matches!(events.peek(), Some(BeginSemantic(SemanticRole::Appendix))) {
                         self.output.push_str("\n\\suppresschapternumbersintoc\n");
                     }
                     //self.output.push_str("\\addtocontents{toc}{{\\bigskip\\par\\noindent\\hfill\\pfbreakdisplay\\hfill\\bigskip\n\n}}");
@@ -742,6 +1158,16 @@ impl LatexWriter {
                 Event(Start(Tag::FootnoteDefinition(_))) => {
                     drop_until!(Event(End(Tag::FootnoteDefinition(_))));
                 }
+                // the index is set by xindy from the entries marked throughout the book
+                BeginSemantic(SemanticRole::Index) => {
+                    drop_until!(EndSemantic(SemanticRole::Index));
+                    self.output.push_str("\n\\printindex\n");
+                }
+                // the glossary is set by the glossaries package, beneath the heading already given
+                Event(Start(Tag::Glossary)) => {
+                    drop_until!(Event(End(Tag::Glossary)));
+                    self.output.push_str("\n{\\renewcommand*{\\glossarysection}[2][]{}\\glsaddall\\printnoidxglossary[sort=def]}\n");
+                }
                 BeginSemantic(role) => {
                     self.current_division = Some(role);
                     match role {
@@ -752,11 +1178,14 @@ impl LatexWriter {
                         SemanticRole::Halftitle => self.output.begin_environment("halftitle"),
                         SemanticRole::Dedication => self.output.begin_environment("dedication"),
                         SemanticRole::Colophon => self.output.begin_environment("colophon"),
+                        SemanticRole::SeriesPage => self.output.begin_environment("seriespage"),
+                        SemanticRole::Frontispiece => self.output.begin_environment("frontispiece"),
                         SemanticRole::Epigraph => unreachable!(),
                         SemanticRole::Introduction
                         | SemanticRole::Foreword
-                        | SemanticRole::Preface => {
-                            if !self.mainmatter_toggled {
+                        | SemanticRole::Preface
+                        | SemanticRole::DramatisPersonae => {
+                            if !self.mainmatter_toggled && !self.in_volume {
                                 self.output.push_str("\n\\mainmatter\n");
                                 self.output.set_counter("secnumdepth", 0);
                                 self.output.push_str(
@@ -768,6 +1197,9 @@ impl LatexWriter {
                         SemanticRole::Appendix => {
                             self.seen_appendices += 1;
                         }
+                        SemanticRole::Volume => {
+                            self.in_volume = true;
+                        }
                         _ => {}
                     }
                 }
@@ -784,11 +1216,24 @@ impl LatexWriter {
                         SemanticRole::Halftitle => self.output.end_environment("halftitle"),
                         SemanticRole::Dedication => self.output.end_environment("dedication"),
                         SemanticRole::Colophon => self.output.end_environment("colophon"),
+                        SemanticRole::SeriesPage => self.output.end_environment("seriespage"),
+                        SemanticRole::Frontispiece => self.output.end_environment("frontispiece"),
                         SemanticRole::Appendix => {
-                            if self.seen_appendices == self.expected_appendices_count {
+                            if !matches!(events.peek(), Some(BeginSemantic(SemanticRole::Appendix)))
+                            {
                                 self.output.push_str("\n\\suppresschapternumbersintoc\n");
                             }
                         }
+                        SemanticRole::Volume => {
+                            self.in_volume = false;
+                            self.current_matter = Some(Matter::Main);
+                            // any appendices of the volume will have changed chapter numbering
+                            if self.seen_appendices > 0 {
+                                self.output
+                                    .push_str("\n\\let\\thechapter\\mainmatterthechapter\n");
+                                self.seen_appendices = 0;
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -799,6 +1244,66 @@ impl LatexWriter {
     }
 }
 
+/// the length of the text to set beside a drop cap at the start of `text`:
+/// the rest of the word the drop cap begins, and as many more as make `words` in all
+fn lettrine_text_len(text: &str, words: usize) -> usize {
+    // a word of a single letter ends with its drop cap
+    let mut count = if text.starts_with(char::is_whitespace) {
+        1
+    } else {
+        0
+    };
+    let mut in_word = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if count >= words {
+                return i;
+            }
+            in_word = false;
+        } else if !in_word {
+            in_word = true;
+            count += 1;
+        }
+    }
+    text.trim_end().len()
+}
+
+/// the commands defining each term in any glossary among `events`, for the glossaries package
+fn glossary_entry_definitions(events: &[BookEvent<'_>]) -> String {
+    let mut definitions = String::new();
+    // the key of the term being defined, with what has been written of it so far
+    let mut current: Option<(String, String, LatexWriter)> = None;
+    for event in events.iter() {
+        match event {
+            BookEvent::Event(Event::Start(Tag::GlossaryTerm(key))) => {
+                current = Some((key.to_string(), String::new(), LatexWriter::default()));
+            }
+            BookEvent::Event(Event::End(Tag::GlossaryTerm(_))) => {
+                if let Some((_, ref mut name, ref mut writer)) = current {
+                    *name = std::mem::take(&mut writer.output);
+                }
+            }
+            BookEvent::Event(Event::End(Tag::GlossaryDefinition)) => {
+                if let Some((key, name, writer)) = current.take() {
+                    definitions.push_str(&format!(
+                        "\n\\newglossaryentry{{{}}}{{name={{{}}}, description={{{}}}}}",
+                        key,
+                        name.trim(),
+                        writer.output.trim()
+                    ));
+                }
+            }
+            BookEvent::Event(e) => {
+                if let Some((_, _, ref mut writer)) = current {
+                    writer.write_plain(e.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    definitions
+}
+
 /// Support for rendering to a tex document
 pub trait TexRenderer {
     /// Render to tex with a pregenerated preamble
@@ -809,9 +1314,45 @@ pub trait TexRenderer {
     fn render_to_tex(self) -> String;
     /// Render with particular options to generate a preamble with
     fn render_to_tex_with_options(self, options: PreambleOptions) -> String;
+    /// The time to which a pdf rendered from this is dated,
+    /// as a number of seconds since the unix epoch, if it is fixed
+    fn get_source_date_epoch(&self) -> Option<i64> {
+        None
+    }
+    /// Render to a fragment of tex as `render_to_tex_with_preamble` does,
+    /// unless what is rendered refers to labels which are never defined;
+    /// a pdf is not rendered while there are any
+    fn try_render_to_tex_with_preamble(
+        self,
+        options: OptionsWithRenderedPreamble,
+    ) -> Result<String, DanglingReferences>
+    where
+        Self: Sized,
+    {
+        Ok(self.render_to_tex_with_preamble(options))
+    }
 }
 
 impl TexRenderer for BookSrc<'_> {
+    fn get_source_date_epoch(&self) -> Option<i64> {
+        self.metadata.get_publication_epoch()
+    }
+
+    fn try_render_to_tex_with_preamble(
+        mut self,
+        options: OptionsWithRenderedPreamble,
+    ) -> Result<String, DanglingReferences> {
+        self.select_format(RenderTarget::Latex);
+        self.apply_transforms(RenderTarget::Latex, &options.transforms);
+        // only what is rendered to latex can be referred to, or leave a reference dangling
+        let dangling = self.cross_references().dangling().to_vec();
+        if dangling.is_empty() {
+            Ok(render_selected_with_preamble(self, options))
+        } else {
+            Err(DanglingReferences(dangling))
+        }
+    }
+
     fn render_to_tex_with_options(self, options: PreambleOptions) -> String {
         let options = OptionsWithRenderedPreamble::from(options);
         self.render_to_tex_with_preamble(options)
@@ -822,46 +1363,83 @@ impl TexRenderer for BookSrc<'_> {
         self.render_to_tex_with_options(options)
     }
 
-    fn render_to_tex_standalone(self, logo: Option<PathBuf>, include_toc: bool) -> String {
-        let mut writer = LatexWriter::default();
-        writer.expected_epigraphs_count = self.expected_epigraph_count;
-        writer.expected_appendices_count = self.expected_appendices_count;
-        writer.include_toc = include_toc;
-        writer.publisher_imprint_logo = logo;
-        writer.write(self.contents);
-        writer.output
-    }
-
-    fn render_to_tex_with_preamble(mut self, mut options: OptionsWithRenderedPreamble) -> String {
-        self.change_headers(options.header_format);
-        options.set_running_footers_from_metadata(std::mem::take(&mut self.metadata));
-        let imprint_logo = options.publisher_imprint_logo;
-        let include_toc = options.include_toc;
-        let text = self.render_to_tex_standalone(imprint_logo, include_toc);
-
-        let secnumdepth = options.latex_secnumdepth;
-
-        let mut start = options.preamble;
-        start.begin_environment("document");
-        start.set_counter("secnumdepth", secnumdepth.as_counter());
-        if let Some(ref page_identifier) = options.page_identifier {
-            start.push_str(&format!(
-                "\n\\renewcommand{{\\pageidentifier}}{{{}}}",
-                page_identifier.trim()
-            ));
-        }
-        if let Some(ref contributor_identifier) = options.contributor_identifier {
-            start.push_str(&format!(
-                "\n\\renewcommand{{\\currentcontributor}}{{{}}}",
-                contributor_identifier.trim()
-            ));
-        }
+    fn render_to_tex_standalone(mut self, logo: Option<PathBuf>, include_toc: bool) -> String {
+        self.select_format(RenderTarget::Latex);
+        self.apply_transforms(RenderTarget::Latex, &TransformPipeline::default());
+        render_selected(self, logo, include_toc)
+    }
+
+    fn render_to_tex_with_preamble(mut self, options: OptionsWithRenderedPreamble) -> String {
+        self.select_format(RenderTarget::Latex);
+        self.apply_transforms(RenderTarget::Latex, &options.transforms);
+        render_selected_with_preamble(self, options)
+    }
+}
+
+/// Render `src`, whose latex content has already been selected and transformed, to tex
+fn render_selected(src: BookSrc<'_>, logo: Option<PathBuf>, include_toc: bool) -> String {
+    let mut writer = LatexWriter::default();
+    writer.cross_references = src.cross_references();
+    writer.include_toc = include_toc;
+    writer.publisher_imprint_logo = logo;
+    writer.write(src.contents);
+    writer.output
+}
 
-        start.push_str(&text);
-        start.end_environment("document");
+/// Render `src`, whose latex content has already been selected and transformed,
+/// to a fragment of tex with a preamble
+fn render_selected_with_preamble(
+    mut src: BookSrc<'_>,
+    mut options: OptionsWithRenderedPreamble,
+) -> String {
+    src.change_headers(options.header_format.clone());
+    // the index is sorted by the rules of the book's language
+    let index_language =
+        bookbinder_common::xindy_language(src.metadata.language.as_deref().unwrap_or("en"));
+    options.set_running_footers_from_metadata(std::mem::take(&mut src.metadata));
+    let imprint_logo = options.publisher_imprint_logo;
+    let include_toc = options.include_toc;
+    let has_index = src
+        .contents
+        .iter()
+        .any(|event| matches!(event, BookEvent::BeginSemantic(SemanticRole::Index)));
+    let glossary_entries = glossary_entry_definitions(&src.contents);
+    let text = render_selected(src, imprint_logo, include_toc);
 
+    let secnumdepth = options.latex_secnumdepth;
+
+    let mut start = options.preamble;
+    if has_index {
+        start.push_str(&format!(
+            "\n\\makeindex[options=-L {} -C utf8, intoc]\n",
+            index_language
+        ));
+    }
+    if !glossary_entries.is_empty() {
         start
+            .push_str("\n\\usepackage[nonumberlist, nopostdot]{glossaries}\n\\makenoidxglossaries");
+        start.push_str(&glossary_entries);
+        start.push('\n');
+    }
+    start.begin_environment("document");
+    start.set_counter("secnumdepth", secnumdepth.as_counter());
+    if let Some(ref page_identifier) = options.page_identifier {
+        start.push_str(&format!(
+            "\n\\renewcommand{{\\pageidentifier}}{{{}}}",
+            page_identifier.trim()
+        ));
     }
+    if let Some(ref contributor_identifier) = options.contributor_identifier {
+        start.push_str(&format!(
+            "\n\\renewcommand{{\\currentcontributor}}{{{}}}",
+            contributor_identifier.trim()
+        ));
+    }
+
+    start.push_str(&text);
+    start.end_environment("document");
+
+    start
 }
 
 /// Support for rendering to a pdf file
@@ -888,8 +1466,9 @@ pub trait PdfRenderer: TexRenderer + Sized {
         options: OptionsWithRenderedPreamble,
     ) -> Result<Vec<u8>, std::io::Error> {
         Self::check_latexmk()?;
-        let tex = self.render_to_tex_with_preamble(options);
-        bookbinder_common::call_latex(&tex)
+        let source_date_epoch = self.get_source_date_epoch();
+        let tex = self.try_render_to_tex_with_preamble(options)?;
+        call_latex(&tex, source_date_epoch)
     }
 
     /// Generate a preamble and then render using it
@@ -897,17 +1476,45 @@ pub trait PdfRenderer: TexRenderer + Sized {
         self,
         options: PreambleOptions,
     ) -> Result<Vec<u8>, std::io::Error> {
-        Self::check_latexmk()?;
-        let tex = self.render_to_tex_with_options(options);
-        bookbinder_common::call_latex(&tex)
+        let options = OptionsWithRenderedPreamble::from(options);
+        self.render_to_pdf_with_preamble(options)
     }
 
     /// render with default options
     fn render_to_pdf(self) -> Result<Vec<u8>, std::io::Error> {
-        Self::check_latexmk()?;
-        let tex = self.render_to_tex();
-        bookbinder_common::call_latex(&tex)
+        let options = PreambleOptions::default();
+        self.render_to_pdf_with_options(options)
     }
 }
 
 impl<T> PdfRenderer for T where T: TexRenderer {}
+
+/// References to labels which do not exist, which prevent a pdf from being rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingReferences(pub Vec<String>);
+
+impl std::fmt::Display for DanglingReferences {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "references to labels which do not exist: {}",
+            self.0.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for DanglingReferences {}
+
+impl From<DanglingReferences> for std::io::Error {
+    fn from(src: DanglingReferences) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, src)
+    }
+}
+
+/// call latex on `tex`, dating the pdf to `source_date_epoch` if it is given
+fn call_latex(tex: &str, source_date_epoch: Option<i64>) -> Result<Vec<u8>, std::io::Error> {
+    match source_date_epoch {
+        Some(epoch) => bookbinder_common::call_latex_with_source_date(tex, epoch),
+        None => bookbinder_common::call_latex(tex),
+    }
+}
@@ -1,14 +1,17 @@
 use crate::LatexSecNumDepth;
 use crate::OptionsWithRenderedPreamble;
-use bookbinder_ast::{NumberFormat, TextHeaderOptions};
+use bookbinder_ast::{
+    BookTransform, NumberFormat, OpeningStyle, SceneBreak, SmallCapsOpening, TextHeaderOptions,
+    TransformPipeline,
+};
 use bookbinder_common::fonts::{font_exists, FontInfo, SANS_FONT_PATHS, SERIF_FONT_PATHS};
-use bookbinder_common::MimeTypeHelper;
+use bookbinder_common::{escape_to_latex, MimeTypeHelper};
 use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 use temp_file_name::TempFilePath;
 
 const DEFAULT_LINESPREAD: f32 = 1.1;
-const DEFAULT_PART_FORMAT: &str = r#"\titleformat{\part}[display]{\headingtypeface\Huge}{\itshape Part \thepart}{1em}{\thispagestyle{empty}}{}"#;
+const DEFAULT_PART_FORMAT: &str = r#"\titleformat{\part}[display]{\headingtypeface\Huge}{\itshape Part \thepart}{1em}{\thispagestyle{empty}}[\printpartepigraph]"#;
 const DEFAULT_TITLESEC_OPTIONS: [&str; 4] = ["center", "sf", "small", "uppercase"];
 
 #[derive(Debug, Clone, Default)]
@@ -210,6 +213,47 @@ impl Default for LatexMargins {
     }
 }
 
+// roughly how many words of body text in the default 12pt type fill a square inch of a page
+const WORDS_PER_SQUARE_INCH: f32 = 7.5;
+
+impl PaperSize {
+    /// Every paper size, from smallest to largest
+    pub const ALL: [PaperSize; 7] = [
+        PaperSize::Inches5x8,
+        PaperSize::Inches5_25x8,
+        PaperSize::Inches5_5x8_5,
+        PaperSize::Inches6x9,
+        PaperSize::USLetter,
+        PaperSize::A4Paper,
+        PaperSize::USLegal,
+    ];
+
+    /// A short name for this size, such as `6x9` or `a4`
+    pub const fn name(self) -> &'static str {
+        match self {
+            PaperSize::Inches5x8 => "5x8",
+            PaperSize::Inches5_25x8 => "5.25x8",
+            PaperSize::Inches5_5x8_5 => "5.5x8.5",
+            PaperSize::Inches6x9 => "6x9",
+            PaperSize::A4Paper => "a4",
+            PaperSize::USLetter => "letter",
+            PaperSize::USLegal => "legal",
+        }
+    }
+
+    /// Roughly how many words of body text fit on a page of this size within its preconfigured margins
+    pub fn words_per_page(self) -> usize {
+        let margins = margins_from_papersize(self);
+        let per_inch = match margins.unit {
+            MeasurementUnit::Inches => 1.0,
+            MeasurementUnit::Mm => 25.4,
+        };
+        let width = (margins.paper_width - margins.left - margins.right) / per_inch;
+        let height = (margins.paper_height - margins.top - margins.bottom) / per_inch;
+        (width * height * WORDS_PER_SQUARE_INCH) as usize
+    }
+}
+
 /// A representation of a font to be used in latex;
 /// since fontspec can load fonts either using names
 /// or paths, we support both options
@@ -393,7 +437,7 @@ impl Default for LatexFontSize {
     }
 }
 
-static PACKAGES_WITHOUT_OPTIONS: [&str; 19] = [
+static PACKAGES_WITHOUT_OPTIONS: [&str; 21] = [
     "amsmath",
     "amssymb",
     "bookmark",
@@ -406,6 +450,7 @@ static PACKAGES_WITHOUT_OPTIONS: [&str; 19] = [
     "longtable",
     "unicode-math",
     "upquote",
+    "verse",
     "xcolor",
     "xurl",
     "fontspec",
@@ -413,8 +458,36 @@ static PACKAGES_WITHOUT_OPTIONS: [&str; 19] = [
     "microtype",
     "hyperref",
     "fmtcount",
+    "alphalph",
 ];
 
+/// The LaTeX to display the value of `counter` in `format`,
+/// for use in redefining commands like `\thechapter`
+pub(crate) fn counter_representation(format: &NumberFormat, counter: &str) -> String {
+    match format {
+        NumberFormat::Words => format!("\\NUMBERstring{{{}}}", counter),
+        NumberFormat::LowercaseWords => format!("\\numberstring{{{}}}", counter),
+        NumberFormat::TitleCaseWords => format!("\\Numberstring{{{}}}", counter),
+        NumberFormat::Ordinal => format!("\\Ordinalstring{{{}}}", counter),
+        NumberFormat::Arabic => format!("\\arabic{{{}}}", counter),
+        NumberFormat::Roman => format!("\\Roman{{{}}}", counter),
+        NumberFormat::LowercaseRoman => format!("\\roman{{{}}}", counter),
+        NumberFormat::Letter => format!("\\AlphAlph{{\\value{{{}}}}}", counter),
+        NumberFormat::Custom(sequence) => {
+            let arabic = format!("\\arabic{{{}}}", counter);
+            let mut representation = format!("\\ifcase\\value{{{}}}{}", counter, arabic);
+            for item in sequence.iter() {
+                representation.push_str("\\or ");
+                representation.push_str(&escape_to_latex(item.as_str()));
+            }
+            representation.push_str("\\else ");
+            representation.push_str(&arabic);
+            representation.push_str("\\fi");
+            representation
+        }
+    }
+}
+
 static PFBREAK_COMMAND: &str = include_str!("resources/preamble_pfbreak.tex");
 static HOUSEKEEPING: &str = include_str!("resources/preamble_housekeeping.tex");
 static NEW_COMMANDS_AND_ENVIRONMENTS: &str = include_str!("resources/preamble_new.tex");
@@ -468,6 +541,9 @@ pub struct PreambleOptions {
     include_toc: bool,
     // custom label for chapters
     chapter_label: Option<Cow<'static, str>>,
+    scene_break: SceneBreak,
+    opening_style: OpeningStyle,
+    transforms: TransformPipeline,
 }
 
 impl PreambleOptions {
@@ -477,6 +553,13 @@ impl PreambleOptions {
         self
     }
 
+    /// Add a transform to apply to the book before rendering,
+    /// after any registered on the `BookSrc` itself
+    pub fn add_transform<T: BookTransform + 'static>(&mut self, transform: T) -> &mut Self {
+        self.transforms.add(transform);
+        self
+    }
+
     /// Set the linespread
     pub fn set_linespread(&mut self, linespread: f32) -> &mut Self {
         self.latex_linespread = linespread;
@@ -543,6 +626,24 @@ impl PreambleOptions {
         }
     }
 
+    /// Set how scene breaks are shown; by default they are a blank line,
+    /// which becomes a dinkus where it falls at the top or bottom of a page.
+    /// An svg ornament is converted to a png
+    pub fn set_scene_break(&mut self, scene_break: SceneBreak) -> Result<&mut Self, String> {
+        self.scene_break = match scene_break {
+            SceneBreak::Ornament(p) => SceneBreak::Ornament(latex_image_path(p)?),
+            other => other,
+        };
+        Ok(self)
+    }
+
+    /// Set how the opening paragraph of each chapter begins,
+    /// as with a drop cap or its first line in small caps
+    pub fn set_opening_style(&mut self, opening_style: OpeningStyle) -> &mut Self {
+        self.opening_style = opening_style;
+        self
+    }
+
     set_font!(set_sans_typeface, sans, doc="Set the sans typeface; this is used in sans text, but is also the fallback typeface for headings, headers and footers, and the titlepage");
     set_font!(set_serif_typeface, serif, doc = "Set the serif typeface");
     set_font!(
@@ -610,6 +711,20 @@ impl PreambleOptions {
         self
     }
 
+    /// Label chapters with numbers in `format`:
+    /// e.g. with `NumberFormat::Ordinal` a first chapter would be labelled
+    /// as `Chapter First`, or just `First` if `only_number_chapters` was called
+    pub fn set_chapter_number_format(&mut self, format: NumberFormat) -> &mut Self {
+        self.header_options.set_chapter_number_format(format);
+        self
+    }
+
+    /// Label parts with numbers in `format`, rather than uppercase roman numerals
+    pub fn set_part_number_format(&mut self, format: NumberFormat) -> &mut Self {
+        self.header_options.set_part_number_format(format);
+        self
+    }
+
     /// Include a table of contents
     pub fn include_toc(&mut self) -> &mut Self {
         self.include_toc = true;
@@ -714,6 +829,9 @@ impl PreambleOptions {
 
     fn get_preamble_packages(&self) -> String {
         let mut packages = String::new();
+        // loaded before hyperref, so that the page numbers of an index link to their pages;
+        // it does nothing unless a book has an index and so calls `\makeindex`
+        packages.push_str("\n\\usepackage[xindy]{imakeidx}");
         for package in PACKAGES_WITHOUT_OPTIONS.iter() {
             let p = format!("\n\\usepackage{{{}}}", package);
             packages.push_str(&p);
@@ -746,6 +864,40 @@ impl PreambleOptions {
         fancy_layout
     }
 
+    /// redefine `\pfbreak` to show the chosen scene break
+    fn get_scene_break_commands(&self) -> String {
+        let display = match self.scene_break {
+            SceneBreak::BlankLine => return String::new(),
+            SceneBreak::Rule => "\\rule[.5ex]{.25\\columnwidth}{.4pt}".into(),
+            SceneBreak::Asterism => {
+                "\\raisebox{-.3ex}{*}\\kern-.15em\\raisebox{.45ex}{*}\\kern-.15em\\raisebox{-.3ex}{*}"
+                    .into()
+            }
+            SceneBreak::Dinkus => "*\\quad*\\quad*".into(),
+            SceneBreak::Glyph(ref glyph) => escape_to_latex(glyph.as_str()),
+            SceneBreak::Ornament(ref p) => {
+                format!("\\includegraphics[height=1.5em]{{{}}}", p.display()).into()
+            }
+        };
+        format!(
+            "\\renewcommand{{\\pfbreakdisplay}}{{{}}}\n\\renewcommand{{\\pfbreak}}{{\\fancypfbreak}}\n",
+            display
+        )
+    }
+
+    /// the packages needed to set the chosen opening style
+    fn get_opening_commands(&self) -> &'static str {
+        match (self.opening_style.drop_cap, self.opening_style.small_caps) {
+            // the text beside a drop cap is otherwise in small caps
+            (Some(_), SmallCapsOpening::None) => {
+                "\\usepackage{lettrine}\n\\renewcommand{\\LettrineTextFont}{\\normalfont}\n"
+            }
+            (Some(_), _) => "\\usepackage{lettrine}\n",
+            (None, SmallCapsOpening::FirstLine) => "\\usepackage{magaz}\n",
+            (None, _) => "",
+        }
+    }
+
     fn generate_latex_preamble(&self) -> String {
         let fixed_len = PATCHES.len()
             + HOUSEKEEPING.len()
@@ -804,6 +956,8 @@ impl PreambleOptions {
         preamble.push('\n');
         preamble.push_str(PFBREAK_COMMAND);
         preamble.push('\n');
+        preamble.push_str(&self.get_scene_break_commands());
+        preamble.push_str(self.get_opening_commands());
         preamble.push_str(NEW_COMMANDS_AND_ENVIRONMENTS);
         preamble.push('\n');
         preamble.push_str(&self.latex_part_format);
@@ -818,30 +972,18 @@ impl PreambleOptions {
 
         preamble.push_str("\\setcounter{tocdepth}{3}\n");
 
-        match self.header_options.chapter_number_format {
-            NumberFormat::Arabic => {}
-            NumberFormat::Roman => {
-                preamble.push_str("\\renewcommand{\\thechapter}{\\Roman{chapter}}");
-            }
-            NumberFormat::Words => {
-                preamble.push_str("\\renewcommand{\\thechapter}{\\NUMBERstring{chapter}}");
-            }
-            NumberFormat::Letter => {
-                preamble.push_str("\\renewcommand{\\thechapter}{\\Alph{chapter}}");
-            }
+        if self.header_options.chapter_number_format != NumberFormat::Arabic {
+            preamble.push_str(&format!(
+                "\\renewcommand{{\\thechapter}}{{{}}}",
+                counter_representation(&self.header_options.chapter_number_format, "chapter")
+            ));
         }
 
-        match self.header_options.part_number_format {
-            NumberFormat::Arabic => {
-                preamble.push_str("\\renewcommand{\\thepart}{\\arabic{part}}");
-            }
-            NumberFormat::Roman => {}
-            NumberFormat::Words => {
-                preamble.push_str("\\renewcommand{\\thepart}{\\NUMBERstring{part}}");
-            }
-            NumberFormat::Letter => {
-                preamble.push_str("\\renewcommand{\\thepart}{\\Alph{part}}");
-            }
+        if self.header_options.part_number_format != NumberFormat::Roman {
+            preamble.push_str(&format!(
+                "\\renewcommand{{\\thepart}}{{{}}}",
+                counter_representation(&self.header_options.part_number_format, "part")
+            ));
         }
 
         if let Some(ref label) = self.chapter_label {
@@ -857,6 +999,11 @@ impl PreambleOptions {
 impl From<PreambleOptions> for OptionsWithRenderedPreamble {
     fn from(src: PreambleOptions) -> Self {
         let preamble = src.generate_latex_preamble();
+        let mut transforms = src.transforms;
+        // the opening style is marked after any other transforms, so that it sees what they leave
+        if !src.opening_style.is_plain() {
+            transforms.add(src.opening_style);
+        }
         OptionsWithRenderedPreamble {
             publisher_imprint_logo: src.publisher_imprint_logo,
             header_format: src.header_options,
@@ -865,6 +1012,7 @@ impl From<PreambleOptions> for OptionsWithRenderedPreamble {
             contributor_identifier: None,
             include_toc: src.include_toc,
             latex_secnumdepth: src.latex_secnumdepth,
+            transforms,
         }
     }
 }
@@ -891,6 +1039,35 @@ impl Default for PreambleOptions {
                 .collect(),
             do_not_suppress_figure_labels: false,
             chapter_label: None,
+            scene_break: SceneBreak::BlankLine,
+            opening_style: OpeningStyle::default(),
+            transforms: TransformPipeline::default(),
         }
     }
 }
+
+/// canonicalize `p`, converting it to a png if it is an svg
+/// and checking it is an image LaTeX can include
+fn latex_image_path(mut p: PathBuf) -> Result<PathBuf, String> {
+    p = p
+        .canonicalize()
+        .map_err(|e| format!("Error canonicalizing path ({}): {}", p.display(), e))?;
+    if p.is_svg() {
+        let svg = std::fs::read_to_string(&p)
+            .map_err(|e| format!("Error reading svg path ({}): {}", p.display(), e))?;
+        let png_path = svg.temp_file_path(Some("bookbinder"), "png");
+        if !png_path.exists() {
+            let png = bookbinder_common::convert_svg_file_to_png(&p, Some(150)).map_err(|e| {
+                format!("Error converting svg file to png ({}): {}", p.display(), e)
+            })?;
+            std::fs::write(&png_path, png)
+                .map_err(|e| format!("Error writing new png path ({}): {}", p.display(), e))?;
+        }
+        p = png_path;
+    }
+    if p.is_latex_supported_image() {
+        Ok(p)
+    } else {
+        Err(format!("Not an image LaTeX can include: {}", p.display()))
+    }
+}
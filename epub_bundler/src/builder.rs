@@ -19,6 +19,8 @@ static OPF_PATH: &str = "document.opf";
 static COVER_IMAGE_ID: &str = "cover_image";
 static CSS_ID: &str = "base_css";
 static NAV_ID: &str = "mainnav";
+// names the namespace of the uuids derived for epubs with no identifier of their own
+static URN_NAMESPACE: &str = "https://github.com/fizzbucket/bookbinder";
 
 #[derive(Debug)]
 pub enum EpubBundlingError {
@@ -287,8 +289,8 @@ impl<'a> ManifestItem<'a> {
         }
     }
 
-    fn from_epub_resource(src: &'a EpubResource) -> Self {
-        let id = format!("resource_{}", Uuid::new_v4().to_simple());
+    fn from_epub_resource(src: &'a EpubResource, i: usize) -> Self {
+        let id = format!("resource_{}", i);
         ManifestItem {
             href: src.output_path.to_str().unwrap(),
             id: Cow::Owned(id),
@@ -319,6 +321,8 @@ impl<'a> ManifestItem<'a> {
 // representation of an abstract epub container
 struct Container<'a> {
     files: Vec<(&'a Path, &'a [u8])>,
+    // the time at which every file is marked as last modified
+    modified: zip::DateTime,
 }
 
 impl<'a> Container<'a> {
@@ -329,9 +333,8 @@ impl<'a> Container<'a> {
         let buf = Vec::new();
         let w = Cursor::new(buf);
         let mut zipper = ZipWriter::new(w);
-        let uncompressed =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-        let compressed = zip::write::FileOptions::default();
+        let compressed = zip::write::FileOptions::default().last_modified_time(self.modified);
+        let uncompressed = compressed.compression_method(zip::CompressionMethod::Stored);
 
         // first add an uncompressed mimetype file so this can be recognised
         zipper.start_file("mimetype", uncompressed)?;
@@ -440,6 +443,38 @@ pub(crate) trait EpubBundler {
     fn generate_opf(&self) -> Result<String, EpubBundlingError>;
 }
 
+impl EpubSource {
+    /// the date on which this epub was last modified: that set,
+    /// or otherwise the time given by `SOURCE_DATE_EPOCH` or, failing that, now
+    fn get_modification_date(&self) -> time::Tm {
+        self.last_modification.unwrap_or_else(|| {
+            bookbinder_common::epoch_to_tm(bookbinder_common::get_source_date_epoch())
+        })
+    }
+
+    /// a urn to identify an epub with no identifier of its own;
+    /// this is a name-based (version 5) uuid derived from its titles and creators,
+    /// so that the same book is identified in the same way each time it is bundled
+    fn derive_urn(&self) -> String {
+        let namespace = Uuid::new_v5(&Uuid::NAMESPACE_URL, URN_NAMESPACE.as_bytes());
+        // titles and creators are separated by control characters, which neither contains
+        let titles = self
+            .title
+            .iter()
+            .map(|t| t.text.as_ref())
+            .collect::<Vec<_>>();
+        let creators = self
+            .creators
+            .iter()
+            .map(|c| c.name.as_ref())
+            .collect::<Vec<_>>();
+        let name = format!("{}\u{1e}{}", titles.join("\u{1f}"), creators.join("\u{1f}"));
+        Uuid::new_v5(&namespace, name.as_bytes())
+            .to_urn()
+            .to_string()
+    }
+}
+
 impl EpubBundler for EpubSource {
     fn bundle_epub(&self) -> Result<Vec<u8>, EpubBundlingError> {
         let mut files = Vec::new();
@@ -469,7 +504,17 @@ impl EpubBundler for EpubSource {
             files.push((&css.output_path, &css.data));
         }
 
-        let container = Container { files };
+        let modified = self.get_modification_date();
+        let modified = zip::DateTime::from_date_and_time(
+            (modified.tm_year + 1900) as u16,
+            (modified.tm_mon + 1) as u8,
+            modified.tm_mday as u8,
+            modified.tm_hour as u8,
+            modified.tm_min as u8,
+            modified.tm_sec as u8,
+        )
+        .unwrap_or_default();
+        let container = Container { files, modified };
 
         let epub = container.to_epub()?;
         Ok(epub)
@@ -511,8 +556,7 @@ impl EpubBundler for EpubSource {
                 groupings.push(grouped);
             }
             None => {
-                let u = Uuid::new_v4();
-                let urn = u.to_urn().to_string();
+                let urn = self.derive_urn();
                 let base = DcMetadata {
                     core: DublinCoreElement::Identifier,
                     id: Some(Cow::Borrowed(IDENTIFIER_ID)),
@@ -548,17 +592,8 @@ impl EpubBundler for EpubSource {
             }
         }
 
-        match self.last_modification {
-            Some(ref m) => {
-                let tag = MetaMetadata::from_last_modified_date(m);
-                meta.push(tag);
-            }
-            None => {
-                let now = time::now_utc();
-                let tag = MetaMetadata::from_last_modified_date(&now);
-                meta.push(tag);
-            }
-        }
+        let modified = self.get_modification_date();
+        meta.push(MetaMetadata::from_last_modified_date(&modified));
 
         let mut dc = Vec::new();
         for mut group in groupings.into_iter() {
@@ -574,8 +609,8 @@ impl EpubBundler for EpubSource {
         for (i, item) in self.contents.iter().enumerate() {
             items.push(ManifestItem::from_epub_content(item, i));
         }
-        for item in self.resources.iter() {
-            items.push(ManifestItem::from_epub_resource(item));
+        for (i, item) in self.resources.iter().enumerate() {
+            items.push(ManifestItem::from_epub_resource(item, i));
         }
         if let Some(ref item) = self.cover_image {
             items.push(ManifestItem::from_cover_image(item));
@@ -957,6 +992,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_reproducible_bundles() -> Result<(), Box<dyn Error>> {
+        let bundle = |title: &str| -> Result<(Vec<u8>, String), Box<dyn Error>> {
+            let mut epub = EpubSource::new();
+            epub.set_title(title)?;
+            epub.add_author("Some Guy")?;
+            epub.add_content(EpubContent::new("Hello world"))?;
+            epub.add_resource(EpubResource {
+                output_path: PathBuf::from("image.png"),
+                data: vec![0, 1, 2],
+                mimetype: MimeType::Png,
+            })?;
+            epub.set_modification_date(bookbinder_common::epoch_to_tm(1577836800))?;
+            Ok((epub.bundle()?, epub.derive_urn()))
+        };
+
+        let (first, urn) = bundle("The Everything Book")?;
+        let (second, same_urn) = bundle("The Everything Book")?;
+        let (_, other_urn) = bundle("The Nothing Book")?;
+        assert_eq!(first, second);
+        assert_eq!(urn, same_urn);
+        assert_ne!(urn, other_urn);
+        // the uuid is derived from the book alone, and so never changes
+        assert_eq!(urn, "urn:uuid:d8f7e115-bfbb-5cfe-b182-dbdc2f0f3f6d");
+        Ok(())
+    }
+
     #[test]
     fn test_toc_stuff() {
         let mut toc_entries = TEManager::new();
@@ -423,6 +423,7 @@ impl EpubSource {
     add_marc_contributor!(doc = "Add an author", add_author, MarcRelator::Aut);
     add_marc_contributor!(add_editor, MarcRelator::Edt);
     add_marc_contributor!(add_translator, MarcRelator::Trl);
+    add_marc_contributor!(add_contributor, MarcRelator::Ctb);
     add_onix_contributor!(add_author_of_foreword, OnixContributorCode::A23);
     add_onix_contributor!(add_author_of_introduction, OnixContributorCode::A23);
     add_onix_contributor!(add_author_of_afterword, OnixContributorCode::A19);
@@ -488,7 +489,27 @@ impl EpubSource {
     }
 
     /// Add a content document
-    pub fn add_content(&mut self, r: EpubContent) -> Result<&mut Self, &'static str> {
+    pub fn add_content(&mut self, mut r: EpubContent) -> Result<&mut Self, &'static str> {
+        // content is named after its hash, so identical documents
+        // (such as the same empty divider page in each volume of an omnibus)
+        // need to be given distinct paths
+        let is_taken = |contents: &[EpubContent], p: &Path| {
+            contents.iter().any(|c| c.output_path.as_path() == p)
+        };
+        if is_taken(&self.contents, &r.output_path) {
+            let stem = r
+                .output_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let mut n = 1;
+            let mut candidate = PathBuf::from(format!("{}_{}.xhtml", stem, n));
+            while is_taken(&self.contents, &candidate) {
+                n += 1;
+                candidate = PathBuf::from(format!("{}_{}.xhtml", stem, n));
+            }
+            r.output_path = candidate;
+        }
         self.contents.push(r);
         Ok(self)
     }
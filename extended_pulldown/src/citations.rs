@@ -0,0 +1,107 @@
+use crate::serde_support::{cowstr, optional_cowstr};
+use crate::{Event, MakeStatic};
+use lazy_static::lazy_static;
+use pulldown_cmark::CowStr;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// what comes between the brackets of `[@key]`, `[@key, p. 4]` or `[@key; @other, ch. 2]`
+static CITATION: &str =
+    r#"^\s*@[^\s,;@\[\]]+(?:,[^;@\[\]]*)?(?:;\s*@[^\s,;@\[\]]+(?:,[^;@\[\]]*)?)*$"#;
+
+lazy_static! {
+    pub(crate) static ref REGGIE: Regex = Regex::new(CITATION).unwrap();
+}
+
+/// A single work cited by its key, with an optional locator within it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationItem<'a> {
+    /// The key of the work in the bibliography
+    #[serde(with = "cowstr")]
+    pub key: CowStr<'a>,
+    /// Where in the work is cited, such as `p. 4`
+    #[serde(with = "optional_cowstr")]
+    pub locator: Option<CowStr<'a>>,
+}
+
+impl MakeStatic for CitationItem<'_> {
+    type AsStatic = CitationItem<'static>;
+    fn into_static(self) -> Self::AsStatic {
+        CitationItem {
+            key: self.key.into_static(),
+            locator: self.locator.map(|l| l.into_static()),
+        }
+    }
+}
+
+/// The markup which gives a citation of `items`
+pub(crate) fn to_markup(items: &[CitationItem<'_>]) -> String {
+    let items = items
+        .iter()
+        .map(|item| match item.locator {
+            Some(ref locator) => format!("@{}, {}", item.key, locator),
+            None => format!("@{}", item.key),
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", items.join("; "))
+}
+
+fn parse_str<'a>(text: &'a str) -> Vec<CitationItem<'a>> {
+    text.split(';')
+        .map(|item| {
+            let item = item.trim().trim_start_matches('@');
+            let mut parts = item.splitn(2, ',');
+            let key = parts.next().unwrap().trim().into();
+            let locator = parts
+                .next()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(CowStr::from);
+            CitationItem { key, locator }
+        })
+        .collect()
+}
+
+/// The citation given by text which matches `REGGIE`,
+/// having been found between square brackets
+pub(crate) fn to_event(text: CowStr<'_>) -> Event<'_> {
+    match text {
+        CowStr::Borrowed(b) => Event::Citation(parse_str(b)),
+        other => Event::Citation(
+            parse_str(&other)
+                .into_iter()
+                .map(|i| i.into_static())
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn citations() {
+        assert!(REGGIE.is_match("@smith2004"));
+        assert!(REGGIE.is_match("@smith2004, p. 4; @jones:99"));
+        assert!(!REGGIE.is_match("see @smith2004"));
+        assert!(!REGGIE.is_match("an email@example.com"));
+
+        let event = to_event("@smith2004, p. 4; @jones:99".into());
+        let expected = Event::Citation(vec![
+            CitationItem {
+                key: "smith2004".into(),
+                locator: Some("p. 4".into()),
+            },
+            CitationItem {
+                key: "jones:99".into(),
+                locator: None,
+            },
+        ]);
+        assert_eq!(event, expected);
+
+        if let Event::Citation(ref items) = expected {
+            assert_eq!(to_markup(items), "[@smith2004, p. 4; @jones:99]");
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::Event;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+// a label is made of letters, numbers and `:`, `.`, `-` or `_`
+static LABEL: &str = r#"[[:alnum:]:._\-]+"#;
+
+lazy_static! {
+    // `{#label}` to label, `{@label}` or `{@page:label}` to refer
+    pub(crate) static ref REGGIE: Regex = Regex::new(&format!(r#"\{{([#@])({})\}}"#, LABEL)).unwrap();
+    static ref LABEL_REGGIE: Regex = Regex::new(&format!("^{}$", LABEL)).unwrap();
+    // anything written as a label or reference, whether or not it is a valid one
+    static ref MARKUP_REGGIE: Regex = Regex::new(r#"\{([#@])([^{}]*)\}"#).unwrap();
+}
+
+/// Whether `label` can be used to label something, as in `{#label}`, and so be referred to;
+/// labels may contain letters, numbers and `:`, `.`, `-` or `_`
+pub fn is_label(label: &str) -> bool {
+    LABEL_REGGIE.is_match(label)
+}
+
+/// The labels written as labels or references in `text` which cannot be labels,
+/// and so are left in it as plain text rather than being marked;
+/// a page reference is given without its `page:`
+pub fn malformed_labels(text: &str) -> impl Iterator<Item = &str> {
+    MARKUP_REGGIE
+        .captures_iter(text)
+        .map(|caps| {
+            let label = caps.get(2).unwrap().as_str();
+            match &caps[1] {
+                "@" => label.strip_prefix("page:").unwrap_or(label),
+                _ => label,
+            }
+        })
+        .filter(|label| !is_label(label))
+}
+
+/// The label or reference marked by a match of `REGGIE`
+pub(crate) fn to_event<'a>(caps: &Captures<'a>) -> Event<'a> {
+    let label = caps.get(2).unwrap().as_str();
+    match &caps[1] {
+        "#" => Event::Label(label.into()),
+        _ => match label.strip_prefix("page:") {
+            Some(label) => Event::PageReference(label.into()),
+            None => Event::Reference(label.into()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markers::split_markers;
+
+    #[test]
+    fn labels_and_references() {
+        let split = split_markers("A Map {#fig:map}".into(), &REGGIE, to_event);
+        assert_eq!(
+            split,
+            vec![Event::Text("A Map".into()), Event::Label("fig:map".into())]
+        );
+
+        let split = split_markers(
+            "see Chapter {@ch-7}, {@page:ch-7}.".into(),
+            &REGGIE,
+            to_event,
+        );
+        assert_eq!(
+            split,
+            vec![
+                Event::Text("see Chapter ".into()),
+                Event::Reference("ch-7".into()),
+                Event::Text(", ".into()),
+                Event::PageReference("ch-7".into()),
+                Event::Text(".".into())
+            ]
+        );
+
+        assert!(!REGGIE.is_match("{not a label} {#with space}"));
+    }
+
+    #[test]
+    fn label_grammar() {
+        for label in ["ch:one", "fig.2", "ch-7", "snake_case"] {
+            assert!(is_label(label));
+            assert!(REGGIE.is_match(&format!("{{#{}}}", label)));
+            assert!(REGGIE.is_match(&format!("{{@page:{}}}", label)));
+        }
+        for label in ["", "with space", "ch:one}", "é"] {
+            assert!(!is_label(label));
+        }
+
+        let malformed = malformed_labels("{#ok} {#no way} {@page:é} {@page:fine} {plain}");
+        assert_eq!(malformed.collect::<Vec<_>>(), ["no way", "é"]);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{Event, Tag};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// a name in capitals at the start of a paragraph, followed by `.` or `:`
+static SPEAKER: &str = r#"^(\p{Lu}(?:[\p{Lu}\d '’.\-]*[\p{Lu}\d])?)([.:])(?:\s+|$)"#;
+// the slugline of a screenplay, such as `INT. KITCHEN -- NIGHT`
+static SCENE_HEADING: &str = r#"^(?:INT\.|EXT\.|INT\./EXT\.|EXT\./INT\.|I/E\.?)\s"#;
+
+lazy_static! {
+    static ref SPEAKER_REGGIE: Regex = Regex::new(SPEAKER).unwrap();
+    static ref SCENE_HEADING_REGGIE: Regex = Regex::new(SCENE_HEADING).unwrap();
+}
+
+/// whether the html class `class` marks a div whose contents are drama
+pub(crate) fn is_drama_class(class: &str) -> bool {
+    class.split_whitespace().any(|c| c == "drama")
+}
+
+/// The speakers named in `list`, the dramatis personae given to a drama div
+/// as `data-personae="HAMLET, HORATIO"`
+pub(crate) fn personae(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// the speaker who opens a paragraph of drama beginning with the text `leading`,
+/// followed by the event `next`, along with the rest of `leading` after their name.
+///
+/// A name in capitals is taken as a speaker's when it is followed by a colon,
+/// stands alone on the first line of the paragraph,
+/// or is in `personae` and followed by a full stop;
+/// otherwise a sentence such as `OK. Let's go.` or a heading such as `ACT II.` would open a speech
+fn speaker<'l>(
+    leading: &'l str,
+    next: Option<&Event<'_>>,
+    personae: &[String],
+) -> Option<(String, &'l str)> {
+    let captures = SPEAKER_REGGIE.captures(leading)?;
+    let name = &captures[1];
+    let rest = &leading[captures[0].len()..];
+    let alone = rest.is_empty() && matches!(next, Some(Event::SoftBreak) | Some(Event::HardBreak));
+    if &captures[2] == ":" || alone || personae.iter().any(|p| p == name) {
+        Some((name.to_string(), rest))
+    } else {
+        None
+    }
+}
+
+/// `events`, with any text in parentheses marked as a parenthetical direction
+fn mark_parentheticals(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut marked = Vec::with_capacity(events.len());
+    for event in events.into_iter() {
+        let text = match event {
+            Event::Text(text) if text.contains('(') => text,
+            event => {
+                marked.push(event);
+                continue;
+            }
+        };
+        let mut rest: &str = &text;
+        while let Some(open) = rest.find('(') {
+            let close = match rest[open..].find(')') {
+                Some(close) => open + close + 1,
+                None => break,
+            };
+            if open > 0 {
+                marked.push(Event::Text(rest[..open].to_string().into()));
+            }
+            marked.push(Event::Start(Tag::Parenthetical));
+            marked.push(Event::Text(rest[open..close].to_string().into()));
+            marked.push(Event::End(Tag::Parenthetical));
+            rest = &rest[close..];
+        }
+        if !rest.is_empty() {
+            marked.push(Event::Text(rest.to_string().into()));
+        }
+    }
+    marked
+}
+
+/// The events of a paragraph of drama, whose contents are `events`:
+/// a scene heading, a stage direction wholly in parentheses or brackets,
+/// a speech beginning with the name of its speaker, or otherwise a plain paragraph;
+/// `personae` are the speakers named in the dramatis personae of the drama
+pub(crate) fn arrange_paragraph<'a>(
+    mut events: Vec<Event<'a>>,
+    personae: &[String],
+) -> Vec<Event<'a>> {
+    let leading = match events.first() {
+        Some(Event::Text(text)) => text.to_string(),
+        _ => String::new(),
+    };
+    let trailing = match events.last() {
+        Some(Event::Text(text)) => text.trim_end().ends_with([')', ']']),
+        _ => false,
+    };
+
+    let tag = if SCENE_HEADING_REGGIE.is_match(&leading) {
+        Tag::SceneHeading
+    } else if leading.starts_with(['(', '[']) && trailing {
+        Tag::StageDirection
+    } else if let Some((speaker, rest)) = speaker(&leading, events.get(1), personae) {
+        if rest.is_empty() {
+            events.remove(0);
+            // a name alone on its line is followed by a break
+            if let Some(Event::SoftBreak) | Some(Event::HardBreak) = events.first() {
+                events.remove(0);
+            }
+        } else {
+            events[0] = Event::Text(rest.to_string().into());
+        }
+        events = mark_parentheticals(events);
+        Tag::Speech(speaker.into())
+    } else {
+        Tag::Paragraph
+    };
+
+    let mut arranged = Vec::with_capacity(events.len() + 2);
+    arranged.push(Event::Start(tag.clone()));
+    arranged.append(&mut events);
+    arranged.push(Event::End(tag));
+    arranged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arrange(text: &str) -> Vec<Event<'_>> {
+        arrange_paragraph(vec![Event::Text(text.into())], &[])
+    }
+
+    #[test]
+    fn speakers_directions_and_scene_headings() {
+        let personae = personae("HAMLET, MRS. HALE,");
+        assert_eq!(personae, vec!["HAMLET", "MRS. HALE"]);
+        let speaker = |events| match arrange_paragraph(events, &personae).remove(0) {
+            Event::Start(Tag::Speech(speaker)) => Some(speaker.to_string()),
+            _ => None,
+        };
+        let text = |text| vec![Event::Text(text)];
+        assert_eq!(
+            speaker(text("MRS. HALE. Well, I never.".into())),
+            Some("MRS. HALE".into())
+        );
+        assert_eq!(
+            speaker(text("FIRST WITCH: When shall we three meet again?".into())),
+            Some("FIRST WITCH".into())
+        );
+        assert_eq!(speaker(text("A: Shall we go?".into())), Some("A".into()));
+        assert_eq!(speaker(text("HAMLET.".into())), Some("HAMLET".into()));
+        assert_eq!(
+            speaker(vec![
+                Event::Text("HORATIO.".into()),
+                Event::SoftBreak,
+                Event::Text("Friends to this ground.".into()),
+            ]),
+            Some("HORATIO".into())
+        );
+        assert_eq!(
+            arrange_paragraph(
+                vec![
+                    Event::Text("HORATIO.".into()),
+                    Event::SoftBreak,
+                    Event::Text("Friends.".into()),
+                ],
+                &[]
+            ),
+            vec![
+                Event::Start(Tag::Speech("HORATIO".into())),
+                Event::Text("Friends.".into()),
+                Event::End(Tag::Speech("HORATIO".into())),
+            ]
+        );
+
+        // capitals followed by a full stop are only a speaker if in the dramatis personae
+        assert_eq!(speaker(text("OK. Let's go.".into())), None);
+        assert_eq!(speaker(text("ACT II. The Same.".into())), None);
+        assert_eq!(speaker(text("HORATIO. Friends.".into())), None);
+        assert_eq!(speaker(text("I. The first".into())), None);
+        assert_eq!(speaker(text("Hamlet. Not a speaker".into())), None);
+        assert_eq!(speaker(text("Hamlet: Not a speaker".into())), None);
+
+        assert_eq!(
+            arrange("INT. KITCHEN -- NIGHT")[0],
+            Event::Start(Tag::SceneHeading)
+        );
+        assert_eq!(
+            arrange("[They fight.]")[0],
+            Event::Start(Tag::StageDirection)
+        );
+        assert_eq!(arrange("(Exit) and more")[0], Event::Start(Tag::Paragraph));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::serde_support::{cowstr, cowstrs};
+use crate::MakeStatic;
+use lazy_static::lazy_static;
+use pulldown_cmark::CowStr;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+// `{^term}`, `{^term!subentry}`, `{^term|see other}` or `{^term|see also other}`
+static INDEX_ENTRY: &str = r#"\{\^([^{}|]+)(?:\|\s*see( also)?\s+([^{}|]+))?\}"#;
+
+lazy_static! {
+    pub(crate) static ref REGGIE: Regex = Regex::new(INDEX_ENTRY).unwrap();
+}
+
+/// A term to be listed in a back-of-book index, marking the place where it appears
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry<'a> {
+    /// The term, followed by any subentries beneath it, from the most general to the most specific
+    #[serde(with = "cowstrs")]
+    pub terms: Vec<CowStr<'a>>,
+    /// Another term to be consulted instead of, or as well as, this one
+    pub cross_reference: Option<IndexCrossReference<'a>>,
+}
+
+/// A pointer from one index entry to another;
+/// an entry with a cross-reference marks no location of its own
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IndexCrossReference<'a> {
+    /// The term is indexed only under another term
+    See(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A related term is indexed as well
+    SeeAlso(#[serde(with = "cowstr")] CowStr<'a>),
+}
+
+impl IndexEntry<'_> {
+    /// The markup which gives this entry
+    pub(crate) fn to_markup(&self) -> String {
+        let mut markup = String::from("{^");
+        markup.push_str(&self.terms.join("!"));
+        match self.cross_reference {
+            Some(IndexCrossReference::See(ref other)) => {
+                markup.push_str("|see ");
+                markup.push_str(other);
+            }
+            Some(IndexCrossReference::SeeAlso(ref other)) => {
+                markup.push_str("|see also ");
+                markup.push_str(other);
+            }
+            None => {}
+        }
+        markup.push('}');
+        markup
+    }
+}
+
+impl MakeStatic for IndexEntry<'_> {
+    type AsStatic = IndexEntry<'static>;
+    fn into_static(self) -> Self::AsStatic {
+        IndexEntry {
+            terms: self.terms.into_iter().map(|t| t.into_static()).collect(),
+            cross_reference: self.cross_reference.map(|c| match c {
+                IndexCrossReference::See(other) => IndexCrossReference::See(other.into_static()),
+                IndexCrossReference::SeeAlso(other) => {
+                    IndexCrossReference::SeeAlso(other.into_static())
+                }
+            }),
+        }
+    }
+}
+
+/// The index entry marked by a match of `REGGIE`
+pub(crate) fn to_event<'a>(caps: &Captures<'a>) -> crate::Event<'a> {
+    let terms = caps
+        .get(1)
+        .unwrap()
+        .as_str()
+        .split('!')
+        .map(|term| term.trim().into())
+        .collect();
+    let cross_reference = caps.get(3).map(|other| {
+        let other = other.as_str().trim().into();
+        if caps.get(2).is_some() {
+            IndexCrossReference::SeeAlso(other)
+        } else {
+            IndexCrossReference::See(other)
+        }
+    });
+    crate::Event::IndexEntry(IndexEntry {
+        terms,
+        cross_reference,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markers::split_markers;
+    use crate::Event;
+
+    #[test]
+    fn index_entries() {
+        let split = split_markers(
+            "Plants {^plants!flowering} and {^flora|see plants}{^trees|see also plants}".into(),
+            &REGGIE,
+            to_event,
+        );
+        let expected = vec![
+            Event::Text("Plants".into()),
+            Event::IndexEntry(IndexEntry {
+                terms: vec!["plants".into(), "flowering".into()],
+                cross_reference: None,
+            }),
+            Event::Text(" and".into()),
+            Event::IndexEntry(IndexEntry {
+                terms: vec!["flora".into()],
+                cross_reference: Some(IndexCrossReference::See("plants".into())),
+            }),
+            Event::IndexEntry(IndexEntry {
+                terms: vec!["trees".into()],
+                cross_reference: Some(IndexCrossReference::SeeAlso("plants".into())),
+            }),
+        ];
+        assert_eq!(split, expected);
+
+        if let Event::IndexEntry(ref entry) = expected[3] {
+            assert_eq!(entry.to_markup(), "{^flora|see plants}");
+        }
+    }
+}
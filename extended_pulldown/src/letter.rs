@@ -0,0 +1,198 @@
+use crate::{Event, Tag};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// `P.S.`, `PS`, `P.P.S.` and the like at the start of a postscript
+static POSTSCRIPT: &str = r#"^(?:P\.?\s?)+S[.:]?(?:\s|$)"#;
+
+lazy_static! {
+    static ref POSTSCRIPT_REGGIE: Regex = Regex::new(POSTSCRIPT).unwrap();
+}
+
+/// the most words a salutation, such as `My dear Mina,`, can have
+const SALUTATION_WORDS: usize = 10;
+/// the most words the close of a letter, such as `Yours ever,`, can have
+const CLOSING_WORDS: usize = 6;
+
+/// whether the html class `class` marks a div whose contents are a letter or document
+pub(crate) fn is_letter_class(class: &str) -> bool {
+    class
+        .split_whitespace()
+        .any(|c| c == "letter" || c == "document")
+}
+
+/// `events`, divided into the blocks at their top level
+fn split_blocks(events: Vec<Event<'_>>) -> Vec<Vec<Event<'_>>> {
+    let mut blocks = Vec::new();
+    let mut block = Vec::new();
+    let mut depth = 0;
+    for event in events.into_iter() {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        block.push(event);
+        if depth == 0 {
+            blocks.push(std::mem::take(&mut block));
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// the text of `block`, if it is a paragraph
+fn paragraph_text(block: &[Event<'_>]) -> Option<String> {
+    if let Some(Event::Start(Tag::Paragraph)) = block.first() {
+        let text = block
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+        Some(text.trim().to_string())
+    } else {
+        None
+    }
+}
+
+/// whether `text` is short enough, and ends as, a salutation or the close of a letter might
+fn is_address(text: &str, max_words: usize, endings: &[char]) -> bool {
+    text.split_whitespace().count() <= max_words && text.ends_with(endings)
+}
+
+/// The events of a letter or other document, whose contents are `events`,
+/// with the paragraphs of its dateline, salutation, signature and postscript marked as such.
+///
+/// The salutation is the first of the opening paragraphs which is short and ends with `,` or `:`,
+/// unless the one after is too, as with the lines of an address,
+/// and any paragraphs before it are the dateline.
+/// A postscript begins with `P.S.` or the like.
+/// The signature begins with a short paragraph ending with `,`, such as `Yours ever,`,
+/// among the last three before any postscript, or is the last of them if it begins with a dash;
+/// a dash earlier on more likely begins a line of dialogue.
+pub(crate) fn arrange_letter(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut blocks = split_blocks(events);
+    let texts = blocks
+        .iter()
+        .map(|block| paragraph_text(block))
+        .collect::<Vec<_>>();
+
+    let is_salutation = |i: usize| {
+        texts
+            .get(i)
+            .and_then(Option::as_deref)
+            .is_some_and(|text| is_address(text, SALUTATION_WORDS, &[',', ':']))
+    };
+    let salutation = (0..texts.len().min(4))
+        .take_while(|i| texts[*i].is_some())
+        .find(|i| is_salutation(*i) && !is_salutation(i + 1));
+    let body = salutation.map_or(0, |i| i + 1);
+
+    let postscript = (body..texts.len())
+        .find(|i| {
+            texts[*i]
+                .as_deref()
+                .is_some_and(|text| POSTSCRIPT_REGGIE.is_match(text))
+        })
+        .unwrap_or(texts.len());
+    let signature = (postscript.saturating_sub(3).max(body)..postscript)
+        .find(|i| {
+            texts[*i].as_deref().is_some_and(|text| {
+                is_address(text, CLOSING_WORDS, &[','])
+                    || (*i + 1 == postscript && text.starts_with(['—', '–', '-']))
+            })
+        })
+        .unwrap_or(postscript);
+
+    for (i, block) in blocks.iter_mut().enumerate() {
+        if texts[i].is_none() {
+            continue;
+        }
+        let tag = match i {
+            i if Some(i) == salutation => Tag::Salutation,
+            i if i < body => Tag::Dateline,
+            i if i >= postscript => Tag::Postscript,
+            i if i >= signature => Tag::Signature,
+            _ => continue,
+        };
+        if let Some(first) = block.first_mut() {
+            *first = Event::Start(tag.clone());
+        }
+        if let Some(last) = block.last_mut() {
+            *last = Event::End(tag);
+        }
+    }
+    blocks.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(text: &str) -> Vec<Event<'_>> {
+        vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text(text.into()),
+            Event::End(Tag::Paragraph),
+        ]
+    }
+
+    fn arrange(paragraphs: &[&'static str]) -> Vec<Tag<'static>> {
+        let events = paragraphs.iter().flat_map(|p| paragraph(p)).collect();
+        arrange_letter(events)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Start(tag) => Some(tag),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parts_of_letters() {
+        assert_eq!(
+            arrange(&[
+                "Castle Dracula,",
+                "Dear Mina,",
+                "I write in haste, for the Count returns tonight.",
+                "Yours ever,",
+                "Jonathan",
+                "P.S. Burn this."
+            ]),
+            vec![
+                Tag::Dateline,
+                Tag::Salutation,
+                Tag::Paragraph,
+                Tag::Signature,
+                Tag::Signature,
+                Tag::Postscript
+            ]
+        );
+        assert_eq!(
+            arrange(&["3 May. Bistritz.", "Left Munich at 8:35 P.M.", "— J. H."]),
+            vec![Tag::Paragraph, Tag::Paragraph, Tag::Signature]
+        );
+        assert_eq!(
+            arrange(&[
+                "Dear Mina,",
+                "— Who is it? I asked.",
+                "— A friend, he said.",
+                "And so he left us."
+            ]),
+            vec![
+                Tag::Salutation,
+                Tag::Paragraph,
+                Tag::Paragraph,
+                Tag::Paragraph
+            ]
+        );
+        assert_eq!(
+            arrange(&["Sir:", "Please send help.", "PS: Soon."]),
+            vec![Tag::Salutation, Tag::Paragraph, Tag::Postscript]
+        );
+    }
+}
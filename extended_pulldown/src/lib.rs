@@ -14,6 +14,14 @@
 //!    * Subscript
 //!    * Superscript
 //!
+//!  - allow labels and cross-references to them, as `{#label}`, `{@label}` and `{@page:label}`.
+//!  - allow marking terms for a back-of-book index, as `{^term}`.
+//!  - allow citing works from a bibliography by key, as `[@key]` or `[@key, p. 4]`.
+//!  - allow marking content as meant for only one output format, as `<span class="pdf-only">` or `<div class="epub-only">`.
+//!  - allow verse, keeping its stanzas and the indentation of its lines, in a fenced block marked `verse`.
+//!  - allow drama, with speeches, stage directions and scene headings, within `<div class="drama">`.
+//!  - allow letters and other documents, with datelines, salutations, signatures and postscripts, within `<div class="letter">`.
+//!
 //! It also provides a function, `flatten_footnotes`,
 //! which replaces footnote references and definitions with a
 //! single group of tagged text; this allows
@@ -80,6 +88,252 @@
 //! to allow wrapping a span of alphanumeric text in `^` to indicate superscript
 //! and in `~` to indicate subscript: `25^th^ July`, `H~2~O`.
 //!
+//! ## Cross-references
+//!
+//! A label can be given to a heading, a figure caption or a place in the text,
+//! and referred to elsewhere either for its number or its page.
+//! Labels may contain letters, numbers and `:`, `.`, `-` or `_`; see `is_label`.
+//!
+//! ```
+//! use extended_pulldown::Parser;
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = "# The Beginning {#ch:start}\n\nAs in Chapter {@ch:start}, {@page:ch:start}...";
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(Heading(1)),
+//!     Text("The Beginning".into()),
+//!     Label("ch:start".into()),
+//!     End(Heading(1)),
+//!     Start(Paragraph),
+//!     Text("As in Chapter ".into()),
+//!     Reference("ch:start".into()),
+//!     Text(", ".into()),
+//!     PageReference("ch:start".into()),
+//!     Text("…".into()),
+//!     End(Paragraph),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
+//! ## Index entries
+//!
+//! A term can be marked for the index at the place it appears.
+//! Subentries follow the term they belong to after a `!`,
+//! and an entry can point elsewhere with `|see` or `|see also`.
+//!
+//! ```
+//! use extended_pulldown::{IndexCrossReference, IndexEntry, Parser};
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = "Roses{^plants!roses} are not trees{^shrubs|see also trees}.";
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(Paragraph),
+//!     Text("Roses".into()),
+//!     IndexEntry(extended_pulldown::IndexEntry {
+//!         terms: vec!["plants".into(), "roses".into()],
+//!         cross_reference: None
+//!     }),
+//!     Text(" are not trees".into()),
+//!     IndexEntry(extended_pulldown::IndexEntry {
+//!         terms: vec!["shrubs".into()],
+//!         cross_reference: Some(IndexCrossReference::SeeAlso("trees".into()))
+//!     }),
+//!     Text(".".into()),
+//!     End(Paragraph),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
+//! ## Citations
+//!
+//! A work in a bibliography can be cited by its key within square brackets,
+//! optionally followed after a comma by a locator such as a page;
+//! several works cited together are separated by `;`.
+//!
+//! ```
+//! use extended_pulldown::{CitationItem, Parser};
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = "As argued [@smith2004, p. 4; @jones1999].";
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(Paragraph),
+//!     Text("As argued ".into()),
+//!     Citation(vec![
+//!         CitationItem {
+//!             key: "smith2004".into(),
+//!             locator: Some("p. 4".into())
+//!         },
+//!         CitationItem {
+//!             key: "jones1999".into(),
+//!             locator: None
+//!         },
+//!     ]),
+//!     Text(".".into()),
+//!     End(Paragraph),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
+//! ## Format-only content
+//!
+//! Text which only makes sense in one output format can be wrapped in a span or div
+//! whose class names that format followed by `-only`: `epub-only`, `pdf-only` or `latex-only`.
+//! Any other class ending in `-only` is left as plain html.
+//! A div encloses markdown set apart from it by blank lines.
+//! The content is kept, tagged with the name of its format, for a renderer to drop or keep.
+//!
+//! ```
+//! use extended_pulldown::Parser;
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = concat!(
+//!     r#"See <span class="pdf-only">page 32</span><span class="epub-only">the next chapter</span>."#,
+//!     "\n\n<div class=\"epub-only\">\n\nTap a footnote number to read the note.\n\n</div>\n"
+//! );
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(Paragraph),
+//!     Text("See ".into()),
+//!     Start(FormatOnly("pdf".into())),
+//!     Text("page 32".into()),
+//!     End(FormatOnly("pdf".into())),
+//!     Start(FormatOnly("epub".into())),
+//!     Text("the next chapter".into()),
+//!     End(FormatOnly("epub".into())),
+//!     Text(".".into()),
+//!     End(Paragraph),
+//!     Start(FormatOnly("epub".into())),
+//!     Start(Paragraph),
+//!     Text("Tap a footnote number to read the note.".into()),
+//!     End(Paragraph),
+//!     End(FormatOnly("epub".into())),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
+//! ## Verse
+//!
+//! A fenced code block whose info string is `verse` holds a poem or other verse.
+//! Its stanzas are set apart by blank lines, and the leading indentation of each line is kept;
+//! each line is otherwise parsed as inline markdown.
+//! Lines can be numbered every so often with `verse numbered`, which numbers every fifth line,
+//! or `verse numbered=10`.
+//!
+//! ```
+//! use extended_pulldown::Parser;
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = "```verse numbered=2\nWhose *woods* these are\n  I think I know.\n\nHis house\n```";
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(Verse(Some(2))),
+//!     Start(Stanza),
+//!     Start(VerseLine(0)),
+//!     Text("Whose ".into()),
+//!     Start(Emphasis),
+//!     Text("woods".into()),
+//!     End(Emphasis),
+//!     Text(" these are".into()),
+//!     End(VerseLine(0)),
+//!     Start(VerseLine(2)),
+//!     Text("I think I know.".into()),
+//!     End(VerseLine(2)),
+//!     End(Stanza),
+//!     Start(Stanza),
+//!     Start(VerseLine(0)),
+//!     Text("His house".into()),
+//!     End(VerseLine(0)),
+//!     End(Stanza),
+//!     End(Verse(Some(2))),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
+//! ## Drama
+//!
+//! Within a div whose class is `drama`, set apart from its contents by blank lines,
+//! a paragraph which begins with a name in capitals followed by `:`, or by `.` or `:` alone on the first line,
+//! is a speech by that speaker, and text in parentheses within a speech is a direction.
+//! A name followed by `.` and the speech on the same line, as in `HAMLET. Who's there?`,
+//! is only taken as a speaker's if it is one of the dramatis personae listed in the div's `data-personae` attribute,
+//! so that sentences such as `OK. Let's go.` and headings such as `ACT II.` are left as they are.
+//! A paragraph wholly in parentheses or brackets is a stage direction,
+//! and one beginning `INT.` or `EXT.` is the heading of a scene in a screenplay.
+//! Headings are left as they are, to mark acts and scenes.
+//!
+//! ```
+//! use extended_pulldown::Parser;
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = concat!(
+//!     "<div class=\"drama\" data-personae=\"HAMLET, KING\">\n\n",
+//!     "(Enter HAMLET.)\n\nHAMLET. (aside) A little more than kin.\n\nOK. Not a speech.\n\n</div>\n"
+//! );
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(StageDirection),
+//!     Text("(Enter HAMLET.)".into()),
+//!     End(StageDirection),
+//!     Start(Speech("HAMLET".into())),
+//!     Start(Parenthetical),
+//!     Text("(aside)".into()),
+//!     End(Parenthetical),
+//!     Text(" A little more than kin.".into()),
+//!     End(Speech("HAMLET".into())),
+//!     Start(Paragraph),
+//!     Text("OK. Not a speech.".into()),
+//!     End(Paragraph),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
+//! ## Letters
+//!
+//! Within a div whose class is `letter` or `document`, set apart from its contents by blank lines,
+//! the parts of a letter are known from their place in it.
+//! The first of its opening paragraphs which is short and ends with `,` or `:` is the salutation,
+//! unless the next is too, as with the lines of an address, and any paragraphs before it are the dateline.
+//! Among the last three paragraphs, a short one ending with `,` begins the signature,
+//! as does the very last if it begins with a dash, and a paragraph beginning `P.S.` begins the postscript.
+//!
+//! ```
+//! use extended_pulldown::Parser;
+//! use extended_pulldown::Event::*;
+//! use extended_pulldown::Tag::*;
+//!
+//! let text = "<div class=\"letter\">\n\nWhitby, 24 July\n\nDear Lucy,\n\nAll is well.\n\nYours,\n\nMina\n\n</div>\n";
+//! let parsed = Parser::new(text).collect::<Vec<_>>();
+//! let expected = vec![
+//!     Start(Letter),
+//!     Start(Dateline),
+//!     Text("Whitby, 24 July".into()),
+//!     End(Dateline),
+//!     Start(Salutation),
+//!     Text("Dear Lucy,".into()),
+//!     End(Salutation),
+//!     Start(Paragraph),
+//!     Text("All is well.".into()),
+//!     End(Paragraph),
+//!     Start(Signature),
+//!     Text("Yours,".into()),
+//!     End(Signature),
+//!     Start(Signature),
+//!     Text("Mina".into()),
+//!     End(Signature),
+//!     End(Letter),
+//! ];
+//! assert_eq!(parsed, expected);
+//! ```
+//!
 //! ## Multipara footnotes
 //!
 //! ```
@@ -170,11 +424,24 @@ use pulldown_cmark::Tag as PulldownTag;
 use pulldown_cmark::{Alignment, LinkType};
 pub use pulldown_cmark::{CodeBlockKind, CowStr, InlineStr};
 use std::collections::HashMap;
+mod citations;
+pub use citations::CitationItem;
+mod cross_references;
+pub use cross_references::{is_label, malformed_labels};
+mod drama;
+mod index;
+mod letter;
+pub use index::{IndexCrossReference, IndexEntry};
+mod markers;
 mod parsing;
 mod quotes;
-pub use parsing::{InlineParser, Parser};
+pub use parsing::{attribute, format_only_class, InlineParser, OffsetIter, Parser, ONLY_FORMATS};
 use std::convert::TryFrom;
 mod sub_and_superscript;
+mod verse;
+use serde::{Deserialize, Serialize};
+mod serde_support;
+use serde_support::{alignments, code_block_kind, cowstr, LinkTypeDef};
 
 /// Options for rendering
 #[derive(Debug)]
@@ -223,19 +490,21 @@ impl From<Options> for PulldownOptions {
     }
 }
 
-/// A markdown event
-#[derive(Debug, Clone, PartialEq)]
+/// A markdown event.
+///
+/// Events can be serialized and deserialized; deserialized events always own their text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Event<'a> {
     /// Start of a tagged element. Events that are yielded after this event and before its corresponding End event are inside this element. Start and end events are guaranteed to be balanced.
     Start(Tag<'a>),
     /// End of a tagged element
     End(Tag<'a>),
     /// Text
-    Text(CowStr<'a>),
+    Text(#[serde(with = "cowstr")] CowStr<'a>),
     /// Inline code
-    Code(CowStr<'a>),
+    Code(#[serde(with = "cowstr")] CowStr<'a>),
     /// Reference to a footnote
-    FootnoteReference(CowStr<'a>),
+    FootnoteReference(#[serde(with = "cowstr")] CowStr<'a>),
     /// A soft line break ('\n')
     SoftBreak,
     /// A hard line break, corresponding to `<br/>` in html
@@ -243,10 +512,22 @@ pub enum Event<'a> {
     /// A horizontal rule
     Rule,
     /// A html node
-    Html(CowStr<'a>),
+    Html(#[serde(with = "cowstr")] CowStr<'a>),
     /// A tasklist marker, rendered as a checkbox in html;
     /// an inner value of true indicates that it is checked.
     TaskListMarker(bool),
+    // additions begin here
+    /// A label, written `{#label}`, naming the heading, figure caption or place in which it appears
+    /// so that it can be referred to elsewhere
+    Label(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A reference to a label, written `{@label}`, to be replaced by its number or title
+    Reference(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A reference to the page of a label, written `{@page:label}`
+    PageReference(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A term marked for the index, written `{^term}`, at the place it appears
+    IndexEntry(IndexEntry<'a>),
+    /// A citation of one or more works, written `[@key]` or `[@key, p. 4; @other]`
+    Citation(Vec<CitationItem<'a>>),
 }
 
 impl<'a> From<Event<'a>> for PulldownEvent<'a> {
@@ -272,6 +553,63 @@ impl<'a> From<Event<'a>> for PulldownEvent<'a> {
             Event::End(Tag::RightAligned) => PulldownEvent::Html("</span>".into()),
             Event::End(Tag::Superscript) => PulldownEvent::Html("</span>".into()),
             Event::End(Tag::Subscript) => PulldownEvent::Html("</span>".into()),
+            Event::Start(Tag::FormatOnly(format)) => {
+                PulldownEvent::Html(format!("<span class=\"{}-only\">", format).into())
+            }
+            Event::End(Tag::FormatOnly(_)) => PulldownEvent::Html("</span>".into()),
+            Event::Start(Tag::Verse(_)) => PulldownEvent::Html("<div class=\"verse\">".into()),
+            Event::Start(Tag::Stanza) => PulldownEvent::Html("<div class=\"stanza\">".into()),
+            Event::End(Tag::Verse(_)) | Event::End(Tag::Stanza) => {
+                PulldownEvent::Html("</div>".into())
+            }
+            Event::Start(Tag::VerseLine(_)) => PulldownEvent::Html("<p>".into()),
+            Event::End(Tag::VerseLine(_)) => PulldownEvent::Html("</p>".into()),
+            Event::Start(Tag::Speech(speaker)) => PulldownEvent::Html(
+                format!(
+                    "<p class=\"speech\"><span class=\"speaker\">{}.</span> ",
+                    speaker
+                )
+                .into(),
+            ),
+            Event::Start(Tag::StageDirection) => {
+                PulldownEvent::Html("<p class=\"stage-direction\">".into())
+            }
+            Event::Start(Tag::SceneHeading) => {
+                PulldownEvent::Html("<p class=\"scene-heading\">".into())
+            }
+            Event::End(Tag::Speech(_))
+            | Event::End(Tag::StageDirection)
+            | Event::End(Tag::SceneHeading) => PulldownEvent::Html("</p>".into()),
+            Event::Start(Tag::Parenthetical) => {
+                PulldownEvent::Html("<span class=\"parenthetical\">".into())
+            }
+            Event::End(Tag::Parenthetical) => PulldownEvent::Html("</span>".into()),
+            Event::Start(Tag::Letter) => PulldownEvent::Html("<div class=\"letter\">".into()),
+            Event::End(Tag::Letter) => PulldownEvent::Html("</div>".into()),
+            Event::Start(Tag::Dateline) => PulldownEvent::Html("<p class=\"dateline\">".into()),
+            Event::Start(Tag::Salutation) => PulldownEvent::Html("<p class=\"salutation\">".into()),
+            Event::Start(Tag::Signature) => PulldownEvent::Html("<p class=\"signature\">".into()),
+            Event::Start(Tag::Postscript) => PulldownEvent::Html("<p class=\"postscript\">".into()),
+            Event::End(Tag::Dateline)
+            | Event::End(Tag::Salutation)
+            | Event::End(Tag::Signature)
+            | Event::End(Tag::Postscript) => PulldownEvent::Html("</p>".into()),
+            Event::Start(Tag::OpeningParagraph(_, _)) => {
+                PulldownEvent::Html("<p class=\"opening\">".into())
+            }
+            Event::End(Tag::OpeningParagraph(_, _)) => PulldownEvent::Html("</p>".into()),
+            Event::Start(Tag::DropCap) => PulldownEvent::Html("<span class=\"drop-cap\">".into()),
+            Event::End(Tag::DropCap) => PulldownEvent::Html("</span>".into()),
+            Event::Start(Tag::GlossaryReference(_)) => {
+                PulldownEvent::Html("<a epub:type=\"glossref\">".into())
+            }
+            Event::End(Tag::GlossaryReference(_)) => PulldownEvent::Html("</a>".into()),
+            Event::Start(Tag::Glossary) => PulldownEvent::Html("<dl>".into()),
+            Event::End(Tag::Glossary) => PulldownEvent::Html("</dl>".into()),
+            Event::Start(Tag::GlossaryTerm(_)) => PulldownEvent::Html("<dt>".into()),
+            Event::End(Tag::GlossaryTerm(_)) => PulldownEvent::Html("</dt>".into()),
+            Event::Start(Tag::GlossaryDefinition) => PulldownEvent::Html("<dd>".into()),
+            Event::End(Tag::GlossaryDefinition) => PulldownEvent::Html("</dd>".into()),
             Event::Start(t) => PulldownEvent::Start(PulldownTag::try_from(t).unwrap()),
             Event::End(t) => PulldownEvent::End(PulldownTag::try_from(t).unwrap()),
             Event::Text(t) => PulldownEvent::Text(t),
@@ -282,6 +620,11 @@ impl<'a> From<Event<'a>> for PulldownEvent<'a> {
             Event::Rule => PulldownEvent::Rule,
             Event::Html(h) => PulldownEvent::Html(h),
             Event::TaskListMarker(b) => PulldownEvent::TaskListMarker(b),
+            Event::Label(l) => PulldownEvent::Text(format!("{{#{}}}", l).into()),
+            Event::Reference(l) => PulldownEvent::Text(format!("{{@{}}}", l).into()),
+            Event::PageReference(l) => PulldownEvent::Text(format!("{{@page:{}}}", l).into()),
+            Event::IndexEntry(entry) => PulldownEvent::Text(entry.to_markup().into()),
+            Event::Citation(items) => PulldownEvent::Text(citations::to_markup(&items).into()),
         }
     }
 }
@@ -355,7 +698,7 @@ impl<'a> TryFrom<Tag<'a>> for PulldownTag<'a> {
 }
 
 /// A tag containing other events
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Tag<'a> {
     /// A paragraph of text and other inline elements
     Paragraph,
@@ -366,15 +709,15 @@ pub enum Tag<'a> {
     /// A block quote to be rendered as a `quotation` in latex
     BlockQuotation,
     /// A code block
-    CodeBlock(CodeBlockKind<'a>),
+    CodeBlock(#[serde(with = "code_block_kind")] CodeBlockKind<'a>),
     /// A list. If the list is ordered the field indicates the number of the first item. Contains only list items.
     List(Option<u64>),
     /// A list item
     Item,
     /// The definition of a footnote
-    FootnoteDefinition(CowStr<'a>),
+    FootnoteDefinition(#[serde(with = "cowstr")] CowStr<'a>),
     /// A table. Contains a vector describing the text-alignment for each of its columns.
-    Table(Vec<Alignment>),
+    Table(#[serde(with = "alignments")] Vec<Alignment>),
     /// A table header. Contains only `TableRows`. Note that the table body starts immediately after the closure of the `TableHead` tag. There is no `TableBody` tag.
     TableHead,
     /// A table row. Is used both for header rows as body rows. Contains only `TableCells`.
@@ -386,9 +729,17 @@ pub enum Tag<'a> {
     /// Strong (bold) text
     Strong,
     /// An image. The first field is the link type, the second the destination URL and the third is a title.
-    Link(LinkType, CowStr<'a>, CowStr<'a>),
+    Link(
+        #[serde(with = "LinkTypeDef")] LinkType,
+        #[serde(with = "cowstr")] CowStr<'a>,
+        #[serde(with = "cowstr")] CowStr<'a>,
+    ),
     /// A link. The first field is the link type, the second the destination URL and the third is a title.
-    Image(LinkType, CowStr<'a>, CowStr<'a>),
+    Image(
+        #[serde(with = "LinkTypeDef")] LinkType,
+        #[serde(with = "cowstr")] CowStr<'a>,
+        #[serde(with = "cowstr")] CowStr<'a>,
+    ),
     /// Struck through text
     Strikethrough,
     // additions begin here
@@ -408,6 +759,47 @@ pub enum Tag<'a> {
     FlattenedFootnote,
     /// A paragraph without an initial indent
     UnindentedParagraph,
+    /// The first use of a term defined in a glossary, with the key of its entry
+    GlossaryReference(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A list of terms and their definitions. Contains only `GlossaryTerm`s, each followed by a `GlossaryDefinition`.
+    Glossary,
+    /// A term defined in a glossary, with the key by which it is referred to
+    GlossaryTerm(#[serde(with = "cowstr")] CowStr<'a>),
+    /// The definition of the term before it in a glossary
+    GlossaryDefinition,
+    /// Content to be rendered only to the named format, such as `pdf` or `epub`
+    FormatOnly(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A poem or other verse. Contains only `Stanza`s.
+    /// The field, if any, is how often its lines are numbered: every fifth line, for instance.
+    Verse(Option<u32>),
+    /// A stanza of verse. Contains only `VerseLine`s.
+    Stanza,
+    /// A line of verse. The field is the width of its leading indentation, in spaces.
+    VerseLine(u32),
+    /// A speech in a play, in place of a paragraph. The field is the name of its speaker.
+    Speech(#[serde(with = "cowstr")] CowStr<'a>),
+    /// A stage direction standing by itself, in place of a paragraph
+    StageDirection,
+    /// A direction within a speech, such as `(aside)`
+    Parenthetical,
+    /// The heading of a scene in a screenplay, such as `INT. KITCHEN -- NIGHT`, in place of a paragraph
+    SceneHeading,
+    /// A letter or other document embedded in the text
+    Letter,
+    /// The place and date at the head of a letter, in place of a paragraph
+    Dateline,
+    /// The greeting with which a letter opens, such as `Dear Mina,`, in place of a paragraph
+    Salutation,
+    /// The close and signature of a letter, in place of a paragraph
+    Signature,
+    /// A postscript to a letter, in place of a paragraph
+    Postscript,
+    /// The opening paragraph of a chapter, in place of a paragraph.
+    /// The fields are the number of lines through which a drop cap at its start falls, if it has one,
+    /// and whether its first line is set in small caps.
+    OpeningParagraph(Option<u8>, bool),
+    /// The initial letter of an opening paragraph, with any quotation marks before it, set as a drop cap
+    DropCap,
 }
 
 trait BoundaryMarker {
@@ -542,6 +934,25 @@ impl MakeStatic for Tag<'_> {
             BlockQuotation => BlockQuotation,
             Strikethrough => Strikethrough,
             UnindentedParagraph => UnindentedParagraph,
+            GlossaryReference(key) => GlossaryReference(key.into_static()),
+            Glossary => Glossary,
+            GlossaryTerm(key) => GlossaryTerm(key.into_static()),
+            GlossaryDefinition => GlossaryDefinition,
+            FormatOnly(format) => FormatOnly(format.into_static()),
+            Verse(numbering) => Verse(numbering),
+            Stanza => Stanza,
+            VerseLine(indent) => VerseLine(indent),
+            Speech(speaker) => Speech(speaker.into_static()),
+            StageDirection => StageDirection,
+            Parenthetical => Parenthetical,
+            SceneHeading => SceneHeading,
+            Letter => Letter,
+            Dateline => Dateline,
+            Salutation => Salutation,
+            Signature => Signature,
+            Postscript => Postscript,
+            OpeningParagraph(drop_cap, first_line) => OpeningParagraph(drop_cap, first_line),
+            DropCap => DropCap,
         }
     }
 }
@@ -561,6 +972,11 @@ impl MakeStatic for Event<'_> {
             Rule => Rule,
             Html(h) => Html(h.into_static()),
             TaskListMarker(b) => TaskListMarker(b),
+            Label(l) => Label(l.into_static()),
+            Reference(l) => Reference(l.into_static()),
+            PageReference(l) => PageReference(l.into_static()),
+            IndexEntry(entry) => IndexEntry(entry.into_static()),
+            Citation(items) => Citation(items.into_iter().map(|i| i.into_static()).collect()),
         }
     }
 }
@@ -932,6 +1348,75 @@ I must get there by hook or by crook. The men said
         assert_eq!(valid_superscripts, expected_valid);
     }
 
+    #[test]
+    fn index_entries_beside_super_and_sub() {
+        use Event::*;
+        use Tag::*;
+        let entry = |terms: &[&'static str]| {
+            IndexEntry(crate::IndexEntry {
+                terms: terms.iter().map(|&t| t.into()).collect(),
+                cross_reference: None,
+            })
+        };
+        let parsed = Parser::new("Water{^water} is H~2~O.\n\nRoses{^plants!roses} are 2^nd^ red.")
+            .collect::<Vec<_>>();
+        let expected = vec![
+            Start(Paragraph),
+            Text("Water".into()),
+            entry(&["water"]),
+            Text(" ".into()),
+            Text("is H".into()),
+            Start(Subscript),
+            Text("2".into()),
+            End(Subscript),
+            Text("O.".into()),
+            End(Paragraph),
+            Start(Paragraph),
+            Text("Roses".into()),
+            entry(&["plants", "roses"]),
+            Text(" ".into()),
+            Text("are 2".into()),
+            Start(Superscript),
+            Text("nd".into()),
+            End(Superscript),
+            Text(" red.".into()),
+            End(Paragraph),
+        ];
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn cross_references_beside_super_and_sub() {
+        use Event::*;
+        use Tag::*;
+        let parsed =
+            Parser::new("See {@ch:one} on the 2^nd^ day, {@page:fig_2}.\n\nH~2~O {#water}")
+                .collect::<Vec<_>>();
+        let expected = vec![
+            Start(Paragraph),
+            Text("See ".into()),
+            Reference("ch:one".into()),
+            Text(" ".into()),
+            Text("on the 2".into()),
+            Start(Superscript),
+            Text("nd".into()),
+            End(Superscript),
+            Text(" day, ".into()),
+            PageReference("fig_2".into()),
+            Text(".".into()),
+            End(Paragraph),
+            Start(Paragraph),
+            Text("H".into()),
+            Start(Subscript),
+            Text("2".into()),
+            End(Subscript),
+            Text("O".into()),
+            Label("water".into()),
+            End(Paragraph),
+        ];
+        assert_eq!(parsed, expected);
+    }
+
     #[test]
     fn blockquotes() {
         use Event::*;
@@ -998,4 +1483,29 @@ I must get there by hook or by crook. The men said
             assert_eq!(received, expected);
         }
     }
+
+    #[test]
+    fn glossary_tags_become_html() {
+        let key = || CowStr::from("hobbit");
+        let tags = [
+            (
+                Tag::GlossaryReference(key()),
+                "<a epub:type=\"glossref\">",
+                "</a>",
+            ),
+            (Tag::Glossary, "<dl>", "</dl>"),
+            (Tag::GlossaryTerm(key()), "<dt>", "</dt>"),
+            (Tag::GlossaryDefinition, "<dd>", "</dd>"),
+        ];
+        for (tag, start, end) in tags {
+            assert_eq!(
+                PulldownEvent::from(Event::Start(tag.clone())),
+                PulldownEvent::Html(start.into())
+            );
+            assert_eq!(
+                PulldownEvent::from(Event::End(tag)),
+                PulldownEvent::Html(end.into())
+            );
+        }
+    }
 }
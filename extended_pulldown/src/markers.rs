@@ -0,0 +1,49 @@
+use crate::{Event, MakeStatic};
+use pulldown_cmark::CowStr;
+use regex::{Captures, Regex};
+
+/// Turn the captures of a marker into the event it stands for
+pub(crate) type MarkerEvent = for<'t> fn(&Captures<'t>) -> Event<'t>;
+
+fn split_str<'a>(text: &'a str, regex: &Regex, to_event: MarkerEvent) -> Vec<Event<'a>> {
+    let mut events = Vec::new();
+    let mut last = 0;
+    for caps in regex.captures_iter(text) {
+        let marker = caps.get(0).unwrap();
+        let event = to_event(&caps);
+        // an invisible marker should not leave behind the space which divides it from its text,
+        // unless it stands before a word
+        let before_word =
+            matches!(text[marker.end()..].chars().next(), Some(c) if c.is_alphanumeric());
+        let preceding = match event {
+            Event::Label(_) | Event::IndexEntry(_) if !before_word => {
+                text[last..marker.start()].trim_end()
+            }
+            _ => &text[last..marker.start()],
+        };
+        if !preceding.is_empty() {
+            events.push(Event::Text(preceding.into()));
+        }
+        events.push(event);
+        last = marker.end();
+    }
+    if last < text.len() {
+        events.push(Event::Text(text[last..].into()));
+    }
+    events
+}
+
+/// Split text into plain text and the events marked within it by matches of `regex`
+pub(crate) fn split_markers<'a>(
+    text: CowStr<'a>,
+    regex: &Regex,
+    to_event: MarkerEvent,
+) -> Vec<Event<'a>> {
+    match text {
+        CowStr::Borrowed(b) => split_str(b, regex, to_event),
+        other => split_str(&other, regex, to_event)
+            .into_iter()
+            .map(|e| e.into_static())
+            .collect(),
+    }
+}
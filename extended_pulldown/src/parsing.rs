@@ -1,15 +1,24 @@
+use crate::citations;
+use crate::cross_references;
+use crate::drama;
+use crate::index;
+use crate::letter;
+use crate::markers::split_markers;
 use crate::quotes::convert_quotes_in_text_segment;
 use crate::sub_and_superscript;
 use crate::sub_and_superscript::disambiguate_sub_and_superscript;
+use crate::verse;
 use crate::BoundaryMarker;
 use crate::{Event, MakeStatic, Options, Tag};
 use pulldown_cmark::Event as PulldownEvent;
+use pulldown_cmark::OffsetIter as PulldownOffsetIter;
 use pulldown_cmark::Parser as PulldownParser;
 use pulldown_cmark::Tag as PulldownTag;
 use pulldown_cmark::{BrokenLink, CodeBlockKind, CowStr, InlineStr};
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
+use std::ops::Range;
 
 /// Markdown event iterator which drops non-inline elements,
 /// keeping only plain text, links, and superscript, subscript,
@@ -67,6 +76,8 @@ where
                 | e @ Event::End(Tag::Superscript)
                 | e @ Event::Start(Tag::Link(_, _, _))
                 | e @ Event::End(Tag::Link(_, _, _))
+                | e @ Event::Start(Tag::FormatOnly(_))
+                | e @ Event::End(Tag::FormatOnly(_))
                 | e @ Event::Text(_) => {
                     if !self.in_dropped_tag {
                         Some(e)
@@ -97,9 +108,32 @@ where
     }
 }
 
+/// The output formats to which content can be limited, by giving it a class such as `pdf-only`
+pub const ONLY_FORMATS: [&str; 3] = ["epub", "pdf", "latex"];
+
+/// The format, such as `pdf` or `epub`, to which content with the html class `class` is limited,
+/// if the class is of the form `pdf-only` and names one of `ONLY_FORMATS`
+pub fn format_only_class(class: &str) -> Option<&str> {
+    class
+        .split_whitespace()
+        .filter_map(|c| c.strip_suffix("-only"))
+        .find(|format| ONLY_FORMATS.iter().any(|f| f.eq_ignore_ascii_case(format)))
+}
+
+/// The value of `key` in the html tag `html`, if it is set
+pub fn attribute<'h>(html: &'h str, key: &str) -> Option<&'h str> {
+    let k = format!("{}=\"", key);
+    let value = html.get(html.find(&k)? + k.len()..)?;
+    match value.find('"') {
+        Some(x) => value.get(..x),
+        None => Some(value),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum CurrentSpan {
     Generic,
+    FormatOnly(String),
     Sans,
     SmallCaps,
     Centred,
@@ -108,15 +142,84 @@ enum CurrentSpan {
     Subscript,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CurrentDiv {
+    Generic,
+    FormatOnly(String),
+    // drama, with the speakers named in its dramatis personae
+    Drama(Vec<String>),
+    Letter,
+}
+
 /// Markdown event iterator
 pub struct Parser<'a> {
-    buffered: VecDeque<Event<'a>>,
+    // every event is kept with the range of the source it was parsed from
+    buffered: VecDeque<(Event<'a>, Range<usize>)>,
     in_verbatim: bool,
-    inner: PulldownParser<'a>,
+    inner: PulldownOffsetIter<'a>,
     smarten: bool,
     current_spans: Vec<CurrentSpan>,
+    current_divs: Vec<CurrentDiv>,
+    // the events of a paragraph of drama, once it has been arranged
+    arranged: VecDeque<(Event<'a>, Range<usize>)>,
+    // whether the events being yielded, rather than loaded, are within a code block
+    in_yielded_code_block: bool,
     // this is just here to avoid allocations
-    buffering: Vec<PulldownEvent<'a>>,
+    buffering: Vec<(PulldownEvent<'a>, Range<usize>)>,
+}
+
+/// Markdown event iterator which yields each event
+/// along with the byte range of the source it was parsed from.
+///
+/// Events made from part of what pulldown gives as one,
+/// such as a label within text, have the range of the whole;
+/// so do events rearranged from several, such as the speeches of drama
+/// or the parts of a letter, where no single one is theirs.
+///
+/// ```
+/// use extended_pulldown::{Event, Parser};
+///
+/// let text = "Some text.\n\nA Map {#fig:map}\n";
+/// let label = Parser::new(text)
+///     .into_offset_iter()
+///     .find(|(event, _)| matches!(event, Event::Label(_)))
+///     .unwrap();
+/// assert_eq!(&text[label.1], "A Map {#fig:map}");
+/// ```
+pub struct OffsetIter<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> Iterator for OffsetIter<'a> {
+    type Item = (Event<'a>, Range<usize>);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.next_located()
+    }
+}
+
+/// Give each of `arranged`, rearranged from the events of `original`,
+/// the range of the first of those it is, or otherwise of the first not yet matched;
+/// any after the last take `last`
+fn relocate<'a>(
+    original: Vec<(Event<'a>, Range<usize>)>,
+    arranged: Vec<Event<'a>>,
+    last: Range<usize>,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut remaining = original.as_slice();
+    arranged
+        .into_iter()
+        .map(|event| {
+            let range = match remaining.iter().position(|(e, _)| *e == event) {
+                Some(i) => {
+                    let range = remaining[i].1.clone();
+                    remaining = &remaining[i + 1..];
+                    range
+                }
+                None => remaining.first().map_or(last.clone(), |(_, r)| r.clone()),
+            };
+            (event, range)
+        })
+        .collect()
 }
 
 impl<'a> Parser<'a> {
@@ -156,7 +259,8 @@ impl<'a> Parser<'a> {
             text,
             options.into(),
             broken_link_callback,
-        );
+        )
+        .into_offset_iter();
         Parser {
             inner,
             buffering: Vec::new(),
@@ -164,17 +268,114 @@ impl<'a> Parser<'a> {
             in_verbatim: false,
             smarten,
             current_spans: Vec::new(),
+            current_divs: Vec::new(),
+            arranged: VecDeque::new(),
+            in_yielded_code_block: false,
+        }
+    }
+
+    /// Yield each event along with the byte range of the source it was parsed from
+    pub fn into_offset_iter(self) -> OffsetIter<'a> {
+        OffsetIter { parser: self }
+    }
+
+    /// the dramatis personae of the drama we are within, if any
+    fn personae(&self) -> Option<&[String]> {
+        self.current_divs.iter().rev().find_map(|div| match div {
+            CurrentDiv::Drama(personae) => Some(personae.as_slice()),
+            _ => None,
+        })
+    }
+
+    fn _next(&mut self) -> Option<(Event<'a>, Range<usize>)> {
+        // a load can leave nothing to yield, as when it finds the beginning of a drama div
+        while self.buffered.is_empty() {
+            if !self.load_buffer() {
+                return None;
+            }
+        }
+        loop {
+            let (event, range) = self.buffered.pop_front()?;
+            let split = match event {
+                Event::Text(t)
+                    if !self.in_yielded_code_block && cross_references::REGGIE.is_match(&t) =>
+                {
+                    split_markers(t, &cross_references::REGGIE, cross_references::to_event)
+                }
+                Event::Text(t) if !self.in_yielded_code_block && index::REGGIE.is_match(&t) => {
+                    split_markers(t, &index::REGGIE, index::to_event)
+                }
+                // pulldown gives the brackets around a citation as text of their own
+                Event::Text(t) if !self.in_yielded_code_block && t.as_ref() == "[" => {
+                    let citation = self.take_citation(range.clone());
+                    return Some(citation.unwrap_or((Event::Text(t), range)));
+                }
+                event => {
+                    match event {
+                        Event::Start(Tag::CodeBlock(_)) => self.in_yielded_code_block = true,
+                        Event::End(Tag::CodeBlock(_)) => self.in_yielded_code_block = false,
+                        _ => {}
+                    }
+                    return Some((event, range));
+                }
+            };
+            // text left between markers may still hold markers of another kind
+            for (i, event) in split.into_iter().enumerate() {
+                self.buffered.insert(i, (event, range.clone()));
+            }
         }
     }
 
-    fn _next(&mut self) -> Option<Event<'a>> {
-        if self.buffered.is_empty() {
-            self.load_buffer();
+    /// Having found an opening bracket,
+    /// take the citation which follows it and its closing bracket from the buffer;
+    /// the citation spans from the range of the opening bracket, `open`, to that of the closing
+    fn take_citation(&mut self, open: Range<usize>) -> Option<(Event<'a>, Range<usize>)> {
+        match (self.buffered.front(), self.buffered.get(1)) {
+            (Some((Event::Text(inner), _)), Some((Event::Text(close), _)))
+                if close.as_ref() == "]" && citations::REGGIE.is_match(inner) => {}
+            _ => return None,
         }
-        self.buffered.pop_front()
+        let inner = match self.buffered.pop_front() {
+            Some((Event::Text(inner), _)) => inner,
+            _ => unreachable!(),
+        };
+        let (_, close) = self.buffered.pop_front()?;
+        Some((citations::to_event(inner), open.start..close.end))
     }
 
-    fn add_super_or_subscript_to_buffering(&mut self, text: CowStr<'a>) {
+    fn add_super_or_subscript_to_buffering(&mut self, text: CowStr<'a>, range: Range<usize>) {
+        // markers are only recognised once text is yielded, and would be dropped
+        // by the inline parser below, so the text around them is handled alone
+        let marker = [&*cross_references::REGGIE, &*index::REGGIE]
+            .iter()
+            .filter_map(|regex| regex.find(&text))
+            .min_by_key(|marker| marker.start())
+            .map(|marker| marker.range());
+        if let Some(marker) = marker {
+            let (before, marker, after) = match text {
+                CowStr::Borrowed(b) => (
+                    CowStr::Borrowed(&b[..marker.start]),
+                    CowStr::Borrowed(&b[marker.clone()]),
+                    CowStr::Borrowed(&b[marker.end..]),
+                ),
+                other => (
+                    other[..marker.start].to_string().into(),
+                    other[marker.clone()].to_string().into(),
+                    other[marker.end..].to_string().into(),
+                ),
+            };
+            for part in [before, marker, after] {
+                if part.is_empty() {
+                    continue;
+                } else if sub_and_superscript::REGGIE.is_match(&part) {
+                    self.add_super_or_subscript_to_buffering(part, range.clone());
+                } else {
+                    self.push_text_to_buffering(part, range.clone());
+                }
+            }
+            return;
+        }
+
         let disambiguated = disambiguate_sub_and_superscript(text);
 
         let reinsert = match disambiguated.chars().next() {
@@ -196,13 +397,27 @@ impl<'a> Parser<'a> {
             p.insert(0, PulldownEvent::Text(CowStr::Inlined(reinsert.into())));
         }
 
-        self.buffering.append(&mut p);
+        self.buffering
+            .extend(p.into_iter().map(|event| (event, range.clone())));
+    }
+
+    /// add text to the buffer, joining it to any text just before it
+    /// so that markers are split from the text around them as they would be otherwise
+    fn push_text_to_buffering(&mut self, text: CowStr<'a>, range: Range<usize>) {
+        match self.buffering.last_mut() {
+            Some((PulldownEvent::Text(previous), previous_range)) => {
+                *previous = format!("{}{}", previous, text).into();
+                previous_range.end = previous_range.end.max(range.end);
+            }
+            _ => self.buffering.push((PulldownEvent::Text(text), range)),
+        }
     }
 
     /// load as few events as possible from pulldown,
-    /// then push them into the buffer
-    fn load_buffer(&mut self) {
-        if let Some(next) = self.inner.next() {
+    /// then push them into the buffer;
+    /// returns false if there were none left to load
+    fn load_buffer(&mut self) -> bool {
+        if let Some((next, range)) = self.inner.next() {
             match next {
                 PulldownEvent::Text(t) if !self.in_verbatim => {
                     // so, first we're going to deal with sub and superscript;
@@ -211,68 +426,68 @@ impl<'a> Parser<'a> {
                     // In turn that'll let us play tricks to do with knowing what's where.
 
                     if sub_and_superscript::REGGIE.is_match(&t) {
-                        self.add_super_or_subscript_to_buffering(t);
+                        self.add_super_or_subscript_to_buffering(t, range);
                     } else {
-                        self.buffering.push(PulldownEvent::Text(t));
+                        self.buffering.push((PulldownEvent::Text(t), range));
                     }
 
-                    while let Some(n) = self.inner.next() {
+                    while let Some((n, range)) = self.inner.next() {
                         match n {
                             PulldownEvent::Text(t) if sub_and_superscript::REGGIE.is_match(&t) => {
-                                self.add_super_or_subscript_to_buffering(t);
+                                self.add_super_or_subscript_to_buffering(t, range);
                             }
                             n if n.resets_quotes() => {
-                                self.buffering.push(n);
+                                self.buffering.push((n, range));
                                 break;
                             }
-                            n => self.buffering.push(n),
+                            n => self.buffering.push((n, range)),
                         }
                     }
                 }
                 PulldownEvent::Start(PulldownTag::BlockQuote) => {
                     let mut score = 1;
-                    self.buffering.push(next);
+                    self.buffering.push((next, range));
                     let mut in_verbatim = false;
-                    while let Some(n) = self.inner.next() {
+                    while let Some((n, range)) = self.inner.next() {
                         match n {
                             e @ PulldownEvent::Start(PulldownTag::CodeBlock(_)) => {
                                 in_verbatim = true;
-                                self.buffering.push(e);
+                                self.buffering.push((e, range));
                             }
                             e @ PulldownEvent::End(PulldownTag::CodeBlock(_)) => {
                                 in_verbatim = false;
-                                self.buffering.push(e);
+                                self.buffering.push((e, range));
                             }
                             PulldownEvent::Text(t)
                                 if !in_verbatim && sub_and_superscript::REGGIE.is_match(&t) =>
                             {
-                                self.add_super_or_subscript_to_buffering(t);
+                                self.add_super_or_subscript_to_buffering(t, range);
                             }
                             PulldownEvent::Start(PulldownTag::BlockQuote) => {
-                                self.buffering.push(n);
+                                self.buffering.push((n, range));
                                 score += 1;
                             }
                             PulldownEvent::End(PulldownTag::BlockQuote) => {
-                                self.buffering.push(n);
+                                self.buffering.push((n, range));
                                 score -= 1;
                                 if score == 0 {
                                     break;
                                 }
                             }
-                            n => self.buffering.push(n),
+                            n => self.buffering.push((n, range)),
                         }
                     }
                 }
                 PulldownEvent::Html(_) => {
-                    self.buffering.push(next);
+                    self.buffering.push((next, range));
                 }
                 e @ PulldownEvent::Start(PulldownTag::FootnoteDefinition(_)) => {
                     let mut in_verbatim = false;
-                    self.buffering.push(e);
-                    while let Some(event) = self.inner.next() {
+                    self.buffering.push((e, range));
+                    while let Some((event, range)) = self.inner.next() {
                         match event {
                             PulldownEvent::Start(PulldownTag::Image(_, _, _)) => {
-                                while let Some(e) = self.inner.next() {
+                                while let Some((e, _)) = self.inner.next() {
                                     if let PulldownEvent::End(PulldownTag::Image(_, _, _)) = e {
                                         break;
                                     }
@@ -282,7 +497,7 @@ impl<'a> Parser<'a> {
                                 CodeBlockKind::Indented,
                             )) => {
                                 let mut footnote_text = String::new();
-                                while let Some(e) = self.inner.next() {
+                                while let Some((e, _)) = self.inner.next() {
                                     match e {
                                         PulldownEvent::End(PulldownTag::CodeBlock(
                                             CodeBlockKind::Indented,
@@ -296,53 +511,79 @@ impl<'a> Parser<'a> {
                                 let parsed =
                                     PulldownParser::new(&footnote_text).map(|e| e.into_static());
 
+                                // what is parsed again has the range of the whole indented text
                                 for event in parsed {
                                     match event {
                                         PulldownEvent::Text(t)
                                             if sub_and_superscript::REGGIE.is_match(&t) =>
                                         {
-                                            self.add_super_or_subscript_to_buffering(t);
+                                            self.add_super_or_subscript_to_buffering(
+                                                t,
+                                                range.clone(),
+                                            );
                                         }
-                                        t => self.buffering.push(t),
+                                        t => self.buffering.push((t, range.clone())),
                                     }
                                 }
                             }
                             e @ PulldownEvent::Start(PulldownTag::CodeBlock(_)) => {
                                 in_verbatim = true;
-                                self.buffering.push(e);
+                                self.buffering.push((e, range));
                             }
                             e @ PulldownEvent::End(PulldownTag::CodeBlock(_)) => {
                                 in_verbatim = false;
-                                self.buffering.push(e);
+                                self.buffering.push((e, range));
                             }
                             PulldownEvent::Text(t)
                                 if !in_verbatim && sub_and_superscript::REGGIE.is_match(&t) =>
                             {
-                                self.add_super_or_subscript_to_buffering(t);
+                                self.add_super_or_subscript_to_buffering(t, range);
                             }
                             e @ PulldownEvent::End(PulldownTag::FootnoteDefinition(_)) => {
-                                self.buffering.push(e);
+                                self.buffering.push((e, range));
                                 break;
                             }
-                            e => self.buffering.push(e),
+                            e => self.buffering.push((e, range)),
+                        }
+                    }
+                }
+                PulldownEvent::Start(PulldownTag::CodeBlock(CodeBlockKind::Fenced(ref info)))
+                    if verse::verse_info(info).is_some() =>
+                {
+                    let numbering = verse::verse_info(info).flatten();
+                    let mut text = String::new();
+                    for (e, _) in self.inner.by_ref() {
+                        match e {
+                            PulldownEvent::End(PulldownTag::CodeBlock(_)) => break,
+                            PulldownEvent::Text(t) => text.push_str(&t),
+                            _ => {}
                         }
                     }
+                    // the range of the opening of a block is that of the whole block
+                    self.buffered.extend(
+                        verse::to_events(&text, numbering)
+                            .into_iter()
+                            .map(|event| (event, range.clone())),
+                    );
+                    return true;
                 }
                 e @ PulldownEvent::Start(PulldownTag::CodeBlock(_)) => {
                     self.in_verbatim = true;
-                    self.buffered.push_back(e.into());
-                    return;
+                    self.buffered.push_back((e.into(), range));
+                    return true;
                 }
                 e @ PulldownEvent::End(PulldownTag::CodeBlock(_)) => {
                     self.in_verbatim = false;
-                    self.buffered.push_back(e.into());
-                    return;
+                    self.buffered.push_back((e.into(), range));
+                    return true;
                 }
                 other => {
-                    self.buffered.push_back(other.into());
-                    return;
+                    self.buffered.push_back((other.into(), range));
+                    return true;
                 }
             }
+        } else {
+            return false;
         }
 
         // so we have special cases to handle:
@@ -357,7 +598,7 @@ impl<'a> Parser<'a> {
         let mut block_quotations = HashMap::new();
         let mut in_block_quotation = None;
 
-        for (idx, event) in self.buffering.iter_mut().enumerate() {
+        for (idx, (event, _)) in self.buffering.iter_mut().enumerate() {
             match event {
                 PulldownEvent::Start(PulldownTag::BlockQuote) => {
                     if in_block_quotation.is_none() {
@@ -426,7 +667,7 @@ impl<'a> Parser<'a> {
         }
 
         for (idx, replacement) in replacements.into_iter() {
-            let target = self.buffering.get_mut(idx).unwrap();
+            let (target, _) = self.buffering.get_mut(idx).unwrap();
             let replacement = PulldownEvent::Text(Self::convert(replacement));
             *target = replacement;
         }
@@ -439,34 +680,20 @@ impl<'a> Parser<'a> {
             }
         }
 
-        for (idx, event) in self.buffering.drain(..).enumerate() {
+        for (idx, (event, range)) in self.buffering.drain(..).enumerate() {
             match event {
                 PulldownEvent::Start(PulldownTag::BlockQuote) if quotations.contains(&idx) => {
-                    self.buffered.push_back(Event::Start(Tag::BlockQuotation));
+                    self.buffered
+                        .push_back((Event::Start(Tag::BlockQuotation), range.clone()));
                 }
                 PulldownEvent::End(PulldownTag::BlockQuote) if quotations.contains(&idx) => {
-                    self.buffered.push_back(Event::End(Tag::BlockQuotation));
+                    self.buffered
+                        .push_back((Event::End(Tag::BlockQuotation), range.clone()));
                 }
                 PulldownEvent::Html(html) => {
                     if html.starts_with("<span") {
-                        let find_val = |key| {
-                            let k = format!("{}=\"", key);
-                            html.find(&k)
-                                .map(|i| i + k.len())
-                                .map(|i| html.get(i..))
-                                .flatten()
-                                .map(|s| {
-                                    if let Some(x) = s.find('"') {
-                                        s.get(..x)
-                                    } else {
-                                        Some(s)
-                                    }
-                                })
-                                .flatten()
-                        };
-
-                        let style = find_val("style");
-                        let class = find_val("class");
+                        let style = attribute(&html, "style");
+                        let class = attribute(&html, "class");
 
                         let find_current_span =
                             |style: Option<&str>, class: Option<&str>| -> CurrentSpan {
@@ -489,7 +716,9 @@ impl<'a> Parser<'a> {
                                         return CurrentSpan::RightAligned;
                                     }
                                 } else if let Some(class) = class {
-                                    if class.contains("sans") {
+                                    if let Some(format) = format_only_class(class) {
+                                        return CurrentSpan::FormatOnly(format.to_string());
+                                    } else if class.contains("sans") {
                                         return CurrentSpan::Sans;
                                     } else if class.contains("smallcaps") {
                                         return CurrentSpan::SmallCaps;
@@ -509,68 +738,184 @@ impl<'a> Parser<'a> {
                         let current_span = find_current_span(style, class);
 
                         match current_span {
-                            CurrentSpan::Sans => self.buffered.push_back(Event::Start(Tag::Sans)),
-                            CurrentSpan::SmallCaps => {
-                                self.buffered.push_back(Event::Start(Tag::SmallCaps))
-                            }
-                            CurrentSpan::Centred => {
-                                self.buffered.push_back(Event::Start(Tag::Centred))
-                            }
-                            CurrentSpan::RightAligned => {
-                                self.buffered.push_back(Event::Start(Tag::RightAligned))
-                            }
-                            CurrentSpan::Superscript => {
-                                self.buffered.push_back(Event::Start(Tag::Superscript))
-                            }
-                            CurrentSpan::Subscript => {
-                                self.buffered.push_back(Event::Start(Tag::Subscript))
-                            }
+                            CurrentSpan::FormatOnly(ref format) => self.buffered.push_back((
+                                Event::Start(Tag::FormatOnly(format.clone().into())),
+                                range.clone(),
+                            )),
+                            CurrentSpan::Sans => self
+                                .buffered
+                                .push_back((Event::Start(Tag::Sans), range.clone())),
+                            CurrentSpan::SmallCaps => self
+                                .buffered
+                                .push_back((Event::Start(Tag::SmallCaps), range.clone())),
+                            CurrentSpan::Centred => self
+                                .buffered
+                                .push_back((Event::Start(Tag::Centred), range.clone())),
+                            CurrentSpan::RightAligned => self
+                                .buffered
+                                .push_back((Event::Start(Tag::RightAligned), range.clone())),
+                            CurrentSpan::Superscript => self
+                                .buffered
+                                .push_back((Event::Start(Tag::Superscript), range.clone())),
+                            CurrentSpan::Subscript => self
+                                .buffered
+                                .push_back((Event::Start(Tag::Subscript), range.clone())),
                             CurrentSpan::Generic => {
-                                self.buffered.push_back(Event::Html(html));
+                                self.buffered.push_back((Event::Html(html), range.clone()));
                             }
                         }
                         self.current_spans.push(current_span);
                     } else if html.starts_with("</span") {
                         match self.current_spans.pop() {
                             Some(CurrentSpan::Generic) => {
-                                self.buffered.push_back(Event::Html(html));
+                                self.buffered.push_back((Event::Html(html), range.clone()));
+                            }
+                            Some(CurrentSpan::FormatOnly(format)) => {
+                                self.buffered.push_back((
+                                    Event::End(Tag::FormatOnly(format.into())),
+                                    range.clone(),
+                                ));
                             }
                             Some(CurrentSpan::Sans) => {
-                                self.buffered.push_back(Event::End(Tag::Sans));
+                                self.buffered
+                                    .push_back((Event::End(Tag::Sans), range.clone()));
                             }
                             Some(CurrentSpan::SmallCaps) => {
-                                self.buffered.push_back(Event::End(Tag::SmallCaps));
+                                self.buffered
+                                    .push_back((Event::End(Tag::SmallCaps), range.clone()));
                             }
                             Some(CurrentSpan::Centred) => {
-                                self.buffered.push_back(Event::End(Tag::Centred));
+                                self.buffered
+                                    .push_back((Event::End(Tag::Centred), range.clone()));
                             }
                             Some(CurrentSpan::RightAligned) => {
-                                self.buffered.push_back(Event::End(Tag::RightAligned));
+                                self.buffered
+                                    .push_back((Event::End(Tag::RightAligned), range.clone()));
                             }
                             Some(CurrentSpan::Subscript) => {
-                                self.buffered.push_back(Event::End(Tag::Subscript));
+                                self.buffered
+                                    .push_back((Event::End(Tag::Subscript), range.clone()));
                             }
                             Some(CurrentSpan::Superscript) => {
-                                self.buffered.push_back(Event::End(Tag::Superscript));
+                                self.buffered
+                                    .push_back((Event::End(Tag::Superscript), range.clone()));
                             }
                             None => {
-                                self.buffered.push_back(Event::Html(html));
+                                self.buffered.push_back((Event::Html(html), range.clone()));
+                            }
+                        }
+                    } else if html.starts_with("<div") {
+                        // a div limited to a format or holding drama or a letter encloses markdown,
+                        // set apart from it by blank lines
+                        let class = attribute(&html, "class");
+                        let current_div = match class.and_then(format_only_class) {
+                            Some(format) => CurrentDiv::FormatOnly(format.to_string()),
+                            None if class.is_some_and(drama::is_drama_class) => {
+                                let personae = attribute(&html, "data-personae");
+                                CurrentDiv::Drama(personae.map(drama::personae).unwrap_or_default())
+                            }
+                            None if class.is_some_and(letter::is_letter_class) => {
+                                CurrentDiv::Letter
+                            }
+                            None => CurrentDiv::Generic,
+                        };
+                        match current_div {
+                            CurrentDiv::FormatOnly(ref format) => self.buffered.push_back((
+                                Event::Start(Tag::FormatOnly(format.clone().into())),
+                                range.clone(),
+                            )),
+                            CurrentDiv::Drama(_) => {}
+                            CurrentDiv::Letter => self
+                                .buffered
+                                .push_back((Event::Start(Tag::Letter), range.clone())),
+                            CurrentDiv::Generic => self
+                                .buffered
+                                .push_back((Event::Html(html.clone()), range.clone())),
+                        }
+                        if current_div != CurrentDiv::Generic || !html.contains("</div") {
+                            self.current_divs.push(current_div);
+                        }
+                    } else if html.starts_with("</div") {
+                        match self.current_divs.pop() {
+                            Some(CurrentDiv::FormatOnly(format)) => {
+                                self.buffered.push_back((
+                                    Event::End(Tag::FormatOnly(format.into())),
+                                    range.clone(),
+                                ));
+                            }
+                            Some(CurrentDiv::Drama(_)) => {}
+                            Some(CurrentDiv::Letter) => self
+                                .buffered
+                                .push_back((Event::End(Tag::Letter), range.clone())),
+                            Some(CurrentDiv::Generic) | None => {
+                                self.buffered.push_back((Event::Html(html), range.clone()))
                             }
                         }
                     } else {
-                        self.buffered.push_back(Event::Html(html));
+                        self.buffered.push_back((Event::Html(html), range.clone()));
                     }
                 }
-                other => self.buffered.push_back(other.into()),
+                other => self.buffered.push_back((other.into(), range.clone())),
             }
         }
+        true
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// the next event, along with the range of the source it was parsed from
+    fn next_located(&mut self) -> Option<(Event<'a>, Range<usize>)> {
+        if let Some(located) = self.arranged.pop_front() {
+            return Some(located);
+        }
+        match self._next()? {
+            // within drama, a paragraph is only known to be a speech or the like once it is complete
+            (Event::Start(Tag::Paragraph), range) if self.personae().is_some() => {
+                let personae = self.personae().map(<[String]>::to_vec).unwrap_or_default();
+                let mut paragraph = Vec::new();
+                while let Some(located) = self._next() {
+                    if let (Event::End(Tag::Paragraph), _) = located {
+                        break;
+                    }
+                    paragraph.push(located);
+                }
+                let events = paragraph.iter().map(|(event, _)| event.clone()).collect();
+                let arranged = drama::arrange_paragraph(events, &personae);
+                self.arranged = relocate(paragraph, arranged, range).into();
+                self.arranged.pop_front()
+            }
+            // the parts of a letter are only known from their place in the whole of it
+            (Event::Start(Tag::Letter), range) => {
+                let mut contents = Vec::new();
+                let mut end = range.clone();
+                let mut depth = 0;
+                while let Some(located) = self._next() {
+                    match located.0 {
+                        Event::Start(Tag::Letter) => depth += 1,
+                        Event::End(Tag::Letter) if depth == 0 => {
+                            end = located.1;
+                            break;
+                        }
+                        Event::End(Tag::Letter) => depth -= 1,
+                        _ => {}
+                    }
+                    contents.push(located);
+                }
+                let events = contents.iter().map(|(event, _)| event.clone()).collect();
+                let arranged = letter::arrange_letter(events);
+                self.arranged = relocate(contents, arranged, end.clone()).into();
+                self.arranged.push_back((Event::End(Tag::Letter), end));
+                Some((Event::Start(Tag::Letter), range))
+            }
+            located => Some(located),
+        }
     }
 }
 
 impl<'a> Iterator for Parser<'a> {
     type Item = Event<'a>;
     fn next(&mut self) -> Option<Self::Item> {
-        self._next()
+        self.next_located().map(|(event, _)| event)
     }
 }
 
@@ -629,4 +974,47 @@ mod tests {
         ];
         assert_eq!(parsed, expected);
     }
+
+    #[test]
+    fn only_known_formats_limit_content() {
+        assert_eq!(format_only_class("pdf-only"), Some("pdf"));
+        assert_eq!(format_only_class("aside EPUB-only"), Some("EPUB"));
+        assert_eq!(format_only_class("latex-only"), Some("latex"));
+        assert_eq!(format_only_class("members-only"), None);
+        assert_eq!(format_only_class("-only"), None);
+        assert_eq!(format_only_class("pdf"), None);
+
+        let parsed = Parser::new(r#"<span class="members-only">Text</span>"#).collect::<Vec<_>>();
+        assert!(!parsed
+            .iter()
+            .any(|e| matches!(e, Event::Start(Tag::FormatOnly(_)))));
+    }
+
+    #[test]
+    fn events_are_located() {
+        let text = "# Heading\n\nAs [@smith] says,\nsee {@fig:map}.\n\n<div class=\"drama\" data-personae=\"HAMLET\">\n\nHAMLET. To be {#speech}.\n\n</div>\n";
+        let line = |offset: usize| text[..offset].matches('\n').count() + 1;
+        let located = Parser::new(text)
+            .into_offset_iter()
+            .filter_map(|(event, range)| match event {
+                Event::Start(Tag::Heading(_))
+                | Event::Start(Tag::Speech(_))
+                | Event::Citation(_)
+                | Event::Reference(_)
+                | Event::Label(_) => Some((event, line(range.start))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let citation = citations::to_event("@smith".into());
+        assert_eq!(
+            located,
+            vec![
+                (Event::Start(Tag::Heading(1)), 1),
+                (citation, 3),
+                (Event::Reference("fig:map".into()), 4),
+                (Event::Start(Tag::Speech("HAMLET".into())), 8),
+                (Event::Label("speech".into()), 8),
+            ]
+        );
+    }
 }
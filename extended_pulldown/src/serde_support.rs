@@ -0,0 +1,139 @@
+// `pulldown_cmark` does not implement serde's traits for its types,
+// so we provide the necessary glue here for use with `#[serde(with = "...")]`.
+//
+// Everything is deserialized as owned data, so that a deserialized event
+// is not tied to the lifetime of its source.
+
+use pulldown_cmark::{Alignment, CodeBlockKind, CowStr, LinkType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "LinkType")]
+pub(crate) enum LinkTypeDef {
+    Inline,
+    Reference,
+    ReferenceUnknown,
+    Collapsed,
+    CollapsedUnknown,
+    Shortcut,
+    ShortcutUnknown,
+    Autolink,
+    Email,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Alignment")]
+enum AlignmentDef {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AlignmentWrapper(#[serde(with = "AlignmentDef")] Alignment);
+
+pub(crate) mod cowstr {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        s: &CowStr<'_>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(s)
+    }
+
+    pub(crate) fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CowStr<'a>, D::Error> {
+        String::deserialize(deserializer).map(CowStr::from)
+    }
+}
+
+pub(crate) mod cowstrs {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        strs: &[CowStr<'_>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(strs.iter().map(|s| s.as_ref()))
+    }
+
+    pub(crate) fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<CowStr<'a>>, D::Error> {
+        let strs = Vec::<String>::deserialize(deserializer)?;
+        Ok(strs.into_iter().map(CowStr::from).collect())
+    }
+}
+
+pub(crate) mod optional_cowstr {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        s: &Option<CowStr<'_>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.as_ref().map(|s| s.as_ref()).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<CowStr<'a>>, D::Error> {
+        Option::<String>::deserialize(deserializer).map(|s| s.map(CowStr::from))
+    }
+}
+
+pub(crate) mod alignments {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        alignments: &[Alignment],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(alignments.iter().map(|a| AlignmentWrapper(*a)))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Alignment>, D::Error> {
+        let wrapped = Vec::<AlignmentWrapper>::deserialize(deserializer)?;
+        Ok(wrapped.into_iter().map(|a| a.0).collect())
+    }
+}
+
+pub(crate) mod code_block_kind {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename = "CodeBlockKind")]
+    enum OwnedCodeBlockKind {
+        Indented,
+        Fenced(String),
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        kind: &CodeBlockKind<'_>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match kind {
+            CodeBlockKind::Indented => {
+                serializer.serialize_unit_variant("CodeBlockKind", 0, "Indented")
+            }
+            CodeBlockKind::Fenced(lang) => {
+                serializer.serialize_newtype_variant("CodeBlockKind", 1, "Fenced", lang.as_ref())
+            }
+        }
+    }
+
+    pub(crate) fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CodeBlockKind<'a>, D::Error> {
+        let kind = match OwnedCodeBlockKind::deserialize(deserializer)? {
+            OwnedCodeBlockKind::Indented => CodeBlockKind::Indented,
+            OwnedCodeBlockKind::Fenced(lang) => CodeBlockKind::Fenced(lang.into()),
+        };
+        Ok(kind)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{Event, MakeStatic, Options, Parser, Tag};
+
+/// How often lines are numbered when a verse block asks for numbering without saying
+const DEFAULT_NUMBERING: u32 = 5;
+
+/// If `info` is the info string of a verse block -- `verse`, `verse numbered` or `verse numbered=10` --
+/// how often its lines are to be numbered, if at all
+pub(crate) fn verse_info(info: &str) -> Option<Option<u32>> {
+    let mut words = info.split_whitespace();
+    if words.next()? != "verse" {
+        return None;
+    }
+    let mut numbering = None;
+    for word in words {
+        if word == "numbered" {
+            numbering = Some(DEFAULT_NUMBERING);
+        } else if let Some(n) = word.strip_prefix("numbered=") {
+            numbering = n.parse().ok().filter(|n| *n > 0);
+        }
+    }
+    Some(numbering)
+}
+
+/// the width of the whitespace with which `line` begins, counting a tab as four spaces
+fn indentation(line: &str) -> u32 {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// `line`, with anything at its beginning which markdown would take
+/// to begin a heading, list, quotation or rule escaped, so that it is read as text
+fn escape_line_start(line: &str) -> String {
+    let mut chars = line.chars();
+    let first = match chars.next() {
+        Some(c) => c,
+        None => return String::new(),
+    };
+    let second = chars.next();
+    let is_rule = line.chars().all(|c| c == first || c.is_whitespace()) && "*-_=".contains(first);
+    let numbered = line.find(|c: char| !c.is_ascii_digit()).filter(|i| {
+        *i > 0
+            && line[*i..].starts_with(['.', ')'])
+            && line[*i + 1..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+    });
+
+    if let Some(i) = numbered {
+        format!("{}\\{}", &line[..i], &line[i..])
+    } else if is_rule
+        || first == '>'
+        || (first == '#' && line.trim_start_matches('#').starts_with(' '))
+        || (first == '#' && line.trim_start_matches('#').is_empty())
+        || ("-+*".contains(first) && second.is_none_or(char::is_whitespace))
+        || line.starts_with("```")
+        || line.starts_with("~~~")
+    {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+/// The events of the verse `text`: a `Verse`, holding a `Stanza` for each group of lines
+/// set apart by blank lines, each of which holds a `VerseLine` for each line.
+/// Lines are parsed as markdown, but only for inline elements.
+pub(crate) fn to_events(text: &str, numbering: Option<u32>) -> Vec<Event<'static>> {
+    let mut events = vec![Event::Start(Tag::Verse(numbering))];
+    let lines = text.lines().collect::<Vec<_>>();
+    for stanza in lines.split(|line| line.trim().is_empty()) {
+        if stanza.is_empty() {
+            continue;
+        }
+        events.push(Event::Start(Tag::Stanza));
+        push_stanza(stanza, &mut events);
+        events.push(Event::End(Tag::Stanza));
+    }
+    events.push(Event::End(Tag::Verse(numbering)));
+    events
+}
+
+/// push the events of the lines of `stanza` to `events`
+fn push_stanza(stanza: &[&str], events: &mut Vec<Event<'static>>) {
+    // the stanza is parsed as a single paragraph broken at each line,
+    // so that quotations can run from one line to another
+    let markdown = stanza
+        .iter()
+        .map(|line| escape_line_start(line.trim()))
+        .collect::<Vec<_>>()
+        .join("\\\n");
+    let mut indents = stanza.iter().map(|line| indentation(line));
+
+    let mut open: Vec<Tag<'static>> = Vec::new();
+    let mut indent = indents.next().unwrap_or_default();
+    events.push(Event::Start(Tag::VerseLine(indent)));
+    for event in Parser::new_ext(&markdown, Options::default()).map(|e| e.into_static()) {
+        match event {
+            Event::Start(Tag::Paragraph) | Event::End(Tag::Paragraph) => {}
+            // a line ends within emphasis or the like, which is closed and opened again
+            // so that each line is whole in itself
+            Event::HardBreak | Event::SoftBreak => {
+                for tag in open.iter().rev() {
+                    events.push(Event::End(tag.clone()));
+                }
+                events.push(Event::End(Tag::VerseLine(indent)));
+                indent = indents.next().unwrap_or_default();
+                events.push(Event::Start(Tag::VerseLine(indent)));
+                for tag in open.iter() {
+                    events.push(Event::Start(tag.clone()));
+                }
+            }
+            Event::Start(tag) => {
+                open.push(tag.clone());
+                events.push(Event::Start(tag));
+            }
+            Event::End(tag) => {
+                open.pop();
+                events.push(Event::End(tag));
+            }
+            event => events.push(event),
+        }
+    }
+    events.push(Event::End(Tag::VerseLine(indent)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verse_info_and_escapes() {
+        assert_eq!(verse_info("verse"), Some(None));
+        assert_eq!(verse_info("verse numbered"), Some(Some(5)));
+        assert_eq!(verse_info("verse numbered=2"), Some(Some(2)));
+        assert_eq!(verse_info("rust"), None);
+        assert_eq!(verse_info(""), None);
+
+        assert_eq!(indentation("\t  Line"), 6);
+        assert_eq!(escape_line_start("1. A line"), "1\\. A line");
+        assert_eq!(escape_line_start("- a dash"), "\\- a dash");
+        assert_eq!(escape_line_start("*emphasis*"), "*emphasis*");
+        assert_eq!(escape_line_start("# hashed"), "\\# hashed");
+        assert_eq!(escape_line_start("1914, and after"), "1914, and after");
+    }
+}